@@ -0,0 +1,73 @@
+// benches/evaluate.rs
+//
+// Throughput of the hot paths: a single `evaluate` call and a full gear
+// search. Run with `cargo bench`; prints time per call.
+
+use osrs_sim::data::{resolve_player_input, DataStore, Resolver};
+use osrs_sim::optimizer::{optimize_gear, AccountConstraints};
+use osrs_sim::{evaluate, BuildInput, EvalContext, PlayerInput, TargetInput};
+use serde::de::DeserializeOwned;
+use std::hint::black_box;
+use std::path::Path;
+use std::time::Instant;
+
+fn read_fixture<T: DeserializeOwned>(file: &str) -> T {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(file);
+    let json = std::fs::read_to_string(&path).expect("fixture");
+    serde_json::from_str(&json).expect("valid fixture")
+}
+
+/// Run `f` `iterations` times and print the mean time per call.
+fn bench(name: &str, iterations: u32, mut f: impl FnMut()) {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let per_call = start.elapsed() / iterations;
+    println!(
+        "{name:<24} {per_call:>12.2?}/call  {:>12.0} calls/s",
+        1.0 / per_call.as_secs_f64()
+    );
+}
+
+fn main() {
+    let data_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("data");
+    let store = DataStore::load_from_dir(&data_dir).expect("data store");
+    let resolver = Resolver::new(&store);
+
+    let player_input: PlayerInput = read_fixture("players/maxed_piety_super_combat.json");
+    let build_input: BuildInput =
+        read_fixture("builds/voidwaker_bandos_salve_ei_slash_accurate.json");
+    let target_input: TargetInput = read_fixture("targets/vorkath.json");
+    let player = resolve_player_input(&player_input);
+    let build = resolver.resolve_build(&build_input).expect("build");
+    let target = resolver
+        .resolve_target_for(&target_input, &player_input)
+        .expect("target");
+
+    bench("evaluate", 1_000_000, || {
+        black_box(evaluate(&EvalContext {
+            player: black_box(&player),
+            build: black_box(&build),
+            target: black_box(&target),
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        }));
+    });
+
+    bench("optimize_gear", 20, || {
+        black_box(
+            optimize_gear(
+                &store,
+                &player_input,
+                &build_input,
+                &target,
+                &AccountConstraints::default(),
+            )
+            .expect("search"),
+        );
+    });
+}
@@ -0,0 +1,78 @@
+#!/usr/bin/env python3
+"""Download cache shared by the ingestion scripts and `osrs-sim cache`.
+
+Every download is saved under the cache directory (`$OSRS_SIM_CACHE_DIR`,
+else `$XDG_CACHE_HOME/osrs-sim`, else `~/.cache/osrs-sim`), one section per
+kind of lookup. Online, each fetch goes to the network and refreshes its
+cached copy. Offline (`--offline`, or `OSRS_SIM_OFFLINE=1`), fetches are
+answered from the cache only and never touch the network, so a run can be
+repeated exactly.
+"""
+
+from __future__ import annotations
+
+import hashlib
+import os
+import urllib.request
+from pathlib import Path
+from typing import Dict, Optional
+
+CACHE_DIR_ENV = "OSRS_SIM_CACHE_DIR"
+OFFLINE_ENV = "OSRS_SIM_OFFLINE"
+
+# Section for item and monster data downloads
+FETCH_DATA = "fetch-data"
+
+
+class OfflineMiss(Exception):
+    """An offline fetch of something that was never cached."""
+
+
+def cache_dir() -> Path:
+    override = os.environ.get(CACHE_DIR_ENV)
+    if override:
+        return Path(override)
+    xdg = os.environ.get("XDG_CACHE_HOME")
+    # Relative XDG paths are invalid and ignored, as the spec says
+    if xdg and Path(xdg).is_absolute():
+        return Path(xdg) / "osrs-sim"
+    return Path.home() / ".cache" / "osrs-sim"
+
+
+def set_offline() -> None:
+    """Make every later fetch, in this process and its children, offline."""
+    os.environ[OFFLINE_ENV] = "1"
+
+
+def is_offline() -> bool:
+    return os.environ.get(OFFLINE_ENV) == "1"
+
+
+def cached_path(url: str, section: str = FETCH_DATA) -> Path:
+    digest = hashlib.sha256(url.encode("utf-8")).hexdigest()[:32]
+    return cache_dir() / section / f"{digest}.json"
+
+
+def fetch(
+    url: str,
+    section: str = FETCH_DATA,
+    headers: Optional[Dict[str, str]] = None,
+    timeout: int = 30,
+) -> bytes:
+    """Body of `url`, downloaded and cached, or from the cache when offline."""
+    path = cached_path(url, section)
+    if is_offline():
+        try:
+            return path.read_bytes()
+        except FileNotFoundError:
+            raise OfflineMiss(f"offline, and {url} isn't cached") from None
+
+    request = urllib.request.Request(url, headers=headers or {})
+    with urllib.request.urlopen(request, timeout=timeout) as response:
+        body = response.read()
+    path.parent.mkdir(parents=True, exist_ok=True)
+    # Write then rename, so an interrupted download never leaves a bad copy
+    partial = path.with_suffix(".partial")
+    partial.write_bytes(body)
+    partial.replace(path)
+    return body
@@ -3,12 +3,15 @@
 
 from __future__ import annotations
 
+import argparse
 import json
 import subprocess
 import sys
 from pathlib import Path
 from typing import Any, Dict, Optional
 
+from fetch_cache import set_offline
+
 
 def load_config(path: Path) -> Dict[str, Any]:
     try:
@@ -71,6 +74,15 @@ def build_cmd(
 
 
 def main() -> int:
+    parser = argparse.ArgumentParser(description="Run item and monster ingestion")
+    parser.add_argument(
+        "--offline",
+        action="store_true",
+        help="Read only cached downloads; never touch the network",
+    )
+    # Children inherit the environment, so this makes their fetches offline
+    if parser.parse_args().offline:
+        set_offline()
     root = Path(__file__).resolve().parents[1]
     config_path = root / "data" / "ingest.json"
     config = load_config(config_path)
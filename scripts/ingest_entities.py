@@ -7,10 +7,11 @@ import argparse
 import json
 import sys
 import urllib.error
-import urllib.request
 from pathlib import Path
 from typing import Any, List, Optional, Tuple
 
+from fetch_cache import OfflineMiss, fetch, set_offline
+
 
 def parse_args() -> argparse.Namespace:
     parser = argparse.ArgumentParser(
@@ -44,6 +45,11 @@ def parse_args() -> argparse.Namespace:
         action="store_true",
         help="Allow missing IDs instead of failing",
     )
+    parser.add_argument(
+        "--offline",
+        action="store_true",
+        help="Read only cached downloads; never touch the network",
+    )
     return parser.parse_args()
 
 
@@ -97,8 +103,9 @@ def fetch_entity(
 ) -> Tuple[Optional[dict], Optional[str]]:
     url = f"{base_url}/{entity_id}.json"
     try:
-        with urllib.request.urlopen(url, timeout=15) as response:
-            payload = response.read().decode("utf-8")
+        payload = fetch(url, timeout=15).decode("utf-8")
+    except OfflineMiss as exc:
+        return None, str(exc)
     except urllib.error.HTTPError as exc:
         return None, f"HTTP {exc.code} for {url}"
     except urllib.error.URLError as exc:
@@ -124,6 +131,8 @@ def resolve_id(entity: dict, keys: Tuple[str, ...]) -> Optional[Any]:
 
 def main() -> int:
     args = parse_args()
+    if args.offline:
+        set_offline()
     whitelist_path = Path(args.whitelist)
     out_dir = Path(args.out_dir)
     base_url = normalize_base_url(args.base_url)
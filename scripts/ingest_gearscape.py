@@ -13,14 +13,16 @@ Idempotent: only writes files that have changed.
 
 from __future__ import annotations
 
+import argparse
 import hashlib
 import json
 import sys
 from pathlib import Path
 from typing import Any
-from urllib.request import urlopen
 from urllib.error import URLError
 
+from fetch_cache import OfflineMiss, fetch, set_offline
+
 EQUIPMENT_URL = "https://api.gearscape.net/api/equipment/all"
 WEAPON_URL = "https://api.gearscape.net/api/weapon/all"
 
@@ -44,10 +46,8 @@ def fetch_json(url: str) -> Any:
     """Fetch JSON from URL."""
     print(f"Fetching {url}...")
     try:
-        with urlopen(url, timeout=30) as resp:
-            data = json.loads(resp.read().decode("utf-8"))
-        return data
-    except URLError as e:
+        return json.loads(fetch(url).decode("utf-8"))
+    except (URLError, OfflineMiss) as e:
         print(f"Error fetching {url}: {e}", file=sys.stderr)
         raise SystemExit(1)
 
@@ -226,6 +226,15 @@ def write_if_changed(path: Path, content: str) -> bool:
 
 
 def main() -> int:
+    parser = argparse.ArgumentParser(description="Ingest items from the Gearscape API")
+    parser.add_argument(
+        "--offline",
+        action="store_true",
+        help="Read only cached downloads; never touch the network",
+    )
+    if parser.parse_args().offline:
+        set_offline()
+
     root = Path(__file__).resolve().parents[1]
     out_dir = root / "data" / "items"
     out_dir.mkdir(parents=True, exist_ok=True)
@@ -15,10 +15,11 @@ import json
 import re
 import sys
 import urllib.parse
-import urllib.request
 from pathlib import Path
 from typing import Any, Optional
 
+from fetch_cache import fetch, set_offline
+
 WIKI_BASE = "https://oldschool.runescape.wiki"
 API_BASE = f"{WIKI_BASE}/api.php"
 USER_AGENT = "osrs-sim (https://github.com/your-repo/osrs-sim)"
@@ -116,6 +117,11 @@ def parse_args() -> argparse.Namespace:
         action="store_true",
         help="Print verbose progress",
     )
+    parser.add_argument(
+        "--offline",
+        action="store_true",
+        help="Read only cached downloads; never touch the network",
+    )
     return parser.parse_args()
 
 
@@ -143,10 +149,7 @@ def fetch_monsters_from_wiki() -> list[dict[str, Any]]:
         }
 
         url = f"{API_BASE}?{urllib.parse.urlencode(params)}"
-        req = urllib.request.Request(url, headers={"User-Agent": USER_AGENT})
-
-        with urllib.request.urlopen(req, timeout=30) as response:
-            data = json.loads(response.read().decode("utf-8"))
+        data = json.loads(fetch(url, headers={"User-Agent": USER_AGENT}).decode("utf-8"))
 
         if "bucket" not in data:
             break
@@ -406,6 +409,8 @@ def should_include(monster: dict[str, Any], filter_mode: str) -> bool:
 
 def main() -> int:
     args = parse_args()
+    if args.offline:
+        set_offline()
     out_dir = Path(args.out_dir)
 
     # Fetch from wiki
@@ -0,0 +1,108 @@
+// src/aoe.rs
+//
+// Attacks that hit more than one target. The main target's DPS is worked
+// out as usual; these add what the targets next to it take, taking them to
+// share the main target's stats, so each rolls the same accuracy.
+
+use crate::model::BuildResolved;
+
+/// Venator bow item ID.
+pub const VENATOR_BOW: u32 = 27610;
+
+/// Most targets a Venator bow arrow bounces to after the first.
+pub const VENATOR_BOUNCES: u8 = 2;
+
+/// Most targets a burst or barrage hits besides the main one: the rest of
+/// the 3x3 area around it.
+pub const AREA_SPELL_TARGETS: u8 = 8;
+
+/// An attack that reaches the targets next to the main one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AreaAttack {
+    /// Venator bow arrows bounce on from a hit that lands.
+    VenatorBounce,
+    /// Ancient Magicks bursts and barrages hit everything in the 3x3 area.
+    AreaSpell,
+}
+
+impl AreaAttack {
+    /// The area attack a build makes, if any.
+    pub fn of(build: &BuildResolved) -> Option<Self> {
+        if build.weapon_id == VENATOR_BOW {
+            return Some(AreaAttack::VenatorBounce);
+        }
+        let spell = build.spell.as_ref()?.name.to_lowercase().replace('_', " ");
+        (spell.ends_with(" burst") || spell.ends_with(" barrage")).then_some(AreaAttack::AreaSpell)
+    }
+
+    /// Average damage per attack dealt to `adjacent_targets` targets next
+    /// to the main one, from the main target's accuracy and max hit.
+    pub fn adjacent_damage(self, accuracy: f64, max_hit: i64, adjacent_targets: u8) -> f64 {
+        match self {
+            AreaAttack::VenatorBounce => venator_bounce_damage(accuracy, max_hit, adjacent_targets),
+            AreaAttack::AreaSpell => {
+                let targets = adjacent_targets.min(AREA_SPELL_TARGETS);
+                f64::from(targets) * accuracy * max_hit as f64 / 2.0
+            }
+        }
+    }
+}
+
+/// Average damage per attack that a Venator bow's bounces deal to the
+/// adjacent targets. An arrow that lands bounces to the next target,
+/// rolling its own accuracy for up to two thirds of the max hit, and
+/// keeps going while its bounces land.
+pub fn venator_bounce_damage(accuracy: f64, max_hit: i64, adjacent_targets: u8) -> f64 {
+    let bounce_average = (max_hit * 2 / 3) as f64 / 2.0;
+    // Chance the arrow reaches each bounce: every hit before it landed
+    let mut reached = accuracy;
+    let mut total = 0.0;
+    for _ in 0..adjacent_targets.min(VENATOR_BOUNCES) {
+        total += reached * accuracy * bounce_average;
+        reached *= accuracy;
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Spell;
+
+    #[test]
+    fn test_venator_bounces_follow_landed_hits() {
+        // Max hit 30: bounces roll up to 20
+        assert_eq!(venator_bounce_damage(0.5, 30, 0), 0.0);
+        assert!((venator_bounce_damage(0.5, 30, 1) - 0.25 * 10.0).abs() < 1e-9);
+        let two = 0.25 * 10.0 + 0.125 * 10.0;
+        assert!((venator_bounce_damage(0.5, 30, 2) - two).abs() < 1e-9);
+        // Only two bounces, however many targets stand around
+        assert_eq!(
+            venator_bounce_damage(0.5, 30, 8),
+            venator_bounce_damage(0.5, 30, 2)
+        );
+    }
+
+    #[test]
+    fn test_area_spells_hit_the_rest_of_the_3x3() {
+        let casting = |name: &str| BuildResolved {
+            spell: Some(Spell {
+                name: name.to_string(),
+                ..Spell::default()
+            }),
+            ..BuildResolved::default()
+        };
+        assert_eq!(
+            AreaAttack::of(&casting("ice_barrage")),
+            Some(AreaAttack::AreaSpell)
+        );
+        assert_eq!(
+            AreaAttack::of(&casting("Smoke Burst")),
+            Some(AreaAttack::AreaSpell)
+        );
+        assert_eq!(AreaAttack::of(&casting("ice blitz")), None);
+        // Each of up to 8 others takes what the main target does
+        let damage = AreaAttack::AreaSpell.adjacent_damage(0.5, 30, 12);
+        assert!((damage - 8.0 * 7.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,240 @@
+// src/bolts.rs
+//
+// Enchanted bolt special effects. Each attack with enchanted bolts has a
+// small chance to proc the bolts' effect; the ones that add damage are
+// folded into the expected damage of an attack. Opal, pearl, diamond and
+// ruby procs land whatever the accuracy roll; dragonstone and onyx procs
+// only on a hit that lands. The Kandarin hard diary makes procs 10% more
+// likely, and the Zaryte crossbow makes them deal 10% more.
+
+use crate::model::TargetResolved;
+
+/// Ruby bolts deal this share of the target's hitpoints, in percent.
+pub const RUBY_HITPOINTS_PCT: u32 = 20;
+
+/// Most a ruby bolt proc deals.
+pub const RUBY_DAMAGE_CAP: u32 = 100;
+
+/// Proc chance multiplier from the Kandarin hard diary, num/den.
+pub const KANDARIN_PROC_CHANCE: (u32, u32) = (11, 10);
+
+/// Proc damage multiplier from the Zaryte crossbow, num/den: ruby procs
+/// take 22% of the target's hitpoints up to 110, diamond procs hit 26%
+/// harder.
+pub const ZARYTE_PROC_DAMAGE: (u32, u32) = (11, 10);
+
+/// Zaryte crossbow item ID.
+pub const ZARYTE_CROSSBOW: u32 = 26374;
+
+/// Enchanted bolt effects that add damage, by their effect name in
+/// data/ammo.json. Jade, topaz, sapphire and emerald bolts don't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoltProc {
+    /// Lucky Lightning: a tenth of the Ranged level in extra damage.
+    Opal,
+    /// Sea Curse: a twentieth of the Ranged level extra, a fifteenth
+    /// against fiery monsters.
+    Pearl,
+    /// Blood Forfeit: a fifth of the target's hitpoints, capped.
+    Ruby,
+    /// Armour Piercing: the hit ignores defence with 15% more max hit.
+    Diamond,
+    /// Dragon's Breath: a fifth of the Ranged level extra, except against
+    /// dragons and fiery monsters.
+    Dragonstone,
+    /// Life Leech: 20% more max hit, except against undead.
+    Onyx,
+}
+
+impl BoltProc {
+    pub fn from_effect(name: &str) -> Option<Self> {
+        match name {
+            "opal" => Some(BoltProc::Opal),
+            "pearl" => Some(BoltProc::Pearl),
+            "ruby" => Some(BoltProc::Ruby),
+            "diamond" => Some(BoltProc::Diamond),
+            "dragonstone" => Some(BoltProc::Dragonstone),
+            "onyx" => Some(BoltProc::Onyx),
+            _ => None,
+        }
+    }
+
+    /// Chance an attack procs the effect, in percent.
+    pub fn chance_pct(self) -> u32 {
+        match self {
+            BoltProc::Opal => 5,
+            BoltProc::Pearl | BoltProc::Ruby | BoltProc::Dragonstone => 6,
+            BoltProc::Diamond => 10,
+            BoltProc::Onyx => 11,
+        }
+    }
+}
+
+/// What an attack needs to work out its bolt proc.
+#[derive(Debug, Clone, Copy)]
+pub struct ProcAttack {
+    pub accuracy: f64,
+    pub max_hit: i64,
+    /// Visible (boosted) Ranged level.
+    pub ranged_level: u32,
+    /// Fired from the Zaryte crossbow, which amplifies proc damage.
+    pub zaryte: bool,
+    /// Kandarin hard diary done, which raises the proc chance.
+    pub kandarin_diary: bool,
+}
+
+impl ProcAttack {
+    fn damage_mult(&self) -> (u32, u32) {
+        if self.zaryte {
+            ZARYTE_PROC_DAMAGE
+        } else {
+            (1, 1)
+        }
+    }
+
+    /// `value` scaled by the proc damage multiplier, rounded down.
+    fn amplified(&self, value: u32) -> u32 {
+        let (num, den) = self.damage_mult();
+        value * num / den
+    }
+
+    /// Chance a proc triggers per attack, 0-1.
+    fn chance(&self, proc: BoltProc) -> f64 {
+        let chance = f64::from(proc.chance_pct()) / 100.0;
+        if self.kandarin_diary {
+            let (num, den) = KANDARIN_PROC_CHANCE;
+            chance * f64::from(num) / f64::from(den)
+        } else {
+            chance
+        }
+    }
+}
+
+fn has_attribute(target: &TargetResolved, attribute: &str) -> bool {
+    target.attributes.iter().any(|a| a == attribute)
+}
+
+/// Average damage per attack with the bolt effect, against an average of
+/// `accuracy * max_hit / 2` without it.
+pub fn expected_damage(proc: BoltProc, attack: ProcAttack, target: &TargetResolved) -> f64 {
+    let chance = attack.chance(proc);
+    let accuracy = attack.accuracy;
+    let average = attack.max_hit as f64 / 2.0;
+    let plain = accuracy * average;
+    let fiery = has_attribute(target, "fiery");
+    // Extra damage of a share (1/divisor) of the Ranged level
+    let level_share = |divisor: u32| {
+        let (num, den) = attack.damage_mult();
+        f64::from(attack.ranged_level * num / (divisor * den))
+    };
+    // Average of a hit rolled up to `pct` percent of the max hit
+    let boosted_average =
+        |pct: u32| (attack.max_hit * i64::from(attack.amplified(pct)) / 100) as f64 / 2.0;
+    // A proc that lands regardless of accuracy, dealing `proc_damage`
+    let guaranteed = |proc_damage: f64| (1.0 - chance) * plain + chance * proc_damage;
+    match proc {
+        BoltProc::Opal => guaranteed(average + level_share(10)),
+        BoltProc::Pearl => guaranteed(average + level_share(if fiery { 15 } else { 20 })),
+        BoltProc::Ruby => {
+            let share = attack.amplified(RUBY_HITPOINTS_PCT);
+            let cap = attack.amplified(RUBY_DAMAGE_CAP);
+            let damage = (u32::from(target.hitpoints) * share / 100).min(cap);
+            guaranteed(f64::from(damage))
+        }
+        BoltProc::Diamond => guaranteed(boosted_average(115)),
+        BoltProc::Dragonstone if fiery || has_attribute(target, "dragon") => plain,
+        BoltProc::Dragonstone => plain + accuracy * chance * level_share(5),
+        BoltProc::Onyx if has_attribute(target, "undead") => plain,
+        BoltProc::Onyx => accuracy * ((1.0 - chance) * average + chance * boosted_average(120)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attack(accuracy: f64) -> ProcAttack {
+        ProcAttack {
+            accuracy,
+            max_hit: 40,
+            ranged_level: 99,
+            zaryte: false,
+            kandarin_diary: false,
+        }
+    }
+
+    fn target(hitpoints: u16, attributes: &[&str]) -> TargetResolved {
+        TargetResolved {
+            hitpoints,
+            attributes: attributes.iter().map(|a| a.to_string()).collect(),
+            ..TargetResolved::default()
+        }
+    }
+
+    #[test]
+    fn test_ruby_scales_with_hitpoints_up_to_the_cap() {
+        let boss = target(1000, &[]);
+        // 6% of attacks deal 100 whether or not they'd hit
+        let damage = expected_damage(BoltProc::Ruby, attack(0.5), &boss);
+        assert!((damage - (0.94 * 10.0 + 0.06 * 100.0)).abs() < 1e-9);
+        let zaryte = ProcAttack {
+            zaryte: true,
+            ..attack(0.5)
+        };
+        let damage = expected_damage(BoltProc::Ruby, zaryte, &boss);
+        assert!((damage - (0.94 * 10.0 + 0.06 * 110.0)).abs() < 1e-9);
+        // 250 hitpoints: 50 per proc
+        let damage = expected_damage(BoltProc::Ruby, attack(0.5), &target(250, &[]));
+        assert!((damage - (0.94 * 10.0 + 0.06 * 50.0)).abs() < 1e-9);
+        // From the Zaryte crossbow, 22% of them: 55
+        let damage = expected_damage(BoltProc::Ruby, zaryte, &target(250, &[]));
+        assert!((damage - (0.94 * 10.0 + 0.06 * 55.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_zaryte_and_kandarin_diary_amplify_procs() {
+        let dummy = target(100, &[]);
+        let zaryte = ProcAttack {
+            zaryte: true,
+            ..attack(0.5)
+        };
+        // Diamond: up to 126% of the max hit, 50
+        let damage = expected_damage(BoltProc::Diamond, zaryte, &dummy);
+        assert!((damage - (0.9 * 10.0 + 0.1 * 25.0)).abs() < 1e-9);
+        // Opal: 11% of the Ranged level, 10 more
+        let damage = expected_damage(BoltProc::Opal, zaryte, &dummy);
+        assert!((damage - (0.95 * 10.0 + 0.05 * 30.0)).abs() < 1e-9);
+        // The diary makes a 5% proc 5.5%
+        let diary = ProcAttack {
+            kandarin_diary: true,
+            ..attack(0.5)
+        };
+        let damage = expected_damage(BoltProc::Opal, diary, &dummy);
+        assert!((damage - (0.945 * 10.0 + 0.055 * 29.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_procs_respect_accuracy_and_immunities() {
+        let plain = 0.5 * 20.0;
+        let dummy = target(100, &[]);
+        // Diamond: 10% of attacks land for up to 46
+        let damage = expected_damage(BoltProc::Diamond, attack(0.5), &dummy);
+        assert!((damage - (0.9 * plain + 0.1 * 23.0)).abs() < 1e-9);
+        // Opal: 5% land for 9 more
+        let damage = expected_damage(BoltProc::Opal, attack(0.5), &dummy);
+        assert!((damage - (0.95 * plain + 0.05 * 29.0)).abs() < 1e-9);
+        // Dragonstone only on a landed hit, and not against dragons
+        let damage = expected_damage(BoltProc::Dragonstone, attack(0.5), &dummy);
+        assert!((damage - (plain + 0.5 * 0.06 * 19.0)).abs() < 1e-9);
+        let dragon = target(100, &["dragon"]);
+        assert_eq!(
+            expected_damage(BoltProc::Dragonstone, attack(0.5), &dragon),
+            plain
+        );
+        // Onyx: 11% of landed hits roll up to 48, never against undead
+        let damage = expected_damage(BoltProc::Onyx, attack(0.5), &dummy);
+        assert!((damage - 0.5 * (0.89 * 20.0 + 0.11 * 24.0)).abs() < 1e-9);
+        let undead = target(100, &["undead"]);
+        assert_eq!(expected_damage(BoltProc::Onyx, attack(0.5), &undead), plain);
+    }
+}
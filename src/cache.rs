@@ -0,0 +1,271 @@
+// src/cache.rs
+//
+// Memoized evaluation. Searches often evaluate the same inputs more than
+// once (boost combinations that resolve to the same player state, gear
+// passes that revisit a build); an `EvalCache` keys results by the resolved
+// inputs and keeps the most recently used ones. The optimizer's boost
+// ranking and gear search use one; level sweeps never repeat an input, so
+// they evaluate directly.
+
+use crate::effects::EffectRegistry;
+use crate::formulas::evaluate_with;
+use crate::model::{BuildResolved, EvalContext, EvalResult, PlayerState, TargetResolved};
+use crate::ordering::OrderingProfile;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+
+/// Hash of everything `evaluate` reads: the resolved player, build and
+/// target, the ordering profile, the adjacent targets and the distance.
+pub fn context_hash(ctx: &EvalContext) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    ctx.player.hash(&mut hasher);
+    ctx.build.hash(&mut hasher);
+    ctx.target.hash(&mut hasher);
+    ctx.ordering.hash(&mut hasher);
+    ctx.adjacent_targets.hash(&mut hasher);
+    ctx.distance.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// An owned copy of an `EvalContext`'s inputs, kept with each result so a
+/// hash collision is a miss rather than someone else's result.
+#[derive(Debug, Clone)]
+struct ContextKey {
+    player: PlayerState,
+    build: BuildResolved,
+    target: TargetResolved,
+    ordering: Option<OrderingProfile>,
+    adjacent_targets: u8,
+    distance: Option<u8>,
+}
+
+impl ContextKey {
+    fn of(ctx: &EvalContext) -> Self {
+        Self {
+            player: ctx.player.clone(),
+            build: ctx.build.clone(),
+            target: ctx.target.clone(),
+            ordering: ctx.ordering.cloned(),
+            adjacent_targets: ctx.adjacent_targets,
+            distance: ctx.distance,
+        }
+    }
+
+    fn matches(&self, ctx: &EvalContext) -> bool {
+        self.player == *ctx.player
+            && self.build == *ctx.build
+            && self.target == *ctx.target
+            && self.ordering.as_ref() == ctx.ordering
+            && self.adjacent_targets == ctx.adjacent_targets
+            && self.distance == ctx.distance
+    }
+}
+
+/// Results of `evaluate_with` keyed by `context_hash`, holding at most
+/// `capacity` of them and dropping the least recently used first. A cache
+/// is for one registry: the key doesn't cover the effects.
+#[derive(Debug, Clone)]
+pub struct EvalCache {
+    capacity: usize,
+    /// Hash -> (inputs, result, last use).
+    entries: HashMap<u64, (ContextKey, EvalResult, u64)>,
+    /// (hash, use) in order of use; stale once the entry's use is newer.
+    order: VecDeque<(u64, u64)>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl EvalCache {
+    /// An empty cache holding up to `capacity` results (at least one).
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// The cached result for `ctx`, or evaluate it and remember the result.
+    pub fn evaluate(&mut self, ctx: &EvalContext, registry: &EffectRegistry) -> EvalResult {
+        let key = context_hash(ctx);
+        self.clock += 1;
+        if let Some((_, result, used)) = self
+            .entries
+            .get_mut(&key)
+            .filter(|(inputs, ..)| inputs.matches(ctx))
+        {
+            *used = self.clock;
+            self.order.push_back((key, self.clock));
+            self.hits += 1;
+            let result = result.clone();
+            self.compact();
+            return result;
+        }
+
+        self.misses += 1;
+        let result = evaluate_with(ctx, registry);
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            self.evict();
+        }
+        // A colliding entry is replaced
+        self.entries
+            .insert(key, (ContextKey::of(ctx), result.clone(), self.clock));
+        self.order.push_back((key, self.clock));
+        result
+    }
+
+    /// Lookups answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Lookups that had to evaluate.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Number of results held.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drop the least recently used result, skipping stale order entries.
+    fn evict(&mut self) {
+        while let Some((key, used)) = self.order.pop_front() {
+            if self
+                .entries
+                .get(&key)
+                .is_some_and(|&(.., last)| last == used)
+            {
+                self.entries.remove(&key);
+                return;
+            }
+        }
+    }
+
+    /// Hits leave stale order entries behind; rebuild the order from the
+    /// live entries once they outnumber them a few times over.
+    fn compact(&mut self) {
+        if self.order.len() <= self.capacity.saturating_mul(4) {
+            return;
+        }
+        let mut live: Vec<(u64, u64)> = self
+            .entries
+            .iter()
+            .map(|(&key, &(.., used))| (key, used))
+            .collect();
+        live.sort_by_key(|&(_, used)| used);
+        self.order = live.into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effects::default_registry;
+    use crate::model::{BuildResolved, PlayerState, TargetResolved};
+
+    #[test]
+    fn test_cache_hits_identical_inputs_and_evicts_least_recent() {
+        let registry = default_registry();
+        let player = PlayerState::default();
+        let build = BuildResolved::default();
+        let targets: Vec<TargetResolved> = (1..=3)
+            .map(|defence_level| TargetResolved {
+                defence_level,
+                ..TargetResolved::default()
+            })
+            .collect();
+        let ctx = |target| EvalContext {
+            player: &player,
+            build: &build,
+            target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        };
+
+        let mut cache = EvalCache::new(2);
+        let first = cache.evaluate(&ctx(&targets[0]), registry);
+        assert_eq!(first.dps, evaluate_with(&ctx(&targets[0]), registry).dps);
+        cache.evaluate(&ctx(&targets[0]), registry);
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+        // Filling past capacity drops the least recently used (targets[1])
+        cache.evaluate(&ctx(&targets[1]), registry);
+        cache.evaluate(&ctx(&targets[0]), registry);
+        cache.evaluate(&ctx(&targets[2]), registry);
+        assert_eq!(cache.len(), 2);
+        cache.evaluate(&ctx(&targets[0]), registry);
+        assert_eq!(cache.hits(), 3);
+        cache.evaluate(&ctx(&targets[1]), registry);
+        assert_eq!(cache.misses(), 4);
+    }
+
+    #[test]
+    fn test_context_hash_tracks_inputs() {
+        let player = PlayerState::default();
+        let build = BuildResolved::default();
+        let target = TargetResolved::default();
+        let hash = |target: &TargetResolved| {
+            context_hash(&EvalContext {
+                player: &player,
+                build: &build,
+                target,
+                ordering: None,
+                adjacent_targets: 0,
+                distance: None,
+            })
+        };
+        assert_eq!(hash(&target), hash(&target.clone()));
+        let tougher = TargetResolved {
+            defence_level: 200,
+            ..target.clone()
+        };
+        assert_ne!(hash(&target), hash(&tougher));
+    }
+
+    #[test]
+    fn test_cached_inputs_are_compared_not_just_hashed() {
+        let player = PlayerState::default();
+        let build = BuildResolved::default();
+        let target = TargetResolved::default();
+        let tougher = TargetResolved {
+            defence_level: 200,
+            ..target.clone()
+        };
+        let ctx = |target| EvalContext {
+            player: &player,
+            build: &build,
+            target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        };
+        let key = ContextKey::of(&ctx(&target));
+        let same = target.clone();
+        assert!(key.matches(&ctx(&same)));
+        assert!(!key.matches(&ctx(&tougher)));
+
+        // An entry under another input's hash is a miss, and is replaced
+        let registry = default_registry();
+        let mut cache = EvalCache::new(4);
+        cache.evaluate(&ctx(&target), registry);
+        let (_, entry) = cache.entries.drain().next().unwrap();
+        let collided = context_hash(&ctx(&tougher));
+        cache.entries.insert(collided, entry);
+        let result = cache.evaluate(&ctx(&tougher), registry);
+        assert_eq!(cache.misses(), 2);
+        assert_eq!(result.dps, evaluate_with(&ctx(&tougher), registry).dps);
+        assert!(cache.entries[&collided].0.matches(&ctx(&tougher)));
+    }
+}
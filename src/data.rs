@@ -3,13 +3,21 @@
 // Data loading from the data/ folder.
 // Structs match the JSON format from osrsbox/wiki.
 
+use crate::effects::{default_registry, EffectRegistry};
+use crate::formulas::{evaluate_with, MIN_DEFENCE_BONUS};
 use crate::model::{
-    AttackType, BuildResolved, DefenceBonuses, EquipmentBonuses, PlayerState, Stance,
-    TargetResolved,
+    AttackType, BuildInput, BuildResolved, Combat, DataFlag, DefenceBonuses, EquipmentBonuses,
+    EquipmentInput, EvalContext, EvalResult, HitDistribution, PlayerInput, PlayerState,
+    PoweredStaff, PreFightAction, RankedStyle, Salamander, SkillsInput, SpecialAttack, Spell,
+    Stance, TargetInput, TargetOverrides, TargetResolved, WeaponStrength, ARMOUR_SLOTS,
+    DEFAULT_TARGET_ATTACK_SPEED, MELEE_RANGE, SPELL_RANGE,
 };
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde::de::{DeserializeOwned, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
 use std::fs;
+use std::io::BufReader;
 use std::path::Path;
 use thiserror::Error;
 
@@ -23,6 +31,20 @@ pub enum DataError {
     ItemNotFound(u32),
     #[error("Monster not found: {0}")]
     MonsterNotFound(u32),
+    #[error("Unknown spell: {0}")]
+    UnknownSpell(String),
+    #[error("Magic builds need a spell (style.spell)")]
+    SpellRequired,
+    #[error("{0} can't be poisoned")]
+    NotPoisonable(String),
+    #[error("Invalid equipment: {}", .0.join("; "))]
+    InvalidLoadout(Vec<String>),
+    #[error("{weapon} can't autocast {spell}")]
+    CannotAutocast { spell: String, weapon: String },
+    #[error("Unknown rune: {0}")]
+    UnknownRune(String),
+    #[error("{0} has no special attack data")]
+    NoSpecialAttack(String),
 }
 
 // =============================================================================
@@ -42,6 +64,24 @@ pub struct ItemData {
     #[serde(default)]
     pub weapon: Option<WeaponData>,
 
+    /// Two-handed weapons leave the shield slot empty.
+    #[serde(default, rename = "_gearscape_two_handed")]
+    pub two_handed: bool,
+
+    /// Item IDs a ranged weapon can fire (arrows, bolts, javelins, tar).
+    /// Null in the data for most non-ranged items.
+    #[serde(default, rename = "_gearscape_ammunition")]
+    pub ammunition: Option<Vec<u32>>,
+
+    /// How the item can be obtained; merged in from data/obtainability.json.
+    #[serde(default)]
+    pub obtainability: Vec<Obtainability>,
+
+    /// Mechanic tags ("obsidian_weapon", "demonbane"); merged in from
+    /// data/tags/*.json.
+    #[serde(default)]
+    pub tags: Vec<String>,
+
     // Other fields we don't need for MVP
     #[serde(default)]
     pub members: bool,
@@ -49,6 +89,18 @@ pub struct ItemData {
     pub tradeable: bool,
 }
 
+/// Obtainability tags for items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Obtainability {
+    /// Only obtainable by trading with other players (e.g. league cosmetics).
+    TradeableOnly,
+    /// Dropped by a raid.
+    RaidDrop,
+    /// Needs a quest (or quest series) to obtain or wield.
+    QuestLocked,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EquipmentData {
     pub slot: String,
@@ -133,6 +185,11 @@ pub struct MonsterData {
     #[serde(default)]
     pub ranged_level: u16,
 
+    /// Magic attack bonus, which the twisted bow scales on when it's above
+    /// the magic level.
+    #[serde(default)]
+    pub attack_magic: i32,
+
     // Defence bonuses
     #[serde(default)]
     pub defence_stab: i32,
@@ -154,28 +211,400 @@ pub struct MonsterData {
     // Slayer info
     #[serde(default)]
     pub slayer_monster: bool,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "null_as_default")]
     pub slayer_level: u8,
 
     // Other
     #[serde(default)]
     pub combat_level: u16,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "null_as_default")]
     pub attack_speed: u8,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "null_as_default")]
     pub max_hit: u16,
     #[serde(default)]
     pub size: u8,
+    #[serde(default)]
+    pub immune_poison: bool,
+
+    /// Known data problems; merged in from data/quality.json.
+    #[serde(default)]
+    pub data_flags: Vec<DataFlag>,
+}
+
+/// Read `null` as the default, for fields the osrsbox dumps leave null (a
+/// non-slayer monster's slayer level).
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::deserialize(deserializer)?.unwrap_or_default())
+}
+
+// =============================================================================
+// Spell Data Structures (matching data/spells/*.json)
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Spellbook {
+    Standard,
+    Ancient,
+    Lunar,
+    Arceuus,
+}
+
+/// Elemental rune family of a standard spell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpellElement {
+    Air,
+    Water,
+    Earth,
+    Fire,
+}
+
+impl Spellbook {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Spellbook::Standard => "standard",
+            Spellbook::Ancient => "ancient",
+            Spellbook::Lunar => "lunar",
+            Spellbook::Arceuus => "arceuus",
+        }
+    }
+}
+
+impl SpellElement {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SpellElement::Air => "air",
+            SpellElement::Water => "water",
+            SpellElement::Earth => "earth",
+            SpellElement::Fire => "fire",
+        }
+    }
+}
+
+/// A rune that stands in for another in spell costs (data/runes.json).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuneData {
+    /// The rune it's used in place of.
+    pub replaces: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpellData {
+    pub name: String,
+    pub base_max_hit: u32,
+    pub spellbook: Spellbook,
+    /// Magic level needed to cast.
+    pub level: u8,
+    #[serde(default)]
+    pub element: Option<SpellElement>,
+    /// Rune name -> runes used per cast.
+    #[serde(default)]
+    pub runes: HashMap<String, u32>,
+    #[serde(default)]
+    pub members: bool,
+    /// Only weapons whose name contains one of these can autocast the spell
+    /// (god spells, Iban Blast). Empty: decided by spellbook.
+    #[serde(default)]
+    pub autocast_with: Vec<String>,
+}
+
+/// A weapon's special attack, from data/specs/*.json, curated with
+/// `spec-db`. Builds with `style.special_attack` use it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpecData {
+    pub name: String,
+    /// Weapons (base item IDs) with this special attack.
+    pub weapons: Vec<u32>,
+    /// Special attack energy used, in percent.
+    pub energy: u8,
+    /// Attack roll multiplier (none: unchanged).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accuracy: Option<Fraction>,
+    /// Max hit multiplier (none: unchanged).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub damage: Option<Fraction>,
+    /// Hits per special attack.
+    #[serde(default = "one_hit")]
+    pub hits: u8,
+    /// Skips the accuracy roll: every hit lands.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub always_hits: bool,
+    /// Share of the target's magic defence roll ignored by magic hits, in
+    /// percent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ignores_magic_defence_pct: Option<u8>,
+}
+
+fn one_hit() -> u8 {
+    1
+}
+
+/// Item tag marking weapons that have a special attack.
+pub const SPECIAL_ATTACK_TAG: &str = "special_attack";
+
+impl SpecData {
+    /// The spec as the engine applies it; missing multipliers are 1.
+    pub fn resolved(&self) -> SpecialAttack {
+        let multiplier = |fraction: Option<Fraction>| {
+            fraction.map_or((1, 1), |f| (i64::from(f.num), i64::from(f.den)))
+        };
+        SpecialAttack {
+            name: self.name.clone(),
+            energy: self.energy,
+            accuracy: multiplier(self.accuracy),
+            damage: multiplier(self.damage),
+            hits: self.hits,
+            always_hits: self.always_hits,
+            ignores_magic_defence_pct: self.ignores_magic_defence_pct.unwrap_or(0),
+        }
+    }
+
+    /// A blank entry for a weapon, to fill in: no energy (which `spec-db`
+    /// flags) and unchanged accuracy and damage.
+    pub fn template(weapon: &ItemData) -> Self {
+        Self {
+            name: weapon.name.clone(),
+            weapons: vec![weapon.id],
+            energy: 0,
+            accuracy: Some(Fraction { num: 1, den: 1 }),
+            damage: Some(Fraction { num: 1, den: 1 }),
+            hits: 1,
+            always_hits: false,
+            ignores_magic_defence_pct: None,
+        }
+    }
+}
+
+/// A num/den multiplier in data files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fraction {
+    pub num: i32,
+    pub den: i32,
+}
+
+/// Staff-type weapons that can't autocast anything.
+const NON_AUTOCAST_STAVES: [&str; 4] = ["crozier", "flail", "rod of ivandis", "skull sceptre"];
+
+/// Staves that can autocast Ancient Magicks (variants included by name).
+const ANCIENT_STAVES: [&str; 7] = [
+    "ancient staff",
+    "ahrim's staff",
+    "master wand",
+    "kodai wand",
+    "nightmare staff",
+    "ancient sceptre",
+    "zuriel's staff",
+];
+
+impl SpellData {
+    /// Whether `weapon` can autocast this spell. Standard spells go on most
+    /// staves and wands, Ancient Magicks need an ancient-capable staff.
+    pub fn can_autocast_with(&self, weapon: &ItemData) -> bool {
+        let staff = weapon
+            .weapon
+            .as_ref()
+            .is_some_and(|w| matches!(w.weapon_type.as_str(), "staff" | "bladed_staff"));
+        let name = weapon.name.as_str();
+        if !staff || NON_AUTOCAST_STAVES.iter().any(|s| name.contains(s)) {
+            return false;
+        }
+        if !self.autocast_with.is_empty() {
+            return self.autocast_with.iter().any(|s| name.contains(s.as_str()));
+        }
+        match self.spellbook {
+            Spellbook::Standard => true,
+            Spellbook::Ancient => ANCIENT_STAVES.iter().any(|s| name.contains(s)),
+            Spellbook::Lunar | Spellbook::Arceuus => false,
+        }
+    }
+
+    /// Ticks between casts of this spell with `weapon` wielded, autocast or
+    /// not: every 5, but a harmonised nightmare staff casts standard spells
+    /// every 4.
+    pub fn cast_ticks(&self, weapon: &ItemData) -> u8 {
+        if self.spellbook == Spellbook::Standard && weapon.name.contains(HARMONISED_STAFF) {
+            HARMONISED_CAST_TICKS
+        } else {
+            SPELL_CAST_TICKS
+        }
+    }
+}
+
+/// One data/tags/*.json file: tag -> IDs, for items and for monsters.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TagFile {
+    #[serde(default)]
+    items: HashMap<String, Vec<u32>>,
+    #[serde(default)]
+    monsters: HashMap<String, Vec<u32>>,
+}
+
+/// data/ammo.json: which bolts each crossbow can fire, and which bolts are
+/// enchanted. Bolts and crossbows the file leaves out aren't gated by tier.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AmmoRules {
+    /// Crossbow item ID -> highest bolt tier it fires (the Ranged level of
+    /// its metal: 61 for rune, 64 for dragon).
+    #[serde(default)]
+    pub crossbows: HashMap<u32, u8>,
+    /// Bolt item ID -> its tier and, for enchanted (e) bolts, their effect.
+    #[serde(default)]
+    pub bolts: HashMap<u32, BoltData>,
+    /// Enchanted bolt effect -> multiplier on the effect's damage when it's
+    /// triggered by the Zaryte crossbow's special attack.
+    #[serde(default)]
+    pub zaryte_spec: HashMap<String, Fraction>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoltData {
+    pub tier: u8,
+    /// Effect of enchanted bolts (`ruby`, `diamond`, ...); none for regular
+    /// bolts.
+    #[serde(default)]
+    pub enchanted: Option<String>,
+}
+
+impl AmmoRules {
+    /// Whether the crossbow's tier allows the bolts.
+    pub fn tier_allows(&self, weapon_id: u32, ammo_id: u32) -> bool {
+        match (self.crossbows.get(&weapon_id), self.bolts.get(&ammo_id)) {
+            (Some(&max_tier), Some(bolt)) => bolt.tier <= max_tier,
+            _ => true,
+        }
+    }
+
+    /// Effect of the enchanted bolts among `equipped`, if the weapon is a
+    /// crossbow that fires them.
+    pub fn bolt_effect(&self, weapon_id: u32, equipped: &[u32]) -> Option<&str> {
+        if !self.crossbows.contains_key(&weapon_id) {
+            return None;
+        }
+        equipped
+            .iter()
+            .find_map(|id| self.bolts.get(id)?.enchanted.as_deref())
+    }
+
+    /// Damage multiplier on a bolt effect triggered by the Zaryte crossbow's
+    /// special attack (1/1 for effects it doesn't enhance).
+    pub fn zaryte_spec_damage(&self, effect: &str) -> Fraction {
+        self.zaryte_spec
+            .get(effect)
+            .copied()
+            .unwrap_or(Fraction { num: 1, den: 1 })
+    }
+}
+
+/// data/ranges.json: how many tiles away each weapon attacks from, by weapon
+/// type with per-weapon exceptions. Weapons of types the file leaves out
+/// (most melee weapons) reach 1 tile.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AttackRanges {
+    /// Weapon type (`bow`, `polearm`, ...) -> range in tiles.
+    #[serde(default)]
+    pub weapon_types: HashMap<String, u8>,
+    /// Weapon item ID -> range in tiles, over its type's.
+    #[serde(default)]
+    pub weapons: HashMap<u32, u8>,
+}
+
+impl AttackRanges {
+    /// Tiles the weapon reaches on any stance but longrange.
+    pub fn range_of(&self, weapon: &ItemData) -> u8 {
+        if let Some(&range) = self.weapons.get(&weapon.id) {
+            return range;
+        }
+        weapon
+            .weapon
+            .as_ref()
+            .and_then(|w| self.weapon_types.get(&w.weapon_type))
+            .copied()
+            .unwrap_or(MELEE_RANGE)
+    }
+}
+
+/// data/quality.json: monster ID -> known problems with its data.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QualityFile {
+    #[serde(default)]
+    monsters: HashMap<u32, Vec<DataFlag>>,
 }
 
 // =============================================================================
 // Data Store
 // =============================================================================
 
+/// Single-file dumps in the osrsbox distribution format: one JSON object
+/// mapping ID strings to entries, loaded alongside the per-entity folders.
+pub const ITEMS_DUMP: &str = "items-complete.json";
+pub const MONSTERS_DUMP: &str = "monsters-complete.json";
+
+/// Read a single-file dump into `into`, keyed by each entry's `id`. Entries
+/// are parsed one at a time from the file and inserted as they're read, so
+/// neither the dump's text nor a second map of the whole dump is held in
+/// memory. The dump's own ID keys are skipped.
+fn read_dump<T: DeserializeOwned>(
+    path: &Path,
+    into: &mut HashMap<u32, T>,
+    id: fn(&T) -> u32,
+) -> Result<(), DataError> {
+    let reader = BufReader::new(fs::File::open(path)?);
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    deserializer.deserialize_map(DumpVisitor { into, id })?;
+    deserializer.end()?;
+    Ok(())
+}
+
+/// Inserts each entry of a dump's map as it's deserialized.
+struct DumpVisitor<'a, T> {
+    into: &'a mut HashMap<u32, T>,
+    id: fn(&T) -> u32,
+}
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for DumpVisitor<'_, T> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an object of ID -> entry")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some((IgnoredAny, entry)) = map.next_entry::<IgnoredAny, T>()? {
+            self.into.insert((self.id)(&entry), entry);
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct DataStore {
     pub items: HashMap<u32, ItemData>,
     pub monsters: HashMap<u32, MonsterData>,
+    /// Cosmetic/ornament variant ID -> functional base item ID.
+    pub variants: HashMap<u32, u32>,
+    /// Combat spells keyed by `spell_key` of their name.
+    pub spells: HashMap<String, SpellData>,
+    /// Special attacks, in file order.
+    pub specs: Vec<SpecData>,
+    /// Crossbow and bolt tiers (data/ammo.json).
+    pub ammo: AmmoRules,
+    /// Substitute runes by name (data/runes.json).
+    pub runes: HashMap<String, RuneData>,
+    /// Weapon attack ranges (data/ranges.json).
+    pub ranges: AttackRanges,
 }
 
 impl DataStore {
@@ -189,11 +618,250 @@ impl DataStore {
         let mut store = Self::new();
         store.load_items(data_dir)?;
         store.load_monsters(data_dir)?;
+        store.load_obtainability(data_dir)?;
+        store.load_variants(data_dir)?;
+        store.load_tags(data_dir)?;
+        store.load_quality(data_dir)?;
+        store.load_ammo(data_dir)?;
+        store.load_ranges(data_dir)?;
+        store.load_spells(data_dir)?;
+        store.load_runes(data_dir)?;
+        store.load_specs(data_dir)?;
         Ok(store)
     }
 
-    /// Load all items from data/items/*.json
+    /// Load all special attacks from data/specs/*.json (one array per file),
+    /// in file name order.
+    fn load_specs(&mut self, data_dir: &Path) -> Result<(), DataError> {
+        let specs_dir = data_dir.join("specs");
+        if !specs_dir.exists() {
+            return Ok(());
+        }
+
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(specs_dir)? {
+            let path = entry?.path();
+            if path.extension().map(|e| e == "json").unwrap_or(false) {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        for path in paths {
+            let content = fs::read_to_string(&path)?;
+            let specs: Vec<SpecData> = serde_json::from_str(&content)?;
+            self.specs.extend(specs);
+        }
+        Ok(())
+    }
+
+    /// Merge data/tags/*.json into the loaded items and monsters. Each file
+    /// maps tags to IDs under `items` and `monsters`; monster tags join the
+    /// monster's attributes. IDs that aren't loaded are ignored.
+    fn load_tags(&mut self, data_dir: &Path) -> Result<(), DataError> {
+        let tags_dir = data_dir.join("tags");
+        if !tags_dir.exists() {
+            return Ok(());
+        }
+
+        for entry in fs::read_dir(tags_dir)? {
+            let path = entry?.path();
+            if path.extension().map(|e| e == "json").unwrap_or(false) {
+                let content = fs::read_to_string(&path)?;
+                let file: TagFile = serde_json::from_str(&content)?;
+                for (tag, ids) in file.items {
+                    for id in ids {
+                        if let Some(item) = self.items.get_mut(&id) {
+                            if !item.tags.contains(&tag) {
+                                item.tags.push(tag.clone());
+                            }
+                        }
+                    }
+                }
+                for (tag, ids) in file.monsters {
+                    for id in ids {
+                        if let Some(monster) = self.monsters.get_mut(&id) {
+                            if !monster.attributes.contains(&tag) {
+                                monster.attributes.push(tag.clone());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        for item in self.items.values_mut() {
+            item.tags.sort();
+        }
+        Ok(())
+    }
+
+    /// Load all spells from data/spells/*.json (one array per spellbook).
+    fn load_spells(&mut self, data_dir: &Path) -> Result<(), DataError> {
+        let spells_dir = data_dir.join("spells");
+        if !spells_dir.exists() {
+            return Ok(());
+        }
+
+        for entry in fs::read_dir(spells_dir)? {
+            let path = entry?.path();
+            if path.extension().map(|e| e == "json").unwrap_or(false) {
+                let content = fs::read_to_string(&path)?;
+                let spells: Vec<SpellData> = serde_json::from_str(&content)?;
+                for spell in spells {
+                    self.spells.insert(spell_key(&spell.name), spell);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Load data/runes.json (substitute rune -> the rune it replaces), if
+    /// present.
+    fn load_runes(&mut self, data_dir: &Path) -> Result<(), DataError> {
+        let path = data_dir.join("runes.json");
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(&path)?;
+        self.runes = serde_json::from_str(&content)?;
+        Ok(())
+    }
+
+    /// Runes one cast of `spell` uses, with each of `substitutes` swapped in
+    /// for the rune it replaces.
+    pub fn runes_per_cast(
+        &self,
+        spell: &SpellData,
+        substitutes: &[String],
+    ) -> Result<BTreeMap<String, u32>, DataError> {
+        let mut runes: BTreeMap<String, u32> = spell
+            .runes
+            .iter()
+            .map(|(rune, &count)| (rune.clone(), count))
+            .collect();
+        for substitute in substitutes {
+            let data = self
+                .runes
+                .get(substitute)
+                .ok_or_else(|| DataError::UnknownRune(substitute.clone()))?;
+            if let Some(count) = runes.remove(&data.replaces) {
+                *runes.entry(substitute.clone()).or_default() += count;
+            }
+        }
+        Ok(runes)
+    }
+
+    /// Load data/variants.json (variant ID -> base item ID). The file is
+    /// optional; entries whose base item isn't loaded are dropped so the
+    /// variant keeps resolving to itself.
+    fn load_variants(&mut self, data_dir: &Path) -> Result<(), DataError> {
+        let path = data_dir.join("variants.json");
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(&path)?;
+        let variants: HashMap<u32, u32> = serde_json::from_str(&content)?;
+        self.variants = variants
+            .into_iter()
+            .filter(|(_, base)| self.items.contains_key(base))
+            .collect();
+        Ok(())
+    }
+
+    /// The functional item behind a cosmetic variant (itself otherwise).
+    /// Merge data/quality.json (monster ID -> data flags) into the loaded
+    /// monsters, most certain first. The file is optional; IDs that aren't
+    /// loaded are ignored.
+    fn load_quality(&mut self, data_dir: &Path) -> Result<(), DataError> {
+        let path = data_dir.join("quality.json");
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(&path)?;
+        let file: QualityFile = serde_json::from_str(&content)?;
+        for (id, flags) in file.monsters {
+            if let Some(monster) = self.monsters.get_mut(&id) {
+                monster.data_flags.extend(flags);
+                monster
+                    .data_flags
+                    .sort_by_key(|flag| std::cmp::Reverse(flag.confidence));
+            }
+        }
+        Ok(())
+    }
+
+    /// Load data/ammo.json, if present.
+    fn load_ammo(&mut self, data_dir: &Path) -> Result<(), DataError> {
+        let path = data_dir.join("ammo.json");
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(&path)?;
+        self.ammo = serde_json::from_str(&content)?;
+        Ok(())
+    }
+
+    /// Load data/ranges.json, if present.
+    fn load_ranges(&mut self, data_dir: &Path) -> Result<(), DataError> {
+        let path = data_dir.join("ranges.json");
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(&path)?;
+        self.ranges = serde_json::from_str(&content)?;
+        Ok(())
+    }
+
+    /// Whether the weapon fires the ammo from its ammo slot: the weapon lists
+    /// it as ammunition and, for crossbows, the bolts are of its tier or
+    /// lower. False for weapons that don't use the slot.
+    pub fn fires(&self, weapon: &ItemData, ammo_id: u32) -> bool {
+        let id = self.canonical_item_id(ammo_id);
+        weapon
+            .ammo_slot_ammunition()
+            .is_some_and(|ammunition| ammunition.contains(&id))
+            && self.ammo.tier_allows(weapon.id, id)
+    }
+
+    /// Stats and effects are resolved against this ID.
+    pub fn canonical_item_id(&self, id: u32) -> u32 {
+        self.variants.get(&id).copied().unwrap_or(id)
+    }
+
+    /// Merge data/obtainability.json (tag -> item IDs) into the loaded items.
+    /// The file is optional; IDs that aren't loaded are ignored.
+    fn load_obtainability(&mut self, data_dir: &Path) -> Result<(), DataError> {
+        let path = data_dir.join("obtainability.json");
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(&path)?;
+        let tags: HashMap<Obtainability, Vec<u32>> = serde_json::from_str(&content)?;
+        for (tag, ids) in tags {
+            for id in ids {
+                if let Some(item) = self.items.get_mut(&id) {
+                    if !item.obtainability.contains(&tag) {
+                        item.obtainability.push(tag);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Load all items from data/items-complete.json, if present, then
+    /// data/items/*.json (which win over the dump).
     fn load_items(&mut self, data_dir: &Path) -> Result<(), DataError> {
+        let dump = data_dir.join(ITEMS_DUMP);
+        if dump.exists() {
+            read_dump(&dump, &mut self.items, |item: &ItemData| item.id)?;
+        }
+
         let items_dir = data_dir.join("items");
         if !items_dir.exists() {
             return Ok(());
@@ -216,8 +884,16 @@ impl DataStore {
         Ok(())
     }
 
-    /// Load all monsters from data/monsters/*.json
+    /// Load all monsters from data/monsters-complete.json, if present, then
+    /// data/monsters/*.json (which win over the dump).
     fn load_monsters(&mut self, data_dir: &Path) -> Result<(), DataError> {
+        let dump = data_dir.join(MONSTERS_DUMP);
+        if dump.exists() {
+            read_dump(&dump, &mut self.monsters, |monster: &MonsterData| {
+                monster.id
+            })?;
+        }
+
         let monsters_dir = data_dir.join("monsters");
         if !monsters_dir.exists() {
             return Ok(());
@@ -249,6 +925,104 @@ impl DataStore {
     pub fn get_monster(&self, id: u32) -> Result<&MonsterData, DataError> {
         self.monsters.get(&id).ok_or(DataError::MonsterNotFound(id))
     }
+
+    /// Weapons tagged `special_attack` with no spec entry, by ID.
+    pub fn missing_specs(&self) -> Vec<&ItemData> {
+        let mut missing: Vec<&ItemData> = self
+            .items
+            .values()
+            .filter(|item| item.tags.iter().any(|t| t == SPECIAL_ATTACK_TAG))
+            .filter(|item| self.get_spec(item.id).is_none())
+            .collect();
+        missing.sort_by_key(|item| item.id);
+        missing
+    }
+
+    /// Problems with the spec entries: bad energy or multipliers, and
+    /// weapons that are unknown, not tagged `special_attack`, or listed twice.
+    pub fn spec_problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut seen = HashMap::new();
+        for spec in &self.specs {
+            if spec.energy == 0 || spec.energy > 100 {
+                problems.push(format!(
+                    "{}: energy {} is not 1-100",
+                    spec.name, spec.energy
+                ));
+            }
+            for (what, fraction) in [("accuracy", spec.accuracy), ("damage", spec.damage)] {
+                if fraction.is_some_and(|f| f.den <= 0 || f.num < 0) {
+                    problems.push(format!("{}: {what} is not a positive fraction", spec.name));
+                }
+            }
+            if spec.ignores_magic_defence_pct.is_some_and(|pct| pct > 100) {
+                problems.push(format!(
+                    "{}: ignores more than 100% of magic defence",
+                    spec.name
+                ));
+            }
+            if spec.hits == 0 || spec.weapons.is_empty() {
+                problems.push(format!("{}: no hits or no weapons", spec.name));
+            }
+            for &id in &spec.weapons {
+                match self.items.get(&id) {
+                    None => problems.push(format!("{}: unknown item {id}", spec.name)),
+                    Some(item) if !item.tags.iter().any(|t| t == SPECIAL_ATTACK_TAG) => problems
+                        .push(format!(
+                            "{}: {} ({id}) isn't tagged {SPECIAL_ATTACK_TAG}",
+                            spec.name, item.name
+                        )),
+                    Some(_) => {}
+                }
+                if let Some(other) = seen.insert(id, &spec.name) {
+                    problems.push(format!("{}: item {id} is also in {other}", spec.name));
+                }
+            }
+        }
+        problems
+    }
+
+    /// Problems with the monster data: monsters without hitpoints, and
+    /// target attributes that effects in `registry` check but no monster has,
+    /// so those effects can never apply.
+    pub fn monster_problems(&self, registry: &EffectRegistry) -> Vec<String> {
+        let mut problems: Vec<String> = self
+            .monsters
+            .values()
+            .filter(|m| m.hitpoints == 0)
+            .map(|m| format!("{} ({}) has 0 hitpoints", m.id, m.name))
+            .collect();
+        problems.sort();
+        let present: HashSet<&str> = self
+            .monsters
+            .values()
+            .flat_map(|m| m.attributes.iter().map(String::as_str))
+            .collect();
+        for effect in &registry.effects {
+            for attribute in &effect.condition.target_attributes {
+                if !present.contains(attribute.as_str()) {
+                    problems.push(format!(
+                        "attribute {attribute:?} (checked by effect {}) is on no monster",
+                        effect.id
+                    ));
+                }
+            }
+        }
+        problems
+    }
+
+    /// The special attack of a weapon (or of the base item of a variant).
+    pub fn get_spec(&self, weapon_id: u32) -> Option<&SpecData> {
+        let id = self.canonical_item_id(weapon_id);
+        self.specs.iter().find(|spec| spec.weapons.contains(&id))
+    }
+
+    /// Get a spell by name ("fire_surge" or "fire surge").
+    pub fn get_spell(&self, name: &str) -> Result<&SpellData, DataError> {
+        self.spells
+            .get(&spell_key(name))
+            .ok_or_else(|| DataError::UnknownSpell(name.to_string()))
+    }
 }
 
 // =============================================================================
@@ -279,6 +1053,70 @@ impl ItemData {
         }
     }
 
+    /// Whether weapon poison can be applied: daggers, spears and hastae for
+    /// melee, darts and knives for ranged.
+    pub fn poisonable(&self) -> bool {
+        let name = self.name.to_lowercase();
+        self.equipable_weapon
+            && ["dagger", "spear", "hasta", "dart", "knife"]
+                .iter()
+                .any(|kind| name.contains(kind))
+    }
+
+    /// The slot the item is worn in (`weapon` for weapons).
+    pub fn slot(&self) -> Option<&str> {
+        self.equipment.as_ref().map(|e| e.slot.as_str())
+    }
+
+    /// Ammo a weapon fires from the ammo slot. `None` for weapons that
+    /// don't use the slot, including blowpipes: their darts are loaded into
+    /// the weapon, even though the data lists them as ammunition.
+    pub fn ammo_slot_ammunition(&self) -> Option<&[u32]> {
+        let ammunition = self.ammunition.as_deref()?;
+        if ammunition.is_empty() || self.weapon.as_ref()?.weapon_type == "thrown" {
+            return None;
+        }
+        Some(ammunition)
+    }
+
+    /// Ammo loaded into the weapon instead of the ammo slot: a blowpipe's
+    /// darts. `None` for every other weapon.
+    pub fn charged_ammunition(&self) -> Option<&[u32]> {
+        let ammunition = self.ammunition.as_deref()?;
+        if ammunition.is_empty() || self.weapon.as_ref()?.weapon_type != "thrown" {
+            return None;
+        }
+        Some(ammunition)
+    }
+
+    /// Whether an ironman can get this item (anything not tradeable-only).
+    pub fn ironman_obtainable(&self) -> bool {
+        !self.obtainability.contains(&Obtainability::TradeableOnly)
+    }
+
+    /// Skill requirements the player doesn't meet, as (skill, required level).
+    /// Skills the player input doesn't track (e.g. slayer) are assumed met.
+    pub fn unmet_requirements(&self, skills: &SkillsInput) -> Vec<(String, u32)> {
+        let Some(reqs) = self
+            .equipment
+            .as_ref()
+            .and_then(|e| e.requirements.as_ref())
+        else {
+            return vec![];
+        };
+        let mut unmet: Vec<(String, u32)> = reqs
+            .iter()
+            .filter(|(skill, &required)| {
+                skills
+                    .level(skill)
+                    .is_some_and(|level| (level as u32) < required)
+            })
+            .map(|(skill, &required)| (skill.clone(), required))
+            .collect();
+        unmet.sort();
+        unmet
+    }
+
     /// Get attack speed (only for weapons).
     pub fn get_attack_speed(&self) -> u8 {
         self.weapon.as_ref().map(|w| w.attack_speed).unwrap_or(4)
@@ -292,6 +1130,8 @@ impl MonsterData {
             name: self.name.clone(),
             hitpoints: self.hitpoints,
             defence_level: self.defence_level,
+            magic_level: self.magic_level,
+            magic_attack_bonus: self.attack_magic,
             defence_bonuses: DefenceBonuses {
                 stab: self.defence_stab,
                 slash: self.defence_slash,
@@ -300,6 +1140,12 @@ impl MonsterData {
                 ranged: self.defence_ranged,
             },
             attributes: self.attributes.clone(),
+            immune_poison: self.immune_poison,
+            // Size 0 means the data didn't say
+            size: self.size.max(1),
+            max_hit: self.max_hit,
+            attack_speed: self.attack_speed,
+            data_flags: self.data_flags.clone(),
         }
     }
 }
@@ -361,7 +1207,9 @@ impl<'a> Resolver<'a> {
         Self { store }
     }
 
-    /// Resolve equipment IDs into aggregated bonuses.
+    /// Resolve equipment IDs into aggregated bonuses. Ammo only adds its
+    /// ranged strength when the weapon fires it; a weapon that fires from the
+    /// ammo slot with ammo it can't fire is an `InvalidLoadout`.
     pub fn resolve_equipment(
         &self,
         equipment_ids: &[Option<u32>],
@@ -371,31 +1219,437 @@ impl<'a> Resolver<'a> {
     ) -> Result<BuildResolved, DataError> {
         let mut bonuses = EquipmentBonuses::default();
 
+        // Variants (ornament kits, recolours) resolve to their base item
+        let canonical = |id: u32| {
+            self.store.get_item(id)?;
+            Ok::<u32, DataError>(self.store.canonical_item_id(id))
+        };
+
         // Add weapon bonuses
-        let weapon = self.store.get_item(weapon_id)?;
+        let weapon = self.store.get_item(canonical(weapon_id)?)?;
         bonuses.add(&weapon.get_bonuses());
         let attack_speed = weapon.get_attack_speed();
 
         // Add other equipment bonuses
+        let mut equipped = vec![weapon.id];
+        let mut equipped_tags = weapon.tags.clone();
         for id in equipment_ids.iter().flatten() {
-            let item = self.store.get_item(*id)?;
+            let item = self.store.get_item(canonical(*id)?)?;
             bonuses.add(&item.get_bonuses());
+            if item.slot() == Some("ammo") && !self.store.fires(weapon, item.id) {
+                if weapon.ammo_slot_ammunition().is_some() {
+                    return Err(DataError::InvalidLoadout(vec![format!(
+                        "{} can't be fired from {}",
+                        item.name, weapon.name
+                    )]));
+                }
+                // Bowfa, blowpipes and non-ranged weapons leave the ammo unfired
+                bonuses.ranged_strength -= item.get_bonuses().ranged_strength;
+            }
+            equipped.push(item.id);
+            equipped_tags.extend(item.tags.iter().cloned());
         }
+        equipped_tags.sort();
+        equipped_tags.dedup();
 
         Ok(BuildResolved {
+            weapon_id: weapon.id,
+            equipped_tags,
             bonuses,
             attack_speed,
-            combat: crate::model::Combat::Melee,
+            combat: attack_type.combat(),
             attack_type,
             stance,
+            spell: None,
+            poison: None,
+            powered_staff: weapon
+                .weapon
+                .as_ref()
+                .filter(|w| w.weapon_type == "powered_staff")
+                .and_then(|_| PoweredStaff::from_item_name(&weapon.name)),
+            salamander: weapon
+                .weapon
+                .as_ref()
+                .filter(|w| w.weapon_type == "salamander")
+                .and_then(|_| Salamander::from_item_name(&weapon.name)),
+            // An uncharged scythe only hits once
+            scythe: weapon
+                .weapon
+                .as_ref()
+                .is_some_and(|w| w.weapon_type == "scythe")
+                && !weapon.name.to_lowercase().contains("(uncharged)"),
+            thrown: weapon
+                .weapon
+                .as_ref()
+                .is_some_and(|w| matches!(w.weapon_type.as_str(), "thrown" | "chinchompa")),
+            chinchompa: weapon
+                .weapon
+                .as_ref()
+                .is_some_and(|w| w.weapon_type == "chinchompa"),
+            weapon_range: self.store.ranges.range_of(weapon),
+            hit_distribution: if weapon.name.to_lowercase().starts_with("osmumten's fang") {
+                HitDistribution::Fang
+            } else {
+                HitDistribution::Standard
+            },
+            weapon_strength: WeaponStrength::from_item_name(&weapon.name),
+            melee_scaled_ranged: weapon.name.to_lowercase().starts_with("eclipse atlatl"),
+            weapon_charged: true,
+            bolt_effect: self
+                .store
+                .ammo
+                .bolt_effect(weapon.id, &equipped)
+                .map(str::to_string),
+            special_attack: None,
+            equipped,
         })
     }
 
+    /// Resolve a full build input (equipment + style).
+    pub fn resolve_build(&self, build: &BuildInput) -> Result<BuildResolved, DataError> {
+        // Every violation at once, before resolving stops at the first
+        let violations = self.slot_violations(&build.equipment)?;
+        if !violations.is_empty() {
+            return Err(DataError::InvalidLoadout(violations));
+        }
+        let mut resolved = self.resolve_equipment(
+            &build.equipment.armour_ids(),
+            build.equipment.weapon,
+            build.style.stance,
+            build.style.attack_type,
+        )?;
+        resolved.combat = build.style.combat;
+        resolved.weapon_charged = !build.weapon_config.uncharged;
+        // Darts add their ranged strength to the blowpipe, not their
+        // attack bonuses
+        if let Some(id) = build.equipment.charged_ammo {
+            let dart = self.store.get_item(self.store.canonical_item_id(id))?;
+            resolved.bonuses.ranged_strength += dart.get_bonuses().ranged_strength;
+            resolved.equipped.push(dart.id);
+        }
+        if let Some(poison) = build.weapon_config.poison {
+            let weapon = self.store.get_item(build.equipment.weapon)?;
+            if !weapon.poisonable() {
+                return Err(DataError::NotPoisonable(weapon.name.clone()));
+            }
+            resolved.poison = Some(poison);
+        }
+        if build.style.special_attack {
+            let spec = self.store.get_spec(resolved.weapon_id).ok_or_else(|| {
+                let name = self
+                    .store
+                    .get_item(resolved.weapon_id)
+                    .map(|w| w.name.clone());
+                DataError::NoSpecialAttack(name.unwrap_or_default())
+            })?;
+            resolved.special_attack = Some(spec.resolved());
+        }
+        // Powered staves cast their own spell at the weapon's speed, and a
+        // salamander's Blaze isn't a spell at all
+        if resolved.combat == Combat::Magic && resolved.salamander.is_some() {
+            if let Some(spell) = &build.style.spell {
+                let weapon = self.store.get_item(resolved.weapon_id)?;
+                return Err(DataError::CannotAutocast {
+                    spell: spell.clone(),
+                    weapon: weapon.name.clone(),
+                });
+            }
+        } else if resolved.combat == Combat::Magic
+            && (resolved.powered_staff.is_none() || build.style.manual_cast)
+        {
+            let name = build
+                .style
+                .spell
+                .as_deref()
+                .ok_or(DataError::SpellRequired)?;
+            let spell = self.store.get_spell(name)?;
+            let weapon = self
+                .store
+                .get_item(self.store.canonical_item_id(resolved.weapon_id))?;
+            if !build.style.manual_cast && !spell.can_autocast_with(weapon) {
+                return Err(DataError::CannotAutocast {
+                    spell: spell.name.clone(),
+                    weapon: weapon.name.clone(),
+                });
+            }
+            resolved.spell = Some(Spell {
+                name: name.to_string(),
+                base_max_hit: spell.base_max_hit,
+                element: spell.element.map(|e| e.as_str().to_string()),
+                spellbook: Some(spell.spellbook.as_str().to_string()),
+                runes: self.store.runes_per_cast(spell, &build.style.runes)?,
+            });
+            // Casts follow the spell, not the weapon's listed speed, and a
+            // powered staff's own spell is set aside
+            resolved.attack_speed = spell.cast_ticks(weapon);
+            resolved.weapon_range = SPELL_RANGE;
+            resolved.powered_staff = None;
+        }
+        Ok(resolved)
+    }
+
+    /// Items that can't be worn together, described for an error message:
+    /// items in the wrong slot, a shield with a two-handed weapon, and a
+    /// ranged weapon without ammo it can fire, and charged ammo the weapon
+    /// can't be loaded with. Empty if the loadout is valid.
+    pub fn slot_violations(&self, equipment: &EquipmentInput) -> Result<Vec<String>, DataError> {
+        let mut violations = Vec::new();
+        let weapon = self.store.get_item(equipment.weapon)?;
+        if weapon.slot() != Some("weapon") {
+            violations.push(format!("{} is not a weapon", weapon.name));
+        }
+        for (slot, id) in ARMOUR_SLOTS.iter().zip(equipment.armour_ids()) {
+            let Some(id) = id else { continue };
+            let item = self.store.get_item(id)?;
+            if item.slot() != Some(slot) {
+                violations.push(format!("{} doesn't go in the {} slot", item.name, slot));
+            }
+        }
+        if weapon.two_handed {
+            if let Some(id) = equipment.shield {
+                let shield = self.store.get_item(id)?;
+                violations.push(format!(
+                    "{} is two-handed and can't be used with {}",
+                    weapon.name, shield.name
+                ));
+            }
+        }
+        if weapon.ammo_slot_ammunition().is_some() {
+            match equipment.ammo {
+                Some(id) if self.store.fires(weapon, id) => {}
+                Some(id) => violations.push(format!(
+                    "{} can't be fired from {}",
+                    self.store.get_item(id)?.name,
+                    weapon.name
+                )),
+                None => violations.push(format!("{} needs ammo", weapon.name)),
+            }
+        }
+        if let Some(id) = equipment.charged_ammo {
+            let loadable = weapon
+                .charged_ammunition()
+                .is_some_and(|ammunition| ammunition.contains(&self.store.canonical_item_id(id)));
+            if !loadable {
+                violations.push(format!(
+                    "{} can't be loaded into {}",
+                    self.store.get_item(id)?.name,
+                    weapon.name
+                ));
+            }
+        }
+        Ok(violations)
+    }
+
+    /// Members items and spells in a build, described for an error message.
+    /// Empty if the build can be used on a free-to-play world.
+    pub fn build_f2p_violations(&self, build: &BuildInput) -> Result<Vec<String>, DataError> {
+        let mut violations = Vec::new();
+        for id in build.equipment.item_ids() {
+            let item = self.store.get_item(id)?;
+            if item.members {
+                violations.push(format!("members item: {} ({})", item.name, item.id));
+            }
+        }
+        if let Some(ref spell) = build.style.spell {
+            if build.style.combat == Combat::Magic && self.store.get_spell(spell)?.members {
+                violations.push(format!("members spell: {spell}"));
+            }
+        }
+        Ok(violations)
+    }
+
     /// Resolve a monster ID into target stats.
     pub fn resolve_monster(&self, monster_id: u32) -> Result<TargetResolved, DataError> {
         let monster = self.store.get_monster(monster_id)?;
         Ok(monster.to_target_resolved())
     }
+
+    /// Resolve a target input (monster reference with overrides, or custom stats).
+    pub fn resolve_target(&self, target: &TargetInput) -> Result<TargetResolved, DataError> {
+        match target {
+            TargetInput::ById(by_id) => {
+                let mut resolved = self.resolve_monster(by_id.monster_id)?;
+                if let Some(ref overrides) = by_id.overrides {
+                    apply_target_overrides(&mut resolved, overrides);
+                }
+                Ok(resolved)
+            }
+            TargetInput::Custom(custom) => Ok(TargetResolved {
+                name: custom.custom.name.clone(),
+                hitpoints: custom.custom.levels.hitpoints,
+                defence_level: custom.custom.levels.defence,
+                magic_level: custom.custom.levels.magic.unwrap_or(1),
+                magic_attack_bonus: custom.custom.magic_attack_bonus,
+                defence_bonuses: clamp_defence_bonuses(custom.custom.defence_bonuses),
+                attributes: custom.custom.attributes.clone(),
+                immune_poison: false,
+                size: custom.custom.size.unwrap_or(1).max(1),
+                max_hit: custom.custom.max_hit.unwrap_or(0),
+                attack_speed: custom
+                    .custom
+                    .attack_speed
+                    .unwrap_or(DEFAULT_TARGET_ATTACK_SPEED),
+                data_flags: vec![],
+            }),
+        }
+    }
+
+    /// Resolve a target as the player meets it: data stats and overrides,
+    /// then the player's pre-fight actions.
+    pub fn resolve_target_for(
+        &self,
+        target: &TargetInput,
+        player: &PlayerInput,
+    ) -> Result<TargetResolved, DataError> {
+        let mut resolved = self.resolve_target(target)?;
+        apply_pre_fight_target(&mut resolved, &player.pre_fight);
+        Ok(resolved)
+    }
+
+    /// List the (attack type, stance) pairs a weapon offers, in the order the
+    /// weapon's style menu shows them. Stances the engine can't model
+    /// (e.g. autocast) are skipped.
+    pub fn weapon_styles(&self, weapon_id: u32) -> Result<Vec<(AttackType, Stance)>, DataError> {
+        let weapon = self.store.get_item(weapon_id)?;
+        let styles = weapon
+            .weapon
+            .as_ref()
+            .map(|w| {
+                w.stances
+                    .iter()
+                    .filter_map(|s| {
+                        Some((
+                            AttackType::from_data_name(&s.attack_type)?,
+                            Stance::from_data_name(&s.attack_style)?,
+                        ))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(styles)
+    }
+
+    /// Evaluate every style the weapon offers within the build's combat style
+    /// and return them ordered by DPS, best first. With a `distance`, styles
+    /// that can't reach the target that far away are left out.
+    pub fn rank_styles(
+        &self,
+        player: &PlayerState,
+        build: &BuildResolved,
+        target: &TargetResolved,
+        distance: Option<u8>,
+    ) -> Result<Vec<RankedStyle>, DataError> {
+        self.rank_styles_with(player, build, target, distance, |ctx| {
+            evaluate_with(ctx, default_registry())
+        })
+    }
+
+    /// `rank_styles` with each style evaluated by `evaluate`: against a
+    /// registry of its own, or through an `EvalCache`.
+    pub fn rank_styles_with(
+        &self,
+        player: &PlayerState,
+        build: &BuildResolved,
+        target: &TargetResolved,
+        distance: Option<u8>,
+        mut evaluate: impl FnMut(&EvalContext) -> EvalResult,
+    ) -> Result<Vec<RankedStyle>, DataError> {
+        let mut ranked: Vec<RankedStyle> = self
+            .weapon_styles(build.weapon_id)?
+            .into_iter()
+            .filter(|(attack_type, _)| attack_type.combat() == build.combat)
+            .filter_map(|(attack_type, stance)| {
+                let candidate = BuildResolved {
+                    attack_type,
+                    stance,
+                    ..build.clone()
+                };
+                if distance.is_some_and(|distance| distance > candidate.attack_range()) {
+                    return None;
+                }
+                let result = evaluate(&EvalContext {
+                    player,
+                    build: &candidate,
+                    target,
+                    ordering: None,
+                    adjacent_targets: 0,
+                    distance,
+                });
+                Some(RankedStyle {
+                    attack_type,
+                    stance,
+                    dps: result.dps,
+                    max_hit: result.max_hit,
+                    accuracy: result.accuracy,
+                })
+            })
+            .collect();
+
+        // Stable sort keeps the weapon's menu order among ties
+        ranked.sort_by(|a, b| b.dps.total_cmp(&a.dps));
+        Ok(ranked)
+    }
+}
+
+/// Apply user overrides on top of a monster's data stats.
+pub fn apply_target_overrides(target: &mut TargetResolved, overrides: &TargetOverrides) {
+    if let Some(def_level) = overrides.defence_level {
+        target.defence_level = clamp_target_level(def_level);
+    }
+    if let Some(magic_level) = overrides.magic_level {
+        target.magic_level = clamp_target_level(magic_level);
+    }
+    if let Some(hitpoints) = overrides.hitpoints_level {
+        target.hitpoints = clamp_target_level(hitpoints);
+    }
+    if let Some(ref def_bonuses) = overrides.defence_bonuses {
+        let bonuses = &mut target.defence_bonuses;
+        for (bonus, value) in [
+            (&mut bonuses.stab, def_bonuses.stab),
+            (&mut bonuses.slash, def_bonuses.slash),
+            (&mut bonuses.crush, def_bonuses.crush),
+            (&mut bonuses.magic, def_bonuses.magic),
+            (&mut bonuses.ranged, def_bonuses.ranged),
+        ] {
+            if let Some(v) = value {
+                *bonus = clamp_defence_bonus(v);
+            }
+        }
+    }
+    for attr in &overrides.attributes_add {
+        if !target.attributes.contains(attr) {
+            target.attributes.push(attr.clone());
+        }
+    }
+    for attr in &overrides.attributes_remove {
+        target.attributes.retain(|a| a != attr);
+    }
+}
+
+/// Clamp a target level to what a monster can have: a drained stat stops at
+/// 0, and the game stores levels in 16 bits.
+pub fn clamp_target_level(level: i64) -> u16 {
+    level.clamp(0, i64::from(u16::MAX)) as u16
+}
+
+/// Clamp a target defence bonus. Negative bonuses are real (some monsters
+/// have them), but anything below `MIN_DEFENCE_BONUS` gives the same zero
+/// defence roll, so it is raised to that; the top end only guards the i32
+/// field.
+pub fn clamp_defence_bonus(bonus: i64) -> i32 {
+    bonus.clamp(i64::from(MIN_DEFENCE_BONUS), i64::from(i32::MAX)) as i32
+}
+
+/// `clamp_defence_bonus` for every attack type, for custom targets.
+pub fn clamp_defence_bonuses(bonuses: DefenceBonuses) -> DefenceBonuses {
+    let clamp = |bonus: i32| clamp_defence_bonus(i64::from(bonus));
+    DefenceBonuses {
+        stab: clamp(bonuses.stab),
+        slash: clamp(bonuses.slash),
+        crush: clamp(bonuses.crush),
+        magic: clamp(bonuses.magic),
+        ranged: clamp(bonuses.ranged),
+    }
 }
 
 // =============================================================================
@@ -414,6 +1668,14 @@ pub fn get_prayer_multipliers(prayer: &str) -> ((i32, i32), (i32, i32), (i32, i3
         "ultimate_strength" | "ultimate strength" => ((1, 1), (23, 20), (1, 1)),
         // Incredible Reflexes: 15% attack
         "incredible_reflexes" | "incredible reflexes" => ((23, 20), (1, 1), (1, 1)),
+        // Superhuman Strength: 10% strength
+        "superhuman_strength" | "superhuman strength" => ((1, 1), (11, 10), (1, 1)),
+        // Improved Reflexes: 10% attack
+        "improved_reflexes" | "improved reflexes" => ((11, 10), (1, 1), (1, 1)),
+        // Burst of Strength: 5% strength
+        "burst_of_strength" | "burst of strength" => ((1, 1), (21, 20), (1, 1)),
+        // Clarity of Thought: 5% attack
+        "clarity_of_thought" | "clarity of thought" => ((21, 20), (1, 1), (1, 1)),
         // Steel Skin: 15% defence
         "steel_skin" | "steel skin" => ((1, 1), (1, 1), (23, 20)),
         // No prayer or unknown
@@ -421,6 +1683,146 @@ pub fn get_prayer_multipliers(prayer: &str) -> ((i32, i32), (i32, i32), (i32, i3
     }
 }
 
+/// Magic accuracy multiplier for a prayer, as (num, den).
+pub fn get_prayer_magic_multiplier(prayer: &str) -> (i32, i32) {
+    match prayer.to_lowercase().as_str() {
+        // Augury: 25% magic accuracy
+        "augury" => (5, 4),
+        // Mystic Might: 15%
+        "mystic_might" | "mystic might" => (23, 20),
+        // Mystic Lore: 10%
+        "mystic_lore" | "mystic lore" => (11, 10),
+        // Mystic Will: 5%
+        "mystic_will" | "mystic will" => (21, 20),
+        _ => (1, 1),
+    }
+}
+
+/// Ranged (accuracy, strength) multipliers for a prayer, as (num, den) pairs.
+pub fn get_prayer_ranged_multipliers(prayer: &str) -> ((i32, i32), (i32, i32)) {
+    match prayer.to_lowercase().as_str() {
+        // Rigour: 20% accuracy, 23% damage
+        "rigour" => ((6, 5), (123, 100)),
+        // Eagle Eye: 15%
+        "eagle_eye" | "eagle eye" => ((23, 20), (23, 20)),
+        // Hawk Eye: 10%
+        "hawk_eye" | "hawk eye" => ((11, 10), (11, 10)),
+        // Sharp Eye: 5%
+        "sharp_eye" | "sharp eye" => ((21, 20), (21, 20)),
+        _ => ((1, 1), (1, 1)),
+    }
+}
+
+/// Level requirements for an offensive prayer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrayerRequirement {
+    pub key: &'static str,
+    pub prayer_level: u8,
+    pub defence_level: u8,
+}
+
+/// Melee offensive prayers the engine models, weakest first.
+pub const MELEE_PRAYERS: &[PrayerRequirement] = &[
+    PrayerRequirement {
+        key: "clarity_of_thought",
+        prayer_level: 7,
+        defence_level: 1,
+    },
+    PrayerRequirement {
+        key: "burst_of_strength",
+        prayer_level: 4,
+        defence_level: 1,
+    },
+    PrayerRequirement {
+        key: "improved_reflexes",
+        prayer_level: 16,
+        defence_level: 1,
+    },
+    PrayerRequirement {
+        key: "superhuman_strength",
+        prayer_level: 13,
+        defence_level: 1,
+    },
+    PrayerRequirement {
+        key: "incredible_reflexes",
+        prayer_level: 34,
+        defence_level: 1,
+    },
+    PrayerRequirement {
+        key: "ultimate_strength",
+        prayer_level: 31,
+        defence_level: 1,
+    },
+    PrayerRequirement {
+        key: "chivalry",
+        prayer_level: 60,
+        defence_level: 65,
+    },
+    PrayerRequirement {
+        key: "piety",
+        prayer_level: 70,
+        defence_level: 70,
+    },
+];
+
+/// Melee potions the engine models (one may be active at a time).
+pub const MELEE_POTIONS: &[&str] = &[
+    "attack",
+    "strength",
+    "super_attack",
+    "super_strength",
+    "super_combat",
+];
+
+/// Prayers that only work on members worlds.
+pub const MEMBERS_PRAYERS: &[&str] = &[
+    "chivalry",
+    "piety",
+    "rigour",
+    "augury",
+    "retribution",
+    "redemption",
+    "smite",
+    "preserve",
+];
+
+/// Potions a free-to-play account can drink.
+pub const F2P_POTIONS: &[&str] = &["attack", "strength", "defence"];
+
+/// Whether a prayer needs membership.
+pub fn is_members_prayer(prayer: &str) -> bool {
+    MEMBERS_PRAYERS.contains(&prayer.to_lowercase().replace(' ', "_").as_str())
+}
+
+/// Whether a potion needs membership (anything but the basic potions).
+pub fn is_members_potion(potion: &str) -> bool {
+    !F2P_POTIONS.contains(&potion.to_lowercase().replace(' ', "_").as_str())
+}
+
+/// Members prayers and potions in a player file, described for an error
+/// message. Empty if the player is F2P-legal.
+pub fn player_f2p_violations(input: &PlayerInput) -> Vec<String> {
+    let mut violations: Vec<String> = input
+        .prayers
+        .iter()
+        .filter(|(key, active)| **active && is_members_prayer(key))
+        .map(|(key, _)| format!("members prayer: {key}"))
+        .collect();
+    // HashMap order is random; keep messages stable
+    violations.sort();
+
+    let boosts = &input.boosts;
+    for spec in [&boosts.melee, &boosts.ranged, &boosts.magic]
+        .into_iter()
+        .flatten()
+    {
+        if is_members_potion(&spec.kind) {
+            violations.push(format!("members potion: {}", spec.kind));
+        }
+    }
+    violations
+}
+
 /// Get attack boost for a given potion type and base level.
 pub fn get_potion_attack_boost(potion: &str, base_level: u8) -> i32 {
     let level = base_level as i32;
@@ -465,6 +1867,50 @@ pub fn get_potion_defence_boost(potion: &str, base_level: u8) -> i32 {
     }
 }
 
+/// Get ranged boost for a given potion type and base level.
+pub fn get_potion_ranged_boost(potion: &str, base_level: u8) -> i32 {
+    let level = base_level as i32;
+    match potion.to_lowercase().as_str() {
+        // Ranging potion: +4 + 10%
+        "ranging" => 4 + (level * 10) / 100,
+        // Super ranging: +5 + 15%
+        "super_ranging" | "super ranging" => 5 + (level * 15) / 100,
+        _ => 0,
+    }
+}
+
+/// Get magic boost for a given potion type and base level.
+pub fn get_potion_magic_boost(potion: &str, base_level: u8) -> i32 {
+    let level = base_level as i32;
+    match potion.to_lowercase().as_str() {
+        // Magic potion: +4
+        "magic" => 4,
+        // Super magic: +5 + 15%
+        "super_magic" | "super magic" => 5 + (level * 15) / 100,
+        // Imbued heart: +1 + 10%
+        "imbued_heart" | "imbued heart" => 1 + (level * 10) / 100,
+        // Saturated heart: +4 + 10%
+        "saturated_heart" | "saturated heart" => 4 + (level * 10) / 100,
+        _ => 0,
+    }
+}
+
+// =============================================================================
+// Spells
+// =============================================================================
+
+/// Ticks between autocasts.
+pub const SPELL_CAST_TICKS: u8 = 5;
+/// Ticks between standard spell autocasts from a harmonised nightmare staff.
+pub const HARMONISED_CAST_TICKS: u8 = 4;
+const HARMONISED_STAFF: &str = "harmonised nightmare staff";
+
+/// Lookup key for a spell name: "Fire Surge", "fire surge" and
+/// "fire_surge" are the same spell.
+pub fn spell_key(name: &str) -> String {
+    name.to_lowercase().replace(' ', "_")
+}
+
 /// Resolve player input into PlayerState.
 pub fn resolve_player(
     skills: &crate::model::SkillsInput,
@@ -476,10 +1922,15 @@ pub fn resolve_player(
     let mut prayer_atk = (1, 1);
     let mut prayer_str = (1, 1);
     let mut prayer_def = (1, 1);
+    let mut prayer_magic = (1, 1);
+    let mut prayer_ranged_atk = (1, 1);
+    let mut prayer_ranged_str = (1, 1);
 
     for (prayer_name, active) in prayers {
         if *active {
             let (atk, str, def) = get_prayer_multipliers(prayer_name);
+            let magic = get_prayer_magic_multiplier(prayer_name);
+            let (ranged_atk, ranged_str) = get_prayer_ranged_multipliers(prayer_name);
             // Take the best multipliers (simplified - in reality prayers don't stack)
             if atk.0 * prayer_atk.1 > prayer_atk.0 * atk.1 {
                 prayer_atk = atk;
@@ -490,6 +1941,15 @@ pub fn resolve_player(
             if def.0 * prayer_def.1 > prayer_def.0 * def.1 {
                 prayer_def = def;
             }
+            if magic.0 * prayer_magic.1 > prayer_magic.0 * magic.1 {
+                prayer_magic = magic;
+            }
+            if ranged_atk.0 * prayer_ranged_atk.1 > prayer_ranged_atk.0 * ranged_atk.1 {
+                prayer_ranged_atk = ranged_atk;
+            }
+            if ranged_str.0 * prayer_ranged_str.1 > prayer_ranged_str.0 * ranged_str.1 {
+                prayer_ranged_str = ranged_str;
+            }
         }
     }
 
@@ -509,6 +1969,16 @@ pub fn resolve_player(
         .as_ref()
         .map(|b| get_potion_defence_boost(&b.kind, skills.defence))
         .unwrap_or(0);
+    let potion_ranged = boosts
+        .ranged
+        .as_ref()
+        .map(|b| get_potion_ranged_boost(&b.kind, skills.ranged))
+        .unwrap_or(0);
+    let potion_magic = boosts
+        .magic
+        .as_ref()
+        .map(|b| get_potion_magic_boost(&b.kind, skills.magic))
+        .unwrap_or(0);
 
     PlayerState {
         attack: skills.attack,
@@ -518,13 +1988,145 @@ pub fn resolve_player(
         magic: skills.magic,
         prayer: skills.prayer,
         hitpoints: skills.hitpoints,
+        current_hitpoints: flags
+            .current_hitpoints
+            .map_or(skills.hitpoints, |hp| hp.clamp(1, skills.hitpoints.max(1))),
         prayer_attack_mult: prayer_atk,
         prayer_strength_mult: prayer_str,
         prayer_defence_mult: prayer_def,
+        prayer_magic_mult: prayer_magic,
+        prayer_ranged_attack_mult: prayer_ranged_atk,
+        prayer_ranged_strength_mult: prayer_ranged_str,
         potion_attack_boost: potion_attack,
         potion_strength_boost: potion_strength,
         potion_defence_boost: potion_defence,
+        potion_ranged_boost: potion_ranged,
+        potion_magic_boost: potion_magic,
         on_slayer_task: flags.on_slayer_task,
         in_wilderness: flags.in_wilderness,
+        forinthry_surge: flags.forinthry_surge,
+        kandarin_hard_diary: flags.kandarin_hard_diary,
+        charge: flags.charge,
+    }
+}
+
+/// Resolve player input into PlayerState, including pre-fight actions.
+pub fn resolve_player_input(input: &PlayerInput) -> PlayerState {
+    let mut player = resolve_player(&input.skills, &input.prayers, &input.boosts, &input.flags);
+    apply_pre_fight_player(&mut player, &input.pre_fight);
+    player
+}
+
+// =============================================================================
+// Pre-fight Actions
+// =============================================================================
+
+/// Apply the player-side effects of pre-fight actions (boosts and drains).
+/// Target-only actions are ignored here.
+pub fn apply_pre_fight_player(player: &mut PlayerState, actions: &[PreFightAction]) {
+    for action in actions {
+        match action {
+            PreFightAction::DragonBattleaxe => {
+                // Drains are 10% of the current (boosted) level
+                let mut drained = 0;
+                for (base, boost) in [
+                    (player.attack, &mut player.potion_attack_boost),
+                    (player.defence, &mut player.potion_defence_boost),
+                    (player.ranged, &mut player.potion_ranged_boost),
+                    (player.magic, &mut player.potion_magic_boost),
+                ] {
+                    let drain = (base as i32 + *boost) / 10;
+                    *boost -= drain;
+                    drained += drain;
+                }
+                player.potion_strength_boost += 10 + drained / 4;
+            }
+            PreFightAction::SmellingSalts => {
+                // Doesn't stack with potions: keep whichever boost is higher
+                for (base, boost) in [
+                    (player.attack, &mut player.potion_attack_boost),
+                    (player.strength, &mut player.potion_strength_boost),
+                    (player.defence, &mut player.potion_defence_boost),
+                    (player.ranged, &mut player.potion_ranged_boost),
+                    (player.magic, &mut player.potion_magic_boost),
+                ] {
+                    *boost = (*boost).max(11 + (base as i32 * 16) / 100);
+                }
+            }
+            PreFightAction::Vulnerability
+            | PreFightAction::DragonWarhammer
+            | PreFightAction::ElderMaul
+            | PreFightAction::BandosGodsword { .. } => {}
+        }
+    }
+}
+
+/// Apply the target-side effects of pre-fight actions (defence reductions).
+/// Player-only actions are ignored here.
+pub fn apply_pre_fight_target(target: &mut TargetResolved, actions: &[PreFightAction]) {
+    for action in actions {
+        let defence = target.defence_level;
+        target.defence_level = match action {
+            PreFightAction::Vulnerability => defence - defence / 10,
+            PreFightAction::DragonWarhammer => defence - defence * 3 / 10,
+            PreFightAction::ElderMaul => defence - defence * 35 / 100,
+            PreFightAction::BandosGodsword { damage } => defence.saturating_sub(*damage),
+            PreFightAction::DragonBattleaxe | PreFightAction::SmellingSalts => defence,
+        };
+    }
+}
+
+// =============================================================================
+// Test Support
+// =============================================================================
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::*;
+
+    /// A one-style slash weapon with the given bonuses and attack requirement.
+    pub fn weapon(id: u32, name: &str, slash: i32, strength: i32, attack_req: u32) -> ItemData {
+        ItemData {
+            id,
+            name: name.to_string(),
+            equipable: true,
+            equipable_weapon: true,
+            equipment: Some(EquipmentData {
+                slot: "weapon".to_string(),
+                attack_stab: 0,
+                attack_slash: slash,
+                attack_crush: 0,
+                attack_magic: 0,
+                attack_ranged: 0,
+                defence_stab: 0,
+                defence_slash: 0,
+                defence_crush: 0,
+                defence_magic: 0,
+                defence_ranged: 0,
+                melee_strength: strength,
+                ranged_strength: 0,
+                magic_damage: 0,
+                prayer: 0,
+                requirements: (attack_req > 1)
+                    .then(|| HashMap::from([("attack".to_string(), attack_req)])),
+            }),
+            weapon: Some(WeaponData {
+                attack_speed: 4,
+                weapon_type: "slash_sword".to_string(),
+                stances: vec![StanceData {
+                    attack_style: "aggressive".to_string(),
+                    attack_type: "slash".to_string(),
+                    combat_style: "slash".to_string(),
+                    experience: "strength".to_string(),
+                    boosts: None,
+                }],
+            }),
+            two_handed: false,
+            ammunition: None,
+            obtainability: vec![],
+            tags: vec![],
+            members: false,
+            tradeable: true,
+        }
     }
 }
@@ -0,0 +1,210 @@
+// src/determinism.rs
+//
+// Cross-platform determinism. An evaluation should give bit-identical
+// results on every target the engine builds for (native, wasm32): a float
+// that rounds differently somewhere shows up as a different DPS. A seeded
+// batch of fuzz scenarios is evaluated and every result folded into one
+// canonical hash, so two builds of the engine can be compared by a single
+// number. The hash is FNV-1a over a fixed byte encoding (floats by their
+// bits, little-endian), so it doesn't depend on std's hasher or on the
+// platform's byte order.
+
+use crate::data::DataStore;
+use crate::fuzz::Fuzzer;
+use crate::model::{AttackType, EvalResult};
+use serde::Serialize;
+
+/// Seed of the batch compared across platforms, unless another is given.
+pub const DETERMINISM_SEED: u64 = 0x5eed_2796;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a over values written in a fixed encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanonicalHasher(u64);
+
+impl Default for CanonicalHasher {
+    fn default() -> Self {
+        CanonicalHasher(FNV_OFFSET)
+    }
+}
+
+impl CanonicalHasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    /// Floats by their bits: results that differ in the last place differ.
+    pub fn write_f64(&mut self, value: f64) {
+        self.write_u64(value.to_bits());
+    }
+
+    /// Length-prefixed, so adjacent strings can't run together.
+    pub fn write_str(&mut self, value: &str) {
+        self.write_u64(value.len() as u64);
+        self.write_bytes(value.as_bytes());
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+
+    /// Every number a result reports, in field order. The stage traces are
+    /// left out: the final numbers already follow from them.
+    pub fn write_result(&mut self, result: &EvalResult) {
+        self.write_f64(result.dps);
+        self.write_u32(result.max_hit);
+        self.write_f64(result.accuracy);
+        self.write_u32(result.attack_roll);
+        self.write_u32(result.defence_roll);
+        self.write_u32(result.interval_ticks.0);
+        self.write_option_f64(result.expected_interval_ticks);
+        self.write_u64(result.hit_max_hits.len() as u64);
+        for &max_hit in &result.hit_max_hits {
+            self.write_u32(max_hit);
+        }
+        self.write_u64(result.style_rolls.len() as u64);
+        for roll in &result.style_rolls {
+            self.write_bytes(&[attack_type_code(roll.attack_type)]);
+            self.write_u32(roll.attack_roll);
+            self.write_u32(roll.defence_roll);
+            self.write_f64(roll.accuracy);
+        }
+        self.write_u64(result.effects_applied.len() as u64);
+        for effect in &result.effects_applied {
+            self.write_str(effect);
+        }
+        self.write_f64(result.poison_dps);
+        self.write_f64(result.burn_dps);
+        self.write_option_f64(result.aoe_dps);
+        match &result.immunity {
+            Some(reason) => {
+                self.write_bytes(&[1]);
+                self.write_str(reason);
+            }
+            None => self.write_bytes(&[0]),
+        }
+    }
+
+    fn write_option_f64(&mut self, value: Option<f64>) {
+        match value {
+            Some(value) => {
+                self.write_bytes(&[1]);
+                self.write_f64(value);
+            }
+            None => self.write_bytes(&[0]),
+        }
+    }
+}
+
+fn attack_type_code(attack_type: AttackType) -> u8 {
+    match attack_type {
+        AttackType::Stab => 0,
+        AttackType::Slash => 1,
+        AttackType::Crush => 2,
+        AttackType::Ranged => 3,
+        AttackType::Magic => 4,
+    }
+}
+
+/// The canonical hash of a seeded batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BatchHash {
+    pub seed: u64,
+    /// Scenarios drawn.
+    pub count: u64,
+    /// Scenarios evaluated and hashed; the rest had no valid draw.
+    pub evaluated: u64,
+    pub hash: u64,
+}
+
+impl BatchHash {
+    /// The hash as 16 hex digits.
+    pub fn hex(&self) -> String {
+        format!("{:016x}", self.hash)
+    }
+}
+
+/// Evaluate `count` fuzz scenarios from `seed` and hash every result, in
+/// order. Scenarios without a valid draw are hashed as a marker, so they
+/// can't shift later results into each other's place.
+pub fn batch_hash(store: &DataStore, seed: u64, count: u64) -> BatchHash {
+    let mut fuzzer = Fuzzer::new(store, seed);
+    let mut hasher = CanonicalHasher::new();
+    let mut evaluated = 0;
+    while fuzzer.index() < count {
+        match fuzzer.next_result() {
+            Some((_, result)) => {
+                evaluated += 1;
+                hasher.write_bytes(&[1]);
+                hasher.write_result(&result);
+            }
+            None => hasher.write_bytes(&[0]),
+        }
+    }
+    BatchHash {
+        seed,
+        count,
+        evaluated,
+        hash: hasher.finish(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv_matches_reference_values() {
+        // FNV-1a 64 test vectors
+        assert_eq!(CanonicalHasher::new().finish(), 0xcbf2_9ce4_8422_2325);
+        let mut hasher = CanonicalHasher::new();
+        hasher.write_bytes(b"a");
+        assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn test_result_hash_sees_the_last_bit() {
+        let result = EvalResult {
+            dps: 1.5,
+            hit_max_hits: vec![30],
+            ..EvalResult::default()
+        };
+        let hash = |result: &EvalResult| {
+            let mut hasher = CanonicalHasher::new();
+            hasher.write_result(result);
+            hasher.finish()
+        };
+        assert_eq!(hash(&result), hash(&result.clone()));
+        let nudged = EvalResult {
+            dps: f64::from_bits(result.dps.to_bits() + 1),
+            ..result.clone()
+        };
+        assert_ne!(hash(&result), hash(&nudged));
+        let renamed = EvalResult {
+            effects_applied: vec!["ab".to_string(), "c".to_string()],
+            ..result.clone()
+        };
+        let regrouped = EvalResult {
+            effects_applied: vec!["a".to_string(), "bc".to_string()],
+            ..result
+        };
+        assert_ne!(hash(&renamed), hash(&regrouped));
+    }
+}
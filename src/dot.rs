@@ -0,0 +1,99 @@
+// src/dot.rs
+//
+// Damage over time. Weapon poison: once a hit poisons the target the
+// poison runs its course (a hit every 30 ticks, one less damage every 5
+// hits) and has to wear off before another hit can poison again. Burns
+// stack instead: each deals 1 damage every 4 ticks, 10 times, with up to 5
+// burning at once.
+
+use crate::model::{Combat, WeaponPoison};
+use crate::ticks::SECONDS_PER_TICK;
+
+/// Ticks between poison hits.
+pub const POISON_INTERVAL_TICKS: u32 = 30;
+
+/// Poison hits before the damage drops by one.
+pub const POISON_HITS_PER_STEP: u32 = 5;
+
+/// Total damage of one poison, from `start` down to 1.
+pub fn poison_total_damage(start: u32) -> u32 {
+    POISON_HITS_PER_STEP * start * (start + 1) / 2
+}
+
+/// How long one poison lasts, in ticks.
+pub fn poison_duration_ticks(start: u32) -> u32 {
+    POISON_HITS_PER_STEP * start * POISON_INTERVAL_TICKS
+}
+
+/// Ticks between burn hits.
+pub const BURN_INTERVAL_TICKS: u32 = 4;
+
+/// Hits (of 1 damage) in one burn.
+pub const BURN_HITS: u32 = 10;
+
+/// Most burns on a target at once.
+pub const MAX_BURNS: u32 = 5;
+
+/// Average burn DPS while attacking continuously, when each attack burns
+/// the target with `chance`: the burns running at once average the rate
+/// they start at times their length, up to the cap.
+pub fn burn_dps(chance: f64, interval_ticks: u8) -> f64 {
+    if chance <= 0.0 || interval_ticks == 0 {
+        return 0.0;
+    }
+
+    let duration_ticks = f64::from(BURN_HITS * BURN_INTERVAL_TICKS);
+    let burning = (chance * duration_ticks / f64::from(interval_ticks)).min(f64::from(MAX_BURNS));
+    burning / (f64::from(BURN_INTERVAL_TICKS) * SECONDS_PER_TICK)
+}
+
+/// Average poison DPS while attacking continuously: each cycle is one full
+/// poison plus the expected wait for the next hit that lands and poisons.
+pub fn poison_dps(poison: WeaponPoison, combat: Combat, accuracy: f64, interval_ticks: u8) -> f64 {
+    let chance = accuracy * poison.apply_chance(combat);
+    if chance <= 0.0 || interval_ticks == 0 {
+        return 0.0;
+    }
+
+    let start = poison.start_damage(combat);
+    let wait_ticks = interval_ticks as f64 / chance;
+    let cycle_ticks = poison_duration_ticks(start) as f64 + wait_ticks;
+    poison_total_damage(start) as f64 / (cycle_ticks * SECONDS_PER_TICK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poison_course() {
+        // p++ melee: 6 x5, 5 x5, ... 1 x5
+        assert_eq!(WeaponPoison::PoisonPlusPlus.start_damage(Combat::Melee), 6);
+        assert_eq!(poison_total_damage(6), 105);
+        assert_eq!(poison_duration_ticks(6), 900);
+        // Ranged (p) starts at 2
+        assert_eq!(WeaponPoison::Poison.start_damage(Combat::Ranged), 2);
+    }
+
+    #[test]
+    fn test_poison_dps() {
+        // Always hits, 4-tick weapon: 1 in 4 attacks poisons, so the wait is
+        // 16 ticks after each 900-tick poison
+        let dps = poison_dps(WeaponPoison::PoisonPlusPlus, Combat::Melee, 1.0, 4);
+        assert!((dps - 105.0 / (916.0 * 0.6)).abs() < 1e-12);
+
+        assert_eq!(poison_dps(WeaponPoison::Poison, Combat::Melee, 0.0, 4), 0.0);
+        let weaker = poison_dps(WeaponPoison::Poison, Combat::Melee, 1.0, 4);
+        assert!(weaker < dps);
+    }
+
+    #[test]
+    fn test_burn_dps() {
+        // 1 in 5 attacks of a 4-tick weapon: 2 burns running, 1 damage
+        // each per 2.4s
+        assert!((burn_dps(0.2, 4) - 2.0 / 2.4).abs() < 1e-12);
+        // Every attack would keep 10 going; only 5 can
+        assert!((burn_dps(1.0, 4) - 5.0 / 2.4).abs() < 1e-12);
+        assert_eq!(burn_dps(0.0, 4), 0.0);
+    }
+}
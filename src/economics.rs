@@ -0,0 +1,165 @@
+// src/economics.rs
+//
+// Supply costs for a simulated trip. Prices are supplied by the caller
+// (there is no price data in the repository).
+
+use crate::model::{BoostSpec, Spell};
+use crate::ticks::Ticks;
+use crate::trip::TripSummary;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Potion usage and cost for one trip.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PotionCost {
+    pub doses: u32,
+    /// Whole potions to bring, i.e. inventory slots taken.
+    pub potions_carried: u32,
+    /// Cost of the doses drunk, if a price was given.
+    pub gp: Option<f64>,
+    pub gp_per_hour: Option<f64>,
+}
+
+/// Cost out the doses a trip used, for potions of `spec.doses` doses priced
+/// at `price_per_potion` each. Leftover doses in the last potion aren't
+/// charged.
+pub fn potion_cost(
+    summary: &TripSummary,
+    spec: &BoostSpec,
+    price_per_potion: Option<u64>,
+) -> PotionCost {
+    let doses_per_potion = u32::from(spec.doses.max(1));
+    let gp =
+        price_per_potion.map(|price| price as f64 / doses_per_potion as f64 * summary.doses as f64);
+
+    PotionCost {
+        doses: summary.doses,
+        potions_carried: summary.doses.div_ceil(doses_per_potion),
+        gp,
+        gp_per_hour: gp
+            .filter(|_| summary.ticks > Ticks(0))
+            .map(|gp| gp / summary.ticks.as_hours_f64()),
+    }
+}
+
+/// Runes cast and their cost for one trip.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuneCost {
+    pub casts: u32,
+    /// Rune name -> runes used over the trip.
+    pub runes: BTreeMap<String, u32>,
+    /// Cost of the runes, if every one of them was priced.
+    pub gp: Option<f64>,
+    pub gp_per_hour: Option<f64>,
+}
+
+/// Cost out the runes a trip's casts of `spell` used, at `prices` (rune
+/// name -> gp each).
+pub fn rune_cost(summary: &TripSummary, spell: &Spell, prices: &HashMap<String, u64>) -> RuneCost {
+    let runes: BTreeMap<String, u32> = spell
+        .runes
+        .iter()
+        .map(|(rune, &per_cast)| (rune.clone(), per_cast.saturating_mul(summary.attacks)))
+        .collect();
+    let gp = runes
+        .iter()
+        .map(|(rune, &count)| prices.get(rune).map(|&price| price as f64 * count as f64))
+        .sum::<Option<f64>>();
+
+    RuneCost {
+        casts: summary.attacks,
+        runes,
+        gp,
+        gp_per_hour: gp
+            .filter(|_| summary.ticks > Ticks(0))
+            .map(|gp| gp / summary.ticks.as_hours_f64()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_potion_cost_by_dose_size() {
+        let summary = TripSummary {
+            ticks: Ticks(Ticks::HOUR.0 / 2),
+            attacks: 0,
+            doses: 6,
+            doses_per_hour: 12.0,
+            avg_dps: 0.0,
+            total_damage: 0.0,
+            damage_in_flight: 0.0,
+            hit_delay_ticks: 0,
+            first_kill_tick: None,
+            target_attacks: 0,
+            damage_taken: 0.0,
+            start_target_defence: 1,
+            end_target_defence: 1,
+            avg_target_defence: 1.0,
+        };
+
+        let four = potion_cost(&summary, &BoostSpec::new("super_combat"), Some(12_000));
+        assert_eq!(four.potions_carried, 2);
+        assert_eq!(four.gp, Some(18_000.0));
+        assert_eq!(four.gp_per_hour, Some(36_000.0));
+
+        // 1-dose potions: same doses, more slots, priced per potion
+        let one = potion_cost(&summary, &one_dose(), Some(3_500));
+        assert_eq!(one.potions_carried, 6);
+        assert_eq!(one.gp, Some(21_000.0));
+
+        assert_eq!(potion_cost(&summary, &one_dose(), None).gp, None);
+    }
+
+    #[test]
+    fn test_rune_cost_needs_every_price() {
+        let summary = TripSummary {
+            ticks: Ticks(Ticks::HOUR.0 / 2),
+            attacks: 600,
+            doses: 0,
+            doses_per_hour: 0.0,
+            avg_dps: 0.0,
+            total_damage: 0.0,
+            damage_in_flight: 0.0,
+            hit_delay_ticks: 0,
+            first_kill_tick: None,
+            target_attacks: 0,
+            damage_taken: 0.0,
+            start_target_defence: 1,
+            end_target_defence: 1,
+            avg_target_defence: 1.0,
+        };
+        let surge = Spell {
+            name: "fire_surge".to_string(),
+            base_max_hit: 24,
+            element: Some("fire".to_string()),
+            spellbook: Some("standard".to_string()),
+            runes: BTreeMap::from([
+                ("sunfire".to_string(), 10),
+                ("air".to_string(), 7),
+                ("wrath".to_string(), 1),
+            ]),
+        };
+        let mut prices = HashMap::from([("sunfire".to_string(), 20), ("air".to_string(), 4)]);
+
+        let unpriced = rune_cost(&summary, &surge, &prices);
+        assert_eq!(unpriced.runes["sunfire"], 6_000);
+        assert_eq!(unpriced.gp, None);
+
+        prices.insert("wrath".to_string(), 300);
+        let cost = rune_cost(&summary, &surge, &prices);
+        assert_eq!(
+            cost.gp,
+            Some(6_000.0 * 20.0 + 4_200.0 * 4.0 + 600.0 * 300.0)
+        );
+        assert_eq!(cost.gp_per_hour, Some(cost.gp.unwrap() * 2.0));
+    }
+
+    fn one_dose() -> BoostSpec {
+        BoostSpec {
+            doses: 1,
+            ..BoostSpec::new("super_combat")
+        }
+    }
+}
@@ -3,7 +3,15 @@
 // Effects system with staged modifiers.
 // Effects hook into different stages of the DPS calculation pipeline.
 
+use crate::formulas::{twisted_bow_accuracy_pct, twisted_bow_damage_pct};
+use crate::mechanics::{TargetMechanic, WeaponMechanic};
+use crate::model::BuildResolved;
+use crate::model::{Combat, EvalContext, PlayerState, Spell, TargetResolved};
+use crate::ordering::{self, OrderingProfile};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
 
 /// Stages in the DPS calculation pipeline where effects can be applied.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
@@ -11,14 +19,24 @@ use serde::{Deserialize, Serialize};
 pub enum Stage {
     /// Applied after effective level calculation (e.g., Void Knight)
     PostEffectiveLevel,
-    /// Applied before roll calculation
+    /// Applied before roll calculation (e.g., imbued Salve amulet magic damage)
     PreRolls,
-    /// Applied after max hit calculation (e.g., Slayer helm, Salve amulet)
+    /// Applied after attack roll and max hit calculation (e.g., Slayer helm, Salve amulet)
     PostMaxHit,
     /// Applied after accuracy calculation
     PostAccuracy,
 }
 
+impl Stage {
+    /// Every stage, in pipeline order.
+    pub const ALL: [Stage; 4] = [
+        Stage::PostEffectiveLevel,
+        Stage::PreRolls,
+        Stage::PostMaxHit,
+        Stage::PostAccuracy,
+    ];
+}
+
 /// Operations that effects can perform.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "op", rename_all = "snake_case")]
@@ -27,6 +45,29 @@ pub enum Op {
     Multiply { stat: Stat, num: i32, den: i32 },
     /// Add a flat value to a stat
     Add { stat: Stat, value: i32 },
+    /// Add `value` per tile of target size, counting at most `max_size` tiles
+    AddPerTargetSize {
+        stat: Stat,
+        value: i32,
+        max_size: u8,
+    },
+    /// Multiply a stat by 1 + missing hitpoints / 100 * hitpoints level / 100
+    /// (Dharok's set)
+    MultiplyByMissingHitpoints { stat: Stat },
+    /// With chance num/den an attack lands regardless of defence and deals
+    /// `extra_damage` more (Verac's set). Accuracy becomes the blended
+    /// chance to land and ExpectedDamage takes the extra damage.
+    IgnoreDefenceChance {
+        num: i32,
+        den: i32,
+        extra_damage: i32,
+    },
+    /// Multiply by the Twisted bow's accuracy (for the attack roll) or
+    /// damage percentage, which grow with the target's magic level
+    TwistedBowScaling { stat: Stat },
+    /// Landed hits deal at least `pct`% of the max hit (sunfire runes); the
+    /// highest minimum applies
+    MinimumHit { pct: i32 },
 }
 
 /// Stats that effects can modify.
@@ -38,6 +79,17 @@ pub enum Stat {
     MaxHit,
     AttackRoll,
     Accuracy,
+    /// Average damage of a landed hit as a factor (1 = unchanged), for
+    /// chance-based extra damage the max hit can't express. Scales DPS only.
+    ExpectedDamage,
+    /// Magic damage bonus in percentage points, summed with the gear's
+    /// (after a powered staff multiplies it) before the magic max hit is
+    /// set, so only `pre_rolls` effects change the max hit.
+    MagicDamage,
+    /// Base max hit added to the cast spell's (chaos gauntlets, Charge),
+    /// before magic damage scales it; likewise only `pre_rolls` effects
+    /// change the max hit.
+    SpellMaxHit,
 }
 
 /// Conditions for effect activation.
@@ -52,6 +104,14 @@ pub struct Condition {
     #[serde(default)]
     pub in_wilderness: Option<bool>,
 
+    /// Effect only applies under Forinthry surge
+    #[serde(default)]
+    pub forinthry_surge: Option<bool>,
+
+    /// Effect only applies while Charge is (or isn't) active
+    #[serde(default)]
+    pub charge: Option<bool>,
+
     /// Effect only applies against targets with specific attributes
     #[serde(default)]
     pub target_attributes: Vec<String>,
@@ -59,10 +119,42 @@ pub struct Condition {
     /// Combat style requirement
     #[serde(default)]
     pub combat: Option<String>,
+
+    /// Items that must be equipped. Every inner list must be matched by at
+    /// least one equipped item (e.g. one list per set piece, holding its
+    /// interchangeable variants).
+    #[serde(default)]
+    pub equipped: Vec<Vec<u32>>,
+
+    /// Item tags that must be equipped (data/tags), each on at least one
+    /// equipped item.
+    #[serde(default)]
+    pub equipped_tags: Vec<String>,
+
+    /// Effect only applies while the weapon is (or isn't) charged
+    #[serde(default)]
+    pub weapon_charged: Option<bool>,
+
+    /// Element the cast spell must have (`fire`, ...)
+    #[serde(default)]
+    pub spell_element: Option<String>,
+
+    /// Spellbook the cast spell must be from (`standard`, ...)
+    #[serde(default)]
+    pub spellbook: Option<String>,
+
+    /// Runes the cast spell must use, each of them (`sunfire`)
+    #[serde(default)]
+    pub runes: Vec<String>,
+
+    /// Spells, one of which must be cast (names as in data/spells)
+    #[serde(default)]
+    pub spells: Vec<String>,
 }
 
 /// A declarative effect that can be applied during DPS calculation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "EffectDef")]
 pub struct Effect {
     pub id: String,
     pub name: String,
@@ -72,6 +164,10 @@ pub struct Effect {
     #[serde(default)]
     pub priority: i16,
 
+    /// Slot in `ordering::PIPELINE_ORDER` that set the stage and priority
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<String>,
+
     /// Effects in the same exclusive group don't stack
     #[serde(default)]
     pub exclusive_group: Option<String>,
@@ -84,17 +180,153 @@ pub struct Effect {
     pub op: Op,
 }
 
-/// Context for evaluating effect conditions.
-pub struct EffectContext {
+/// An effect as written in data/effects: its place in the pipeline is
+/// either an `order` slot or an explicit `stage` and `priority`.
+#[derive(Deserialize)]
+struct EffectDef {
+    id: String,
+    name: String,
+    #[serde(default)]
+    stage: Option<Stage>,
+    #[serde(default)]
+    priority: Option<i16>,
+    #[serde(default)]
+    order: Option<String>,
+    #[serde(default)]
+    exclusive_group: Option<String>,
+    #[serde(default)]
+    condition: Condition,
+    op: Op,
+}
+
+impl TryFrom<EffectDef> for Effect {
+    type Error = String;
+
+    fn try_from(def: EffectDef) -> Result<Self, Self::Error> {
+        let (stage, priority) = match &def.order {
+            Some(name) => {
+                let slot = ordering::slot_named(name)
+                    .ok_or_else(|| format!("effect {}: unknown order slot {name}", def.id))?;
+                // An explicit stage or priority has to agree with the slot
+                if def.stage.is_some_and(|stage| stage != slot.stage)
+                    || def
+                        .priority
+                        .is_some_and(|priority| priority != slot.priority)
+                {
+                    return Err(format!(
+                        "effect {}: stage and priority differ from order slot {name}",
+                        def.id
+                    ));
+                }
+                (slot.stage, slot.priority)
+            }
+            None => (
+                def.stage
+                    .ok_or_else(|| format!("effect {}: needs an order or a stage", def.id))?,
+                def.priority.unwrap_or_default(),
+            ),
+        };
+        Ok(Effect {
+            id: def.id,
+            name: def.name,
+            stage,
+            priority,
+            order: def.order,
+            exclusive_group: def.exclusive_group,
+            condition: def.condition,
+            op: def.op,
+        })
+    }
+}
+
+/// Context for evaluating effect conditions. Borrows from the evaluation's
+/// player, build and target so building one doesn't allocate.
+#[derive(Debug, Clone, Copy)]
+pub struct EffectContext<'a> {
     pub on_slayer_task: bool,
     pub in_wilderness: bool,
-    pub target_attributes: Vec<String>,
-    pub combat: String,
+    pub forinthry_surge: bool,
+    pub charge: bool,
+    pub target_attributes: &'a [String],
+    /// Target name, for mechanics registered against one monster.
+    pub target_name: &'a str,
+    pub combat: &'a str,
+    /// Every equipped item ID, weapon included.
+    pub equipped: &'a [u32],
+    /// Tags of the equipped items.
+    pub equipped_tags: &'a [String],
+    /// Whether the weapon has charges (`weapon_config.uncharged` unset).
+    pub weapon_charged: bool,
+    /// The spell cast, for magic builds that cast one.
+    pub spell: Option<&'a Spell>,
+    /// Target width in tiles, for size-scaled ops.
+    pub target_size: u8,
+    /// Target magic for the Twisted bow's scaling (`twisted_bow_magic`).
+    pub target_magic: u16,
+    /// Player hitpoints level and hitpoints left, for hitpoint-scaled ops.
+    pub hitpoints: u8,
+    pub current_hitpoints: u8,
+}
+
+impl<'a> EffectContext<'a> {
+    /// Build the condition context for an evaluation.
+    pub fn from_eval(ctx: &EvalContext<'a>) -> Self {
+        let combat = match ctx.build.combat {
+            Combat::Melee => "melee",
+            Combat::Ranged => "ranged",
+            Combat::Magic => "magic",
+        };
+        Self {
+            on_slayer_task: ctx.player.on_slayer_task,
+            in_wilderness: ctx.player.in_wilderness,
+            forinthry_surge: ctx.player.forinthry_surge,
+            charge: ctx.player.charge,
+            target_attributes: &ctx.target.attributes,
+            target_name: &ctx.target.name,
+            combat,
+            equipped: &ctx.build.equipped,
+            equipped_tags: &ctx.build.equipped_tags,
+            weapon_charged: ctx.build.weapon_charged,
+            spell: ctx.build.spell.as_ref(),
+            target_size: ctx.target.size,
+            target_magic: ctx.target.twisted_bow_magic(),
+            hitpoints: ctx.player.hitpoints,
+            current_hitpoints: ctx.player.current_hitpoints,
+        }
+    }
+
+    /// The fixed part of a context: player situation and target only, with
+    /// no build. For `EffectRegistry::specialize`.
+    pub fn for_situation(player: &PlayerState, target: &'a TargetResolved) -> Self {
+        Self {
+            on_slayer_task: player.on_slayer_task,
+            in_wilderness: player.in_wilderness,
+            forinthry_surge: player.forinthry_surge,
+            charge: player.charge,
+            target_attributes: &target.attributes,
+            target_name: &target.name,
+            combat: "",
+            equipped: &[],
+            equipped_tags: &[],
+            weapon_charged: true,
+            spell: None,
+            target_size: target.size,
+            target_magic: target.twisted_bow_magic(),
+            hitpoints: player.hitpoints,
+            current_hitpoints: player.current_hitpoints,
+        }
+    }
 }
 
 impl Effect {
     /// Check if the effect's conditions are met.
     pub fn conditions_met(&self, ctx: &EffectContext) -> bool {
+        self.situation_conditions_met(ctx) && self.setup_conditions_met(ctx)
+    }
+
+    /// Conditions on the player's situation and the target (slayer task,
+    /// wilderness, target attributes), which stay fixed while gear varies.
+    fn situation_conditions_met(&self, ctx: &EffectContext) -> bool {
         let cond = &self.condition;
 
         // Check slayer task condition
@@ -110,6 +342,15 @@ impl Effect {
                 return false;
             }
         }
+        if cond
+            .forinthry_surge
+            .is_some_and(|required| ctx.forinthry_surge != required)
+        {
+            return false;
+        }
+        if cond.charge.is_some_and(|required| ctx.charge != required) {
+            return false;
+        }
 
         // Check target attributes
         if !cond.target_attributes.is_empty() {
@@ -122,33 +363,121 @@ impl Effect {
             }
         }
 
+        true
+    }
+
+    /// Conditions on the build: combat style and equipped items.
+    fn setup_conditions_met(&self, ctx: &EffectContext) -> bool {
+        let cond = &self.condition;
+
         // Check combat style
         if let Some(ref required_combat) = cond.combat {
-            if ctx.combat.to_lowercase() != required_combat.to_lowercase() {
+            if !ctx.combat.eq_ignore_ascii_case(required_combat) {
                 return false;
             }
         }
 
+        // Check equipment
+        if !cond
+            .equipped
+            .iter()
+            .all(|any_of| any_of.iter().any(|id| ctx.equipped.contains(id)))
+        {
+            return false;
+        }
+        if !cond
+            .equipped_tags
+            .iter()
+            .all(|tag| ctx.equipped_tags.contains(tag))
+        {
+            return false;
+        }
+        if cond
+            .weapon_charged
+            .is_some_and(|required| ctx.weapon_charged != required)
+        {
+            return false;
+        }
+
+        // Check the spell cast
+        if let Some(ref required_element) = cond.spell_element {
+            if ctx.spell.and_then(|s| s.element.as_ref()) != Some(required_element) {
+                return false;
+            }
+        }
+        if let Some(ref required_spellbook) = cond.spellbook {
+            if ctx.spell.and_then(|s| s.spellbook.as_ref()) != Some(required_spellbook) {
+                return false;
+            }
+        }
+        if !cond
+            .runes
+            .iter()
+            .all(|rune| ctx.spell.is_some_and(|s| s.runes.contains_key(rune)))
+        {
+            return false;
+        }
+        if !cond.spells.is_empty()
+            && !ctx
+                .spell
+                .is_some_and(|s| cond.spells.iter().any(|name| s.is(name)))
+        {
+            return false;
+        }
+
         true
     }
+
+    /// Whether the effect is keyed on one of these target attributes.
+    pub fn targets_any(&self, attributes: &[String]) -> bool {
+        self.condition
+            .target_attributes
+            .iter()
+            .any(|attr| attributes.contains(attr))
+    }
 }
 
 /// Mutable state that effects modify during evaluation.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EngineState {
-    pub effective_attack: i32,
-    pub effective_strength: i32,
-    pub max_hit: i32,
-    pub attack_roll: i32,
+    pub effective_attack: i64,
+    pub effective_strength: i64,
+    pub max_hit: i64,
+    pub attack_roll: i64,
     pub accuracy: f64,
+    pub expected_damage: f64,
+    /// Magic damage from effects, in percentage points (`Stat::MagicDamage`).
+    pub magic_damage: i64,
+    /// Spell base max hit from effects (`Stat::SpellMaxHit`).
+    pub spell_max_hit: i64,
+    /// Least damage a landed hit deals, in percent of its max hit
+    /// (`Op::MinimumHit`).
+    pub min_hit_pct: i64,
+}
+
+impl Default for EngineState {
+    fn default() -> Self {
+        Self {
+            effective_attack: 0,
+            effective_strength: 0,
+            max_hit: 0,
+            attack_roll: 0,
+            accuracy: 0.0,
+            expected_damage: 1.0,
+            magic_damage: 0,
+            spell_max_hit: 0,
+            min_hit_pct: 0,
+        }
+    }
 }
 
 impl EngineState {
-    /// Apply an effect operation to the state.
-    pub fn apply(&mut self, op: &Op) {
+    /// Apply an effect operation to the state. `ctx` supplies the target
+    /// size and player hitpoints for the ops scaled by them.
+    pub fn apply(&mut self, op: &Op, ctx: &EffectContext) {
         match op {
             Op::Multiply { stat, num, den } => {
-                let mult = |v: i32| (v * num) / den;
+                let mult = |v: i64| v.saturating_mul(i64::from(*num)) / i64::from(*den);
                 match stat {
                     Stat::EffectiveAttack => self.effective_attack = mult(self.effective_attack),
                     Stat::EffectiveStrength => {
@@ -157,23 +486,106 @@ impl EngineState {
                     Stat::MaxHit => self.max_hit = mult(self.max_hit),
                     Stat::AttackRoll => self.attack_roll = mult(self.attack_roll),
                     Stat::Accuracy => self.accuracy = self.accuracy * (*num as f64) / (*den as f64),
+                    Stat::ExpectedDamage => {
+                        self.expected_damage = self.expected_damage * (*num as f64) / (*den as f64)
+                    }
+                    Stat::MagicDamage => self.magic_damage = mult(self.magic_damage),
+                    Stat::SpellMaxHit => self.spell_max_hit = mult(self.spell_max_hit),
                 }
             }
             Op::Add { stat, value } => match stat {
-                Stat::EffectiveAttack => self.effective_attack += value,
-                Stat::EffectiveStrength => self.effective_strength += value,
-                Stat::MaxHit => self.max_hit += value,
-                Stat::AttackRoll => self.attack_roll += value,
+                Stat::EffectiveAttack => self.effective_attack += i64::from(*value),
+                Stat::EffectiveStrength => self.effective_strength += i64::from(*value),
+                Stat::MaxHit => self.max_hit += i64::from(*value),
+                Stat::AttackRoll => self.attack_roll += i64::from(*value),
                 Stat::Accuracy => self.accuracy += *value as f64,
+                Stat::ExpectedDamage => self.expected_damage += *value as f64,
+                Stat::MagicDamage => self.magic_damage += i64::from(*value),
+                Stat::SpellMaxHit => self.spell_max_hit += i64::from(*value),
             },
+            Op::AddPerTargetSize {
+                stat,
+                value,
+                max_size,
+            } => {
+                let tiles = i32::from(ctx.target_size.min(*max_size));
+                self.apply(
+                    &Op::Add {
+                        stat: *stat,
+                        value: value.saturating_mul(tiles),
+                    },
+                    ctx,
+                );
+            }
+            Op::MultiplyByMissingHitpoints { stat } => {
+                // In ten-thousandths: missing% * level%
+                let missing = i32::from(ctx.hitpoints.saturating_sub(ctx.current_hitpoints));
+                let bonus = missing * i32::from(ctx.hitpoints);
+                self.apply(
+                    &Op::Multiply {
+                        stat: *stat,
+                        num: 10_000 + bonus,
+                        den: 10_000,
+                    },
+                    ctx,
+                );
+            }
+            Op::IgnoreDefenceChance {
+                num,
+                den,
+                extra_damage,
+            } => {
+                // Per attack: (1 - p) * accuracy * avg + p * (avg + extra),
+                // with avg = max hit / 2 for the uniform 0..=max roll
+                let chance = f64::from(*num) / f64::from(*den);
+                let landed = chance + (1.0 - chance) * self.accuracy;
+                let plain = landed * self.max_hit as f64 / 2.0;
+                if plain > 0.0 {
+                    let bonus = chance * f64::from(*extra_damage);
+                    self.expected_damage *= (plain + bonus) / plain;
+                }
+                self.accuracy = landed;
+            }
+            Op::TwistedBowScaling { stat } => {
+                let pct = match stat {
+                    Stat::AttackRoll | Stat::Accuracy => twisted_bow_accuracy_pct(ctx.target_magic),
+                    _ => twisted_bow_damage_pct(ctx.target_magic),
+                };
+                self.apply(
+                    &Op::Multiply {
+                        stat: *stat,
+                        num: pct,
+                        den: 100,
+                    },
+                    ctx,
+                );
+            }
+            Op::MinimumHit { pct } => {
+                self.min_hit_pct = self.min_hit_pct.max(i64::from(*pct).clamp(0, 100));
+            }
         }
     }
 }
 
-/// Collection of effects that can be applied.
-#[derive(Debug, Clone, Default)]
+/// Collection of effects that can be applied, and the target mechanics
+/// registered alongside them.
+#[derive(Clone, Default)]
 pub struct EffectRegistry {
     pub effects: Vec<Effect>,
+    mechanics: Vec<Arc<dyn TargetMechanic>>,
+    weapon_mechanics: Vec<Arc<dyn WeaponMechanic>>,
+}
+
+impl fmt::Debug for EffectRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mechanics: Vec<&str> = self.mechanics.iter().map(|m| m.id()).collect();
+        let weapon_mechanics: Vec<&str> = self.weapon_mechanics.iter().map(|m| m.id()).collect();
+        f.debug_struct("EffectRegistry")
+            .field("effects", &self.effects)
+            .field("mechanics", &mechanics)
+            .field("weapon_mechanics", &weapon_mechanics)
+            .finish()
+    }
 }
 
 impl EffectRegistry {
@@ -186,6 +598,98 @@ impl EffectRegistry {
         self.effects.push(effect);
     }
 
+    /// Add a target mechanic. It runs after the effects at each stage, in
+    /// the order mechanics were registered.
+    pub fn register_mechanic(&mut self, mechanic: Box<dyn TargetMechanic>) {
+        self.mechanics.push(Arc::from(mechanic));
+    }
+
+    /// IDs of the registered target mechanics.
+    pub fn mechanic_ids(&self) -> Vec<&str> {
+        self.mechanics.iter().map(|m| m.id()).collect()
+    }
+
+    /// Add a weapon mechanic. Mechanics for the same weapon run in the
+    /// order they were registered; the last one with hit ranges sets them.
+    pub fn register_weapon_mechanic(&mut self, mechanic: Box<dyn WeaponMechanic>) {
+        self.weapon_mechanics.push(Arc::from(mechanic));
+    }
+
+    /// The registered weapon mechanics that apply to `build`.
+    pub fn weapon_mechanics_for(&self, build: &BuildResolved) -> Vec<&dyn WeaponMechanic> {
+        self.weapon_mechanics
+            .iter()
+            .filter(|m| m.applies(build))
+            .map(|m| m.as_ref())
+            .collect()
+    }
+
+    /// Every item ID some effect's equipment condition mentions.
+    pub fn referenced_items(&self) -> HashSet<u32> {
+        self.effects
+            .iter()
+            .flat_map(|e| e.condition.equipped.iter().flatten().copied())
+            .collect()
+    }
+
+    /// Every item tag some effect's equipment condition mentions.
+    pub fn referenced_tags(&self) -> HashSet<&str> {
+        self.effects
+            .iter()
+            .flat_map(|e| e.condition.equipped_tags.iter().map(String::as_str))
+            .collect()
+    }
+
+    /// The effects whose slayer task, wilderness and target conditions hold
+    /// in `ctx`, with those conditions dropped, so evaluations that only
+    /// change the build (gear searches, style ranking) re-check just combat
+    /// style and equipment. Only valid for the player situation and target
+    /// `ctx` was built from.
+    pub fn specialize(&self, ctx: &EffectContext) -> EffectRegistry {
+        let effects = self
+            .effects
+            .iter()
+            .filter(|e| e.situation_conditions_met(ctx))
+            .map(|e| {
+                let mut effect = e.clone();
+                effect.condition.on_slayer_task = None;
+                effect.condition.in_wilderness = None;
+                effect.condition.forinthry_surge = None;
+                effect.condition.charge = None;
+                effect.condition.target_attributes.clear();
+                effect
+            })
+            .collect();
+        EffectRegistry {
+            effects,
+            mechanics: self.mechanics.clone(),
+            weapon_mechanics: self.weapon_mechanics.clone(),
+        }
+    }
+
+    /// The effects placed by `profile` instead of the live order: each
+    /// effect with an `order` slot the profile lists takes its placement.
+    pub fn reordered(&self, profile: &OrderingProfile) -> EffectRegistry {
+        let effects = self
+            .effects
+            .iter()
+            .map(|e| {
+                let mut effect = e.clone();
+                if let Some(placement) = e.order.as_deref().and_then(|slot| profile.placement(slot))
+                {
+                    effect.stage = placement.stage;
+                    effect.priority = placement.priority;
+                }
+                effect
+            })
+            .collect();
+        EffectRegistry {
+            effects,
+            mechanics: self.mechanics.clone(),
+            weapon_mechanics: self.weapon_mechanics.clone(),
+        }
+    }
+
     /// Get effects for a specific stage, sorted by priority.
     pub fn for_stage(&self, stage: Stage) -> Vec<&Effect> {
         let mut effects: Vec<_> = self.effects.iter().filter(|e| e.stage == stage).collect();
@@ -193,18 +697,26 @@ impl EffectRegistry {
         effects
     }
 
-    /// Apply all effects for a stage that meet their conditions.
-    /// Handles exclusive groups (only first matching effect in group applies).
-    pub fn apply_stage(
-        &self,
-        stage: Stage,
-        ctx: &EffectContext,
-        state: &mut EngineState,
-    ) -> Vec<String> {
-        let mut applied = Vec::new();
-        let mut used_groups = std::collections::HashSet::new();
+    /// Effects for a stage that meet their conditions, in application order.
+    pub fn select_stage(&self, stage: Stage, ctx: &EffectContext) -> Vec<&Effect> {
+        self.applicable(ctx)
+            .into_iter()
+            .filter(|e| e.stage == stage)
+            .collect()
+    }
+
+    /// Every effect that would apply, across all stages in pipeline order.
+    /// Handles exclusive groups: only the first matching effect in a group
+    /// applies, and a group can span stages (an imbued salve's `pre_rolls`
+    /// magic damage rules out the slayer helm's `post_max_hit` multiplier).
+    pub fn applicable(&self, ctx: &EffectContext) -> Vec<&Effect> {
+        let mut selected = Vec::new();
+        let mut used_groups = HashSet::new();
 
-        for effect in self.for_stage(stage) {
+        for effect in Stage::ALL
+            .into_iter()
+            .flat_map(|stage| self.for_stage(stage))
+        {
             // Check exclusive group
             if let Some(ref group) = effect.exclusive_group {
                 if used_groups.contains(group) {
@@ -217,9 +729,7 @@ impl EffectRegistry {
                 continue;
             }
 
-            // Apply the effect
-            state.apply(&effect.op);
-            applied.push(effect.id.clone());
+            selected.push(effect);
 
             // Mark exclusive group as used
             if let Some(ref group) = effect.exclusive_group {
@@ -227,90 +737,256 @@ impl EffectRegistry {
             }
         }
 
+        selected
+    }
+
+    /// Apply all effects for a stage that meet their conditions, then the
+    /// target mechanics that apply.
+    pub fn apply_stage(
+        &self,
+        stage: Stage,
+        ctx: &EffectContext,
+        state: &mut EngineState,
+    ) -> Vec<String> {
+        let mut applied: Vec<String> = self
+            .select_stage(stage, ctx)
+            .into_iter()
+            .map(|effect| {
+                state.apply(&effect.op, ctx);
+                effect.id.clone()
+            })
+            .collect();
+        for mechanic in &self.mechanics {
+            if mechanic.applies(ctx) && mechanic.apply(stage, ctx, state) {
+                applied.push(mechanic.id().to_string());
+            }
+        }
         applied
     }
 }
 
+/// Combined multiplier per stat for a list of effects, ignoring rounding
+/// between steps. Stats appear in the order they are first touched; flat
+/// `Add` ops aren't included.
+pub fn multiplier_stack(effects: &[&Effect]) -> Vec<(Stat, f64)> {
+    let mut stack: Vec<(Stat, f64)> = Vec::new();
+    for effect in effects {
+        if let Op::Multiply { stat, num, den } = effect.op {
+            let factor = num as f64 / den as f64;
+            match stack.iter_mut().find(|(s, _)| *s == stat) {
+                Some((_, total)) => *total *= factor,
+                None => stack.push((stat, factor)),
+            }
+        }
+    }
+    stack
+}
+
 // =============================================================================
 // Built-in Effects
 // =============================================================================
 
-/// Create the default effect registry with common effects.
+/// Built-in effects shipped as data (data/effects): (file name, contents).
+/// The files are embedded unparsed and parsed on first use, so a bad file
+/// builds fine; `test_shipped_effects_are_valid` is what catches it.
+const SHIPPED_EFFECTS: [(&str, &str); 10] = [
+    (
+        "slayer_salve_void.json",
+        include_str!("../data/effects/slayer_salve_void.json"),
+    ),
+    (
+        "obsidian.json",
+        include_str!("../data/effects/obsidian.json"),
+    ),
+    (
+        "dragonbane.json",
+        include_str!("../data/effects/dragonbane.json"),
+    ),
+    (
+        "demonbane.json",
+        include_str!("../data/effects/demonbane.json"),
+    ),
+    ("leafy.json", include_str!("../data/effects/leafy.json")),
+    (
+        "wilderness.json",
+        include_str!("../data/effects/wilderness.json"),
+    ),
+    ("avarice.json", include_str!("../data/effects/avarice.json")),
+    ("runes.json", include_str!("../data/effects/runes.json")),
+    ("spells.json", include_str!("../data/effects/spells.json")),
+    (
+        "twisted_bow.json",
+        include_str!("../data/effects/twisted_bow.json"),
+    ),
+];
+
+const COLOSSAL_BLADE: u32 = 27021;
+/// Barrows sets: helm, weapon, body, legs.
+const AHRIMS_SET: [u32; 4] = [4708, 4710, 4712, 4714];
+const DHAROKS_SET: [u32; 4] = [4716, 4718, 4720, 4722];
+const KARILS_SET: [u32; 4] = [4732, 4734, 4736, 4738];
+const VERACS_SET: [u32; 4] = [4753, 4755, 4757, 4759];
+/// Charged and fully charged.
+const AMULET_OF_THE_DAMNED: [u32; 2] = [12851, 12853];
+/// Keris and the keris partisans (plain, breaching, corruption, sun, and
+/// amascut inside and outside ToA).
+const KERIS: [u32; 7] = [10581, 25979, 25981, 27287, 27291, 30891, 30892];
+
+/// A condition requiring every item in `set` (each its own any-of group).
+fn every_piece(set: &[u32]) -> Vec<Vec<u32>> {
+    set.iter().map(|&id| vec![id]).collect()
+}
+
+/// A Barrows set worn with the amulet of the damned.
+fn damned_set(set: &[u32]) -> Vec<Vec<u32>> {
+    let mut equipped = every_piece(set);
+    equipped.push(AMULET_OF_THE_DAMNED.to_vec());
+    equipped
+}
+
+/// An effect declared here, placed by its `ordering` slot.
+fn ordered(id: &str, name: &str, order: &str, condition: Condition, op: Op) -> Effect {
+    let slot = ordering::slot_named(order).unwrap_or_else(|| panic!("no order slot {order}"));
+    Effect {
+        id: id.to_string(),
+        name: name.to_string(),
+        stage: slot.stage,
+        priority: slot.priority,
+        order: Some(order.to_string()),
+        exclusive_group: None,
+        condition,
+        op,
+    }
+}
+
+/// The built-in registry, created once and shared by every evaluation.
+pub fn default_registry() -> &'static EffectRegistry {
+    static REGISTRY: OnceLock<EffectRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(default_effects)
+}
+
+/// Parse a data/effects file: a JSON array of effects.
+pub fn parse_effects(json: &str) -> Result<Vec<Effect>, serde_json::Error> {
+    serde_json::from_str(json)
+}
+
+/// Create the default effect registry with common effects: the shipped
+/// data/effects files (slayer helm, salve, void, obsidian), then the ones
+/// declared here.
 pub fn default_effects() -> EffectRegistry {
     let mut registry = EffectRegistry::new();
+    for (file, json) in SHIPPED_EFFECTS {
+        let effects = parse_effects(json)
+            .unwrap_or_else(|e| panic!("shipped effects file {file} is invalid: {e}"));
+        for effect in effects {
+            registry.register(effect);
+        }
+    }
 
-    // Slayer helm (i) - 7/6 multiplier to max hit and accuracy on task
-    registry.register(Effect {
-        id: "slayer_helm_melee".to_string(),
-        name: "Slayer helm (i) - Melee".to_string(),
-        stage: Stage::PostMaxHit,
-        priority: 50,
-        exclusive_group: Some("slayer_salve".to_string()),
-        condition: Condition {
-            on_slayer_task: Some(true),
+    // Colossal blade - +2 max hit per tile of target size, up to 5 tiles.
+    // Added to the base max hit, before the slayer helm and salve.
+    registry.register(ordered(
+        "colossal_blade_size",
+        "Colossal blade - Target size",
+        "colossal_blade",
+        Condition {
             combat: Some("melee".to_string()),
+            equipped: vec![vec![COLOSSAL_BLADE]],
             ..Default::default()
         },
-        op: Op::Multiply {
+        Op::AddPerTargetSize {
             stat: Stat::MaxHit,
-            num: 7,
-            den: 6,
+            value: 2,
+            max_size: 5,
         },
-    });
-
-    // Salve amulet (ei) - 6/5 multiplier against undead
-    registry.register(Effect {
-        id: "salve_ei_melee".to_string(),
-        name: "Salve amulet (ei) - Melee".to_string(),
-        stage: Stage::PostMaxHit,
-        priority: 50,
-        exclusive_group: Some("slayer_salve".to_string()),
-        condition: Condition {
-            target_attributes: vec!["undead".to_string()],
+    ));
+
+    // Dharok's set - max hit x (1 + missing hp/100 * hp level/100) with all
+    // four pieces, after the slayer helm and salve
+    registry.register(ordered(
+        "dharok_set",
+        "Dharok's set - Missing hitpoints",
+        "dharok",
+        Condition {
             combat: Some("melee".to_string()),
+            equipped: every_piece(&DHAROKS_SET),
             ..Default::default()
         },
-        op: Op::Multiply {
-            stat: Stat::MaxHit,
-            num: 6,
-            den: 5,
-        },
-    });
-
-    // Void Knight melee - 11/10 multiplier to effective levels
-    registry.register(Effect {
-        id: "void_melee_str".to_string(),
-        name: "Void Knight - Melee Strength".to_string(),
-        stage: Stage::PostEffectiveLevel,
-        priority: 100,
-        exclusive_group: None,
-        condition: Condition {
+        Op::MultiplyByMissingHitpoints { stat: Stat::MaxHit },
+    ));
+
+    // Verac's set - 1 in 4 hits ignores defence and deals 1 more damage,
+    // after every other accuracy change
+    registry.register(ordered(
+        "verac_set",
+        "Verac's set - Defiler",
+        "verac",
+        Condition {
             combat: Some("melee".to_string()),
+            equipped: every_piece(&VERACS_SET),
             ..Default::default()
         },
-        op: Op::Multiply {
-            stat: Stat::EffectiveStrength,
-            num: 11,
-            den: 10,
+        Op::IgnoreDefenceChance {
+            num: 1,
+            den: 4,
+            extra_damage: 1,
         },
-    });
+    ));
 
-    registry.register(Effect {
-        id: "void_melee_atk".to_string(),
-        name: "Void Knight - Melee Attack".to_string(),
-        stage: Stage::PostEffectiveLevel,
-        priority: 100,
-        exclusive_group: None,
-        condition: Condition {
+    // Amulet of the damned with Ahrim's - 1 in 4 spells deal 30% more, so
+    // a landed hit averages 1 + 0.25 * 0.3 times as much
+    registry.register(ordered(
+        "ahrim_damned",
+        "Ahrim's set + amulet of the damned",
+        "damage_chance",
+        Condition {
+            combat: Some("magic".to_string()),
+            equipped: damned_set(&AHRIMS_SET),
+            ..Default::default()
+        },
+        Op::Multiply {
+            stat: Stat::ExpectedDamage,
+            num: 43,
+            den: 40,
+        },
+    ));
+
+    // Amulet of the damned with Karil's - 1 in 4 attacks hit again for half
+    // the damage, so 1 + 0.25 * 0.5 times as much per attack
+    registry.register(ordered(
+        "karil_damned",
+        "Karil's set + amulet of the damned",
+        "damage_chance",
+        Condition {
+            combat: Some("ranged".to_string()),
+            equipped: damned_set(&KARILS_SET),
+            ..Default::default()
+        },
+        Op::Multiply {
+            stat: Stat::ExpectedDamage,
+            num: 9,
+            den: 8,
+        },
+    ));
+
+    // Keris - 1/51 chance to deal triple damage to kalphites, so a landed
+    // hit averages 1 + 2/51 times as much
+    registry.register(ordered(
+        "keris_kalphite_crit",
+        "Keris - Kalphite critical hit",
+        "damage_chance",
+        Condition {
+            target_attributes: vec!["kalphite".to_string()],
             combat: Some("melee".to_string()),
+            equipped: vec![KERIS.to_vec()],
             ..Default::default()
         },
-        op: Op::Multiply {
-            stat: Stat::EffectiveAttack,
-            num: 11,
-            den: 10,
+        Op::Multiply {
+            stat: Stat::ExpectedDamage,
+            num: 53,
+            den: 51,
         },
-    });
+    ));
 
     registry
 }
@@ -319,6 +995,102 @@ pub fn default_effects() -> EffectRegistry {
 mod tests {
     use super::*;
 
+    const SALVE_AMULET_EI: u32 = 12018;
+    const VOID_MELEE_HELM: u32 = 11665;
+    const VOID_GLOVES: u32 = 8842;
+    const BERSERKER_NECKLACE: u32 = 11128;
+    const OBSIDIAN_ARMOUR: [u32; 3] = [21298, 21301, 21304];
+    const DRAGON_HUNTER_LANCE: u32 = 22978;
+    const ARCLIGHT: u32 = 19675;
+    const CRAWS_BOW: u32 = 22550;
+    const VIGGORAS_CHAINMACE: u32 = 22545;
+    const AMULET_OF_AVARICE: u32 = 22557;
+
+    #[test]
+    fn test_shipped_effects_are_valid() {
+        let registry = default_effects();
+        let mut ids = HashSet::new();
+        for effect in &registry.effects {
+            assert!(ids.insert(effect.id.as_str()), "duplicate id {}", effect.id);
+        }
+        for (file, json) in SHIPPED_EFFECTS {
+            let effects = parse_effects(json).expect(file);
+            assert!(!effects.is_empty(), "{file} is empty");
+            for effect in &effects {
+                if let Op::Multiply { den, .. } = effect.op {
+                    assert!(den > 0, "{} divides by {den}", effect.id);
+                }
+                assert!(
+                    effect
+                        .condition
+                        .equipped
+                        .iter()
+                        .all(|any_of| !any_of.is_empty()),
+                    "{} requires an empty item list",
+                    effect.id
+                );
+                // Shipped effects take their place from the ordering table
+                assert!(effect.order.is_some(), "{} has no order slot", effect.id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_effect_order_slot_sets_stage_and_priority() {
+        let parse = |placement: &str| {
+            let json = format!(
+                r#"[{{"id": "x", "name": "x", {placement},
+                    "op": {{"op": "add", "stat": "max_hit", "value": 1}}}}]"#
+            );
+            parse_effects(&json).map(|effects| (effects[0].stage, effects[0].priority))
+        };
+        assert_eq!(
+            parse(r#""order": "salve""#).unwrap(),
+            (Stage::PostMaxHit, 40)
+        );
+        assert_eq!(
+            parse(r#""order": "salve", "stage": "post_max_hit""#).unwrap(),
+            (Stage::PostMaxHit, 40)
+        );
+        assert_eq!(
+            parse(r#""stage": "pre_rolls", "priority": 7"#).unwrap(),
+            (Stage::PreRolls, 7)
+        );
+        assert!(parse(r#""order": "nowhere""#).is_err());
+        assert!(parse(r#""order": "salve", "priority": 41"#).is_err());
+        assert!(parse(r#""priority": 7"#).is_err());
+    }
+
+    #[test]
+    fn test_reordered_moves_only_slots_the_profile_lists() {
+        let registry = default_registry();
+        let placement = |registry: &EffectRegistry, id: &str| {
+            let effect = registry.effects.iter().find(|e| e.id == id).unwrap();
+            (effect.stage, effect.priority)
+        };
+        let mut profile = OrderingProfile {
+            version: "test".to_string(),
+            slots: Default::default(),
+        };
+        profile.slots.insert(
+            "slayer_helm".to_string(),
+            ordering::Placement {
+                stage: Stage::PostMaxHit,
+                priority: 20,
+            },
+        );
+        let reordered = registry.reordered(&profile);
+        assert_eq!(
+            placement(&reordered, "slayer_helm_melee"),
+            (Stage::PostMaxHit, 20)
+        );
+        assert_eq!(
+            placement(&reordered, "salve_ei_melee"),
+            placement(registry, "salve_ei_melee")
+        );
+        assert_eq!(reordered.effects.len(), registry.effects.len());
+    }
+
     #[test]
     fn test_effect_conditions_slayer_task() {
         let effect = Effect {
@@ -326,6 +1098,7 @@ mod tests {
             name: "Test".to_string(),
             stage: Stage::PostMaxHit,
             priority: 0,
+            order: None,
             exclusive_group: None,
             condition: Condition {
                 on_slayer_task: Some(true),
@@ -341,15 +1114,37 @@ mod tests {
         let ctx_on_task = EffectContext {
             on_slayer_task: true,
             in_wilderness: false,
-            target_attributes: vec![],
-            combat: "melee".to_string(),
+            forinthry_surge: false,
+            charge: false,
+            target_attributes: &[],
+            target_name: "",
+            combat: "melee",
+            equipped: &[],
+            equipped_tags: &[],
+            weapon_charged: true,
+            spell: None,
+            target_size: 1,
+            target_magic: 1,
+            hitpoints: 99,
+            current_hitpoints: 99,
         };
 
         let ctx_off_task = EffectContext {
             on_slayer_task: false,
             in_wilderness: false,
-            target_attributes: vec![],
-            combat: "melee".to_string(),
+            forinthry_surge: false,
+            charge: false,
+            target_attributes: &[],
+            target_name: "",
+            combat: "melee",
+            equipped: &[],
+            equipped_tags: &[],
+            weapon_charged: true,
+            spell: None,
+            target_size: 1,
+            target_magic: 1,
+            hitpoints: 99,
+            current_hitpoints: 99,
         };
 
         assert!(effect.conditions_met(&ctx_on_task));
@@ -369,7 +1164,10 @@ mod tests {
             den: 6,
         };
 
-        state.apply(&op);
+        state.apply(
+            &op,
+            &EffectContext::for_situation(&PlayerState::default(), &TargetResolved::default()),
+        );
         assert_eq!(state.max_hit, 35); // 30 * 7/6 = 35
     }
 
@@ -382,6 +1180,7 @@ mod tests {
             name: "Effect A".to_string(),
             stage: Stage::PostMaxHit,
             priority: 10,
+            order: None,
             exclusive_group: Some("group1".to_string()),
             condition: Condition::default(),
             op: Op::Multiply {
@@ -396,6 +1195,7 @@ mod tests {
             name: "Effect B".to_string(),
             stage: Stage::PostMaxHit,
             priority: 20,
+            order: None,
             exclusive_group: Some("group1".to_string()),
             condition: Condition::default(),
             op: Op::Multiply {
@@ -408,8 +1208,19 @@ mod tests {
         let ctx = EffectContext {
             on_slayer_task: false,
             in_wilderness: false,
-            target_attributes: vec![],
-            combat: "melee".to_string(),
+            forinthry_surge: false,
+            charge: false,
+            target_attributes: &[],
+            target_name: "",
+            combat: "melee",
+            equipped: &[],
+            equipped_tags: &[],
+            weapon_charged: true,
+            spell: None,
+            target_size: 1,
+            target_magic: 1,
+            hitpoints: 99,
+            current_hitpoints: 99,
         };
 
         let mut state = EngineState {
@@ -424,4 +1235,541 @@ mod tests {
         assert_eq!(applied[0], "effect_a");
         assert_eq!(state.max_hit, 20); // 10 * 2 = 20, not 10 * 2 * 3 = 60
     }
+
+    #[test]
+    fn test_equipped_condition_needs_every_group() {
+        let registry = default_effects();
+        let void_atk = registry
+            .effects
+            .iter()
+            .find(|e| e.id == "void_melee_atk")
+            .unwrap();
+
+        let ctx = EffectContext {
+            on_slayer_task: false,
+            in_wilderness: false,
+            forinthry_surge: false,
+            charge: false,
+            target_attributes: &[],
+            target_name: "",
+            combat: "melee",
+            equipped: &[0, VOID_MELEE_HELM, 13072, 8840],
+            equipped_tags: &[],
+            weapon_charged: true,
+            spell: None,
+            target_size: 1,
+            target_magic: 1,
+            hitpoints: 99,
+            current_hitpoints: 99,
+        };
+        assert!(!void_atk.conditions_met(&ctx));
+
+        // Elite top with a regular robe still counts as a set
+        let ctx = EffectContext {
+            equipped: &[0, VOID_MELEE_HELM, 13072, 8840, VOID_GLOVES],
+            equipped_tags: &[],
+            ..ctx
+        };
+        assert!(void_atk.conditions_met(&ctx));
+    }
+
+    #[test]
+    fn test_salve_tier_sets_multiplier_and_styles() {
+        let registry = default_effects();
+        let undead = ["undead".to_string()];
+        let (salve, salve_e, salve_i) = (4081, 10588, 12017);
+        let apply = |neck: u32, combat: &str| {
+            let ctx = EffectContext {
+                on_slayer_task: false,
+                in_wilderness: false,
+                forinthry_surge: false,
+                charge: false,
+                target_attributes: &undead,
+                target_name: "",
+                combat,
+                equipped: &[neck],
+                equipped_tags: &[],
+                weapon_charged: true,
+                spell: None,
+                target_size: 1,
+                target_magic: 1,
+                hitpoints: 99,
+                current_hitpoints: 99,
+            };
+            let mut state = EngineState {
+                max_hit: 60,
+                attack_roll: 30_000,
+                ..Default::default()
+            };
+            registry.apply_stage(Stage::PreRolls, &ctx, &mut state);
+            registry.apply_stage(Stage::PostMaxHit, &ctx, &mut state);
+            (state.max_hit, state.attack_roll, state.magic_damage)
+        };
+
+        assert_eq!(apply(salve, "melee"), (70, 35_000, 0));
+        assert_eq!(apply(salve_e, "melee"), (72, 36_000, 0));
+        assert_eq!(apply(salve_i, "melee"), (70, 35_000, 0));
+        assert_eq!(apply(SALVE_AMULET_EI, "melee"), (72, 36_000, 0));
+        // Only imbued amulets work with ranged and magic
+        for combat in ["ranged", "magic"] {
+            assert_eq!(apply(salve, combat), (60, 30_000, 0));
+            assert_eq!(apply(salve_e, combat), (60, 30_000, 0));
+        }
+        assert_eq!(apply(salve_i, "ranged"), (70, 35_000, 0));
+        assert_eq!(apply(SALVE_AMULET_EI, "ranged"), (72, 36_000, 0));
+        // Magic damage is added to the gear's rather than multiplying the max hit
+        assert_eq!(apply(salve_i, "magic"), (60, 34_500, 15));
+        assert_eq!(apply(SALVE_AMULET_EI, "magic"), (60, 36_000, 20));
+    }
+
+    #[test]
+    fn test_salve_takes_precedence_and_stacks_multipliers() {
+        let registry = default_effects();
+        let undead = ["undead".to_string()];
+        let ctx = EffectContext {
+            on_slayer_task: true,
+            in_wilderness: false,
+            forinthry_surge: false,
+            charge: false,
+            target_attributes: &undead,
+            target_name: "",
+            combat: "melee",
+            equipped: &[0, 11865, SALVE_AMULET_EI],
+            equipped_tags: &[],
+            weapon_charged: true,
+            spell: None,
+            target_size: 1,
+            target_magic: 1,
+            hitpoints: 99,
+            current_hitpoints: 99,
+        };
+
+        let applied = registry.applicable(&ctx);
+        let ids: Vec<&str> = applied.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["salve_ei_melee", "salve_ei_melee_accuracy"]);
+
+        let stack = multiplier_stack(&applied);
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack[0].0, Stat::MaxHit);
+        assert!((stack[0].1 - 1.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_specialize_drops_situation_conditions() {
+        let registry = default_effects();
+        let undead = ["undead".to_string()];
+        let situation = EffectContext {
+            on_slayer_task: false,
+            in_wilderness: false,
+            forinthry_surge: false,
+            charge: false,
+            target_attributes: &undead,
+            target_name: "",
+            combat: "",
+            equipped: &[],
+            equipped_tags: &[],
+            weapon_charged: true,
+            spell: None,
+            target_size: 1,
+            target_magic: 1,
+            hitpoints: 99,
+            current_hitpoints: 99,
+        };
+        let specialized = registry.specialize(&situation);
+        assert!(specialized.effects.len() < registry.effects.len());
+        assert!(specialized
+            .effects
+            .iter()
+            .all(|e| e.condition.on_slayer_task.is_none()
+                && e.condition.target_attributes.is_empty()));
+
+        // Same effects for any build in that situation
+        for equipped in [&[0, 11865, SALVE_AMULET_EI][..], &[0, 11865], &[0]] {
+            let ctx = EffectContext {
+                combat: "melee",
+                equipped,
+                ..situation
+            };
+            let ids = |r: &EffectRegistry| -> Vec<String> {
+                r.applicable(&ctx).iter().map(|e| e.id.clone()).collect()
+            };
+            assert_eq!(ids(&specialized), ids(&registry));
+        }
+    }
+
+    #[test]
+    fn test_colossal_blade_scales_with_target_size() {
+        let registry = default_effects();
+        let max_hit_against = |size| {
+            let target = TargetResolved {
+                size,
+                ..TargetResolved::default()
+            };
+            let ctx = EffectContext {
+                combat: "melee",
+                equipped: &[COLOSSAL_BLADE],
+                ..EffectContext::for_situation(&PlayerState::default(), &target)
+            };
+            let mut state = EngineState {
+                max_hit: 40,
+                ..Default::default()
+            };
+            registry.apply_stage(Stage::PostMaxHit, &ctx, &mut state);
+            state.max_hit
+        };
+        assert_eq!(max_hit_against(1), 42);
+        assert_eq!(max_hit_against(3), 46);
+        // Capped at 5 tiles
+        assert_eq!(max_hit_against(7), 50);
+    }
+
+    #[test]
+    fn test_dharok_scales_with_missing_hitpoints() {
+        let registry = default_effects();
+        let target = TargetResolved::default();
+        let full_set = DHAROKS_SET;
+        let max_hit_at = |current_hitpoints, equipped: &[u32]| {
+            let player = PlayerState {
+                current_hitpoints,
+                ..PlayerState::default()
+            };
+            let ctx = EffectContext {
+                combat: "melee",
+                equipped,
+                ..EffectContext::for_situation(&player, &target)
+            };
+            let mut state = EngineState {
+                max_hit: 50,
+                ..Default::default()
+            };
+            registry.apply_stage(Stage::PostMaxHit, &ctx, &mut state);
+            state.max_hit
+        };
+        assert_eq!(max_hit_at(99, &full_set), 50);
+        // 98 missing of 99: 50 * (10000 + 98 * 99) / 10000 = 98.51
+        assert_eq!(max_hit_at(1, &full_set), 98);
+        // 49 missing: 50 * 1.4851
+        assert_eq!(max_hit_at(50, &full_set), 74);
+        // Three pieces aren't a set
+        assert_eq!(max_hit_at(1, &full_set[..3]), 50);
+    }
+
+    #[test]
+    fn test_verac_blends_accuracy_and_damage() {
+        let registry = default_effects();
+        let (player, target) = (PlayerState::default(), TargetResolved::default());
+        let full_set = VERACS_SET;
+        let after = |accuracy, equipped: &[u32]| {
+            let ctx = EffectContext {
+                combat: "melee",
+                equipped,
+                ..EffectContext::for_situation(&player, &target)
+            };
+            let mut state = EngineState {
+                max_hit: 30,
+                accuracy,
+                ..Default::default()
+            };
+            registry.apply_stage(Stage::PostAccuracy, &ctx, &mut state);
+            state
+        };
+
+        // Lands 0.25 + 0.75 * 0.5; per attack 0.375 * 15 + 0.25 * 16
+        let state = after(0.5, &full_set);
+        assert!((state.accuracy - 0.625).abs() < 1e-12);
+        let per_attack = state.accuracy * 15.0 * state.expected_damage;
+        assert!((per_attack - 9.625).abs() < 1e-12);
+
+        // Lands a quarter of the time even at 0 accuracy
+        let state = after(0.0, &full_set);
+        assert!((state.accuracy - 0.25).abs() < 1e-12);
+        assert!((state.accuracy * 15.0 * state.expected_damage - 4.0).abs() < 1e-12);
+
+        // Three pieces aren't a set
+        let state = after(0.5, &full_set[1..]);
+        assert_eq!(state.accuracy, 0.5);
+        assert_eq!(state.expected_damage, 1.0);
+    }
+
+    #[test]
+    fn test_berserker_necklace_needs_obsidian_weapon() {
+        let registry = default_effects();
+        let (player, target) = (PlayerState::default(), TargetResolved::default());
+        let obsidian = ["obsidian_weapon".to_string()];
+        let max_hit_with = |equipped: &[u32], equipped_tags: &[String]| {
+            let ctx = EffectContext {
+                combat: "melee",
+                equipped,
+                equipped_tags,
+                ..EffectContext::for_situation(&player, &target)
+            };
+            let mut state = EngineState {
+                max_hit: 41,
+                ..Default::default()
+            };
+            registry.apply_stage(Stage::PostMaxHit, &ctx, &mut state);
+            state.max_hit
+        };
+        // floor(41 * 6 / 5)
+        assert_eq!(max_hit_with(&[BERSERKER_NECKLACE], &obsidian), 49);
+        assert_eq!(max_hit_with(&[BERSERKER_NECKLACE], &[]), 41);
+        assert_eq!(max_hit_with(&[], &obsidian), 41);
+    }
+
+    #[test]
+    fn test_obsidian_set_stacks_before_berserker_necklace() {
+        let registry = default_effects();
+        let (player, target) = (PlayerState::default(), TargetResolved::default());
+        let obsidian = ["obsidian_weapon".to_string()];
+        let apply = |equipped: &[u32]| {
+            let ctx = EffectContext {
+                combat: "melee",
+                equipped,
+                equipped_tags: &obsidian,
+                ..EffectContext::for_situation(&player, &target)
+            };
+            let mut state = EngineState {
+                max_hit: 41,
+                attack_roll: 20_000,
+                ..Default::default()
+            };
+            registry.apply_stage(Stage::PostMaxHit, &ctx, &mut state);
+            (state.max_hit, state.attack_roll)
+        };
+
+        // floor(41 * 11/10) = 45
+        assert_eq!(apply(&OBSIDIAN_ARMOUR), (45, 22_000));
+        // Then the necklace: floor(45 * 6/5) = 54
+        let mut with_necklace = OBSIDIAN_ARMOUR.to_vec();
+        with_necklace.push(BERSERKER_NECKLACE);
+        assert_eq!(apply(&with_necklace), (54, 22_000));
+        // Two pieces aren't a set
+        assert_eq!(apply(&OBSIDIAN_ARMOUR[1..]), (41, 20_000));
+    }
+
+    #[test]
+    fn test_revenant_weapons_need_wilderness_and_charges() {
+        let registry = default_effects();
+        let apply = |in_wilderness, weapon_charged| {
+            let ctx = EffectContext {
+                on_slayer_task: false,
+                in_wilderness,
+                forinthry_surge: false,
+                charge: false,
+                target_attributes: &[],
+                target_name: "",
+                combat: "ranged",
+                equipped: &[CRAWS_BOW],
+                equipped_tags: &[],
+                weapon_charged,
+                spell: None,
+                target_size: 1,
+                target_magic: 1,
+                hitpoints: 99,
+                current_hitpoints: 99,
+            };
+            let mut state = EngineState {
+                max_hit: 30,
+                attack_roll: 20_000,
+                ..Default::default()
+            };
+            registry.apply_stage(Stage::PostMaxHit, &ctx, &mut state);
+            (state.max_hit, state.attack_roll)
+        };
+
+        assert_eq!(apply(true, true), (45, 30_000));
+        assert_eq!(apply(true, false), (30, 20_000));
+        assert_eq!(apply(false, true), (30, 20_000));
+    }
+
+    #[test]
+    fn test_slayer_helm_variant_picks_the_styles_it_boosts() {
+        let registry = default_effects();
+        let (black_mask, slayer_helmet, slayer_helmet_i) = (8921, 11864, 11865);
+        let apply = |head: u32, combat: &str| {
+            let ctx = EffectContext {
+                on_slayer_task: true,
+                in_wilderness: false,
+                forinthry_surge: false,
+                charge: false,
+                target_attributes: &[],
+                target_name: "",
+                combat,
+                equipped: &[head],
+                equipped_tags: &[],
+                weapon_charged: true,
+                spell: None,
+                target_size: 1,
+                target_magic: 1,
+                hitpoints: 99,
+                current_hitpoints: 99,
+            };
+            let mut state = EngineState {
+                max_hit: 40,
+                attack_roll: 24_000,
+                ..Default::default()
+            };
+            registry.apply_stage(Stage::PostMaxHit, &ctx, &mut state);
+            (state.max_hit, state.attack_roll)
+        };
+
+        // Every variant boosts melee by 7/6
+        for head in [black_mask, slayer_helmet, slayer_helmet_i] {
+            assert_eq!(apply(head, "melee"), (46, 28_000));
+        }
+        // Only imbued ones boost ranged and magic, by 23/20
+        for combat in ["ranged", "magic"] {
+            assert_eq!(apply(black_mask, combat), (40, 24_000));
+            assert_eq!(apply(slayer_helmet, combat), (40, 24_000));
+            assert_eq!(apply(slayer_helmet_i, combat), (46, 27_600));
+        }
+    }
+
+    #[test]
+    fn test_avarice_replaces_slayer_helm_and_stacks_with_revenant_weapons() {
+        let registry = default_effects();
+        let revenant = ["revenant".to_string()];
+        let apply = |forinthry_surge, target_attributes: &[String]| {
+            let ctx = EffectContext {
+                on_slayer_task: true,
+                in_wilderness: true,
+                forinthry_surge,
+                charge: false,
+                target_attributes,
+                target_name: "",
+                combat: "melee",
+                equipped: &[11865, AMULET_OF_AVARICE, VIGGORAS_CHAINMACE],
+                equipped_tags: &[],
+                weapon_charged: true,
+                spell: None,
+                target_size: 1,
+                target_magic: 1,
+                hitpoints: 99,
+                current_hitpoints: 99,
+            };
+            let mut state = EngineState {
+                max_hit: 30,
+                attack_roll: 20_000,
+                ..Default::default()
+            };
+            registry.apply_stage(Stage::PostMaxHit, &ctx, &mut state);
+            (state.max_hit, state.attack_roll)
+        };
+
+        // Avarice instead of the helm: floor(30 * 6/5) = 36, then 36 * 3/2
+        assert_eq!(apply(false, &revenant), (54, 36_000));
+        // Surge: floor(30 * 27/20) = 40, then 40 * 3/2
+        assert_eq!(apply(true, &revenant), (60, 40_500));
+        // Not a revenant: the helm applies, floor(30 * 7/6) = 35
+        assert_eq!(apply(true, &[]), (52, 34_999));
+    }
+
+    #[test]
+    fn test_dragon_hunter_lance_stacks_after_slayer_helm() {
+        let registry = default_effects();
+        let dragon = ["dragon".to_string()];
+        let apply = |on_slayer_task, target_attributes: &[String]| {
+            let ctx = EffectContext {
+                on_slayer_task,
+                in_wilderness: false,
+                forinthry_surge: false,
+                charge: false,
+                target_attributes,
+                target_name: "",
+                combat: "melee",
+                equipped: &[11865, DRAGON_HUNTER_LANCE],
+                equipped_tags: &[],
+                weapon_charged: true,
+                spell: None,
+                target_size: 1,
+                target_magic: 1,
+                hitpoints: 99,
+                current_hitpoints: 99,
+            };
+            let mut state = EngineState {
+                max_hit: 40,
+                attack_roll: 30_000,
+                ..Default::default()
+            };
+            registry.apply_stage(Stage::PostMaxHit, &ctx, &mut state);
+            (state.max_hit, state.attack_roll)
+        };
+
+        // floor(40 * 6/5) = 48
+        assert_eq!(apply(false, &dragon), (48, 36_000));
+        // Slayer helm first: floor(40 * 7/6) = 46, then floor(46 * 6/5) = 55
+        assert_eq!(apply(true, &dragon), (55, 42_000));
+        assert_eq!(apply(false, &[]), (40, 30_000));
+    }
+
+    #[test]
+    fn test_demonbane_is_halved_against_resistant_demons() {
+        let registry = default_effects();
+        let demon = ["demon".to_string()];
+        let resistant = ["demon".to_string(), "demonbane_resistant".to_string()];
+        let apply = |target_attributes: &[String]| {
+            let ctx = EffectContext {
+                on_slayer_task: false,
+                in_wilderness: false,
+                forinthry_surge: false,
+                charge: false,
+                target_attributes,
+                target_name: "",
+                combat: "melee",
+                equipped: &[ARCLIGHT],
+                equipped_tags: &[],
+                weapon_charged: true,
+                spell: None,
+                target_size: 1,
+                target_magic: 1,
+                hitpoints: 99,
+                current_hitpoints: 99,
+            };
+            let mut state = EngineState {
+                max_hit: 40,
+                attack_roll: 20_000,
+                ..Default::default()
+            };
+            let applied = registry.apply_stage(Stage::PostMaxHit, &ctx, &mut state);
+            (state.max_hit, state.attack_roll, applied.len())
+        };
+
+        // +70%: floor(40 * 17/10) = 68
+        assert_eq!(apply(&demon), (68, 34_000, 2));
+        // +35% and the full bonus doesn't stack on top: floor(40 * 27/20) = 54
+        assert_eq!(apply(&resistant), (54, 27_000, 2));
+        assert_eq!(apply(&[]), (40, 20_000, 0));
+    }
+
+    #[test]
+    fn test_damned_barrows_sets() {
+        let registry = default_effects();
+        let (player, target) = (PlayerState::default(), TargetResolved::default());
+        let expected_damage = |combat, equipped: &[u32]| {
+            let ctx = EffectContext {
+                combat,
+                equipped,
+                ..EffectContext::for_situation(&player, &target)
+            };
+            let mut state = EngineState::default();
+            registry.apply_stage(Stage::PostAccuracy, &ctx, &mut state);
+            state.expected_damage
+        };
+        let with_amulet = |set: &[u32]| {
+            let mut equipped = set.to_vec();
+            equipped.push(AMULET_OF_THE_DAMNED[0]);
+            equipped
+        };
+
+        assert_eq!(expected_damage("magic", &with_amulet(&AHRIMS_SET)), 1.075);
+        assert_eq!(expected_damage("ranged", &with_amulet(&KARILS_SET)), 1.125);
+        // The set alone, or the amulet with part of the set, does nothing
+        assert_eq!(expected_damage("ranged", &KARILS_SET), 1.0);
+        assert_eq!(
+            expected_damage("ranged", &with_amulet(&KARILS_SET[1..])),
+            1.0
+        );
+        // Nor with the wrong combat style
+        assert_eq!(expected_damage("melee", &with_amulet(&KARILS_SET)), 1.0);
+    }
 }
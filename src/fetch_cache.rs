@@ -0,0 +1,183 @@
+// src/fetch_cache.rs
+//
+// The download cache shared by the ingestion scripts (scripts/fetch_cache.py):
+// where it lives, what it holds, and clearing it. Downloads are kept one
+// section per kind of lookup (`fetch-data` for item and monster data);
+// offline runs of the scripts read only these copies. The CLI itself reads
+// only local files.
+
+use crate::data::DataError;
+use serde::Serialize;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Overrides where the cache lives.
+pub const CACHE_DIR_ENV: &str = "OSRS_SIM_CACHE_DIR";
+
+/// Set to `1` to keep the scripts off the network.
+pub const OFFLINE_ENV: &str = "OSRS_SIM_OFFLINE";
+
+/// Extensions of the files the scripts write: downloads, and ones
+/// interrupted before they were renamed into place.
+const CACHE_FILE_EXTENSIONS: [&str; 2] = ["json", "partial"];
+
+/// One section of the cache and what's in it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CacheSection {
+    pub name: String,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// The cache directory: `$OSRS_SIM_CACHE_DIR`, else `$XDG_CACHE_HOME/osrs-sim`,
+/// else `~/.cache/osrs-sim`. None without any of them set.
+pub fn cache_dir() -> Option<PathBuf> {
+    resolve_cache_dir(
+        std::env::var_os(CACHE_DIR_ENV),
+        std::env::var_os("XDG_CACHE_HOME"),
+        std::env::var_os("HOME"),
+    )
+}
+
+/// `cache_dir` from the values of its environment variables. A relative
+/// `XDG_CACHE_HOME` is ignored, as the XDG spec says.
+pub fn resolve_cache_dir(
+    override_dir: Option<OsString>,
+    xdg_cache_home: Option<OsString>,
+    home: Option<OsString>,
+) -> Option<PathBuf> {
+    let set = |value: Option<OsString>| value.filter(|v| !v.is_empty()).map(PathBuf::from);
+    if let Some(dir) = set(override_dir) {
+        return Some(dir);
+    }
+    if let Some(xdg) = set(xdg_cache_home).filter(|p| p.is_absolute()) {
+        return Some(xdg.join("osrs-sim"));
+    }
+    set(home).map(|home| home.join(".cache").join("osrs-sim"))
+}
+
+/// The sections in `dir` and the cached files in each, by name. A cache
+/// that doesn't exist yet is empty.
+pub fn cache_status(dir: &Path) -> Result<Vec<CacheSection>, DataError> {
+    let mut sections = Vec::new();
+    for (name, path) in section_dirs(dir)? {
+        let mut section = CacheSection {
+            name,
+            files: 0,
+            bytes: 0,
+        };
+        for file in cached_files(&path)? {
+            section.files += 1;
+            section.bytes += fs::metadata(&file)?.len();
+        }
+        sections.push(section);
+    }
+    Ok(sections)
+}
+
+/// Delete every cached file in `dir`, and sections left empty, returning
+/// what was there. Files the scripts didn't write are left alone.
+pub fn clear_cache(dir: &Path) -> Result<Vec<CacheSection>, DataError> {
+    let cleared = cache_status(dir)?;
+    for (_, path) in section_dirs(dir)? {
+        for file in cached_files(&path)? {
+            fs::remove_file(file)?;
+        }
+        if fs::read_dir(&path)?.next().is_none() {
+            fs::remove_dir(&path)?;
+        }
+    }
+    Ok(cleared)
+}
+
+fn section_dirs(dir: &Path) -> Result<Vec<(String, PathBuf)>, DataError> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let mut sections = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            sections.push((
+                entry.file_name().to_string_lossy().into_owned(),
+                entry.path(),
+            ));
+        }
+    }
+    sections.sort();
+    Ok(sections)
+}
+
+fn cached_files(section: &Path) -> Result<Vec<PathBuf>, DataError> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(section)? {
+        let entry = entry?;
+        let path = entry.path();
+        let ours = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| CACHE_FILE_EXTENSIONS.contains(&ext));
+        if ours && entry.file_type()?.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_dir_precedence() {
+        let some = |s: &str| Some(OsString::from(s));
+        assert_eq!(
+            resolve_cache_dir(some("/tmp/c"), some("/xdg"), some("/home/u")),
+            Some(PathBuf::from("/tmp/c"))
+        );
+        assert_eq!(
+            resolve_cache_dir(None, some("/xdg"), some("/home/u")),
+            Some(PathBuf::from("/xdg/osrs-sim"))
+        );
+        // Empty and relative XDG values fall through to the home directory
+        for xdg in [some(""), some("relative"), None] {
+            assert_eq!(
+                resolve_cache_dir(some(""), xdg, some("/home/u")),
+                Some(PathBuf::from("/home/u/.cache/osrs-sim"))
+            );
+        }
+        assert_eq!(resolve_cache_dir(None, None, None), None);
+    }
+
+    #[test]
+    fn test_status_and_clear_touch_only_cached_files() {
+        let dir = std::env::temp_dir().join(format!("osrs-sim-cache-{}", std::process::id()));
+        assert_eq!(cache_status(&dir).unwrap(), vec![]);
+
+        let fetched = dir.join("fetch-data");
+        fs::create_dir_all(&fetched).unwrap();
+        fs::write(fetched.join("a.json"), "{}").unwrap();
+        fs::write(fetched.join("b.partial"), "{\"id\"").unwrap();
+        fs::write(fetched.join("notes.txt"), "keep").unwrap();
+        let expected = vec![CacheSection {
+            name: "fetch-data".to_string(),
+            files: 2,
+            bytes: 7,
+        }];
+        assert_eq!(cache_status(&dir).unwrap(), expected);
+
+        assert_eq!(clear_cache(&dir).unwrap(), expected);
+        assert!(fetched.join("notes.txt").exists());
+        assert_eq!(cache_status(&dir).unwrap()[0].files, 0);
+
+        // A section left empty goes too
+        fs::remove_file(fetched.join("notes.txt")).unwrap();
+        fs::write(fetched.join("a.json"), "{}").unwrap();
+        clear_cache(&dir).unwrap();
+        assert!(!fetched.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
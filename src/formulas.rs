@@ -1,9 +1,22 @@
 // src/formulas.rs
 //
-// Core combat math for melee DPS calculation.
-// All formulas use integer math with explicit floors where specified.
+// Core combat math for melee and magic DPS calculation.
+// All formulas use i64 integer math with explicit floors where specified;
+// rolls saturate rather than overflow and are narrowed to u32 in results.
 
-use crate::model::{EvalContext, EvalResult};
+use crate::aoe::AreaAttack;
+use crate::bolts::{self, BoltProc, ProcAttack, ZARYTE_CROSSBOW};
+use crate::dot::{burn_dps, poison_dps};
+use crate::effects::{default_registry, EffectContext, EffectRegistry, EngineState, Stage};
+use crate::model::{
+    AttackType, BuildResolved, Combat, DpsChange, DrainAnalysis, EvalContext, EvalResult,
+    HitDistribution, SensitivityAnalysis, SkillsInput, StageTrace, Stance, StyleRoll,
+    TargetResolved, MAGIC_DART, SLAYER_STAFF_E,
+};
+use crate::moons::MoonSet;
+use crate::specs;
+use crate::ticks::Ticks;
+use thiserror::Error;
 
 // =============================================================================
 // Core Formula Functions (pure, testable)
@@ -16,10 +29,10 @@ pub fn effective_attack_level(
     potion_boost: i32,
     prayer_mult: (i32, i32), // (numerator, denominator)
     style_bonus: i32,
-) -> i32 {
-    let boosted = base_level + potion_boost;
-    let prayed = (boosted * prayer_mult.0) / prayer_mult.1; // floor via integer division
-    prayed + style_bonus + 8
+) -> i64 {
+    let boosted = i64::from(base_level) + i64::from(potion_boost);
+    let prayed = apply_prayer(boosted, prayer_mult); // floor via integer division
+    prayed + i64::from(style_bonus) + 8
 }
 
 /// Calculate effective strength level for melee.
@@ -29,28 +42,52 @@ pub fn effective_strength_level(
     potion_boost: i32,
     prayer_mult: (i32, i32),
     style_bonus: i32,
-) -> i32 {
-    let boosted = base_level + potion_boost;
-    let prayed = (boosted * prayer_mult.0) / prayer_mult.1;
-    prayed + style_bonus + 8
+) -> i64 {
+    let boosted = i64::from(base_level) + i64::from(potion_boost);
+    let prayed = apply_prayer(boosted, prayer_mult);
+    prayed + i64::from(style_bonus) + 8
+}
+
+/// Calculate effective magic level for magic accuracy.
+/// Formula: floor((base + potion) * prayer) + style_bonus + 9
+pub fn effective_magic_level(
+    base_level: i32,
+    potion_boost: i32,
+    prayer_mult: (i32, i32),
+    style_bonus: i32,
+) -> i64 {
+    let boosted = i64::from(base_level) + i64::from(potion_boost);
+    apply_prayer(boosted, prayer_mult) + i64::from(style_bonus) + 9
+}
+
+/// Boosted level times a prayer multiplier, floored. Neither factor can
+/// overflow an i64 on its own, so the product can't either.
+fn apply_prayer(boosted: i64, prayer_mult: (i32, i32)) -> i64 {
+    boosted * i64::from(prayer_mult.0) / i64::from(prayer_mult.1)
 }
 
 /// Calculate effective defence level (for target's defence roll).
 /// For monsters, this is just their defence level + 9 (no style bonus).
-pub fn effective_defence_level(defence_level: i32) -> i32 {
-    defence_level + 9
+pub fn effective_defence_level(defence_level: i32) -> i64 {
+    i64::from(defence_level) + 9
 }
 
 /// Calculate max attack roll.
-/// Formula: effective_attack * (equipment_bonus + 64)
-pub fn max_attack_roll(effective_attack: i32, equipment_bonus: i32) -> i32 {
-    effective_attack * (equipment_bonus + 64)
+/// Formula: effective_attack * (equipment_bonus + 64), never below zero
+///
+/// Rolls are i64 and saturate, so extreme boosts or bonuses can't overflow.
+pub fn max_attack_roll(effective_attack: i64, equipment_bonus: i32) -> i64 {
+    effective_attack
+        .saturating_mul(i64::from(equipment_bonus) + 64)
+        .max(0)
 }
 
 /// Calculate max defence roll.
-/// Formula: effective_defence * (defence_bonus + 64)
-pub fn max_defence_roll(effective_defence: i32, defence_bonus: i32) -> i32 {
-    effective_defence * (defence_bonus + 64)
+/// Formula: effective_defence * (defence_bonus + 64), never below zero
+pub fn max_defence_roll(effective_defence: i64, defence_bonus: i32) -> i64 {
+    effective_defence
+        .saturating_mul(i64::from(defence_bonus) + 64)
+        .max(0)
 }
 
 /// Calculate hit chance (accuracy).
@@ -59,9 +96,9 @@ pub fn max_defence_roll(effective_defence: i32, defence_bonus: i32) -> i32 {
 /// Formula:
 ///   If A > D: accuracy = 1 - (D + 2) / (2 * (A + 1))
 ///   Else:     accuracy = A / (2 * (D + 1))
-pub fn hit_chance(attack_roll: i32, defence_roll: i32) -> f64 {
-    let a = attack_roll as f64;
-    let d = defence_roll as f64;
+pub fn hit_chance(attack_roll: i64, defence_roll: i64) -> f64 {
+    let a = attack_roll.max(0) as f64;
+    let d = defence_roll.max(0) as f64;
 
     if attack_roll > defence_roll {
         1.0 - (d + 2.0) / (2.0 * (a + 1.0))
@@ -70,84 +107,822 @@ pub fn hit_chance(attack_roll: i32, defence_roll: i32) -> f64 {
     }
 }
 
+/// Hit chance taking the better of two attack rolls against one defence
+/// roll (Osmumten's fang).
+///
+/// Formula:
+///   If A > D: accuracy = 1 - (D + 2) * (2D + 3) / (6 * (A + 1)^2)
+///   Else:     accuracy = A * (4A + 5) / (6 * (A + 1) * (D + 1))
+pub fn fang_hit_chance(attack_roll: i64, defence_roll: i64) -> f64 {
+    let a = attack_roll.max(0) as f64;
+    let d = defence_roll.max(0) as f64;
+
+    if attack_roll > defence_roll {
+        1.0 - (d + 2.0) * (2.0 * d + 3.0) / (6.0 * (a + 1.0) * (a + 1.0))
+    } else {
+        a * (4.0 * a + 5.0) / (6.0 * (a + 1.0) * (d + 1.0))
+    }
+}
+
 /// Calculate max hit for melee.
 /// Formula: floor(0.5 + effective_strength * (str_bonus + 64) / 640)
 ///
 /// Using integer math: floor((effective * (bonus + 64) + 320) / 640)
-pub fn max_hit_melee(effective_strength: i32, str_bonus: i32) -> i32 {
+pub fn max_hit_melee(effective_strength: i64, str_bonus: i32) -> i64 {
     // The +320 is equivalent to +0.5 when dividing by 640
-    (effective_strength * (str_bonus + 64) + 320) / 640
+    (effective_strength.saturating_mul(i64::from(str_bonus) + 64) + 320).max(0) / 640
+}
+
+/// Calculate max hit for ranged; same shape as melee with ranged strength.
+/// Formula: floor(0.5 + effective_ranged_strength * (ranged_str + 64) / 640)
+pub fn max_hit_ranged(effective_strength: i64, ranged_str_bonus: i32) -> i64 {
+    (effective_strength.saturating_mul(i64::from(ranged_str_bonus) + 64) + 320).max(0) / 640
+}
+
+/// Calculate max hit for a spell.
+/// Formula: floor(base_max_hit * (100 + magic_damage) / 100)
+pub fn max_hit_magic(base_max_hit: i64, magic_damage_pct: i64) -> i64 {
+    (base_max_hit.saturating_mul(magic_damage_pct.saturating_add(100)) / 100).max(0)
+}
+
+/// Twisted bow magic: the target's, capped at 250.
+const TWISTED_BOW_MAGIC_CAP: i32 = 250;
+
+/// Twisted bow accuracy in percent against a target's magic m:
+/// 140 + (3m - 10) / 100 - (3m/10 - 100)^2 / 100, at most 140. Each
+/// division rounds down on its own.
+pub fn twisted_bow_accuracy_pct(target_magic: u16) -> i32 {
+    let triple = i32::from(target_magic).min(TWISTED_BOW_MAGIC_CAP) * 3;
+    (140 + (triple - 10) / 100 - (triple / 10 - 100).pow(2) / 100).min(140)
+}
+
+/// Twisted bow damage in percent against a target's magic m:
+/// 250 + (3m - 14) / 100 - (3m/10 - 140)^2 / 100, at most 250. Each
+/// division rounds down on its own.
+pub fn twisted_bow_damage_pct(target_magic: u16) -> i32 {
+    let triple = i32::from(target_magic).min(TWISTED_BOW_MAGIC_CAP) * 3;
+    (250 + (triple - 14) / 100 - (triple / 10 - 140).pow(2) / 100).min(250)
+}
+
+/// Ticks between attacks for a weapon's listed speed in the given stance.
+/// Rapid makes ranged attacks one tick faster.
+pub fn attack_interval(weapon_speed: u8, combat: Combat, stance: Stance) -> u8 {
+    match (combat, stance) {
+        (Combat::Ranged, Stance::Rapid) => weapon_speed.saturating_sub(1).max(1),
+        _ => weapon_speed,
+    }
+}
+
+/// Max hit of each hit in one attack. The Scythe of vitur hits once per
+/// tile of target size, up to three times, each hit at half the max of the
+/// one before (100%/50%/25%); everything else hits once.
+pub fn hit_max_hits(max_hit: i64, scythe: bool, target_size: u8) -> Vec<i64> {
+    let hits = if scythe { target_size.clamp(1, 3) } else { 1 };
+    (0..hits).map(|i| max_hit >> i).collect()
 }
 
 /// Calculate DPS (damage per second).
 /// Formula: hit_chance * (max_hit / 2) / (interval_ticks * 0.6)
 ///
 /// Average hit when you hit = max_hit / 2 (uniform distribution 0 to max_hit)
-pub fn calculate_dps(accuracy: f64, max_hit: i32, interval_ticks: u8) -> f64 {
+pub fn calculate_dps(accuracy: f64, max_hit: i64, interval_ticks: u8) -> f64 {
+    calculate_dps_range(accuracy, 0, max_hit, interval_ticks)
+}
+
+/// Multiplier (num, den) on a chinchompa's attack roll at `distance` tiles.
+/// Each fuse is fully accurate in its own bracket of 1-3, 4-6 and 7+ tiles
+/// (short fuse, accurate, nearest; medium, rapid; long, longrange,
+/// furthest), 75% one bracket off and 50% two off.
+pub fn chinchompa_fuse(stance: Stance, distance: u8) -> (i64, i64) {
+    let bracket: u8 = match distance {
+        0..=3 => 0,
+        4..=6 => 1,
+        _ => 2,
+    };
+    let best: u8 = match stance {
+        Stance::Rapid => 1,
+        Stance::Longrange => 2,
+        _ => 0,
+    };
+    match best.abs_diff(bracket) {
+        0 => (4, 4),
+        1 => (3, 4),
+        _ => (2, 4),
+    }
+}
+
+/// Damage range of a landed hit raised to a minimum of `min_hit_pct`% of
+/// its max hit (rounded down), when that's above its low end.
+pub fn with_minimum_hit((min_hit, max_hit): (i64, i64), min_hit_pct: i64) -> (i64, i64) {
+    (min_hit.max(max_hit * min_hit_pct / 100), max_hit)
+}
+
+/// DPS when a landed hit deals anywhere from `min_hit` to `max_hit`.
+/// Formula: hit_chance * ((min_hit + max_hit) / 2) / (interval_ticks * 0.6)
+pub fn calculate_dps_range(accuracy: f64, min_hit: i64, max_hit: i64, interval_ticks: u8) -> f64 {
     if interval_ticks == 0 {
         return 0.0;
     }
 
-    let avg_hit = max_hit as f64 / 2.0;
-    let interval_seconds = interval_ticks as f64 * 0.6;
+    let avg_hit = (min_hit + max_hit) as f64 / 2.0;
+    accuracy * avg_hit / Ticks::from(interval_ticks).as_secs_f64()
+}
 
-    accuracy * avg_hit / interval_seconds
+/// Calculate a player's combat level.
+/// Formula: floor(base + max(melee, ranged, magic)) where
+///   base   = 0.25 * (defence + hitpoints + floor(prayer / 2))
+///   melee  = 0.325 * (attack + strength)
+///   ranged = 0.325 * floor(3 * ranged / 2)
+///   magic  = 0.325 * floor(3 * magic / 2)
+///
+/// Using integer math over a common denominator of 40 (0.25 = 10/40, 0.325 = 13/40).
+pub fn combat_level(skills: &SkillsInput) -> u8 {
+    let base = skills.defence as u32 + skills.hitpoints as u32 + skills.prayer as u32 / 2;
+    let melee = skills.attack as u32 + skills.strength as u32;
+    let ranged = skills.ranged as u32 * 3 / 2;
+    let magic = skills.magic as u32 * 3 / 2;
+    let offence = melee.max(ranged).max(magic);
+    ((10 * base + 13 * offence) / 40) as u8
+}
+
+// =============================================================================
+// Experience
+// =============================================================================
+
+/// Total experience needed to reach `level` (1-126, 1 = 0 XP).
+/// Formula: floor(sum_{l=1}^{level-1} floor(l + 300 * 2^(l/7)) / 4)
+pub fn xp_for_level(level: u8) -> u32 {
+    let points: f64 = (1..level.max(1) as u32)
+        .map(|l| (l as f64 + 300.0 * 2f64.powf(l as f64 / 7.0)).floor())
+        .sum();
+    (points / 4.0).floor() as u32
+}
+
+/// Experience still needed to go from the start of `from` to `to`
+/// (0 if `to` is not above `from`).
+pub fn xp_between(from: u8, to: u8) -> u32 {
+    xp_for_level(to).saturating_sub(xp_for_level(from))
+}
+
+// =============================================================================
+// Input Validation
+// =============================================================================
+
+/// Lowest defence bonus that still means something: `bonus + 64` is the
+/// defence roll's multiplier, so anything lower gives the same roll of 0.
+pub const MIN_DEFENCE_BONUS: i32 = -64;
+
+/// A context the formulas can't give a meaningful answer for.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ContextError {
+    #[error("Attack speed must be at least 1 tick")]
+    ZeroAttackSpeed,
+
+    #[error("Target {0} has 0 hitpoints")]
+    ZeroHitpoints(String),
+
+    #[error("Target {target} has a {attack_type:?} defence bonus of {bonus}, below -64")]
+    DefenceBonusTooLow {
+        target: String,
+        attack_type: AttackType,
+        bonus: i32,
+    },
+
+    #[error("Magic build has no spell to cast")]
+    NoSpell,
+
+    #[error("Target is {distance} tiles away, beyond the build's attack range of {range}")]
+    OutOfRange { distance: u8, range: u8 },
+}
+
+/// Check a context for degenerate inputs before evaluating it.
+///
+/// `evaluate` never fails: a 0-tick weapon gives 0 DPS, and a defence bonus
+/// below -64 is floored to a defence roll of 0. Targets from the resolver
+/// already have their levels and bonuses clamped; callers building contexts
+/// by hand should call this first, or use `try_evaluate`, to report those
+/// as errors instead.
+pub fn validate_context(ctx: &EvalContext) -> Result<(), ContextError> {
+    let (build, target) = (ctx.build, ctx.target);
+    if build.attack_speed == 0 {
+        return Err(ContextError::ZeroAttackSpeed);
+    }
+    if target.hitpoints == 0 {
+        return Err(ContextError::ZeroHitpoints(target.name.clone()));
+    }
+    for &attack_type in build.combat.attack_types() {
+        let bonus = target.defence_bonuses.defence_bonus_for(attack_type);
+        if bonus < MIN_DEFENCE_BONUS {
+            return Err(ContextError::DefenceBonusTooLow {
+                target: target.name.clone(),
+                attack_type,
+                bonus,
+            });
+        }
+    }
+    let casts_own_spell = build.powered_staff.is_some() || build.salamander.is_some();
+    if build.combat == Combat::Magic && build.spell.is_none() && !casts_own_spell {
+        return Err(ContextError::NoSpell);
+    }
+    if let Some(distance) = ctx.distance {
+        let range = build.attack_range();
+        if distance > range {
+            return Err(ContextError::OutOfRange { distance, range });
+        }
+    }
+    Ok(())
 }
 
 // =============================================================================
 // Main Evaluation Function
 // =============================================================================
 
-/// Evaluate DPS for a given context (player + build + target).
+/// Evaluate DPS for a given context (player + build + target), applying the
+/// built-in effects. Degenerate inputs aren't rejected; see
+/// `validate_context`.
 pub fn evaluate(ctx: &EvalContext) -> EvalResult {
+    evaluate_with(ctx, default_registry())
+}
+
+/// Validate the context, then evaluate it with the built-in effects.
+pub fn try_evaluate(ctx: &EvalContext) -> Result<EvalResult, ContextError> {
+    validate_context(ctx)?;
+    Ok(evaluate(ctx))
+}
+
+/// Evaluate DPS with an explicit effect registry, placed by the context's
+/// ordering profile if it has one.
+pub fn evaluate_with(ctx: &EvalContext, registry: &EffectRegistry) -> EvalResult {
+    let reordered;
+    let registry = match ctx.ordering {
+        Some(profile) => {
+            reordered = registry.reordered(profile);
+            &reordered
+        }
+        None => registry,
+    };
     let player = ctx.player;
     let build = ctx.build;
     let target = ctx.target;
+    let effect_ctx = EffectContext::from_eval(ctx);
 
-    // Get stance bonuses for melee
-    let (atk_style_bonus, str_style_bonus) = build.stance.melee_bonuses();
+    // Invisible stance levels for this combat style
+    let stance = build
+        .stance
+        .bonuses(build.combat, build.powered_staff.is_some());
 
-    // Calculate effective levels
-    let eff_attack = effective_attack_level(
-        player.attack as i32,
-        player.potion_attack_boost,
-        player.prayer_attack_mult,
-        atk_style_bonus,
-    );
+    // Calculate effective levels. Ranged uses the ranged level for both;
+    // magic accuracy comes from the magic level and its max hit from the
+    // spell rather than a strength level.
+    let (effective_attack, effective_strength) = match build.combat {
+        Combat::Melee => (
+            effective_attack_level(
+                player.attack as i32,
+                player.potion_attack_boost,
+                player.prayer_attack_mult,
+                stance.accuracy,
+            ),
+            effective_strength_level(
+                player.strength as i32,
+                player.potion_strength_boost,
+                player.prayer_strength_mult,
+                stance.strength,
+            ),
+        ),
+        Combat::Ranged => (
+            effective_attack_level(
+                player.ranged as i32,
+                player.potion_ranged_boost,
+                player.prayer_ranged_attack_mult,
+                stance.accuracy,
+            ),
+            // The Eclipse atlatl's max hit scales with strength instead, still
+            // boosted by ranged prayers
+            if build.melee_scaled_ranged {
+                effective_strength_level(
+                    player.strength as i32,
+                    player.potion_strength_boost,
+                    player.prayer_ranged_strength_mult,
+                    stance.strength,
+                )
+            } else {
+                effective_strength_level(
+                    player.ranged as i32,
+                    player.potion_ranged_boost,
+                    player.prayer_ranged_strength_mult,
+                    stance.strength,
+                )
+            },
+        ),
+        Combat::Magic => (
+            effective_magic_level(
+                player.magic as i32,
+                player.potion_magic_boost,
+                player.prayer_magic_mult,
+                stance.accuracy,
+            ),
+            0,
+        ),
+    };
+    let mut state = EngineState {
+        effective_attack,
+        effective_strength,
+        ..EngineState::default()
+    };
+    // Each stage's state is kept for explaining the result
+    let mut stages = Vec::with_capacity(Stage::ALL.len());
+    let trace = |stage, state: &EngineState, effects: &[String]| StageTrace {
+        stage,
+        state: state.clone(),
+        effects: effects.to_vec(),
+    };
+    let mut effects_applied =
+        registry.apply_stage(Stage::PostEffectiveLevel, &effect_ctx, &mut state);
+    stages.push(trace(Stage::PostEffectiveLevel, &state, &effects_applied));
+    let applied = registry.apply_stage(Stage::PreRolls, &effect_ctx, &mut state);
+    stages.push(trace(Stage::PreRolls, &state, &applied));
+    effects_applied.extend(applied);
 
-    let eff_strength = effective_strength_level(
-        player.strength as i32,
-        player.potion_strength_boost,
-        player.prayer_strength_mult,
-        str_style_bonus,
-    );
+    // Magic max hit: a powered staff's built-in spell scales with visible
+    // magic; otherwise it's the chosen spell's base max hit (Magic Dart's
+    // scales too). Effects add to the base (chaos gauntlets, Charge)
+    let magic_gear_mult = match (build.combat, build.powered_staff) {
+        (Combat::Magic, Some(staff)) => staff.gear_multiplier(),
+        _ => 1,
+    };
+    let visible_magic = i64::from(player.magic) + i64::from(player.potion_magic_boost);
+    let spell_base = match (build.powered_staff, &build.spell) {
+        (Some(staff), _) => staff.base_max_hit(visible_magic),
+        (None, Some(spell)) => spell.base_max_hit_at(
+            visible_magic,
+            build.weapon_id == SLAYER_STAFF_E && player.on_slayer_task,
+        ),
+        (None, None) => 0,
+    }
+    .saturating_add(state.spell_max_hit);
+    // Gear magic damage is multiplied by a powered staff first, then effect
+    // bonuses (imbued salve) are added to it; multipliers on the max hit
+    // itself (imbued slayer helm) come after, in `PostMaxHit`
+    let magic_damage = i64::from(build.bonuses.magic_damage)
+        .saturating_mul(i64::from(magic_gear_mult))
+        .saturating_add(state.magic_damage);
+    // A salamander's Blaze has its own strength and ignores magic damage;
+    // Scorch and Flare take the normal melee and ranged (tar) paths
+    let magic_max_hit = match build.salamander {
+        Some(salamander) => salamander.blaze_max_hit(visible_magic),
+        None => max_hit_magic(spell_base, magic_damage),
+    };
 
-    let eff_defence = effective_defence_level(target.defence_level as i32);
+    // Mechanics downstream code registered for the weapon
+    let weapon_mechanics = registry.weapon_mechanics_for(build);
 
-    // Get equipment bonuses for the attack type
-    let attack_bonus = build.bonuses.attack_bonus_for(build.attack_type);
-    let defence_bonus = target.defence_bonuses.defence_bonus_for(build.attack_type);
+    // What a special attack does beyond its data's multipliers
+    let spec_mechanic = build
+        .special_attack
+        .as_ref()
+        .and_then(|spec| specs::mechanic(&spec.name));
 
-    // Calculate rolls
-    let atk_roll = max_attack_roll(eff_attack, attack_bonus);
-    let def_roll = max_defence_roll(eff_defence, defence_bonus);
+    // Rolls and max hit, then the effects that scale them. The closure
+    // repeats this for any attack type so the per-type rolls match.
+    let roll_state = |attack_type| {
+        let mut s = EngineState {
+            attack_roll: max_attack_roll(
+                state.effective_attack,
+                build
+                    .bonuses
+                    .attack_bonus_for(attack_type)
+                    .saturating_mul(magic_gear_mult),
+            ),
+            max_hit: match build.combat {
+                Combat::Melee => max_hit_melee(state.effective_strength, build.melee_strength()),
+                Combat::Ranged if build.melee_scaled_ranged => {
+                    max_hit_ranged(state.effective_strength, build.melee_strength())
+                }
+                Combat::Ranged => {
+                    max_hit_ranged(state.effective_strength, build.bonuses.ranged_strength)
+                }
+                Combat::Magic => magic_max_hit,
+            },
+            ..state.clone()
+        };
+        let mut applied = registry.apply_stage(Stage::PostMaxHit, &effect_ctx, &mut s);
+        // A special attack scales the rolls after every gear effect
+        if let Some(spec) = &build.special_attack {
+            let (num, den) = spec.accuracy;
+            s.attack_roll = s.attack_roll.saturating_mul(num) / den;
+            let (num, den) = spec.damage;
+            s.max_hit = s.max_hit.saturating_mul(num) / den;
+        }
+        // A chinchompa's fuse suits some distances better than others
+        if let (true, Some(distance)) = (build.chinchompa, ctx.distance) {
+            let (num, den) = chinchompa_fuse(build.stance, distance);
+            s.attack_roll = s.attack_roll.saturating_mul(num) / den;
+        }
+        for mechanic in &weapon_mechanics {
+            if mechanic.modify_rolls(attack_type, &mut s) {
+                applied.push(mechanic.id().to_string());
+            }
+        }
+        let mut def_roll = target.defence_roll(attack_type);
+        // Some special attacks ignore part of the magic defence roll
+        if let (Some(spec), AttackType::Magic) = (&build.special_attack, attack_type) {
+            let kept = 100 - i64::from(spec.ignores_magic_defence_pct.min(100));
+            def_roll = def_roll * kept / 100;
+        }
+        s.accuracy = match build.hit_distribution.for_attack_type(attack_type) {
+            HitDistribution::Standard => hit_chance(s.attack_roll, def_roll),
+            HitDistribution::Fang => fang_hit_chance(s.attack_roll, def_roll),
+        };
+        if build
+            .special_attack
+            .as_ref()
+            .is_some_and(|spec| spec.always_hits)
+        {
+            s.accuracy = 1.0;
+        }
+        (s, def_roll, applied)
+    };
 
-    // Calculate accuracy
-    let accuracy = hit_chance(atk_roll, def_roll);
+    let (mut state, def_roll, applied) = roll_state(build.attack_type);
+    stages.push(trace(Stage::PostMaxHit, &state, &applied));
+    effects_applied.extend(applied);
+    let applied = registry.apply_stage(Stage::PostAccuracy, &effect_ctx, &mut state);
+    stages.push(trace(Stage::PostAccuracy, &state, &applied));
+    effects_applied.extend(applied);
 
-    // Calculate max hit
-    let max_hit = max_hit_melee(eff_strength, build.bonuses.melee_strength);
+    // Enchanted bolt procs scale the average damage of an attack
+    let bolt_proc = build.bolt_effect.as_deref().and_then(BoltProc::from_effect);
+    let plain_damage = state.accuracy * state.max_hit as f64 / 2.0;
+    if let (Combat::Ranged, Some(proc)) = (build.combat, bolt_proc) {
+        if plain_damage > 0.0 {
+            let attack = ProcAttack {
+                accuracy: state.accuracy,
+                max_hit: state.max_hit,
+                ranged_level: (i32::from(player.ranged) + player.potion_ranged_boost).max(0) as u32,
+                zaryte: build.weapon_id == ZARYTE_CROSSBOW,
+                kandarin_diary: player.kandarin_hard_diary,
+            };
+            state.expected_damage *= bolts::expected_damage(proc, attack, target) / plain_damage;
+        }
+    }
 
-    // Calculate DPS
-    let dps = calculate_dps(accuracy, max_hit, build.attack_speed);
+    // Calculate DPS; each hit of a multi-hit attack rolls accuracy on its own,
+    // the weapon's distribution sets the damage range of a landed hit, and
+    // chance-based damage effects scale the average
+    let interval_ticks = attack_interval(build.attack_speed, build.combat, build.stance);
+    let distribution = build.hit_distribution.for_attack_type(build.attack_type);
+    // A special attack's hits each roll like a normal attack, unless its
+    // mechanic sets their damage range or hits depend on each other and it
+    // has its own distribution
+    let spec_hits = build.special_attack.as_ref().map_or(1, |spec| spec.hits);
+    let spec_range = spec_mechanic.and_then(|mechanic| mechanic.damage_range);
+    let spec_damage = spec_mechanic.and_then(|mechanic| mechanic.expected_damage);
+    // Set effects that speed up some attacks shorten the average interval,
+    // scaling every per-second rate below
+    let moon_set = MoonSet::of(build);
+    let expected_interval = moon_set
+        .filter(|_| interval_ticks > 0)
+        .and_then(|set| set.expected_interval(interval_ticks, state.accuracy));
+    let speedup = expected_interval.map_or(1.0, |ticks| f64::from(interval_ticks) / ticks);
+    let mechanic_ranges = weapon_mechanics
+        .iter()
+        .filter_map(|mechanic| mechanic.hit_ranges(state.max_hit, target))
+        .next_back()
+        .filter(|ranges| !ranges.is_empty());
+    let hit_ranges: Vec<(i64, i64)> = match mechanic_ranges {
+        Some(ranges) => ranges,
+        None => hit_max_hits(state.max_hit, build.scythe, target.size)
+            .into_iter()
+            .flat_map(|max_hit| std::iter::repeat_n(max_hit, usize::from(spec_hits)))
+            .map(|max_hit| match spec_range {
+                Some(range) => range(max_hit),
+                None => distribution.damage_range(max_hit),
+            })
+            .collect(),
+    };
+    // A minimum hit (sunfire runes) raises the low end of every hit
+    let hit_ranges: Vec<(i64, i64)> = hit_ranges
+        .into_iter()
+        .map(|range| with_minimum_hit(range, state.min_hit_pct))
+        .collect();
+    let dps = match spec_damage {
+        Some(expected_damage) if interval_ticks > 0 => {
+            expected_damage(state.accuracy, state.max_hit)
+                / Ticks::from(interval_ticks).as_secs_f64()
+        }
+        Some(_) => 0.0,
+        None => hit_ranges
+            .iter()
+            .map(|&(min_hit, max_hit)| {
+                calculate_dps_range(state.accuracy, min_hit, max_hit, interval_ticks)
+            })
+            .sum::<f64>(),
+    } * state.expected_damage
+        * speedup;
+    // Venator bow bounces and area spells also hit the targets next to
+    // the main one
+    let aoe_dps = AreaAttack::of(build)
+        .filter(|_| ctx.adjacent_targets > 0 && interval_ticks > 0)
+        .map(|area| {
+            let adjacent =
+                area.adjacent_damage(state.accuracy, state.max_hit, ctx.adjacent_targets);
+            dps + adjacent * state.expected_damage * speedup
+                / Ticks::from(interval_ticks).as_secs_f64()
+        });
+    let poison_dps = match build.poison {
+        Some(poison) if !target.immune_poison => {
+            poison_dps(poison, build.combat, state.accuracy, interval_ticks) * speedup
+        }
+        _ => 0.0,
+    };
+    let burn_dps = moon_set.map_or(0.0, |set| {
+        burn_dps(set.burn_chance(state.accuracy), interval_ticks)
+    });
+
+    // Same effective level, every attack type the style could switch to
+    let style_rolls = build
+        .combat
+        .attack_types()
+        .iter()
+        .map(|&attack_type| {
+            let (mut s, defence_roll, _) = roll_state(attack_type);
+            registry.apply_stage(Stage::PostAccuracy, &effect_ctx, &mut s);
+            StyleRoll {
+                attack_type,
+                attack_roll: reported(s.attack_roll),
+                defence_roll: reported(defence_roll),
+                accuracy: s.accuracy,
+            }
+        })
+        .collect();
+
+    // Rolls are still reported for a target the build can't damage
+    let immunity = immunity(build, target);
+    let (dps, poison_dps, burn_dps, aoe_dps) = match immunity {
+        Some(_) => (0.0, 0.0, 0.0, aoe_dps.map(|_| 0.0)),
+        None => (dps, poison_dps, burn_dps, aoe_dps),
+    };
 
     EvalResult {
         dps,
-        max_hit: max_hit as u32,
-        accuracy,
-        attack_roll: atk_roll as u32,
-        defence_roll: def_roll as u32,
-        interval_ticks: build.attack_speed,
+        max_hit: reported(hit_ranges[0].1),
+        accuracy: state.accuracy,
+        attack_roll: reported(state.attack_roll),
+        defence_roll: reported(def_roll),
+        interval_ticks: Ticks::from(interval_ticks),
+        expected_interval_ticks: expected_interval,
+        hit_max_hits: hit_ranges
+            .iter()
+            .map(|&(_, max_hit)| reported(max_hit))
+            .collect(),
+        style_rolls,
+        effects_applied,
+        stages,
+        poison_dps,
+        burn_dps,
+        aoe_dps,
+        immunity,
+        data_warnings: target.data_flags.clone(),
+    }
+}
+
+/// Target attribute of monsters only leaf-bladed weapons, broad ammunition
+/// and Magic Dart can damage (kurasks and turoths).
+pub const LEAFY_ATTRIBUTE: &str = "leafy";
+/// Item tag of the leaf-bladed melee weapons.
+pub const LEAF_BLADED_TAG: &str = "leaf_bladed";
+/// Item tag of broad arrows and bolts.
+pub const BROAD_AMMO_TAG: &str = "broad_ammo";
+
+/// Target attribute of tier 2 vampyres (juvinates), which only silver
+/// weapons, magic or Efaritay's aid can damage.
+pub const VAMPYRE_TIER_2_ATTRIBUTE: &str = "vampyre2";
+/// Target attribute of tier 3 vampyres (vyrewatch), which only blisterwood
+/// weapons and the Ivandis flail can damage.
+pub const VAMPYRE_TIER_3_ATTRIBUTE: &str = "vampyre3";
+/// Item tag of silver weapons (blisterwood ones included).
+pub const SILVER_WEAPON_TAG: &str = "silver_weapon";
+/// Item tag of the weapons that can damage tier 3 vampyres.
+pub const BLISTERWOOD_WEAPON_TAG: &str = "blisterwood_weapon";
+/// Efaritay's aid lets any weapon damage tier 2 vampyres.
+const EFARITAYS_AID: u32 = 21140;
+
+/// A target attribute that makes the target immune to all but some builds.
+struct Immunity {
+    attribute: &'static str,
+    can_damage: fn(&BuildResolved) -> bool,
+    /// What can damage it, for the explanation.
+    damaged_by: &'static str,
+}
+
+const IMMUNITIES: [Immunity; 3] = [
+    Immunity {
+        attribute: LEAFY_ATTRIBUTE,
+        can_damage: |build| match build.combat {
+            Combat::Melee => has_tag(build, LEAF_BLADED_TAG),
+            Combat::Ranged => has_tag(build, BROAD_AMMO_TAG),
+            Combat::Magic => build
+                .spell
+                .as_ref()
+                .is_some_and(|spell| spell.is(MAGIC_DART)),
+        },
+        damaged_by: "leaf-bladed weapons, broad ammunition or Magic Dart",
+    },
+    Immunity {
+        attribute: VAMPYRE_TIER_2_ATTRIBUTE,
+        can_damage: |build| {
+            build.combat == Combat::Magic
+                || has_tag(build, SILVER_WEAPON_TAG)
+                || build.equipped.contains(&EFARITAYS_AID)
+        },
+        damaged_by: "silver weapons, magic, or any weapon with Efaritay's aid",
+    },
+    Immunity {
+        attribute: VAMPYRE_TIER_3_ATTRIBUTE,
+        can_damage: |build| build.combat == Combat::Melee && has_tag(build, BLISTERWOOD_WEAPON_TAG),
+        damaged_by: "blisterwood weapons and the Ivandis flail",
+    },
+];
+
+fn has_tag(build: &BuildResolved, tag: &str) -> bool {
+    build.equipped_tags.iter().any(|t| t == tag)
+}
+
+/// Why the build can't damage the target at all, or `None` if it can.
+pub fn immunity(build: &BuildResolved, target: &TargetResolved) -> Option<String> {
+    IMMUNITIES
+        .iter()
+        .find(|immunity| {
+            target.attributes.iter().any(|a| a == immunity.attribute)
+                && !(immunity.can_damage)(build)
+        })
+        .map(|immunity| {
+            format!(
+                "{} can only be damaged by {}",
+                target.name, immunity.damaged_by
+            )
+        })
+}
+
+/// Attack roll of the reference attacker `TargetResolved::style_weaknesses`
+/// rates styles with: 99 in the attacking skill with no boosts, prayers or
+/// stance bonus (effective 107), and +100 accuracy in every style.
+pub const REFERENCE_ATTACK_ROLL: i64 = 107 * (100 + 64);
+
+impl TargetResolved {
+    /// Magic the twisted bow scales on: the higher of the magic level and
+    /// the magic attack bonus.
+    pub fn twisted_bow_magic(&self) -> u16 {
+        let bonus = u16::try_from(self.magic_attack_bonus.max(0)).unwrap_or(u16::MAX);
+        self.magic_level.max(bonus)
+    }
+
+    /// Max defence roll against `attack_type`. Monsters defend magic with
+    /// their magic level.
+    pub fn defence_roll(&self, attack_type: AttackType) -> i64 {
+        let defence_level = match attack_type {
+            AttackType::Magic => self.magic_level,
+            _ => self.defence_level,
+        };
+        max_defence_roll(
+            effective_defence_level(i32::from(defence_level)),
+            self.defence_bonuses.defence_bonus_for(attack_type),
+        )
+    }
+
+    /// Every attack type by how well the reference attacker
+    /// (`REFERENCE_ATTACK_ROLL`) lands it, weakest defence first; ties keep
+    /// melee first.
+    pub fn style_weaknesses(&self) -> Vec<StyleRoll> {
+        let mut rolls: Vec<StyleRoll> = AttackType::ALL
+            .iter()
+            .map(|&attack_type| {
+                let defence_roll = self.defence_roll(attack_type);
+                StyleRoll {
+                    attack_type,
+                    attack_roll: reported(REFERENCE_ATTACK_ROLL),
+                    defence_roll: reported(defence_roll),
+                    accuracy: hit_chance(REFERENCE_ATTACK_ROLL, defence_roll),
+                }
+            })
+            .collect();
+        rolls.sort_by_key(|roll| roll.defence_roll);
+        rolls
+    }
+
+    /// The attack type the target has the lowest defence roll against.
+    pub fn weakest_style(&self) -> AttackType {
+        self.style_weaknesses()[0].attack_type
+    }
+}
+
+/// Narrow an internal roll or max hit to the `u32` reported in results,
+/// saturating rather than wrapping if it doesn't fit.
+fn reported(value: i64) -> u32 {
+    u32::try_from(value.max(0)).unwrap_or(u32::MAX)
+}
+
+// =============================================================================
+// Defence Drain Analysis
+// =============================================================================
+
+/// Compare DPS at the target's current defence with DPS once it has been
+/// drained to zero. Defence bonuses are left as they are.
+pub fn drain_analysis(ctx: &EvalContext) -> DrainAnalysis {
+    let drained = TargetResolved {
+        defence_level: 0,
+        ..ctx.target.clone()
+    };
+    let dps_at_zero = evaluate(&EvalContext {
+        target: &drained,
+        ..*ctx
+    })
+    .dps;
+
+    DrainAnalysis {
+        defence_level: ctx.target.defence_level,
+        dps: evaluate(ctx).dps,
+        dps_at_zero,
+        // BGS drains one defence level per point of damage
+        bgs_damage_to_zero: ctx.target.defence_level as u32,
+    }
+}
+
+// =============================================================================
+// Sensitivity Analysis
+// =============================================================================
+
+/// DPS change from one more point of strength bonus, attack bonus, attack
+/// level and target defence, each on its own. Bonuses and levels are those
+/// of the build's attack type and combat style.
+pub fn sensitivity_analysis(ctx: &EvalContext) -> SensitivityAnalysis {
+    let dps = evaluate(ctx).dps;
+    let change = |changed: &EvalContext| {
+        let changed_dps = evaluate(changed).dps;
+        DpsChange {
+            dps: changed_dps,
+            change: changed_dps - dps,
+            pct: if dps > 0.0 {
+                (changed_dps / dps - 1.0) * 100.0
+            } else {
+                0.0
+            },
+        }
+    };
+    let with_build = |edit: fn(&mut BuildResolved)| {
+        let mut build = ctx.build.clone();
+        edit(&mut build);
+        change(&EvalContext {
+            build: &build,
+            ..*ctx
+        })
+    };
+
+    let strength_bonus = with_build(|build| match build.combat {
+        Combat::Melee => build.bonuses.melee_strength += 1,
+        Combat::Ranged => build.bonuses.ranged_strength += 1,
+        Combat::Magic => build.bonuses.magic_damage += 1,
+    });
+    let attack_bonus = with_build(|build| {
+        let bonuses = &mut build.bonuses;
+        match build.attack_type {
+            AttackType::Stab => bonuses.attack_stab += 1,
+            AttackType::Slash => bonuses.attack_slash += 1,
+            AttackType::Crush => bonuses.attack_crush += 1,
+            AttackType::Magic => bonuses.attack_magic += 1,
+            AttackType::Ranged => bonuses.attack_ranged += 1,
+        }
+    });
+
+    let mut player = ctx.player.clone();
+    let level = match ctx.build.combat {
+        Combat::Melee => &mut player.attack,
+        Combat::Ranged => &mut player.ranged,
+        Combat::Magic => &mut player.magic,
+    };
+    *level = level.saturating_add(1);
+    let attack_level = change(&EvalContext {
+        player: &player,
+        ..*ctx
+    });
+
+    let mut target = ctx.target.clone();
+    let defence = match ctx.build.attack_type {
+        AttackType::Magic => &mut target.magic_level,
+        _ => &mut target.defence_level,
+    };
+    *defence = defence.saturating_add(1);
+    let target_defence = change(&EvalContext {
+        target: &target,
+        ..*ctx
+    });
+
+    SensitivityAnalysis {
+        dps,
+        strength_bonus,
+        attack_bonus,
+        attack_level,
+        target_defence,
     }
 }
 
@@ -158,6 +933,7 @@ pub fn evaluate(ctx: &EvalContext) -> EvalResult {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fuzz::Rng;
 
     #[test]
     fn test_effective_attack_level_no_boosts() {
@@ -183,6 +959,53 @@ mod tests {
         assert_eq!(result, 152);
     }
 
+    #[test]
+    fn test_twisted_bow_scaling() {
+        // Capped at 140% accuracy and 250% damage; magic over 250 counts as 250
+        assert_eq!(twisted_bow_accuracy_pct(250), 140);
+        assert_eq!(twisted_bow_damage_pct(250), 215);
+        assert_eq!(twisted_bow_damage_pct(400), 215);
+        assert_eq!(twisted_bow_accuracy_pct(100), 93);
+        assert_eq!(twisted_bow_damage_pct(100), 131);
+        assert_eq!(twisted_bow_accuracy_pct(1), 40);
+        assert_eq!(twisted_bow_damage_pct(1), 54);
+        // The linear term uses 3m itself, not 3m/10 rounded down: these
+        // levels gain a percent from it
+        for (magic, damage) in [
+            (38, 85),
+            (39, 85),
+            (72, 111),
+            (73, 111),
+            (105, 135),
+            (106, 135),
+            (138, 156),
+            (139, 156),
+            (172, 176),
+            (173, 176),
+            (205, 194),
+            (206, 194),
+            (238, 210),
+            (239, 210),
+        ] {
+            assert_eq!(twisted_bow_damage_pct(magic), damage, "magic {magic}");
+        }
+        assert_eq!(twisted_bow_accuracy_pct(38), 62);
+        assert_eq!(twisted_bow_accuracy_pct(239), 139);
+    }
+
+    #[test]
+    fn test_chinchompa_fuse_by_stance_and_distance() {
+        // Short fuse up close, medium at 4-6 tiles, long from 7
+        assert_eq!(chinchompa_fuse(Stance::Accurate, 1), (4, 4));
+        assert_eq!(chinchompa_fuse(Stance::Accurate, 5), (3, 4));
+        assert_eq!(chinchompa_fuse(Stance::Accurate, 9), (2, 4));
+        assert_eq!(chinchompa_fuse(Stance::Rapid, 3), (3, 4));
+        assert_eq!(chinchompa_fuse(Stance::Rapid, 6), (4, 4));
+        assert_eq!(chinchompa_fuse(Stance::Rapid, 7), (3, 4));
+        assert_eq!(chinchompa_fuse(Stance::Longrange, 2), (2, 4));
+        assert_eq!(chinchompa_fuse(Stance::Longrange, 10), (4, 4));
+    }
+
     #[test]
     fn test_max_hit_basic() {
         // Effective strength 118, str bonus 0
@@ -223,6 +1046,425 @@ mod tests {
         assert!(result > 0.49 && result < 0.51);
     }
 
+    #[test]
+    fn test_style_rolls_cover_melee_types() {
+        use crate::model::{AttackType, BuildResolved, PlayerState, TargetResolved};
+
+        let player = PlayerState::default();
+        let mut build = BuildResolved::default();
+        build.bonuses.attack_stab = 10;
+        build.bonuses.attack_slash = 80;
+        let mut target = TargetResolved::default();
+        target.defence_bonuses.slash = 50;
+
+        let result = evaluate(&EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        });
+
+        let types: Vec<_> = result.style_rolls.iter().map(|r| r.attack_type).collect();
+        assert_eq!(
+            types,
+            vec![AttackType::Stab, AttackType::Slash, AttackType::Crush]
+        );
+        // The style actually used matches the headline numbers
+        let slash = result.style_rolls[1];
+        assert_eq!(slash.attack_roll, result.attack_roll);
+        assert_eq!(slash.defence_roll, result.defence_roll);
+        assert!((slash.accuracy - result.accuracy).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_combat_level() {
+        let mut skills = SkillsInput {
+            attack: 99,
+            strength: 99,
+            defence: 99,
+            ranged: 99,
+            magic: 99,
+            prayer: 99,
+            hitpoints: 99,
+        };
+        assert_eq!(combat_level(&skills), 126);
+
+        // Fresh account
+        skills = SkillsInput {
+            attack: 1,
+            strength: 1,
+            defence: 1,
+            ranged: 1,
+            magic: 1,
+            prayer: 1,
+            hitpoints: 10,
+        };
+        assert_eq!(combat_level(&skills), 3);
+
+        // 1 defence pure: 60 att / 99 str / 52 pray / 99 hp
+        skills = SkillsInput {
+            attack: 60,
+            strength: 99,
+            defence: 1,
+            ranged: 99,
+            magic: 94,
+            prayer: 52,
+            hitpoints: 99,
+        };
+        // 0.25 * (1 + 99 + 26) + 0.325 * 159 = 83.175
+        assert_eq!(combat_level(&skills), 83);
+    }
+
+    #[test]
+    fn test_xp_for_level() {
+        assert_eq!(xp_for_level(1), 0);
+        assert_eq!(xp_for_level(2), 83);
+        assert_eq!(xp_for_level(10), 1_154);
+        assert_eq!(xp_for_level(70), 737_627);
+        assert_eq!(xp_for_level(92), 6_517_253);
+        assert_eq!(xp_for_level(99), 13_034_431);
+        assert_eq!(xp_between(92, 99), 6_517_178);
+        assert_eq!(xp_between(99, 92), 0);
+    }
+
+    #[test]
+    fn test_magic_formulas() {
+        // 99 magic, imbued heart (+10), augury (5/4)
+        // floor(109 * 5/4) = 136, + 9 = 145
+        assert_eq!(effective_magic_level(99, 10, (5, 4), 0), 145);
+        assert_eq!(effective_magic_level(99, 0, (1, 1), 0), 108);
+        // Accurate powered staff: +2
+        assert_eq!(effective_magic_level(99, 0, (1, 1), 2), 110);
+
+        // Fire surge (24) with 15% magic damage: floor(27.6) = 27
+        assert_eq!(max_hit_magic(24, 15), 27);
+        assert_eq!(max_hit_magic(24, 0), 24);
+    }
+
+    #[test]
+    fn test_evaluate_magic_build() {
+        use crate::model::{AttackType, BuildResolved, PlayerState, Spell, Stance};
+
+        let player = PlayerState::default();
+        let mut build = BuildResolved {
+            combat: Combat::Magic,
+            attack_type: AttackType::Magic,
+            stance: Stance::Accurate,
+            attack_speed: 5,
+            spell: Some(Spell {
+                name: "ice_barrage".to_string(),
+                base_max_hit: 30,
+                ..Spell::default()
+            }),
+            ..BuildResolved::default()
+        };
+        build.bonuses.attack_magic = 100;
+        build.bonuses.magic_damage = 10;
+        // Melee strength plays no part in a spell's max hit
+        build.bonuses.melee_strength = 150;
+        let target = TargetResolved::default();
+
+        let result = evaluate(&EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        });
+        assert_eq!(result.max_hit, 33);
+        // 108 * (100 + 64)
+        assert_eq!(result.attack_roll, 17_712);
+        assert_eq!(result.interval_ticks, Ticks(5));
+        assert_eq!(result.style_rolls.len(), 1);
+        assert_eq!(result.style_rolls[0].attack_type, AttackType::Magic);
+    }
+
+    #[test]
+    fn test_magic_defence_uses_magic_level() {
+        use crate::model::{BuildResolved, PlayerState, Spell};
+
+        let player = PlayerState::default();
+        let build = BuildResolved {
+            combat: Combat::Magic,
+            attack_type: AttackType::Magic,
+            spell: Some(Spell {
+                name: "fire_surge".to_string(),
+                base_max_hit: 24,
+                ..Spell::default()
+            }),
+            ..BuildResolved::default()
+        };
+        let mut target = TargetResolved {
+            defence_level: 300,
+            magic_level: 50,
+            ..TargetResolved::default()
+        };
+        target.defence_bonuses.magic = 20;
+
+        let result = evaluate(&EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        });
+        assert_eq!(result.defence_roll, (50 + 9) * (20 + 64));
+    }
+
+    #[test]
+    fn test_powered_staff_max_hit() {
+        use crate::model::{AttackType, BuildResolved, PlayerState, PoweredStaff};
+
+        let evaluate_staff = |staff, magic_boost| {
+            let player = PlayerState {
+                potion_magic_boost: magic_boost,
+                ..PlayerState::default()
+            };
+            let mut build = BuildResolved {
+                combat: Combat::Magic,
+                attack_type: AttackType::Magic,
+                powered_staff: Some(staff),
+                ..BuildResolved::default()
+            };
+            build.bonuses.attack_magic = 20;
+            build.bonuses.magic_damage = 10;
+            evaluate(&EvalContext {
+                player: &player,
+                build: &build,
+                target: &TargetResolved::default(),
+                ordering: None,
+                adjacent_targets: 0,
+                distance: None,
+            })
+        };
+
+        // Swamp at 99: 33 - 2 = 31, +10% = 34
+        assert_eq!(
+            evaluate_staff(PoweredStaff::TridentOfTheSwamp, 0).max_hit,
+            34
+        );
+        // Boosted to 112: 37 - 1 = 36, +10% = 39
+        assert_eq!(evaluate_staff(PoweredStaff::Sanguinesti, 13).max_hit, 39);
+        // Shadow at 99: 34, triple gear bonus: +30% = 44; magic attack 60
+        let shadow = evaluate_staff(PoweredStaff::TumekensShadow, 0);
+        assert_eq!(shadow.max_hit, 44);
+        // Accurate stance on a powered staff: 99 + 2 + 9
+        assert_eq!(shadow.attack_roll, 110 * (60 + 64));
+    }
+
+    #[test]
+    fn test_keris_crit_vs_kalphites() {
+        use crate::model::{BuildResolved, PlayerState};
+
+        let mut build = BuildResolved {
+            equipped: vec![10581],
+            ..BuildResolved::default()
+        };
+        build.bonuses.attack_slash = 50;
+        build.bonuses.melee_strength = 50;
+        let against = |attributes: &[&str]| {
+            let target = TargetResolved {
+                attributes: attributes.iter().map(|a| a.to_string()).collect(),
+                ..TargetResolved::default()
+            };
+            evaluate(&EvalContext {
+                player: &PlayerState::default(),
+                build: &build,
+                target: &target,
+                ordering: None,
+                adjacent_targets: 0,
+                distance: None,
+            })
+        };
+
+        let plain = against(&[]);
+        let kalphite = against(&["kalphite"]);
+        // Same max hit; 1 in 51 hits deals triple, so 53/51 the damage
+        assert_eq!(kalphite.max_hit, plain.max_hit);
+        assert!((kalphite.dps / plain.dps - 53.0 / 51.0).abs() < 1e-12);
+        assert!(kalphite
+            .effects_applied
+            .contains(&"keris_kalphite_crit".to_string()));
+    }
+
+    #[test]
+    fn test_bulwark_strength_from_defence() {
+        use crate::model::{AttackType, BuildResolved, PlayerState, WeaponStrength};
+
+        let max_hit = |defence: i32| {
+            let mut build = BuildResolved {
+                attack_type: AttackType::Crush,
+                weapon_strength: Some(WeaponStrength::Bulwark),
+                ..BuildResolved::default()
+            };
+            build.bonuses.melee_strength = 38;
+            build.bonuses.defence_stab = defence;
+            build.bonuses.defence_slash = defence;
+            build.bonuses.defence_crush = defence;
+            build.bonuses.defence_ranged = defence;
+            (
+                build.melee_strength(),
+                evaluate(&EvalContext {
+                    player: &PlayerState::default(),
+                    build: &build,
+                    target: &TargetResolved::default(),
+                    ordering: None,
+                    adjacent_targets: 0,
+                    distance: None,
+                })
+                .max_hit,
+            )
+        };
+
+        // Below (800 + 12 * 38) total defence the listed 38 stands
+        assert_eq!(max_hit(300).0, 38);
+        assert_eq!(max_hit(314).0, 38);
+        // 4 * 400 = 1600: (1600 - 800) / 12 = 66, 28 over the listed 38
+        let (strength, max) = max_hit(400);
+        assert_eq!(strength, 66);
+        // Effective strength 99 + 8 = 107: (107 * 130 + 320) / 640
+        assert_eq!(max, 22);
+        // Magic defence doesn't count, and other weapons ignore defence
+        let mut build = BuildResolved::default();
+        build.bonuses.defence_magic = 5000;
+        build.bonuses.defence_stab = 5000;
+        assert_eq!(build.melee_strength(), 0);
+        build.weapon_strength = Some(WeaponStrength::Bulwark);
+        assert_eq!(build.melee_strength(), (5000 - 800) / 12 - 38);
+    }
+
+    #[test]
+    fn test_ranged_stance_bonuses() {
+        use crate::model::{AttackType, BuildResolved, PlayerState};
+
+        let player = PlayerState {
+            potion_ranged_boost: 13,
+            prayer_ranged_attack_mult: (6, 5),
+            prayer_ranged_strength_mult: (123, 100),
+            ..PlayerState::default()
+        };
+        let ranged = |stance| {
+            let mut build = BuildResolved {
+                combat: Combat::Ranged,
+                attack_type: AttackType::Ranged,
+                stance,
+                ..BuildResolved::default()
+            };
+            build.bonuses.attack_ranged = 100;
+            build.bonuses.ranged_strength = 50;
+            // Melee strength is ignored for ranged
+            build.bonuses.melee_strength = 200;
+            evaluate(&EvalContext {
+                player: &player,
+                build: &build,
+                target: &TargetResolved::default(),
+                ordering: None,
+                adjacent_targets: 0,
+                distance: None,
+            })
+        };
+
+        // Accurate: floor(112 * 1.2) + 3 + 8 = 145, floor(112 * 1.23) + 3 + 8 = 148
+        let accurate = ranged(Stance::Accurate);
+        assert_eq!(accurate.attack_roll, 145 * 164);
+        assert_eq!(accurate.max_hit, (148 * 114 + 320) / 640);
+        // Rapid: no invisible levels, but a tick faster
+        let rapid = ranged(Stance::Rapid);
+        assert_eq!(rapid.attack_roll, 142 * 164);
+        assert_eq!(rapid.interval_ticks, Ticks(3));
+        assert_eq!(ranged(Stance::Longrange).attack_roll, 142 * 164);
+    }
+
+    #[test]
+    fn test_rapid_ranged_interval() {
+        // Crossbow (6 ticks) and blowpipe (3 ticks) on rapid
+        assert_eq!(attack_interval(6, Combat::Ranged, Stance::Rapid), 5);
+        assert_eq!(attack_interval(3, Combat::Ranged, Stance::Rapid), 2);
+        assert_eq!(attack_interval(6, Combat::Ranged, Stance::Accurate), 6);
+        assert_eq!(attack_interval(4, Combat::Melee, Stance::Aggressive), 4);
+    }
+
+    #[test]
+    fn test_scythe_hits_by_target_size() {
+        use crate::model::{BuildResolved, PlayerState};
+
+        assert_eq!(hit_max_hits(47, true, 1), vec![47]);
+        assert_eq!(hit_max_hits(47, true, 2), vec![47, 23]);
+        assert_eq!(hit_max_hits(47, true, 5), vec![47, 23, 11]);
+        assert_eq!(hit_max_hits(47, false, 5), vec![47]);
+
+        let player = PlayerState::default();
+        let build = BuildResolved {
+            scythe: true,
+            attack_speed: 5,
+            ..BuildResolved::default()
+        };
+        let evaluate_size = |size| {
+            evaluate(&EvalContext {
+                player: &player,
+                build: &build,
+                target: &TargetResolved {
+                    size,
+                    ..TargetResolved::default()
+                },
+                ordering: None,
+                adjacent_targets: 0,
+                distance: None,
+            })
+        };
+        let small = evaluate_size(1);
+        let large = evaluate_size(3);
+        assert_eq!(small.hit_max_hits, vec![small.max_hit]);
+        assert_eq!(large.hit_max_hits.len(), 3);
+        assert_eq!(large.max_hit, small.max_hit);
+        // Every hit rolls the same accuracy
+        let total: u32 = large.hit_max_hits.iter().sum();
+        let expected = calculate_dps(large.accuracy, i64::from(total), 5);
+        assert!((large.dps - expected).abs() < 1e-12);
+        assert!(large.dps > small.dps);
+    }
+
+    #[test]
+    fn test_fang_hit_distribution() {
+        use crate::model::{BuildResolved, PlayerState};
+
+        // Better of two attack rolls: matches enumerating every roll pair
+        // (A = 10, D = 7: 191/242; A = 7, D = 10: 7/16)
+        assert!((fang_hit_chance(10, 7) - 191.0 / 242.0).abs() < 1e-12);
+        assert!((fang_hit_chance(7, 10) - 7.0 / 16.0).abs() < 1e-12);
+        assert!(fang_hit_chance(10, 7) > hit_chance(10, 7));
+        assert_eq!(HitDistribution::Fang.damage_range(40), (6, 34));
+
+        let player = PlayerState::default();
+        let fang = |attack_type| {
+            let build = BuildResolved {
+                attack_type,
+                hit_distribution: HitDistribution::Fang,
+                ..BuildResolved::default()
+            };
+            evaluate(&EvalContext {
+                player: &player,
+                build: &build,
+                target: &TargetResolved::default(),
+                ordering: None,
+                adjacent_targets: 0,
+                distance: None,
+            })
+        };
+        let stab = fang(AttackType::Stab);
+        let slash = fang(AttackType::Slash);
+        // Narrowed range on stab only: max hit 11 -> 1 to 10
+        assert_eq!(slash.max_hit, 11);
+        assert_eq!(stab.max_hit, 10);
+        assert!(stab.accuracy > slash.accuracy);
+        let expected = calculate_dps_range(stab.accuracy, 1, 10, 4);
+        assert!((stab.dps - expected).abs() < 1e-12);
+    }
+
     #[test]
     fn test_dps_calculation() {
         // 50% accuracy, max hit 30, 4 tick weapon
@@ -230,4 +1472,423 @@ mod tests {
         // 0.5 * 15 / 2.4 = 7.5 / 2.4 = 3.125
         assert!((result - 3.125).abs() < 0.001);
     }
+
+    #[test]
+    fn test_minimum_hit_raises_the_low_end() {
+        // 10% of 25 rounds down to 2
+        assert_eq!(with_minimum_hit((0, 25), 10), (2, 25));
+        assert_eq!(with_minimum_hit((0, 25), 0), (0, 25));
+        // Already above it (the fang's 15% to 85%)
+        assert_eq!(with_minimum_hit((6, 34), 10), (6, 34));
+        // 0.5 * (3 + 30) / 2 / 2.4
+        let dps = calculate_dps_range(0.5, 3, 30, 4);
+        assert!((dps - 0.5 * 16.5 / 2.4).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_drain_analysis() {
+        use crate::model::{BuildResolved, PlayerState};
+
+        let player = PlayerState::default();
+        let build = BuildResolved::default();
+        let target = TargetResolved {
+            defence_level: 200,
+            ..TargetResolved::default()
+        };
+        let ctx = EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        };
+
+        let analysis = drain_analysis(&ctx);
+        assert_eq!(analysis.defence_level, 200);
+        assert_eq!(analysis.bgs_damage_to_zero, 200);
+        assert!((analysis.dps - evaluate(&ctx).dps).abs() < 1e-12);
+        assert!(analysis.dps_at_zero > analysis.dps);
+    }
+
+    #[test]
+    fn test_sensitivity_analysis() {
+        use crate::model::{BuildResolved, PlayerState};
+
+        let player = PlayerState::default();
+        let mut build = BuildResolved::default();
+        build.bonuses.melee_strength = 50;
+        let target = TargetResolved {
+            defence_level: 200,
+            ..TargetResolved::default()
+        };
+        let ctx = EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        };
+
+        let analysis = sensitivity_analysis(&ctx);
+        assert!((analysis.dps - evaluate(&ctx).dps).abs() < 1e-12);
+        // Against high defence an attack bonus point is worth more than a
+        // strength point, which may not reach another max hit
+        assert!(analysis.attack_bonus.change > 0.0);
+        assert!(analysis.attack_level.change > 0.0);
+        assert!(analysis.attack_bonus.change > analysis.strength_bonus.change);
+        assert!(analysis.target_defence.change < 0.0);
+        let change = analysis.attack_bonus;
+        assert!((change.dps - analysis.dps - change.change).abs() < 1e-12);
+        assert!((change.pct - change.change / analysis.dps * 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_leafy_targets_need_leaf_bladed_gear() {
+        use crate::model::{BuildResolved, PlayerState, Spell};
+
+        let player = PlayerState::default();
+        let kurask = TargetResolved {
+            name: "Kurask".to_string(),
+            attributes: vec![LEAFY_ATTRIBUTE.to_string()],
+            ..TargetResolved::default()
+        };
+        let eval = |build: &BuildResolved| {
+            evaluate(&EvalContext {
+                player: &player,
+                build,
+                target: &kurask,
+                ordering: None,
+                adjacent_targets: 0,
+                distance: None,
+            })
+        };
+
+        let plain = eval(&BuildResolved::default());
+        assert_eq!(plain.dps, 0.0);
+        assert!(plain
+            .immunity
+            .as_deref()
+            .is_some_and(|r| r.starts_with("Kurask")));
+        // Rolls are still reported
+        assert!(plain.attack_roll > 0);
+
+        let leaf_bladed = BuildResolved {
+            equipped_tags: vec![LEAF_BLADED_TAG.to_string()],
+            ..BuildResolved::default()
+        };
+        let result = eval(&leaf_bladed);
+        assert!(result.dps > 0.0);
+        assert_eq!(result.immunity, None);
+
+        // Broad ammo only counts for ranged attacks
+        let broad_melee = BuildResolved {
+            equipped_tags: vec![BROAD_AMMO_TAG.to_string()],
+            ..BuildResolved::default()
+        };
+        assert!(immunity(&broad_melee, &kurask).is_some());
+        let magic_dart = BuildResolved {
+            combat: Combat::Magic,
+            spell: Some(Spell {
+                name: "Magic Dart".to_string(),
+                base_max_hit: 10,
+                ..Spell::default()
+            }),
+            ..BuildResolved::default()
+        };
+        assert_eq!(immunity(&magic_dart, &kurask), None);
+        assert_eq!(
+            immunity(&BuildResolved::default(), &TargetResolved::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_vampyre_tiers_need_silver_or_blisterwood() {
+        use crate::model::BuildResolved;
+
+        let vampyre = |tier: &str| TargetResolved {
+            name: "Vampyre".to_string(),
+            attributes: vec![tier.to_string()],
+            ..TargetResolved::default()
+        };
+        let (juvinate, vyrewatch) = (
+            vampyre(VAMPYRE_TIER_2_ATTRIBUTE),
+            vampyre(VAMPYRE_TIER_3_ATTRIBUTE),
+        );
+        let with_tags = |tags: &[&str]| BuildResolved {
+            equipped_tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..BuildResolved::default()
+        };
+
+        let plain = BuildResolved::default();
+        assert!(immunity(&plain, &vampyre("vampyre1")).is_none());
+        assert!(immunity(&plain, &juvinate).is_some());
+        assert!(immunity(&plain, &vyrewatch).is_some());
+
+        let silver = with_tags(&[SILVER_WEAPON_TAG]);
+        assert!(immunity(&silver, &juvinate).is_none());
+        assert!(immunity(&silver, &vyrewatch).is_some());
+        let blisterwood = with_tags(&[SILVER_WEAPON_TAG, BLISTERWOOD_WEAPON_TAG]);
+        assert!(immunity(&blisterwood, &vyrewatch).is_none());
+
+        let ring = BuildResolved {
+            equipped: vec![EFARITAYS_AID],
+            ..BuildResolved::default()
+        };
+        assert!(immunity(&ring, &juvinate).is_none());
+        let magic = BuildResolved {
+            combat: Combat::Magic,
+            ..BuildResolved::default()
+        };
+        assert!(immunity(&magic, &juvinate).is_none());
+        assert!(immunity(&magic, &vyrewatch)
+            .is_some_and(|reason| reason.ends_with("blisterwood weapons and the Ivandis flail")));
+    }
+
+    #[test]
+    fn test_validate_context_rejects_degenerate_inputs() {
+        use crate::model::{BuildResolved, PlayerState};
+
+        let player = PlayerState::default();
+        let build = BuildResolved::default();
+        let target = TargetResolved::default();
+        let check = |build: &BuildResolved, target: &TargetResolved| {
+            try_evaluate(&EvalContext {
+                player: &player,
+                build,
+                target,
+                ordering: None,
+                adjacent_targets: 0,
+                distance: None,
+            })
+            .map(|_| ())
+        };
+        assert_eq!(check(&build, &target), Ok(()));
+
+        let instant = BuildResolved {
+            attack_speed: 0,
+            ..build.clone()
+        };
+        assert_eq!(check(&instant, &target), Err(ContextError::ZeroAttackSpeed));
+
+        let dead = TargetResolved {
+            hitpoints: 0,
+            ..target.clone()
+        };
+        assert!(matches!(
+            check(&build, &dead),
+            Err(ContextError::ZeroHitpoints(_))
+        ));
+
+        // -64 is the lowest bonus that keeps a defence roll; melee checks
+        // every melee type, not just the one in use
+        let mut soft = target.clone();
+        soft.defence_bonuses.crush = -64;
+        assert_eq!(check(&build, &soft), Ok(()));
+        soft.defence_bonuses.crush = -65;
+        assert!(matches!(
+            check(&build, &soft),
+            Err(ContextError::DefenceBonusTooLow {
+                attack_type: AttackType::Crush,
+                bonus: -65,
+                ..
+            })
+        ));
+
+        let no_spell = BuildResolved {
+            combat: Combat::Magic,
+            attack_type: AttackType::Magic,
+            ..build.clone()
+        };
+        assert_eq!(check(&no_spell, &target), Err(ContextError::NoSpell));
+    }
+
+    #[test]
+    fn test_weakest_style_has_the_lowest_defence_roll() {
+        let target = TargetResolved {
+            defence_level: 100,
+            magic_level: 10,
+            defence_bonuses: crate::model::DefenceBonuses {
+                stab: 50,
+                slash: 20,
+                crush: 50,
+                ranged: 200,
+                magic: 100,
+            },
+            ..TargetResolved::default()
+        };
+        // (100 + 9) * (20 + 64) beats magic's (10 + 9) * (100 + 64)
+        assert_eq!(target.defence_roll(AttackType::Slash), 109 * 84);
+        assert_eq!(target.defence_roll(AttackType::Magic), 19 * 164);
+        assert_eq!(target.weakest_style(), AttackType::Magic);
+        let weaknesses = target.style_weaknesses();
+        let order: Vec<AttackType> = weaknesses.iter().map(|r| r.attack_type).collect();
+        assert_eq!(
+            order,
+            [
+                AttackType::Magic,
+                AttackType::Slash,
+                AttackType::Stab,
+                AttackType::Crush,
+                AttackType::Ranged
+            ]
+        );
+        assert!(weaknesses
+            .windows(2)
+            .all(|pair| pair[0].accuracy >= pair[1].accuracy));
+    }
+
+    #[test]
+    fn test_target_overrides_are_clamped() {
+        use crate::data::apply_target_overrides;
+        use crate::model::{BuildResolved, DefenceBonusesPartial, PlayerState, TargetOverrides};
+
+        let mut target = TargetResolved::default();
+        apply_target_overrides(
+            &mut target,
+            &TargetOverrides {
+                defence_level: Some(-20),
+                magic_level: Some(100_000),
+                hitpoints_level: Some(500),
+                defence_bonuses: Some(DefenceBonusesPartial {
+                    slash: Some(-1_000),
+                    crush: Some(-10),
+                    magic: Some(i64::MAX),
+                    ..DefenceBonusesPartial::default()
+                }),
+                ..TargetOverrides::default()
+            },
+        );
+        assert_eq!(target.defence_level, 0);
+        assert_eq!(target.magic_level, u16::MAX);
+        assert_eq!(target.hitpoints, 500);
+        assert_eq!(target.defence_bonuses.slash, MIN_DEFENCE_BONUS);
+        assert_eq!(target.defence_bonuses.crush, -10);
+        assert_eq!(target.defence_bonuses.magic, i32::MAX);
+
+        // Clamped overrides always pass validation
+        let ctx = EvalContext {
+            player: &PlayerState::default(),
+            build: &BuildResolved::default(),
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        };
+        assert_eq!(validate_context(&ctx), Ok(()));
+    }
+
+    #[test]
+    fn test_rolls_do_not_overflow_i32() {
+        // 500k effective attack with +5000 gear used to wrap past i32::MAX
+        let roll = max_attack_roll(500_000, 5_000);
+        assert_eq!(roll, 500_000 * 5_064);
+        assert_eq!(max_attack_roll(i64::MAX, i32::MAX), i64::MAX);
+        assert_eq!(max_defence_roll(100, -200), 0);
+        assert_eq!(max_hit_melee(110, -100), 0);
+        assert_eq!(reported(roll), 2_532_000_000);
+        assert_eq!(reported(i64::MAX), u32::MAX);
+        assert_eq!(reported(-5), 0);
+    }
+
+    /// Extreme-input values for the fuzz test: mostly edge values, otherwise
+    /// anything in range.
+    trait EdgeValues {
+        fn i32(&mut self) -> i32;
+    }
+
+    impl EdgeValues for Rng {
+        fn i32(&mut self) -> i32 {
+            const EDGES: [i32; 9] = [i32::MIN, -65, -64, -1, 0, 1, 99, 10_000, i32::MAX];
+            match self.next() % 3 {
+                0 => EDGES[(self.next() % EDGES.len() as u64) as usize],
+                _ => self.next() as i32,
+            }
+        }
+    }
+
+    #[test]
+    fn test_fuzz_evaluate_extreme_inputs() {
+        use crate::model::{
+            BuildResolved, DefenceBonuses, EquipmentBonuses, PlayerState, PoweredStaff, Salamander,
+            Spell, WeaponStrength,
+        };
+
+        let mut rng = Rng::new(0x9e37_79b9_7f4a_7c15);
+        let prayers = [(1, 1), (6, 5), (23, 20), (5, 4), (123, 100)];
+        for i in 0..5_000 {
+            let prayer = prayers[i % prayers.len()];
+            let player = PlayerState {
+                attack: rng.next() as u8,
+                strength: rng.next() as u8,
+                ranged: rng.next() as u8,
+                magic: rng.next() as u8,
+                prayer_attack_mult: prayer,
+                prayer_strength_mult: prayer,
+                prayer_magic_mult: prayer,
+                prayer_ranged_attack_mult: prayer,
+                prayer_ranged_strength_mult: prayer,
+                potion_attack_boost: rng.i32(),
+                potion_strength_boost: rng.i32(),
+                potion_ranged_boost: rng.i32(),
+                potion_magic_boost: rng.i32(),
+                ..PlayerState::default()
+            };
+            let combat = [Combat::Melee, Combat::Ranged, Combat::Magic][i % 3];
+            let build = BuildResolved {
+                bonuses: EquipmentBonuses {
+                    attack_stab: rng.i32(),
+                    attack_slash: rng.i32(),
+                    attack_crush: rng.i32(),
+                    attack_magic: rng.i32(),
+                    attack_ranged: rng.i32(),
+                    melee_strength: rng.i32(),
+                    ranged_strength: rng.i32(),
+                    magic_damage: rng.i32(),
+                    defence_stab: rng.i32(),
+                    defence_slash: rng.i32(),
+                    defence_crush: rng.i32(),
+                    defence_ranged: rng.i32(),
+                    ..EquipmentBonuses::default()
+                },
+                attack_speed: rng.next() as u8,
+                combat,
+                attack_type: combat.attack_types()[0],
+                spell: Some(Spell {
+                    name: "fuzz".to_string(),
+                    base_max_hit: rng.next() as u32,
+                    ..Spell::default()
+                }),
+                powered_staff: (i % 7 == 0).then_some(PoweredStaff::TumekensShadow),
+                salamander: (i % 11 == 0).then_some(Salamander::Tecu),
+                weapon_strength: (i % 13 == 0).then_some(WeaponStrength::Bulwark),
+                ..BuildResolved::default()
+            };
+            let target = TargetResolved {
+                defence_level: rng.next() as u16,
+                magic_level: rng.next() as u16,
+                defence_bonuses: DefenceBonuses {
+                    stab: rng.i32(),
+                    slash: rng.i32(),
+                    crush: rng.i32(),
+                    magic: rng.i32(),
+                    ranged: rng.i32(),
+                },
+                ..TargetResolved::default()
+            };
+
+            let result = evaluate(&EvalContext {
+                player: &player,
+                build: &build,
+                target: &target,
+                ordering: None,
+                adjacent_targets: 0,
+                distance: None,
+            });
+            assert!((0.0..=1.0).contains(&result.accuracy), "{result:?}");
+            assert!(result.dps.is_finite() && result.dps >= 0.0, "{result:?}");
+        }
+    }
 }
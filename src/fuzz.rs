@@ -0,0 +1,519 @@
+// src/fuzz.rs
+//
+// Random scenarios for soak testing. Players, builds and targets are drawn
+// from the data store, resolved like CLI input, evaluated, and the result
+// checked against invariants every evaluation must meet. A seed fixes the
+// whole sequence, so any failure can be replayed.
+
+use crate::data::{resolve_player_input, DataStore, Resolver, MELEE_POTIONS, MELEE_PRAYERS};
+use crate::formulas::{evaluate, validate_context};
+use crate::model::{
+    BoostSpec, BuildInput, Combat, EquipmentInput, EvalContext, EvalResult, FlagsInput,
+    PlayerInput, PreFightAction, SkillsInput, StyleInput, TargetByIdInput, TargetInput,
+    TargetOverrides, WeaponConfig, ARMOUR_SLOTS,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Ranged and magic prayers tried alongside the melee ones.
+const OTHER_PRAYERS: [&str; 6] = [
+    "sharp_eye",
+    "eagle_eye",
+    "rigour",
+    "mystic_will",
+    "mystic_might",
+    "augury",
+];
+
+const RANGED_POTIONS: [&str; 2] = ["ranging", "super_ranging"];
+const MAGIC_POTIONS: [&str; 3] = ["magic", "super_magic", "imbued_heart"];
+
+/// Attempts at drawing a scenario the resolver accepts before giving up.
+const MAX_ATTEMPTS: u32 = 100;
+
+/// One generated scenario, in the same shape as the CLI's input files.
+#[derive(Debug, Clone, Serialize)]
+pub struct FuzzScenario {
+    pub player: PlayerInput,
+    pub build: BuildInput,
+    pub target: TargetInput,
+}
+
+/// A scenario whose evaluation broke an invariant or panicked.
+#[derive(Debug, Clone, Serialize)]
+pub struct FuzzFailure {
+    /// Position in the seeded sequence (0-based).
+    pub index: u64,
+    pub scenario: FuzzScenario,
+    pub problems: Vec<String>,
+}
+
+/// What happened to one scenario.
+#[derive(Debug, Clone)]
+pub enum FuzzOutcome {
+    Passed,
+    /// No valid scenario was drawn in `MAX_ATTEMPTS` tries.
+    Rejected,
+    Failed(Box<FuzzFailure>),
+}
+
+/// Problems with an evaluation result; empty if it's sound.
+pub fn check_invariants(result: &EvalResult) -> Vec<String> {
+    let mut problems = Vec::new();
+    if !(0.0..=1.0).contains(&result.accuracy) {
+        problems.push(format!("accuracy {} outside 0..=1", result.accuracy));
+    }
+    if !result.dps.is_finite() || result.dps < 0.0 {
+        problems.push(format!("DPS {} not finite and non-negative", result.dps));
+    }
+    if !result.poison_dps.is_finite() || result.poison_dps < 0.0 {
+        problems.push(format!(
+            "poison DPS {} not finite and non-negative",
+            result.poison_dps
+        ));
+    }
+    if !result.burn_dps.is_finite() || result.burn_dps < 0.0 {
+        problems.push(format!(
+            "burn DPS {} not finite and non-negative",
+            result.burn_dps
+        ));
+    }
+    for roll in &result.style_rolls {
+        if !(0.0..=1.0).contains(&roll.accuracy) {
+            problems.push(format!(
+                "{:?} accuracy {} outside 0..=1",
+                roll.attack_type, roll.accuracy
+            ));
+        }
+    }
+    if result
+        .hit_max_hits
+        .first()
+        .is_some_and(|&m| m != result.max_hit)
+    {
+        problems.push(format!(
+            "first hit max {:?} differs from max hit {}",
+            result.hit_max_hits.first(),
+            result.max_hit
+        ));
+    }
+    if result.interval_ticks.0 == 0 {
+        problems.push("0-tick attack interval".to_string());
+    }
+    problems
+}
+
+/// Tiny xorshift generator, so runs are reproducible without a dependency.
+/// Also drives the extreme-input test in `formulas`.
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        // xorshift never leaves 0
+        Rng(seed.max(1))
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Uniform-enough value in `low..=high`.
+    fn range(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next() % (high - low + 1)
+    }
+
+    fn chance(&mut self, one_in: u64) -> bool {
+        self.next().is_multiple_of(one_in)
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        let i = self.next() % items.len() as u64;
+        items.get(i as usize)
+    }
+}
+
+/// Draws scenarios from a data store and checks them.
+pub struct Fuzzer<'a> {
+    store: &'a DataStore,
+    rng: Rng,
+    index: u64,
+    weapons: Vec<u32>,
+    /// Wearable item IDs per armour slot.
+    armour: HashMap<&'static str, Vec<u32>>,
+    monsters: Vec<u32>,
+    spells: Vec<String>,
+}
+
+impl<'a> Fuzzer<'a> {
+    pub fn new(store: &'a DataStore, seed: u64) -> Self {
+        // Pools are sorted: HashMap order would make seeds unreproducible
+        let mut weapons: Vec<u32> = store
+            .items
+            .values()
+            .filter(|item| item.weapon.is_some() && item.slot() == Some("weapon"))
+            .map(|item| item.id)
+            .collect();
+        weapons.sort_unstable();
+        let mut armour: HashMap<&'static str, Vec<u32>> = HashMap::new();
+        for slot in ARMOUR_SLOTS {
+            let mut ids: Vec<u32> = store
+                .items
+                .values()
+                .filter(|item| item.slot() == Some(slot))
+                .map(|item| item.id)
+                .collect();
+            ids.sort_unstable();
+            armour.insert(slot, ids);
+        }
+        let mut monsters: Vec<u32> = store.monsters.keys().copied().collect();
+        monsters.sort_unstable();
+        let mut spells: Vec<String> = store.spells.keys().cloned().collect();
+        spells.sort_unstable();
+
+        Self {
+            store,
+            rng: Rng::new(seed),
+            index: 0,
+            weapons,
+            armour,
+            monsters,
+            spells,
+        }
+    }
+
+    /// Scenarios drawn so far.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Draw the next scenario and check it.
+    pub fn step(&mut self) -> FuzzOutcome {
+        let index = self.index;
+        self.index += 1;
+        for _ in 0..MAX_ATTEMPTS {
+            let Some(scenario) = self.scenario() else {
+                continue;
+            };
+            let checked = catch_unwind(AssertUnwindSafe(|| check_scenario(self.store, &scenario)));
+            let problems = match checked {
+                Ok(None) => continue,
+                Ok(Some(problems)) => problems,
+                Err(panic) => vec![format!("panicked: {}", panic_message(&*panic))],
+            };
+            if problems.is_empty() {
+                return FuzzOutcome::Passed;
+            }
+            return FuzzOutcome::Failed(Box::new(FuzzFailure {
+                index,
+                scenario,
+                problems,
+            }));
+        }
+        FuzzOutcome::Rejected
+    }
+
+    /// Draw the next scenario the resolver accepts and evaluate it, without
+    /// checking invariants; `None` if no valid scenario was drawn in
+    /// `MAX_ATTEMPTS` tries. Advances the sequence like `step`.
+    pub fn next_result(&mut self) -> Option<(FuzzScenario, EvalResult)> {
+        self.index += 1;
+        for _ in 0..MAX_ATTEMPTS {
+            let Some(scenario) = self.scenario() else {
+                continue;
+            };
+            if let Some(result) = evaluate_scenario(self.store, &scenario) {
+                return Some((scenario, result));
+            }
+        }
+        None
+    }
+
+    /// A random scenario, or `None` if the data has nothing to draw from.
+    fn scenario(&mut self) -> Option<FuzzScenario> {
+        let build = self.build()?;
+        let player = self.player(build.style.combat);
+        let target = self.target()?;
+        Some(FuzzScenario {
+            player,
+            build,
+            target,
+        })
+    }
+
+    fn build(&mut self) -> Option<BuildInput> {
+        let weapon_id = *self.rng.pick(&self.weapons)?;
+        let weapon = self.store.get_item(weapon_id).ok()?;
+        let styles = Resolver::new(self.store).weapon_styles(weapon_id).ok()?;
+        let &(attack_type, stance) = self.rng.pick(&styles)?;
+
+        let mut equipment = EquipmentInput {
+            weapon: weapon_id,
+            ..EquipmentInput::default()
+        };
+        for slot in ARMOUR_SLOTS {
+            if slot == "ammo" || (slot == "shield" && weapon.two_handed) || self.rng.chance(3) {
+                continue;
+            }
+            let item = self.rng.pick(&self.armour[slot]).copied();
+            if let Some(entry) = equipment.armour_slot_mut(slot) {
+                *entry = item;
+            }
+        }
+        equipment.ammo = match weapon.ammo_slot_ammunition() {
+            Some(ammunition) => {
+                let fired: Vec<u32> = ammunition
+                    .iter()
+                    .copied()
+                    .filter(|&id| self.store.fires(weapon, id))
+                    .collect();
+                Some(*self.rng.pick(&fired)?)
+            }
+            None => None,
+        };
+        // Blowpipes sometimes go without darts
+        equipment.charged_ammo = match weapon.charged_ammunition() {
+            Some(ammunition) if !self.rng.chance(4) => {
+                let loaded: Vec<u32> = ammunition
+                    .iter()
+                    .copied()
+                    .filter(|id| self.store.items.contains_key(id))
+                    .collect();
+                self.rng.pick(&loaded).copied()
+            }
+            _ => None,
+        };
+
+        // Spells only for staves that can cast them; powered staves and
+        // salamanders are rejected with one, so sometimes leave it out
+        let combat = attack_type.combat();
+        let spell = if combat == Combat::Magic && !self.rng.chance(4) {
+            let usable: Vec<&String> = self
+                .spells
+                .iter()
+                .filter(|key| {
+                    self.store
+                        .get_spell(key)
+                        .is_ok_and(|spell| spell.can_autocast_with(weapon))
+                })
+                .collect();
+            self.rng.pick(&usable).map(|key| key.to_string())
+        } else {
+            None
+        };
+
+        Some(BuildInput {
+            schema_version: 1,
+            equipment,
+            style: StyleInput {
+                combat,
+                attack_type,
+                stance,
+                special_attack: false,
+                manual_cast: false,
+                runes: vec![],
+                spell,
+            },
+            weapon_config: WeaponConfig::default(),
+        })
+    }
+
+    fn player(&mut self, combat: Combat) -> PlayerInput {
+        let mut level = |low| self.rng.range(low, 99) as u8;
+        let skills = SkillsInput {
+            attack: level(1),
+            strength: level(1),
+            defence: level(1),
+            ranged: level(1),
+            magic: level(1),
+            prayer: level(1),
+            hitpoints: level(10),
+        };
+
+        let mut prayers = HashMap::new();
+        if !self.rng.chance(3) {
+            let melee = MELEE_PRAYERS.iter().map(|p| p.key);
+            let all: Vec<&str> = melee.chain(OTHER_PRAYERS).collect();
+            if let Some(prayer) = self.rng.pick(&all) {
+                prayers.insert(prayer.to_string(), true);
+            }
+        }
+
+        let potions: &[&str] = match combat {
+            Combat::Melee => MELEE_POTIONS,
+            Combat::Ranged => &RANGED_POTIONS,
+            Combat::Magic => &MAGIC_POTIONS,
+        };
+        let potion = (!self.rng.chance(3))
+            .then(|| self.rng.pick(potions).map(|kind| BoostSpec::new(kind)))
+            .flatten();
+        let mut boosts = crate::model::BoostsInput::default();
+        match combat {
+            Combat::Melee => boosts.melee = potion,
+            Combat::Ranged => boosts.ranged = potion,
+            Combat::Magic => boosts.magic = potion,
+        }
+
+        let flags = FlagsInput {
+            on_slayer_task: self.rng.chance(2),
+            in_wilderness: self.rng.chance(8),
+            forinthry_surge: self.rng.chance(16),
+            kandarin_hard_diary: self.rng.chance(2),
+            charge: self.rng.chance(4),
+            current_hitpoints: self
+                .rng
+                .chance(3)
+                .then(|| self.rng.range(1, u64::from(skills.hitpoints)) as u8),
+        };
+
+        let mut pre_fight = vec![];
+        if self.rng.chance(6) {
+            let actions = [
+                PreFightAction::DragonBattleaxe,
+                PreFightAction::SmellingSalts,
+                PreFightAction::Vulnerability,
+                PreFightAction::DragonWarhammer,
+                PreFightAction::ElderMaul,
+                PreFightAction::BandosGodsword {
+                    damage: self.rng.range(0, 80) as u16,
+                },
+            ];
+            pre_fight.extend(self.rng.pick(&actions).cloned());
+        }
+
+        PlayerInput {
+            schema_version: 1,
+            skills,
+            prayers,
+            boosts,
+            flags,
+            pre_fight,
+        }
+    }
+
+    fn target(&mut self) -> Option<TargetInput> {
+        let monster_id = *self.rng.pick(&self.monsters)?;
+        // Overrides may be far out of range; the resolver clamps them
+        let overrides = self.rng.chance(4).then(|| TargetOverrides {
+            defence_level: Some(self.rng.range(0, 1000) as i64 - 200),
+            ..TargetOverrides::default()
+        });
+        Some(TargetInput::ById(TargetByIdInput {
+            schema_version: 1,
+            monster_id,
+            overrides,
+        }))
+    }
+}
+
+/// Resolve and evaluate a scenario: `None` if the resolver or input
+/// validation rejects it, else the broken invariants.
+pub fn check_scenario(store: &DataStore, scenario: &FuzzScenario) -> Option<Vec<String>> {
+    evaluate_scenario(store, scenario).map(|result| check_invariants(&result))
+}
+
+/// Resolve and evaluate a scenario: `None` if the resolver or input
+/// validation rejects it.
+pub fn evaluate_scenario(store: &DataStore, scenario: &FuzzScenario) -> Option<EvalResult> {
+    let resolver = Resolver::new(store);
+    let player = resolve_player_input(&scenario.player);
+    let build = resolver.resolve_build(&scenario.build).ok()?;
+    let target = resolver
+        .resolve_target_for(&scenario.target, &scenario.player)
+        .ok()?;
+    let ctx = EvalContext {
+        player: &player,
+        build: &build,
+        target: &target,
+        ordering: None,
+        adjacent_targets: 0,
+        distance: None,
+    };
+    validate_context(&ctx).ok()?;
+    Some(evaluate(&ctx))
+}
+
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "(no message)".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::test_support::weapon;
+    use crate::model::AttackType;
+
+    #[test]
+    fn test_check_invariants() {
+        let sound = EvalResult {
+            dps: 1.0,
+            accuracy: 0.5,
+            max_hit: 20,
+            hit_max_hits: vec![20],
+            ..EvalResult::default()
+        };
+        assert!(check_invariants(&sound).is_empty());
+
+        let broken = EvalResult {
+            dps: f64::NAN,
+            accuracy: 1.5,
+            ..sound.clone()
+        };
+        assert_eq!(check_invariants(&broken).len(), 2);
+        let mismatched = EvalResult {
+            hit_max_hits: vec![19],
+            ..sound
+        };
+        assert_eq!(check_invariants(&mismatched).len(), 1);
+    }
+
+    #[test]
+    fn test_fuzzer_is_seeded() {
+        let mut store = DataStore::new();
+        store
+            .items
+            .insert(1, weapon(1, "rune scimitar", 45, 44, 40));
+        store
+            .monsters
+            .insert(10, serde_json::from_value(monster_json()).unwrap());
+
+        let draw = |seed| {
+            let mut fuzzer = Fuzzer::new(&store, seed);
+            (0..20)
+                .map(|_| {
+                    let scenario = fuzzer.scenario().unwrap();
+                    assert_eq!(scenario.build.style.attack_type, AttackType::Slash);
+                    assert!(matches!(
+                        fuzzer.step(),
+                        FuzzOutcome::Passed | FuzzOutcome::Rejected
+                    ));
+                    serde_json::to_string(&scenario).unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draw(7), draw(7));
+        assert_ne!(draw(7), draw(8));
+    }
+
+    fn monster_json() -> serde_json::Value {
+        serde_json::json!({
+            "id": 10,
+            "name": "Goblin",
+            "hitpoints": 5,
+            "defence_level": 1,
+            "magic_level": 1,
+        })
+    }
+}
@@ -2,17 +2,37 @@
 //
 // OSRS DPS Simulator - Library entry point
 
+pub mod aoe;
+pub mod bolts;
+pub mod cache;
 pub mod data;
+pub mod determinism;
+pub mod dot;
+pub mod economics;
 pub mod effects;
+pub mod fetch_cache;
 pub mod formulas;
+pub mod fuzz;
+pub mod mechanics;
 pub mod model;
+pub mod moons;
+pub mod optimizer;
+pub mod ordering;
+pub mod planner;
+pub mod render;
+pub mod specs;
+pub mod ticks;
+pub mod trip;
 
 // Re-export commonly used types
+pub use cache::EvalCache;
 pub use data::{DataError, DataStore, Resolver};
 pub use effects::{default_effects, EffectContext, EffectRegistry, EngineState, Stage};
-pub use formulas::evaluate;
+pub use formulas::{evaluate, try_evaluate, validate_context, ContextError};
+pub use mechanics::{TargetMechanic, WeaponMechanic};
 pub use model::{
-    AttackType, BuildInput, BuildResolved, Combat, DefenceBonuses, EquipmentBonuses,
-    EquipmentInput, EvalContext, EvalResult, PlayerInput, PlayerState, Stance, StyleInput,
-    TargetInput, TargetResolved,
+    AttackType, BuildInput, BuildResolved, Combat, Confidence, DataFlag, DefenceBonuses,
+    EquipmentBonuses, EquipmentInput, EvalContext, EvalResult, PlayerInput, PlayerState,
+    PreFightAction, Stance, StyleInput, StyleRoll, TargetInput, TargetResolved,
 };
+pub use ticks::Ticks;
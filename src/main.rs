@@ -3,12 +3,37 @@
 // CLI for the OSRS DPS Simulator
 
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use osrs_sim::{
-    data::{resolve_player, DataStore, Resolver},
-    evaluate, BuildInput, EvalContext, PlayerInput, TargetInput, TargetResolved,
+    data::{
+        player_f2p_violations, resolve_player_input, DataStore, Resolver, SpecData,
+        SPECIAL_ATTACK_TAG,
+    },
+    determinism::{batch_hash, DETERMINISM_SEED},
+    economics::{potion_cost, rune_cost},
+    effects::{default_registry, multiplier_stack, Condition, Effect, Op, Stat},
+    evaluate, fetch_cache,
+    formulas::{
+        drain_analysis, evaluate_with, sensitivity_analysis, validate_context,
+        REFERENCE_ATTACK_ROLL,
+    },
+    fuzz::{FuzzOutcome, Fuzzer},
+    model::{BoostSpec, Stance, ARMOUR_SLOTS, LONGRANGE_BONUS, MAX_ATTACK_RANGE},
+    optimizer::{
+        bis_table, explain_gear, nearly_available, optimize_gear, rank_boosts, AccountConstraints,
+        BoostFilter, LossReason,
+    },
+    ordering,
+    planner::{max_hit_breakpoints, plan_progression, SkillGoal},
+    render::{Align, Cell, Detail, English, EvalReport, Paint, Renderer, Table, Theme},
+    trip::{simulate_trip, Exposure, RepotPolicy},
+    AttackType, BuildInput, Combat, EffectContext, EffectRegistry, EvalContext, PlayerInput,
+    TargetInput, TargetResolved, Ticks,
 };
-use std::path::PathBuf;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(name = "osrs-sim")]
@@ -17,21 +42,166 @@ use std::path::PathBuf;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Plain output without colors (also set by the NO_COLOR environment
+    /// variable, and whenever stdout isn't a terminal)
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Never touch the network: `fetch-data` re-ingests from cached
+    /// downloads only (every other command reads only local files)
+    #[arg(long, global = true)]
+    offline: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Evaluate DPS for a given setup
     Eval {
-        /// Path to player JSON file
+        #[command(flatten)]
+        scenario: ScenarioArgs,
+
+        /// Output as JSON (shorthand for --format json)
+        #[arg(long)]
+        json: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+
+        /// Show detailed breakdown
+        #[arg(long)]
+        explain: bool,
+
+        /// Only show the headline numbers
+        #[arg(long, conflicts_with = "explain")]
+        compact: bool,
+
+        /// Try every stance the weapon offers and use the highest-DPS one
+        #[arg(long)]
+        auto_style: bool,
+
+        /// Try every usable prayer/potion combination and use the best one
+        #[arg(long)]
+        auto_boosts: bool,
+
+        /// Prayers --auto-boosts may use (comma-separated; default: all)
+        #[arg(long, value_delimiter = ',')]
+        allow_prayers: Option<Vec<String>>,
+
+        /// Potions --auto-boosts may use (comma-separated; default: all)
+        #[arg(long, value_delimiter = ',')]
+        allow_potions: Option<Vec<String>>,
+
+        /// Free-to-play account: reject members items, spells, prayers and
+        /// potions, and keep --auto-boosts to F2P ones
         #[arg(long)]
-        player: PathBuf,
+        f2p: bool,
 
-        /// Path to build JSON file
+        /// Effect stacking order: "live" or a game version it's pinned to
+        /// (e.g. 2026-10), to reproduce results from before a change
+        #[arg(long, default_value = ordering::LIVE_VERSION)]
+        ordering: String,
+
+        /// Other targets next to the main one, for attacks that hit several
+        /// (Venator bow bounces, bursts and barrages); reports the total AoE DPS
+        #[arg(long, default_value_t = 0)]
+        adjacent_targets: u8,
+
+        /// Tiles to the target: fails if the build can't reach it, sets a
+        /// chinchompa's accuracy, and keeps --auto-style to styles that reach
         #[arg(long)]
-        build: PathBuf,
+        distance: Option<u8>,
+    },
+
+    /// Search for the highest-DPS gear the player can wear
+    ///
+    /// The build's combat style is kept; its gear is only a starting point.
+    Optimize {
+        #[command(flatten)]
+        scenario: ScenarioArgs,
 
-        /// Path to target JSON file
+        /// Also report items within this many levels that would improve the setup
+        #[arg(long, default_value_t = 0)]
+        relax_levels: u32,
+
+        /// Also list the runners-up per slot and why each lost
+        #[arg(long)]
+        explain: bool,
+
+        #[command(flatten)]
+        account: AccountArgs,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Plan DPS and gear milestones while training toward goal levels
+    ///
+    /// The player file holds current levels; the build's combat style is kept.
+    Plan {
+        #[command(flatten)]
+        scenario: ScenarioArgs,
+
+        /// Goal level, e.g. strength=99 (repeatable)
+        #[arg(long = "goal", required = true)]
+        goals: Vec<SkillGoal>,
+
+        /// Levels gained per bracket
+        #[arg(long, default_value_t = 5)]
+        step: u8,
+
+        #[command(flatten)]
+        account: AccountArgs,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Rank the best items per slot by DPS, keeping the rest of the build
+    BisTable {
+        #[command(flatten)]
+        scenario: ScenarioArgs,
+
+        /// Items to list per slot
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+
+        #[command(flatten)]
+        account: AccountArgs,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List the levels where the build's max hit goes up, with XP needed
+    Breakpoints {
+        #[command(flatten)]
+        scenario: ScenarioArgs,
+
+        /// Skill to train
+        #[arg(long, default_value = "strength")]
+        skill: String,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// DPS of several players with several builds against one target, as a matrix
+    Compare {
+        /// Player JSON files, one row each (comma-separated or repeated)
+        #[arg(long = "player", required = true, value_delimiter = ',')]
+        players: Vec<PathBuf>,
+
+        /// Build JSON files, one column each (comma-separated or repeated)
+        #[arg(long = "build", required = true, value_delimiter = ',')]
+        builds: Vec<PathBuf>,
+
+        /// Path to target JSON file, or a built-in target (`dummy`, `dummy:undead`)
         #[arg(long)]
         target: PathBuf,
 
@@ -43,11 +213,105 @@ enum Commands {
         #[arg(long)]
         json: bool,
 
-        /// Show detailed breakdown
+        /// For each player, line the builds up stage by stage (effective
+        /// levels, rolls, effects applied) and mark where they diverge
         #[arg(long)]
         explain: bool,
     },
 
+    /// Compare DPS now with the target's defence drained to zero
+    Drain {
+        #[command(flatten)]
+        scenario: ScenarioArgs,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// DPS change per +1 strength bonus, attack bonus, attack level and
+    /// target defence, to tell accuracy upgrades from strength ones
+    Sensitivity {
+        #[command(flatten)]
+        scenario: ScenarioArgs,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Simulate a trip with potion decay and a re-pot policy
+    Trip {
+        #[command(flatten)]
+        scenario: ScenarioArgs,
+
+        /// Trip length in minutes
+        #[arg(long, default_value = "60")]
+        minutes: u32,
+
+        /// Trip length in ticks instead, for a short deadline (a healer to
+        /// kill before it heals, a shield about to come back)
+        #[arg(long, conflicts_with = "minutes")]
+        ticks: Option<u32>,
+
+        /// Distance to the target in tiles: ranged and magic damage lands
+        /// once the projectile arrives (default: as soon as it's dealt)
+        #[arg(long)]
+        distance: Option<u8>,
+
+        /// The target only gets an attack in every this many ticks, from
+        /// flinching it (default: at its own attack speed)
+        #[arg(long, value_name = "TICKS")]
+        flinch_every: Option<u32>,
+
+        /// Attack from a safespot: the target never hits back
+        #[arg(long, conflicts_with = "flinch_every")]
+        safespot: bool,
+
+        /// Re-drink when the potion boost falls below this many levels
+        /// (default: drink once at the start)
+        #[arg(long)]
+        repot_below: Option<i32>,
+
+        /// Price of one potion (as carried, e.g. 4-dose) in gp
+        #[arg(long)]
+        potion_price: Option<u64>,
+
+        /// Price of a rune as NAME=GP (e.g. sunfire=30), for a spell's rune
+        /// cost; repeat or comma-separate for each rune
+        #[arg(long = "rune-price", value_delimiter = ',', value_parser = parse_rune_price)]
+        rune_prices: Vec<(String, u64)>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Evaluate random valid scenarios and check engine invariants
+    ///
+    /// Each scenario is a random player, build and target drawn from the
+    /// data. Results must be panic-free, with accuracy in 0..=1 and finite
+    /// DPS. Failures are printed as replayable input JSON.
+    FuzzScenarios {
+        /// Number of scenarios (0: run until interrupted)
+        #[arg(long, default_value_t = 10_000)]
+        count: u64,
+
+        /// Seed for the scenario sequence (default: from the clock, or a
+        /// fixed one with --determinism)
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Print one canonical hash of every result instead of checking
+        /// invariants, to compare builds of the engine across platforms
+        #[arg(long)]
+        determinism: bool,
+
+        /// Path to data directory
+        #[arg(long, default_value = "./data")]
+        data_dir: PathBuf,
+    },
+
     /// Validate data files
     ValidateData {
         /// Path to data directory
@@ -55,6 +319,17 @@ enum Commands {
         data_dir: PathBuf,
     },
 
+    /// List spec weapons missing special attack data, or scaffold entries for them
+    SpecDb {
+        /// Print template data/specs entries for the missing weapons as JSON
+        #[arg(long)]
+        scaffold: bool,
+
+        /// Path to data directory
+        #[arg(default_value = "./data")]
+        data_dir: PathBuf,
+    },
+
     /// Show info about a specific item
     ItemInfo {
         /// Item ID
@@ -65,254 +340,1410 @@ enum Commands {
         data_dir: PathBuf,
     },
 
-    /// Show info about a specific monster
-    MonsterInfo {
-        /// Monster ID
-        id: u32,
+    /// Show info about a specific monster
+    MonsterInfo {
+        /// Monster ID
+        id: u32,
+
+        /// Build JSON file to quick-check DPS with, adding the styles the
+        /// monster defends worst against
+        #[arg(long)]
+        build: Option<PathBuf>,
+
+        /// Player JSON file for the quick check (default: 99 all, no boosts)
+        #[arg(long, requires = "build")]
+        player: Option<PathBuf>,
+
+        /// Path to data directory
+        #[arg(long, default_value = "./data")]
+        data_dir: PathBuf,
+    },
+
+    /// Download item and monster data with the ingestion scripts, through
+    /// the shared download cache
+    FetchData {
+        /// Path to the scripts directory
+        #[arg(long, default_value = "./scripts")]
+        scripts_dir: PathBuf,
+    },
+
+    /// Inspect or empty the download cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Show which effects a monster's attributes can trigger
+    Attributes {
+        /// Monster ID
+        id: u32,
+
+        /// Build JSON file to show the effect stack for
+        #[arg(long)]
+        build: Option<PathBuf>,
+
+        /// Player JSON file (default: 99 all, no boosts)
+        #[arg(long)]
+        player: Option<PathBuf>,
+
+        /// Path to data directory
+        #[arg(long, default_value = "./data")]
+        data_dir: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Where the cache is and what each section holds
+    Status {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Delete every cached download
+    Clear,
+}
+
+/// Player/build/target files shared by the scenario commands.
+#[derive(Args)]
+struct ScenarioArgs {
+    /// Path to player JSON file
+    #[arg(long)]
+    player: PathBuf,
+
+    /// Path to build JSON file
+    #[arg(long)]
+    build: PathBuf,
+
+    /// Path to target JSON file, or a built-in target: `dummy` (no
+    /// defence, endless hitpoints) or `dummy:undead`
+    #[arg(long)]
+    target: PathBuf,
+
+    /// Path to data directory (default: ./data)
+    #[arg(long, default_value = "./data")]
+    data_dir: PathBuf,
+}
+
+/// Account restrictions shared by the gear search commands.
+#[derive(Args)]
+struct AccountArgs {
+    /// Stop training a skill (or suggesting levels) before combat would exceed this
+    #[arg(long)]
+    max_combat: Option<u8>,
+
+    /// Only use items an ironman can obtain
+    #[arg(long)]
+    ironman: bool,
+
+    /// Free-to-play account: only F2P items, and reject members prayers/potions
+    #[arg(long)]
+    f2p: bool,
+
+    /// Skip items with any of these tags from data/tags (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    exclude_tags: Vec<String>,
+}
+
+impl AccountArgs {
+    fn constraints(&self) -> AccountConstraints {
+        AccountConstraints {
+            max_combat_level: self.max_combat,
+            ironman: self.ironman,
+            f2p: self.f2p,
+            exclude_tags: self.exclude_tags.clone(),
+        }
+    }
+}
+
+/// Parsed scenario inputs and the data store they refer to.
+struct Scenario {
+    store: DataStore,
+    player: PlayerInput,
+    build: BuildInput,
+    target: TargetInput,
+}
+
+impl ScenarioArgs {
+    /// Load the data store and parse the three input files.
+    fn load(&self) -> Result<Scenario> {
+        Ok(Scenario {
+            store: load_store(&self.data_dir)?,
+            player: read_json(&self.player, "player")?,
+            build: read_json(&self.build, "build")?,
+            target: read_target(&self.target)?,
+        })
+    }
+}
+
+/// Read a target file, or name a built-in target (`dummy`, `dummy:*`).
+fn read_target(path: &Path) -> Result<TargetInput> {
+    match path.to_str() {
+        Some(name) if name.starts_with("dummy:") || name == "dummy" => TargetInput::builtin(name)
+            .with_context(|| {
+                format!("Unknown built-in target {name:?} (try dummy or dummy:undead)")
+            }),
+        _ => read_json(path, "target"),
+    }
+}
+
+/// How results are printed to stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Human-readable report
+    Text,
+    /// Pretty-printed JSON
+    Json,
+    /// Compact monospace block sized for a single Discord message
+    Discord,
+}
+
+/// Discord rejects messages longer than this many characters.
+const DISCORD_MESSAGE_LIMIT: usize = 2000;
+
+/// Presentation and search switches for `eval`.
+struct EvalOptions {
+    format: OutputFormat,
+    explain: bool,
+    compact: bool,
+    auto_style: bool,
+    auto_boosts: Option<BoostFilter>,
+    f2p: bool,
+    ordering: String,
+    adjacent_targets: u8,
+    distance: Option<u8>,
+}
+
+/// Whether to color output: stdout is a terminal, and neither `--no-color`
+/// nor a non-empty `NO_COLOR` (https://no-color.org) asks for plain text.
+fn use_color(no_color: bool) -> bool {
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    !no_color && !no_color_env && std::io::stdout().is_terminal()
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let theme = Theme {
+        color: use_color(cli.no_color),
+        ..Theme::default()
+    };
+
+    match cli.command {
+        Commands::Eval {
+            scenario,
+            json,
+            format,
+            explain,
+            compact,
+            auto_style,
+            auto_boosts,
+            allow_prayers,
+            allow_potions,
+            f2p,
+            ordering,
+            adjacent_targets,
+            distance,
+        } => {
+            let options = EvalOptions {
+                format: if json { OutputFormat::Json } else { format },
+                explain,
+                compact,
+                auto_style,
+                auto_boosts: auto_boosts.then_some(BoostFilter {
+                    prayers: allow_prayers,
+                    potions: allow_potions,
+                    f2p,
+                }),
+                f2p,
+                ordering,
+                adjacent_targets,
+                distance,
+            };
+            cmd_eval(&scenario, &options, theme)
+        }
+
+        Commands::Optimize {
+            scenario,
+            relax_levels,
+            explain,
+            account,
+            json,
+        } => cmd_optimize(
+            &scenario,
+            relax_levels,
+            explain,
+            &account.constraints(),
+            json,
+            &theme,
+        ),
+
+        Commands::Plan {
+            scenario,
+            goals,
+            step,
+            account,
+            json,
+        } => cmd_plan(
+            &scenario,
+            &goals,
+            step,
+            &account.constraints(),
+            json,
+            &theme,
+        ),
+
+        Commands::BisTable {
+            scenario,
+            top,
+            account,
+            json,
+        } => cmd_bis_table(&scenario, top, &account.constraints(), json, &theme),
+
+        Commands::Breakpoints {
+            scenario,
+            skill,
+            json,
+        } => cmd_breakpoints(&scenario, &skill, json, &theme),
+
+        Commands::Compare {
+            players,
+            builds,
+            target,
+            data_dir,
+            json,
+            explain,
+        } => cmd_compare(&players, &builds, &target, &data_dir, json, explain, &theme),
+
+        Commands::Drain { scenario, json } => cmd_drain(&scenario, json),
+
+        Commands::Sensitivity { scenario, json } => cmd_sensitivity(&scenario, json),
+
+        Commands::Trip {
+            scenario,
+            minutes,
+            ticks,
+            distance,
+            flinch_every,
+            safespot,
+            repot_below,
+            potion_price,
+            rune_prices,
+            json,
+        } => {
+            let duration = ticks.map_or(Ticks::from_minutes(minutes), Ticks);
+            let exposure = match flinch_every {
+                _ if safespot => Exposure::Safespot,
+                Some(every) => Exposure::Flinched(every),
+                None => Exposure::Tanked,
+            };
+            let policy = repot_below.map_or(RepotPolicy::Once, RepotPolicy::BelowBoost);
+            let prices = TripPrices {
+                potion: potion_price,
+                runes: rune_prices.into_iter().collect(),
+            };
+            cmd_trip(
+                &scenario, duration, distance, exposure, policy, &prices, json,
+            )
+        }
+
+        Commands::FuzzScenarios {
+            count,
+            seed,
+            determinism,
+            data_dir,
+        } if determinism => cmd_determinism(count, seed, &data_dir),
+        Commands::FuzzScenarios {
+            count,
+            seed,
+            data_dir,
+            ..
+        } => cmd_fuzz_scenarios(count, seed, &data_dir, &theme),
+
+        Commands::ValidateData { data_dir } => cmd_validate_data(&data_dir, &theme),
+
+        Commands::SpecDb { scaffold, data_dir } => cmd_spec_db(scaffold, &data_dir, &theme),
+
+        Commands::ItemInfo { id, data_dir } => cmd_item_info(id, &data_dir),
+
+        Commands::MonsterInfo {
+            id,
+            build,
+            player,
+            data_dir,
+        } => cmd_monster_info(id, build.as_deref(), player.as_deref(), &data_dir),
+
+        Commands::FetchData { scripts_dir } => cmd_fetch_data(&scripts_dir, cli.offline),
+
+        Commands::Cache { action } => cmd_cache(action),
+
+        Commands::Attributes {
+            id,
+            build,
+            player,
+            data_dir,
+        } => cmd_attributes(id, build.as_deref(), player.as_deref(), &data_dir),
+    }
+}
+
+fn cmd_eval(scenario: &ScenarioArgs, options: &EvalOptions, theme: Theme) -> Result<()> {
+    let format = options.format;
+    let explain = options.explain;
+    let ordering = ordering::profile(&options.ordering).with_context(|| {
+        format!(
+            "Unknown ordering profile {} (known: {})",
+            options.ordering,
+            ordering::versions().join(", ")
+        )
+    })?;
+
+    // Load data store and input files
+    let Scenario {
+        store,
+        player: mut player_input,
+        build: build_input,
+        target: target_input,
+    } = scenario.load()?;
+    if options.f2p {
+        check_f2p(&store, &player_input, Some(&build_input))?;
+    }
+
+    // Resolve player state
+    let mut player = resolve_player_input(&player_input);
+
+    // Resolve build and target
+    let resolver = Resolver::new(&store);
+    let mut build = resolver
+        .resolve_build(&build_input)
+        .with_context(|| "Failed to resolve equipment")?;
+    let target = resolver
+        .resolve_target_for(&target_input, &player_input)
+        .with_context(|| "Failed to resolve target")?;
+
+    // Optionally swap in the weapon's best style before evaluating
+    let ranked_styles = if options.auto_style {
+        let ranked = resolver.rank_styles(&player, &build, &target, options.distance)?;
+        if let Some(best) = ranked.first() {
+            build.attack_type = best.attack_type;
+            build.stance = best.stance;
+        }
+        ranked
+    } else {
+        vec![]
+    };
+
+    // Then the best prayers/potion for that style
+    let ranked_boosts = match options.auto_boosts {
+        Some(ref filter) => {
+            let ranked = rank_boosts(&player_input, &build, &target, filter);
+            if let Some(best) = ranked.first() {
+                player_input.prayers = best.prayers.iter().map(|p| (p.clone(), true)).collect();
+                player_input.boosts.melee = best.potion.as_ref().map(|kind| BoostSpec::new(kind));
+                player = resolve_player_input(&player_input);
+            }
+            ranked
+        }
+        None => vec![],
+    };
+
+    // Create eval context and evaluate
+    let ctx = EvalContext {
+        player: &player,
+        build: &build,
+        target: &target,
+        ordering: Some(&ordering),
+        adjacent_targets: options.adjacent_targets,
+        distance: options.distance,
+    };
+
+    validate_context(&ctx).with_context(|| "Invalid evaluation input")?;
+    let result = evaluate(&ctx);
+
+    // Output results
+    if format == OutputFormat::Json {
+        if options.auto_style || options.auto_boosts.is_some() {
+            let output = serde_json::json!({
+                "result": result,
+                "styles": ranked_styles,
+                "boosts": ranked_boosts,
+            });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        } else {
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        }
+    } else if format == OutputFormat::Discord {
+        let weapon = store.get_item(build_input.equipment.weapon)?;
+        println!(
+            "{}",
+            render_discord(&target, &weapon.name, &build, &result, explain)
+        );
+    } else {
+        let theme = Theme {
+            detail: if explain {
+                Detail::Verbose
+            } else if options.compact {
+                Detail::Compact
+            } else {
+                Detail::Standard
+            },
+            ..theme
+        };
+        let report = EvalReport {
+            skills: &player_input.skills,
+            player: &player,
+            build: &build,
+            target: &target,
+            result: &result,
+            ranked_styles: &ranked_styles,
+            ranked_boosts: &ranked_boosts,
+        };
+        println!("{}", Renderer::new(&English, theme).eval(&report));
+    }
+
+    Ok(())
+}
+
+/// Fail with every members-only choice in the player file (and build, if
+/// given) listed.
+fn check_f2p(store: &DataStore, player: &PlayerInput, build: Option<&BuildInput>) -> Result<()> {
+    let mut violations = player_f2p_violations(player);
+    if let Some(build) = build {
+        violations.extend(Resolver::new(store).build_f2p_violations(build)?);
+    }
+    if !violations.is_empty() {
+        anyhow::bail!(
+            "Not usable on a F2P account:\n  {}",
+            violations.join("\n  ")
+        );
+    }
+    Ok(())
+}
+
+/// Render an evaluation as a Discord-ready message.
+///
+/// Everything after the header sits in a code block so columns stay aligned
+/// in Discord's proportional font. The result is always within
+/// `DISCORD_MESSAGE_LIMIT` characters.
+fn render_discord(
+    target: &TargetResolved,
+    weapon_name: &str,
+    build: &osrs_sim::BuildResolved,
+    result: &osrs_sim::EvalResult,
+    explain: bool,
+) -> String {
+    let mut rows = vec![
+        ("DPS", format!("{:.3}", result.dps)),
+        ("Max hit", result.max_hit.to_string()),
+        ("Accuracy", format!("{:.2}%", result.accuracy * 100.0)),
+        (
+            "Speed",
+            format!(
+                "{}t ({:.1}s)",
+                result.interval_ticks.0,
+                result.interval_ticks.as_secs_f64()
+            ),
+        ),
+    ];
+    if explain {
+        rows.push(("Atk roll", result.attack_roll.to_string()));
+        rows.push(("Def roll", result.defence_roll.to_string()));
+        rows.push((
+            "Style",
+            format!("{:?} / {:?}", build.attack_type, build.stance).to_lowercase(),
+        ));
+    }
+
+    let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    let mut body = String::new();
+    for (label, value) in &rows {
+        body.push_str(&format!("{label:<width$}  {value}\n"));
+    }
+
+    let header = format!("**{}** vs **{}**", weapon_name, target.name);
+    fit_discord_message(&header, &body, DISCORD_MESSAGE_LIMIT)
+}
+
+/// Join a header and a code-block body, truncating the body so the whole
+/// message fits in `limit` characters.
+fn fit_discord_message(header: &str, body: &str, limit: usize) -> String {
+    const FENCE_OPEN: &str = "```\n";
+    const FENCE_CLOSE: &str = "```";
+    const ELLIPSIS: &str = "…\n";
+
+    let overhead = header.chars().count() + 1 + FENCE_OPEN.len() + FENCE_CLOSE.len();
+    let budget = limit.saturating_sub(overhead);
+
+    let body = if body.chars().count() <= budget {
+        body.to_string()
+    } else {
+        let keep = budget.saturating_sub(ELLIPSIS.chars().count());
+        let mut truncated: String = body.chars().take(keep).collect();
+        truncated.push_str(ELLIPSIS);
+        truncated
+    };
+
+    format!("{header}\n{FENCE_OPEN}{body}{FENCE_CLOSE}")
+}
+
+fn cmd_optimize(
+    scenario: &ScenarioArgs,
+    relax_levels: u32,
+    explain: bool,
+    constraints: &AccountConstraints,
+    json_output: bool,
+    theme: &Theme,
+) -> Result<()> {
+    let Scenario {
+        store,
+        player: player_input,
+        build: build_input,
+        target: target_input,
+    } = scenario.load()?;
+    if constraints.f2p {
+        // The build is only a starting point; members gear in it is dropped
+        check_f2p(&store, &player_input, None)?;
+    }
+
+    let target = Resolver::new(&store)
+        .resolve_target_for(&target_input, &player_input)
+        .with_context(|| "Failed to resolve target")?;
+
+    let best = optimize_gear(&store, &player_input, &build_input, &target, constraints)?;
+    let unlocks = if relax_levels > 0 {
+        nearly_available(
+            &store,
+            &player_input,
+            &build_input,
+            &target,
+            relax_levels,
+            constraints,
+        )?
+    } else {
+        vec![]
+    };
+    let rejected = if explain {
+        explain_gear(
+            &store,
+            &player_input,
+            &best.build,
+            &target,
+            EXPLAIN_PER_SLOT,
+            constraints,
+        )?
+    } else {
+        vec![]
+    };
+
+    if json_output {
+        let mut output = serde_json::json!({ "best": best, "nearly_available": unlocks });
+        if explain {
+            output["rejected"] = serde_json::to_value(&rejected)?;
+        }
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    let item_name = |id: u32| {
+        store
+            .get_item(id)
+            .map(|i| i.name.clone())
+            .unwrap_or_else(|_| format!("#{id}"))
+    };
+
+    println!("=== Best Setup vs {} ===", target.name);
+    println!();
+    println!(
+        "  {:<8} {}",
+        "weapon",
+        item_name(best.build.equipment.weapon)
+    );
+    for (slot, id) in ARMOUR_SLOTS.iter().zip(best.build.equipment.armour_ids()) {
+        if let Some(id) = id {
+            println!("  {:<8} {}", slot, item_name(id));
+        }
+    }
+    println!();
+    println!(
+        "Style: {:?} / {:?}",
+        best.build.style.attack_type, best.build.style.stance
+    );
+    println!("DPS:   {:.4}", best.dps);
+    println!(
+        "({} items excluded by level requirements)",
+        best.excluded.len()
+    );
+
+    if explain {
+        println!();
+        println!("=== Runners-up ===");
+        let mut table = Table::new(&[
+            Align::Left,
+            Align::Left,
+            Align::Right,
+            Align::Right,
+            Align::Right,
+            Align::Right,
+            Align::Left,
+        ])
+        .header([
+            "Slot", "Item", "DPS", "Delta", "Max hit", "Accuracy", "Why not",
+        ]);
+        for item in &rejected {
+            let delta = item.dps - best.dps;
+            table.row([
+                Cell::from(item.slot.as_str()),
+                item.name.as_str().into(),
+                format!("{:.4}", item.dps).into(),
+                Cell::painted(format!("{delta:+.4}"), Paint::for_delta(delta)),
+                item.max_hit.to_string().into(),
+                format!("{:.2}%", item.accuracy * 100.0).into(),
+                describe_loss(&item.reason).into(),
+            ]);
+        }
+        for line in table.render(theme, "  ") {
+            println!("{line}");
+        }
+    }
+
+    if relax_levels > 0 {
+        println!();
+        println!("=== Nearly Available (within {relax_levels} levels) ===");
+        if unlocks.is_empty() {
+            println!("  Nothing within reach would change the setup.");
+        }
+        let mut table = Table::new(&[
+            Align::Left,
+            Align::Right,
+            Align::Right,
+            Align::Right,
+            Align::Right,
+            Align::Right,
+            Align::Left,
+        ])
+        .header([
+            "Skill", "Level", "Needed", "DPS", "Delta", "Gear", "New gear",
+        ]);
+        for unlock in &unlocks {
+            let names: Vec<_> = unlock.new_items.iter().map(|(_, n)| n.as_str()).collect();
+            let delta = unlock.dps_after - unlock.dps_before;
+            table.row([
+                Cell::from(unlock.skill.as_str()),
+                unlock.level.to_string().into(),
+                format!("+{}", unlock.levels_needed).into(),
+                format!("{:.4}", unlock.dps_after).into(),
+                Cell::painted(format!("{delta:+.4}"), Paint::for_delta(delta)),
+                Cell::painted(
+                    format!("{:+.4}", unlock.dps_from_gear),
+                    Paint::for_delta(unlock.dps_from_gear),
+                ),
+                names.join(", ").into(),
+            ]);
+        }
+        if !table.is_empty() {
+            for line in table.render(theme, "  ") {
+                println!("{line}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Runners-up `optimize --explain` lists per slot.
+const EXPLAIN_PER_SLOT: usize = 2;
+
+/// Why an item lost its slot, in words.
+fn describe_loss(reason: &LossReason) -> String {
+    match reason {
+        LossReason::AccuracyTradeOff => "hits harder but lands less often".to_string(),
+        LossReason::MaxHitTradeOff => "more accurate but a lower max hit".to_string(),
+        LossReason::Outclassed => "no higher max hit or accuracy".to_string(),
+        LossReason::Tie => "same DPS as the pick".to_string(),
+        LossReason::Requirements { missing } => {
+            let needs: Vec<String> = missing
+                .iter()
+                .map(|(skill, level)| format!("{level} {skill}"))
+                .collect();
+            format!("would win; needs {}", needs.join(", "))
+        }
+        LossReason::Constraint => "would win; ruled out by account options".to_string(),
+    }
+}
+
+fn cmd_plan(
+    scenario: &ScenarioArgs,
+    goals: &[SkillGoal],
+    step: u8,
+    constraints: &AccountConstraints,
+    json_output: bool,
+    theme: &Theme,
+) -> Result<()> {
+    let Scenario {
+        store,
+        player: player_input,
+        build: build_input,
+        target: target_input,
+    } = scenario.load()?;
+
+    for goal in goals {
+        if player_input.skills.level(&goal.skill).is_none() {
+            anyhow::bail!("Unknown skill in goal: {}", goal.skill);
+        }
+    }
+    if constraints.f2p {
+        check_f2p(&store, &player_input, None)?;
+    }
+
+    let target = Resolver::new(&store)
+        .resolve_target_for(&target_input, &player_input)
+        .with_context(|| "Failed to resolve target")?;
+
+    let milestones = plan_progression(
+        &store,
+        &player_input,
+        &build_input,
+        &target,
+        goals,
+        step,
+        constraints,
+    )?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&milestones)?);
+        return Ok(());
+    }
+
+    println!("=== Progression vs {} ===", target.name);
+    println!();
+    let mut align = vec![Align::Right; goals.len() + 3];
+    align.push(Align::Left);
+    let header = goals
+        .iter()
+        .map(|goal| goal.skill.as_str())
+        .chain(["combat", "XP", "DPS", "New gear"]);
+    let mut table = Table::new(&align).header(header);
+    let best_dps = milestones.iter().map(|m| m.dps).fold(f64::MIN, f64::max);
+    for milestone in &milestones {
+        let mut row: Vec<Cell> = milestone
+            .levels
+            .iter()
+            .map(|(_, level)| level.to_string().into())
+            .collect();
+        let xp: u32 = milestone.xp_needed.iter().map(|(_, xp)| xp).sum();
+        let names: Vec<_> = milestone
+            .new_items
+            .iter()
+            .map(|(_, n)| n.as_str())
+            .collect();
+        let dps_paint = if milestone.dps == best_dps {
+            Paint::Best
+        } else {
+            Paint::Plain
+        };
+        row.extend([
+            milestone.combat_level.to_string().into(),
+            xp.to_string().into(),
+            Cell::painted(format!("{:.4}", milestone.dps), dps_paint),
+            names.join(", ").into(),
+        ]);
+        table.row(row);
+    }
+    for line in table.render(theme, "") {
+        println!("{line}");
+    }
+
+    Ok(())
+}
+
+fn cmd_bis_table(
+    scenario: &ScenarioArgs,
+    top: usize,
+    constraints: &AccountConstraints,
+    json_output: bool,
+    theme: &Theme,
+) -> Result<()> {
+    let Scenario {
+        store,
+        player: player_input,
+        build: build_input,
+        target: target_input,
+    } = scenario.load()?;
+    if constraints.f2p {
+        check_f2p(&store, &player_input, None)?;
+    }
+
+    let target = Resolver::new(&store)
+        .resolve_target_for(&target_input, &player_input)
+        .with_context(|| "Failed to resolve target")?;
+
+    let rankings = bis_table(
+        &store,
+        &player_input,
+        &build_input,
+        &target,
+        top,
+        constraints,
+    )?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&rankings)?);
+        return Ok(());
+    }
+
+    println!("=== Best in slot vs {} ===", target.name);
+    for ranking in rankings.iter().filter(|r| !r.picks.is_empty()) {
+        println!();
+        println!(
+            "{} (empty: {:.4} DPS)",
+            theme.paint(&ranking.slot, Paint::Heading),
+            ranking.empty_dps
+        );
+        let mut table = Table::new(&[Align::Right, Align::Left, Align::Right, Align::Right])
+            .header(["#", "Item", "DPS", "Gain"]);
+        for (rank, pick) in ranking.picks.iter().enumerate() {
+            let paint = if rank == 0 { Paint::Best } else { Paint::Plain };
+            table.row([
+                Cell::from((rank + 1).to_string()),
+                Cell::from(pick.name.as_str()),
+                Cell {
+                    text: format!("{:.4}", pick.dps),
+                    paint,
+                },
+                Cell {
+                    text: format!("{:+.4}", pick.dps_gain),
+                    paint: Paint::for_delta(pick.dps_gain),
+                },
+            ]);
+        }
+        for line in table.render(theme, "  ") {
+            println!("{line}");
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_breakpoints(
+    scenario: &ScenarioArgs,
+    skill: &str,
+    json_output: bool,
+    theme: &Theme,
+) -> Result<()> {
+    let Scenario {
+        store,
+        player: player_input,
+        build: build_input,
+        target: target_input,
+    } = scenario.load()?;
+
+    let skill = skill.to_lowercase();
+    let Some(current) = player_input.skills.level(&skill) else {
+        anyhow::bail!("Unknown skill: {skill}");
+    };
+
+    let resolver = Resolver::new(&store);
+    let build = resolver
+        .resolve_build(&build_input)
+        .with_context(|| "Failed to resolve equipment")?;
+    let target = resolver
+        .resolve_target_for(&target_input, &player_input)
+        .with_context(|| "Failed to resolve target")?;
+
+    let breakpoints = max_hit_breakpoints(&player_input, &build, &target, &skill);
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&breakpoints)?);
+        return Ok(());
+    }
+
+    println!(
+        "=== Max hit breakpoints: {skill} (from {current}) vs {} ===",
+        target.name
+    );
+    println!();
+    if breakpoints.is_empty() {
+        println!("  No further max hits from {skill} with this setup.");
+    }
+    let mut table = Table::new(&[Align::Right, Align::Right, Align::Right, Align::Right]).header([
+        "Level",
+        "Max hit",
+        "DPS",
+        "XP needed",
+    ]);
+    for bp in &breakpoints {
+        table.row([
+            bp.level.to_string(),
+            bp.max_hit.to_string(),
+            format!("{:.4}", bp.dps),
+            bp.xp_needed.to_string(),
+        ]);
+    }
+    if !table.is_empty() {
+        for line in table.render(theme, "  ") {
+            println!("{line}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Load the data store, with a readable error if the directory is wrong.
+fn load_store(data_dir: &Path) -> Result<DataStore> {
+    DataStore::load_from_dir(data_dir)
+        .with_context(|| format!("Failed to load data from {:?}", data_dir))
+}
+
+/// Read and parse a JSON input file (`kind` is used in error messages).
+fn read_json<T: DeserializeOwned>(path: &Path, kind: &str) -> Result<T> {
+    let json = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {kind} file: {:?}", path))?;
+    serde_json::from_str(&json).with_context(|| format!("Failed to parse {kind} JSON: {:?}", path))
+}
+
+fn cmd_compare(
+    player_files: &[PathBuf],
+    build_files: &[PathBuf],
+    target_file: &Path,
+    data_dir: &Path,
+    json_output: bool,
+    explain: bool,
+    theme: &Theme,
+) -> Result<()> {
+    let store = load_store(data_dir)?;
+    let resolver = Resolver::new(&store);
+    let target_input = read_target(target_file)?;
+    // Rows and columns are labelled by file name
+    let label = |path: &PathBuf| {
+        path.file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string())
+    };
+
+    let mut builds = Vec::new();
+    for path in build_files {
+        let input: BuildInput = read_json(path, "build")?;
+        let build = resolver
+            .resolve_build(&input)
+            .with_context(|| format!("Failed to resolve build {path:?}"))?;
+        builds.push(build);
+    }
+
+    let mut target_name = String::new();
+    let mut rows = Vec::new();
+    for path in player_files {
+        let player_input: PlayerInput = read_json(path, "player")?;
+        let player = resolve_player_input(&player_input);
+        // The target can depend on the player (pre-fight drains)
+        let target = resolver
+            .resolve_target_for(&target_input, &player_input)
+            .with_context(|| "Failed to resolve target")?;
+        let mut row = Vec::new();
+        for (build, build_path) in builds.iter().zip(build_files) {
+            let ctx = EvalContext {
+                player: &player,
+                build,
+                target: &target,
+                ordering: None,
+                adjacent_targets: 0,
+                distance: None,
+            };
+            validate_context(&ctx)
+                .with_context(|| format!("Invalid input: {path:?} with {build_path:?}"))?;
+            row.push(evaluate(&ctx));
+        }
+        rows.push(row);
+        target_name = target.name;
+    }
+
+    let players: Vec<String> = player_files.iter().map(label).collect();
+    let build_labels: Vec<String> = build_files.iter().map(label).collect();
+    let dps: Vec<Vec<f64>> = rows
+        .iter()
+        .map(|row| row.iter().map(|result| result.dps).collect())
+        .collect();
+    if json_output {
+        let mut output = serde_json::json!({
+            "target": target_name,
+            "players": players,
+            "builds": build_labels,
+            "dps": dps,
+        });
+        if explain {
+            let stages: Vec<Vec<_>> = rows
+                .iter()
+                .map(|row| row.iter().map(|result| &result.stages).collect())
+                .collect();
+            output["stages"] = serde_json::to_value(stages)?;
+        }
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    println!("=== DPS vs {target_name} ===");
+    println!();
+    let mut aligns = vec![Align::Left];
+    aligns.extend(build_labels.iter().map(|_| Align::Right));
+    let mut table = Table::new(&aligns)
+        .header(std::iter::once("Player".to_string()).chain(build_labels.iter().cloned()));
+    for (player, row) in players.iter().zip(&dps) {
+        // Each player's best build stands out
+        let best = row.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let cells = row.iter().map(|&dps| Cell {
+            text: format!("{dps:.4}"),
+            paint: if builds.len() > 1 && dps == best {
+                Paint::Best
+            } else {
+                Paint::Plain
+            },
+        });
+        table.row(std::iter::once(Cell::from(player.as_str())).chain(cells));
+    }
+    for line in table.render(theme, "  ") {
+        println!("{line}");
+    }
+
+    if explain {
+        let renderer = Renderer::new(&English, *theme);
+        for (player, row) in players.iter().zip(&rows) {
+            let results: Vec<_> = row.iter().collect();
+            println!();
+            println!(
+                "{}",
+                renderer.stage_comparison(player, &build_labels, &results)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_drain(scenario: &ScenarioArgs, json_output: bool) -> Result<()> {
+    let Scenario {
+        store,
+        player: player_input,
+        build: build_input,
+        target: target_input,
+    } = scenario.load()?;
+
+    let player = resolve_player_input(&player_input);
+    let resolver = Resolver::new(&store);
+    let build = resolver
+        .resolve_build(&build_input)
+        .with_context(|| "Failed to resolve equipment")?;
+    let target = resolver
+        .resolve_target_for(&target_input, &player_input)
+        .with_context(|| "Failed to resolve target")?;
+
+    let analysis = drain_analysis(&EvalContext {
+        player: &player,
+        build: &build,
+        target: &target,
+        ordering: None,
+        adjacent_targets: 0,
+        distance: None,
+    });
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&analysis)?);
+        return Ok(());
+    }
+
+    let gain = if analysis.dps > 0.0 {
+        (analysis.dps_at_zero / analysis.dps - 1.0) * 100.0
+    } else {
+        0.0
+    };
+    println!("=== Defence drain: {} ===", target.name);
+    println!();
+    println!(
+        "  Defence {:>4}:     DPS {:.4}",
+        analysis.defence_level, analysis.dps
+    );
+    println!(
+        "  Defence    0:     DPS {:.4} (+{gain:.1}%)",
+        analysis.dps_at_zero
+    );
+    println!("  BGS damage to 0:  {}", analysis.bgs_damage_to_zero);
 
-        /// Path to data directory
-        #[arg(long, default_value = "./data")]
-        data_dir: PathBuf,
-    },
+    Ok(())
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+fn cmd_sensitivity(scenario: &ScenarioArgs, json_output: bool) -> Result<()> {
+    let Scenario {
+        store,
+        player: player_input,
+        build: build_input,
+        target: target_input,
+    } = scenario.load()?;
 
-    match cli.command {
-        Commands::Eval {
-            player,
-            build,
-            target,
-            data_dir,
-            json,
-            explain,
-        } => cmd_eval(&player, &build, &target, &data_dir, json, explain),
+    let player = resolve_player_input(&player_input);
+    let resolver = Resolver::new(&store);
+    let build = resolver
+        .resolve_build(&build_input)
+        .with_context(|| "Failed to resolve equipment")?;
+    let target = resolver
+        .resolve_target_for(&target_input, &player_input)
+        .with_context(|| "Failed to resolve target")?;
 
-        Commands::ValidateData { data_dir } => cmd_validate_data(&data_dir),
+    let analysis = sensitivity_analysis(&EvalContext {
+        player: &player,
+        build: &build,
+        target: &target,
+        ordering: None,
+        adjacent_targets: 0,
+        distance: None,
+    });
 
-        Commands::ItemInfo { id, data_dir } => cmd_item_info(id, &data_dir),
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&analysis)?);
+        return Ok(());
+    }
 
-        Commands::MonsterInfo { id, data_dir } => cmd_monster_info(id, &data_dir),
+    let (strength, level) = match build.combat {
+        Combat::Melee => ("+1 strength bonus", "+1 Attack level"),
+        Combat::Ranged => ("+1 ranged strength", "+1 Ranged level"),
+        Combat::Magic => ("+1% magic damage", "+1 Magic level"),
+    };
+    let attack = format!("+1 {:?} attack", build.attack_type).to_lowercase();
+    let defence = match build.attack_type {
+        AttackType::Magic => "+1 target Magic",
+        _ => "+1 target Defence",
+    };
+    println!("=== Sensitivity: {} ===", target.name);
+    println!();
+    println!("  {:<20} DPS {:.4}", "Current", analysis.dps);
+    for (label, change) in [
+        (strength, analysis.strength_bonus),
+        (attack.as_str(), analysis.attack_bonus),
+        (level, analysis.attack_level),
+        (defence, analysis.target_defence),
+    ] {
+        println!(
+            "  {label:<20} DPS {:.4} ({:+.4}, {:+.2}%)",
+            change.dps, change.change, change.pct
+        );
     }
+    println!();
+    let (accuracy, strength) = (analysis.attack_bonus.change, analysis.strength_bonus.change);
+    if accuracy > strength {
+        println!("  A point of accuracy is worth more than a point of strength here.");
+    } else if strength > accuracy {
+        println!("  A point of strength is worth more than a point of accuracy here.");
+    } else {
+        println!("  Accuracy and strength points are worth the same here.");
+    }
+
+    Ok(())
+}
+
+/// Supply prices given to `trip`.
+struct TripPrices {
+    potion: Option<u64>,
+    /// Rune name -> gp each.
+    runes: HashMap<String, u64>,
+}
+
+/// Parse a `--rune-price` value, `NAME=GP`.
+fn parse_rune_price(value: &str) -> Result<(String, u64), String> {
+    let (name, gp) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=GP, got {value:?}"))?;
+    let gp = gp
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid price in {value:?}"))?;
+    Ok((name.trim().to_lowercase(), gp))
 }
 
-fn cmd_eval(
-    player_path: &PathBuf,
-    build_path: &PathBuf,
-    target_path: &PathBuf,
-    data_dir: &PathBuf,
+fn cmd_trip(
+    scenario: &ScenarioArgs,
+    duration: Ticks,
+    distance: Option<u8>,
+    exposure: Exposure,
+    policy: RepotPolicy,
+    prices: &TripPrices,
     json_output: bool,
-    explain: bool,
 ) -> Result<()> {
-    // Load data store
-    let store = DataStore::load_from_dir(data_dir)
-        .with_context(|| format!("Failed to load data from {:?}", data_dir))?;
-
-    // Load and parse input files
-    let player_json = std::fs::read_to_string(player_path)
-        .with_context(|| format!("Failed to read player file: {:?}", player_path))?;
-    let player_input: PlayerInput = serde_json::from_str(&player_json)
-        .with_context(|| format!("Failed to parse player JSON: {:?}", player_path))?;
-
-    let build_json = std::fs::read_to_string(build_path)
-        .with_context(|| format!("Failed to read build file: {:?}", build_path))?;
-    let build_input: BuildInput = serde_json::from_str(&build_json)
-        .with_context(|| format!("Failed to parse build JSON: {:?}", build_path))?;
-
-    let target_json = std::fs::read_to_string(target_path)
-        .with_context(|| format!("Failed to read target file: {:?}", target_path))?;
-    let target_input: TargetInput = serde_json::from_str(&target_json)
-        .with_context(|| format!("Failed to parse target JSON: {:?}", target_path))?;
-
-    // Resolve player state
-    let player = resolve_player(
-        &player_input.skills,
-        &player_input.prayers,
-        &player_input.boosts,
-        &player_input.flags,
-    );
+    let Scenario {
+        store,
+        player: player_input,
+        build: build_input,
+        target: target_input,
+    } = scenario.load()?;
 
-    // Resolve build
     let resolver = Resolver::new(&store);
-    let equipment_ids = [
-        build_input.equipment.head,
-        build_input.equipment.cape,
-        build_input.equipment.neck,
-        build_input.equipment.ammo,
-        build_input.equipment.body,
-        build_input.equipment.shield,
-        build_input.equipment.legs,
-        build_input.equipment.hands,
-        build_input.equipment.feet,
-        build_input.equipment.ring,
-    ];
-
     let build = resolver
-        .resolve_equipment(
-            &equipment_ids,
-            build_input.equipment.weapon,
-            build_input.style.stance,
-            build_input.style.attack_type,
-        )
+        .resolve_build(&build_input)
         .with_context(|| "Failed to resolve equipment")?;
+    let target = resolver
+        .resolve_target(&target_input)
+        .with_context(|| "Failed to resolve target")?;
 
-    // Resolve target
-    let target = match target_input {
-        TargetInput::ById(ref by_id) => {
-            let mut resolved = resolver
-                .resolve_monster(by_id.monster_id)
-                .with_context(|| format!("Failed to resolve monster {}", by_id.monster_id))?;
-
-            // Apply overrides if any
-            if let Some(ref overrides) = by_id.overrides {
-                if let Some(def_level) = overrides.defence_level {
-                    resolved.defence_level = def_level;
-                }
-                if let Some(ref def_bonuses) = overrides.defence_bonuses {
-                    if let Some(v) = def_bonuses.stab {
-                        resolved.defence_bonuses.stab = v;
-                    }
-                    if let Some(v) = def_bonuses.slash {
-                        resolved.defence_bonuses.slash = v;
-                    }
-                    if let Some(v) = def_bonuses.crush {
-                        resolved.defence_bonuses.crush = v;
-                    }
-                    if let Some(v) = def_bonuses.magic {
-                        resolved.defence_bonuses.magic = v;
-                    }
-                    if let Some(v) = def_bonuses.ranged {
-                        resolved.defence_bonuses.ranged = v;
-                    }
-                }
-                for attr in &overrides.attributes_add {
-                    if !resolved.attributes.contains(attr) {
-                        resolved.attributes.push(attr.clone());
-                    }
-                }
-                for attr in &overrides.attributes_remove {
-                    resolved.attributes.retain(|a| a != attr);
-                }
-            }
-            resolved
-        }
-        TargetInput::Custom(ref custom) => TargetResolved {
-            name: custom.custom.name.clone(),
-            hitpoints: custom.custom.levels.hitpoints,
-            defence_level: custom.custom.levels.defence,
-            defence_bonuses: custom.custom.defence_bonuses,
-            attributes: custom.custom.attributes.clone(),
-        },
-    };
-
-    // Create eval context and evaluate
-    let ctx = EvalContext {
-        player: &player,
-        build: &build,
-        target: &target,
-    };
-
-    let result = evaluate(&ctx);
+    let summary = simulate_trip(
+        &player_input,
+        &build,
+        &target,
+        duration,
+        policy,
+        distance,
+        exposure,
+    );
+    let cost = player_input
+        .boosts
+        .melee
+        .as_ref()
+        .map(|spec| potion_cost(&summary, spec, prices.potion));
+    let runes = build
+        .spell
+        .as_ref()
+        .map(|spell| rune_cost(&summary, spell, &prices.runes));
 
-    // Output results
     if json_output {
-        println!("{}", serde_json::to_string_pretty(&result)?);
+        let output =
+            serde_json::json!({ "trip": summary, "potion_cost": cost, "rune_cost": runes });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    let policy_text = match policy {
+        RepotPolicy::Once => "drink once".to_string(),
+        RepotPolicy::BelowBoost(levels) => format!("re-drink below +{levels}"),
+    };
+    let length = if duration.0.is_multiple_of(Ticks::MINUTE.0) {
+        format!("{} min", duration.0 / Ticks::MINUTE.0)
     } else {
-        println!("=== DPS Evaluation ===");
-        println!();
-        println!("Target: {}", target.name);
-        println!();
-        println!("Results:");
-        println!("  DPS:            {:.4}", result.dps);
-        println!("  Max Hit:        {}", result.max_hit);
-        println!("  Accuracy:       {:.2}%", result.accuracy * 100.0);
-        println!("  Attack Roll:    {}", result.attack_roll);
-        println!("  Defence Roll:   {}", result.defence_roll);
+        format!("{} ticks ({duration})", duration.0)
+    };
+    println!("=== Trip: {length} vs {} ({policy_text}) ===", target.name);
+    println!();
+    println!("  Average DPS:    {:.4}", summary.avg_dps);
+    println!("  Total damage:   {:.0}", summary.total_damage);
+    if let Some(distance) = distance {
         println!(
-            "  Attack Speed:   {} ticks ({:.1}s)",
-            result.interval_ticks,
-            result.interval_ticks as f64 * 0.6
+            "  Hit delay:      {} ticks at {distance} tiles",
+            summary.hit_delay_ticks
         );
-
-        if explain {
-            println!();
-            println!("=== Breakdown ===");
-            println!();
-            println!("Player Stats:");
-            println!(
-                "  Attack:   {} (base) + {} (potion) * {}/{} (prayer)",
-                player.attack,
-                player.potion_attack_boost,
-                player.prayer_attack_mult.0,
-                player.prayer_attack_mult.1
-            );
-            println!(
-                "  Strength: {} (base) + {} (potion) * {}/{} (prayer)",
-                player.strength,
-                player.potion_strength_boost,
-                player.prayer_strength_mult.0,
-                player.prayer_strength_mult.1
-            );
-            println!();
-            println!("Equipment Bonuses:");
-            println!(
-                "  Attack ({:?}): {}",
-                build.attack_type,
-                build.bonuses.attack_bonus_for(build.attack_type)
-            );
-            println!("  Melee Strength: {}", build.bonuses.melee_strength);
-            println!();
-            println!("Target:");
-            println!("  Defence Level: {}", target.defence_level);
-            println!(
-                "  Defence ({:?}): {}",
-                build.attack_type,
-                target.defence_bonuses.defence_bonus_for(build.attack_type)
-            );
-            println!("  Attributes: {:?}", target.attributes);
+        println!(
+            "  In flight:      {:.0} (dealt, not landed by the end)",
+            summary.damage_in_flight
+        );
+    }
+    match summary.first_kill_tick {
+        Some(tick) => println!("  First kill:     tick {} ({tick})", tick.0),
+        None => println!("  First kill:     not within the trip"),
+    }
+    let exposure_text = match exposure {
+        Exposure::Tanked => "tanked".to_string(),
+        Exposure::Flinched(every) => format!("flinched every {every} ticks"),
+        Exposure::Safespot => "safespotted".to_string(),
+    };
+    println!(
+        "  Damage taken:   {:.0} from {} attacks ({exposure_text}, max hit {})",
+        summary.damage_taken, summary.target_attacks, target.max_hit
+    );
+    println!("  Doses used:     {}", summary.doses);
+    println!("  Doses per hour: {:.1}", summary.doses_per_hour);
+    if summary.start_target_defence != target.defence_level {
+        println!(
+            "  Target defence: {} -> {} (avg {:.1}, base {})",
+            summary.start_target_defence,
+            summary.end_target_defence,
+            summary.avg_target_defence,
+            target.defence_level
+        );
+    }
+    if let Some(cost) = cost {
+        println!("  Potions needed: {}", cost.potions_carried);
+        if let (Some(gp), Some(gp_per_hour)) = (cost.gp, cost.gp_per_hour) {
+            println!("  Potion cost:    {gp:.0} gp ({gp_per_hour:.0} gp/h)");
+        }
+    }
+    if let Some(runes) = runes {
+        let used: Vec<String> = runes
+            .runes
+            .iter()
+            .map(|(rune, count)| format!("{count} {rune}"))
+            .collect();
+        println!("  Casts:          {}", runes.casts);
+        println!("  Runes used:     {}", used.join(", "));
+        if let (Some(gp), Some(gp_per_hour)) = (runes.gp, runes.gp_per_hour) {
+            println!("  Rune cost:      {gp:.0} gp ({gp_per_hour:.0} gp/h)");
         }
     }
 
     Ok(())
 }
 
-fn cmd_validate_data(data_dir: &PathBuf) -> Result<()> {
+fn cmd_validate_data(data_dir: &Path, theme: &Theme) -> Result<()> {
     println!("Validating data in {:?}...", data_dir);
 
-    let store = DataStore::load_from_dir(data_dir)
-        .with_context(|| format!("Failed to load data from {:?}", data_dir))?;
+    let store = load_store(data_dir)?;
 
     println!("Loaded {} items", store.items.len());
     println!("Loaded {} monsters", store.monsters.len());
+    println!("Loaded {} item variants", store.variants.len());
+    println!("Loaded {} spells", store.spells.len());
+    println!("Loaded {} special attacks", store.specs.len());
 
     // Basic validation
     let mut warnings = 0;
+    let warn = |message: String| {
+        println!(
+            "{}",
+            theme.paint(&format!("Warning: {message}"), Paint::Warning)
+        );
+    };
 
     for (id, item) in &store.items {
         if item.equipable && item.equipment.is_none() {
-            println!(
-                "Warning: Item {} ({}) is equipable but has no equipment data",
+            warn(format!(
+                "Item {} ({}) is equipable but has no equipment data",
                 id, item.name
-            );
+            ));
             warnings += 1;
         }
         if item.equipable_weapon && item.weapon.is_none() {
-            println!(
-                "Warning: Item {} ({}) is a weapon but has no weapon data",
+            warn(format!(
+                "Item {} ({}) is a weapon but has no weapon data",
                 id, item.name
-            );
+            ));
+            warnings += 1;
+        }
+    }
+
+    for (variant, base) in &store.variants {
+        let slots = [variant, base].map(|id| {
+            store
+                .items
+                .get(id)
+                .and_then(|i| i.equipment.as_ref())
+                .map(|e| e.slot.as_str())
+        });
+        if slots[0] != slots[1] {
+            warn(format!(
+                "Variant {} is mapped to item {} in a different slot",
+                variant, base
+            ));
             warnings += 1;
         }
     }
 
-    for (id, monster) in &store.monsters {
-        if monster.hitpoints == 0 {
-            println!("Warning: Monster {} ({}) has 0 hitpoints", id, monster.name);
+    for spell in store.spells.values() {
+        if spell.base_max_hit == 0 || spell.runes.is_empty() {
+            warn(format!("Spell {} has no max hit or no runes", spell.name));
             warnings += 1;
         }
     }
 
+    for problem in store.spec_problems() {
+        warn(format!("Special attack {problem}"));
+        warnings += 1;
+    }
+
+    for problem in store.monster_problems(default_registry()) {
+        warn(format!("Monster {problem}"));
+        warnings += 1;
+    }
+
     if warnings > 0 {
         println!();
         println!("Found {} warnings", warnings);
@@ -323,9 +1754,175 @@ fn cmd_validate_data(data_dir: &PathBuf) -> Result<()> {
     Ok(())
 }
 
-fn cmd_item_info(id: u32, data_dir: &PathBuf) -> Result<()> {
-    let store = DataStore::load_from_dir(data_dir)
-        .with_context(|| format!("Failed to load data from {:?}", data_dir))?;
+fn cmd_spec_db(scaffold: bool, data_dir: &Path, theme: &Theme) -> Result<()> {
+    let store = load_store(data_dir)?;
+    let missing = store.missing_specs();
+
+    if scaffold {
+        let templates: Vec<SpecData> = missing
+            .iter()
+            .map(|item| SpecData::template(item))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&templates)?);
+        return Ok(());
+    }
+
+    let tagged = store
+        .items
+        .values()
+        .filter(|item| item.tags.iter().any(|t| t == SPECIAL_ATTACK_TAG))
+        .count();
+    println!("=== Special attack data ===");
+    println!();
+    println!(
+        "  {} of {tagged} weapons tagged {SPECIAL_ATTACK_TAG} have an entry ({} entries)",
+        tagged - missing.len(),
+        store.specs.len()
+    );
+
+    let problems = store.spec_problems();
+    if !problems.is_empty() {
+        println!();
+        for problem in &problems {
+            println!(
+                "{}",
+                theme.paint(&format!("Warning: {problem}"), Paint::Warning)
+            );
+        }
+    }
+
+    if !missing.is_empty() {
+        println!();
+        println!("Missing ({}; --scaffold prints templates):", missing.len());
+        let mut table = Table::new(&[Align::Right, Align::Left]).header(["ID", "Weapon"]);
+        for item in &missing {
+            table.row([item.id.to_string(), item.name.clone()]);
+        }
+        for line in table.render(theme, "  ") {
+            println!("{line}");
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_fuzz_scenarios(count: u64, seed: Option<u64>, data_dir: &Path, theme: &Theme) -> Result<()> {
+    let store = load_store(data_dir)?;
+    let seed = seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(1, |d| d.as_nanos() as u64)
+    });
+    println!("Fuzzing with seed {seed} (replay with --seed {seed})");
+
+    let mut fuzzer = Fuzzer::new(&store, seed);
+    let (mut rejected, mut failures) = (0u64, 0u64);
+    while count == 0 || fuzzer.index() < count {
+        match fuzzer.step() {
+            FuzzOutcome::Passed => {}
+            FuzzOutcome::Rejected => rejected += 1,
+            FuzzOutcome::Failed(failure) => {
+                failures += 1;
+                let heading = format!(
+                    "Failure in scenario {}: {}",
+                    failure.index,
+                    failure.problems.join("; ")
+                );
+                println!("{}", theme.paint(&heading, Paint::Warning));
+                println!("{}", serde_json::to_string_pretty(&failure.scenario)?);
+            }
+        }
+        if fuzzer.index().is_multiple_of(10_000) {
+            eprintln!("  {} scenarios, {failures} failures", fuzzer.index());
+        }
+    }
+
+    println!(
+        "Checked {} scenarios ({rejected} with no valid draw), {failures} failures",
+        fuzzer.index()
+    );
+    if failures > 0 {
+        anyhow::bail!("{failures} scenarios broke engine invariants");
+    }
+    Ok(())
+}
+
+fn cmd_determinism(count: u64, seed: Option<u64>, data_dir: &Path) -> Result<()> {
+    if count == 0 {
+        anyhow::bail!("--determinism needs a --count above 0");
+    }
+    let store = load_store(data_dir)?;
+    let batch = batch_hash(&store, seed.unwrap_or(DETERMINISM_SEED), count);
+    println!(
+        "Determinism hash: {} (seed {}, {} scenarios, {} evaluated)",
+        batch.hex(),
+        batch.seed,
+        batch.count,
+        batch.evaluated
+    );
+    Ok(())
+}
+
+fn cmd_fetch_data(scripts_dir: &Path, offline: bool) -> Result<()> {
+    let script = scripts_dir.join("ingest_all.py");
+    let mut command = std::process::Command::new("python3");
+    command.arg(&script);
+    if offline {
+        command.arg("--offline").env(fetch_cache::OFFLINE_ENV, "1");
+    }
+    let status = command
+        .status()
+        .with_context(|| format!("Failed to run {}", script.display()))?;
+    if !status.success() {
+        anyhow::bail!("{} failed ({status})", script.display());
+    }
+    Ok(())
+}
+
+fn cmd_cache(action: CacheAction) -> Result<()> {
+    let dir = fetch_cache::cache_dir().context(format!(
+        "No cache directory: set {}, XDG_CACHE_HOME or HOME",
+        fetch_cache::CACHE_DIR_ENV
+    ))?;
+    match action {
+        CacheAction::Status { json } => {
+            let sections = fetch_cache::cache_status(&dir)?;
+            if json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&serde_json::json!({
+                        "dir": dir,
+                        "sections": sections,
+                    }))?
+                );
+                return Ok(());
+            }
+            println!("Cache: {}", dir.display());
+            if sections.is_empty() {
+                println!("  (empty)");
+            }
+            for section in &sections {
+                println!(
+                    "  {}: {} files, {} bytes",
+                    section.name, section.files, section.bytes
+                );
+            }
+        }
+        CacheAction::Clear => {
+            let cleared = fetch_cache::clear_cache(&dir)?;
+            let files: u64 = cleared.iter().map(|s| s.files).sum();
+            let bytes: u64 = cleared.iter().map(|s| s.bytes).sum();
+            println!(
+                "Cleared {files} files ({bytes} bytes) from {}",
+                dir.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn cmd_item_info(id: u32, data_dir: &Path) -> Result<()> {
+    let store = load_store(data_dir)?;
 
     let item = store.get_item(id)?;
 
@@ -333,6 +1930,17 @@ fn cmd_item_info(id: u32, data_dir: &PathBuf) -> Result<()> {
     println!();
     println!("Equipable: {}", item.equipable);
     println!("Is Weapon: {}", item.equipable_weapon);
+    if !item.obtainability.is_empty() {
+        let tags: Vec<String> = item
+            .obtainability
+            .iter()
+            .map(|tag| format!("{tag:?}"))
+            .collect();
+        println!("Obtainability: {}", tags.join(", "));
+    }
+    if !item.tags.is_empty() {
+        println!("Tags: {}", item.tags.join(", "));
+    }
 
     if let Some(ref eq) = item.equipment {
         println!();
@@ -366,9 +1974,23 @@ fn cmd_item_info(id: u32, data_dir: &PathBuf) -> Result<()> {
         println!(
             "  Attack Speed: {} ticks ({:.1}s)",
             wpn.attack_speed,
-            wpn.attack_speed as f64 * 0.6
+            Ticks::from(wpn.attack_speed).as_secs_f64()
         );
         println!("  Weapon Type:  {}", wpn.weapon_type);
+        let range = store.ranges.range_of(item);
+        let longrange = wpn
+            .stances
+            .iter()
+            .any(|s| Stance::from_data_name(&s.attack_style) == Some(Stance::Longrange));
+        if longrange {
+            let further = (range + LONGRANGE_BONUS).min(MAX_ATTACK_RANGE);
+            println!("  Range:        {range} tiles ({further} on longrange)");
+        } else {
+            println!("  Range:        {range} tiles");
+        }
+        if let Some(ammunition) = item.ammo_slot_ammunition() {
+            println!("  Ammunition:   {} items", ammunition.len());
+        }
         println!();
         println!("  Stances:");
         for stance in &wpn.stances {
@@ -382,11 +2004,31 @@ fn cmd_item_info(id: u32, data_dir: &PathBuf) -> Result<()> {
     Ok(())
 }
 
-fn cmd_monster_info(id: u32, data_dir: &PathBuf) -> Result<()> {
-    let store = DataStore::load_from_dir(data_dir)
-        .with_context(|| format!("Failed to load data from {:?}", data_dir))?;
+fn cmd_monster_info(
+    id: u32,
+    build_file: Option<&Path>,
+    player_file: Option<&Path>,
+    data_dir: &Path,
+) -> Result<()> {
+    let store = load_store(data_dir)?;
 
     let monster = store.get_monster(id)?;
+    // Read the quick-check build up front so a bad file fails before output
+    let resolver = Resolver::new(&store);
+    let quick_check = match build_file {
+        Some(path) => {
+            let build_input: BuildInput = read_json(path, "build")?;
+            let build = resolver
+                .resolve_build(&build_input)
+                .with_context(|| "Failed to resolve equipment")?;
+            let player = match player_file {
+                Some(path) => resolve_player_input(&read_json::<PlayerInput>(path, "player")?),
+                None => osrs_sim::PlayerState::default(),
+            };
+            Some((path, build, player))
+        }
+        None => None,
+    };
 
     println!("=== Monster: {} (ID: {}) ===", monster.name, monster.id);
     println!();
@@ -425,5 +2067,261 @@ fn cmd_monster_info(id: u32, data_dir: &PathBuf) -> Result<()> {
         println!("  Slayer Level Required: {}", monster.slayer_level);
     }
 
+    if !monster.data_flags.is_empty() {
+        println!();
+        println!("Data Warnings:");
+        for flag in &monster.data_flags {
+            println!("  [{}] {}", flag.confidence.as_str(), flag.note);
+        }
+    }
+
+    // Every attack type by the monster's defence roll against it, weakest
+    // first; the quick-check build's own is marked
+    let target = resolver.resolve_monster(id)?;
+    let build_type = quick_check.as_ref().map(|(_, build, _)| build.attack_type);
+    println!();
+    println!(
+        "Weakest To (defence roll, accuracy of a reference {} attack roll):",
+        REFERENCE_ATTACK_ROLL
+    );
+    for roll in target.style_weaknesses() {
+        let name = format!("{:?}", roll.attack_type).to_lowercase();
+        let marker = if Some(roll.attack_type) == build_type {
+            "  <- build"
+        } else {
+            ""
+        };
+        println!(
+            "  {name:<8}{:>8}  {:>6.2}%{marker}",
+            roll.defence_roll,
+            roll.accuracy * 100.0
+        );
+    }
+
+    let Some((build_file, build, player)) = quick_check else {
+        return Ok(());
+    };
+    let result = evaluate(&EvalContext {
+        player: &player,
+        build: &build,
+        target: &target,
+        ordering: None,
+        adjacent_targets: 0,
+        distance: None,
+    });
+
+    println!();
+    println!("Quick Check ({}):", build_file.display());
+    println!("  DPS:          {:.4}", result.dps);
+    println!("  Max Hit:      {}", result.max_hit);
+    println!("  Accuracy:     {:.2}%", result.accuracy * 100.0);
+    if result.dps > 0.0 {
+        let seconds = f64::from(target.hitpoints) / result.dps;
+        println!("  Time to Kill: {seconds:.1}s");
+    }
+    if let Some(reason) = &result.immunity {
+        println!("  {reason}");
+    }
+
+    Ok(())
+}
+
+fn cmd_attributes(
+    id: u32,
+    build_file: Option<&Path>,
+    player_file: Option<&Path>,
+    data_dir: &Path,
+) -> Result<()> {
+    let store = load_store(data_dir)?;
+    let resolver = Resolver::new(&store);
+    let target = resolver.resolve_monster(id)?;
+    let registry = default_registry();
+
+    println!("=== Attributes: {} (ID: {}) ===", target.name, id);
+    println!();
+    if target.attributes.is_empty() {
+        println!("Attributes: none");
+    } else {
+        println!("Attributes: {}", target.attributes.join(", "));
+    }
+
+    // Every known effect keyed on one of the monster's attributes
+    println!();
+    println!("Attribute effects that can trigger:");
+    let keyed: Vec<&Effect> = registry
+        .effects
+        .iter()
+        .filter(|e| e.targets_any(&target.attributes))
+        .collect();
+    if keyed.is_empty() {
+        println!("  (none known)");
+    }
+    for effect in keyed {
+        println!("  {:<36} {}", effect.name, describe_op(&effect.op));
+        let requirements = describe_requirements(&store, &effect.condition);
+        if !requirements.is_empty() {
+            println!("  {:<36} needs {}", "", requirements);
+        }
+    }
+
+    let Some(build_file) = build_file else {
+        return Ok(());
+    };
+    let build_input: BuildInput = read_json(build_file, "build")?;
+    let build = resolver
+        .resolve_build(&build_input)
+        .with_context(|| "Failed to resolve equipment")?;
+    let player = match player_file {
+        Some(path) => resolve_player_input(&read_json::<PlayerInput>(path, "player")?),
+        None => osrs_sim::PlayerState::default(),
+    };
+    let ctx = EvalContext {
+        player: &player,
+        build: &build,
+        target: &target,
+        ordering: None,
+        adjacent_targets: 0,
+        distance: None,
+    };
+
+    let applied = registry.applicable(&EffectContext::from_eval(&ctx));
+    println!();
+    println!("With this build:");
+    if applied.is_empty() {
+        println!("  No effects apply");
+    }
+    for effect in &applied {
+        println!("  {:<36} {}", effect.name, describe_op(&effect.op));
+    }
+    let stack = multiplier_stack(&applied);
+    if !stack.is_empty() {
+        let parts: Vec<String> = stack
+            .iter()
+            .map(|(stat, factor)| format!("{} x{:.4}", stat_label(*stat), factor))
+            .collect();
+        println!("  Combined: {}", parts.join(", "));
+    }
+
+    let without = evaluate_with(&ctx, &EffectRegistry::new());
+    let with = evaluate(&ctx);
+    println!();
+    println!(
+        "  DPS: {:.4} without effects -> {:.4} with effects",
+        without.dps, with.dps
+    );
+
     Ok(())
 }
+
+fn stat_label(stat: Stat) -> &'static str {
+    match stat {
+        Stat::EffectiveAttack => "effective attack",
+        Stat::EffectiveStrength => "effective strength",
+        Stat::MaxHit => "max hit",
+        Stat::AttackRoll => "attack roll",
+        Stat::Accuracy => "accuracy",
+        Stat::ExpectedDamage => "expected damage",
+        Stat::MagicDamage => "magic damage %",
+        Stat::SpellMaxHit => "spell max hit",
+    }
+}
+
+fn describe_op(op: &Op) -> String {
+    match op {
+        Op::Multiply { stat, num, den } => format!("{} x{num}/{den}", stat_label(*stat)),
+        Op::Add { stat, value } => format!("{} {value:+}", stat_label(*stat)),
+        Op::AddPerTargetSize {
+            stat,
+            value,
+            max_size,
+        } => format!(
+            "{} {value:+} per tile of size (up to {max_size})",
+            stat_label(*stat)
+        ),
+        Op::MultiplyByMissingHitpoints { stat } => {
+            format!("{} x(1 + missing hp/100 * hp level/100)", stat_label(*stat))
+        }
+        Op::IgnoreDefenceChance {
+            num,
+            den,
+            extra_damage,
+        } => format!("{num}/{den} chance to ignore defence, {extra_damage:+} damage"),
+        Op::TwistedBowScaling { stat } => {
+            format!(
+                "{} x twisted bow scaling by target magic",
+                stat_label(*stat)
+            )
+        }
+        Op::MinimumHit { pct } => format!("landed hits deal at least {pct}% of the max hit"),
+    }
+}
+
+/// Summarize an effect's non-attribute requirements, naming items from the store.
+fn describe_requirements(store: &DataStore, condition: &Condition) -> String {
+    let mut parts = Vec::new();
+    if let Some(ref combat) = condition.combat {
+        parts.push(combat.clone());
+    }
+    if condition.on_slayer_task == Some(true) {
+        parts.push("slayer task".to_string());
+    }
+    if condition.in_wilderness == Some(true) {
+        parts.push("wilderness".to_string());
+    }
+    if condition.forinthry_surge == Some(true) {
+        parts.push("forinthry surge".to_string());
+    }
+    if condition.charge == Some(true) {
+        parts.push("charge".to_string());
+    }
+    for any_of in &condition.equipped {
+        let names: Vec<String> = any_of
+            .iter()
+            .map(|&id| {
+                store
+                    .get_item(id)
+                    .map(|item| item.name.clone())
+                    .unwrap_or_else(|_| format!("item {id}"))
+            })
+            .collect();
+        parts.push(names.join(" / "));
+    }
+    for tag in &condition.equipped_tags {
+        parts.push(format!("a {tag} item"));
+    }
+    if condition.weapon_charged == Some(true) {
+        parts.push("charged".to_string());
+    }
+    if let Some(ref element) = condition.spell_element {
+        parts.push(format!("a {element} spell"));
+    }
+    if let Some(ref spellbook) = condition.spellbook {
+        parts.push(format!("a {spellbook} spellbook spell"));
+    }
+    for rune in &condition.runes {
+        parts.push(format!("{rune} runes"));
+    }
+    if !condition.spells.is_empty() {
+        parts.push(condition.spells.join(" / "));
+    }
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discord_message_fits_limit() {
+        let body = "DPS  1.000\n".repeat(500);
+        let message = fit_discord_message("**voidwaker** vs **Jad**", &body, DISCORD_MESSAGE_LIMIT);
+        assert!(message.chars().count() <= DISCORD_MESSAGE_LIMIT);
+        assert!(message.ends_with("…\n```"));
+    }
+
+    #[test]
+    fn test_discord_message_short_body_untouched() {
+        let message = fit_discord_message("header", "DPS  1.000\n", DISCORD_MESSAGE_LIMIT);
+        assert_eq!(message, "header\n```\nDPS  1.000\n```");
+    }
+}
@@ -0,0 +1,184 @@
+// src/mechanics.rs
+//
+// Target and weapon mechanics written in code rather than data. Effects
+// cover what the data format can describe; a boss mechanic it can't yet (a
+// damage cap, a phase that halves accuracy) or a weapon with rolls or hits
+// of its own can be prototyped downstream by implementing `TargetMechanic`
+// or `WeaponMechanic` and registering it with an `EffectRegistry`, then
+// folded into the data format or formulas.rs once it settles.
+
+use crate::effects::{EffectContext, EngineState, Stage};
+use crate::model::{AttackType, BuildResolved, TargetResolved};
+
+/// A mechanic of the target that adjusts the engine state as the pipeline
+/// runs. Registered with `EffectRegistry::register_mechanic`, it runs after
+/// the effects of every stage against targets it `applies` to.
+pub trait TargetMechanic: Send + Sync {
+    /// ID reported in `effects_applied` when the mechanic changes a stage.
+    fn id(&self) -> &str;
+
+    /// Whether the mechanic applies against the context's target (by
+    /// `target_name` or `target_attributes`, usually).
+    fn applies(&self, ctx: &EffectContext) -> bool;
+
+    /// Adjust the state after `stage`'s effects; true if it changed
+    /// anything.
+    fn apply(&self, stage: Stage, ctx: &EffectContext, state: &mut EngineState) -> bool;
+}
+
+/// A mechanic of the weapon that changes its rolls or how its hits land.
+/// Registered with `EffectRegistry::register_weapon_mechanic`, it runs for
+/// builds it `applies` to.
+pub trait WeaponMechanic: Send + Sync {
+    /// ID reported in `effects_applied` when the mechanic changes the rolls.
+    fn id(&self) -> &str;
+
+    /// Whether the build's weapon has the mechanic (by `weapon_id`,
+    /// usually).
+    fn applies(&self, build: &BuildResolved) -> bool;
+
+    /// Adjust the attack roll and max hit for `attack_type`, after every
+    /// effect and the special attack; true if it changed anything.
+    fn modify_rolls(&self, _attack_type: AttackType, _state: &mut EngineState) -> bool {
+        false
+    }
+
+    /// Damage range (min, max) of each hit of one attack, given the final
+    /// max hit, when they aren't the weapon's usual ones. Each hit rolls
+    /// accuracy on its own.
+    fn hit_ranges(&self, _max_hit: i64, _target: &TargetResolved) -> Option<Vec<(i64, i64)>> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effects::EffectRegistry;
+    use crate::formulas::evaluate_with;
+    use crate::model::{EvalContext, PlayerState};
+
+    /// Halves the max hit against one named target.
+    struct Shielded(&'static str);
+
+    impl TargetMechanic for Shielded {
+        fn id(&self) -> &str {
+            "shielded"
+        }
+
+        fn applies(&self, ctx: &EffectContext) -> bool {
+            ctx.target_name == self.0
+        }
+
+        fn apply(&self, stage: Stage, _ctx: &EffectContext, state: &mut EngineState) -> bool {
+            if stage != Stage::PostMaxHit {
+                return false;
+            }
+            state.max_hit /= 2;
+            true
+        }
+    }
+
+    #[test]
+    fn test_registered_mechanics_run_against_their_target() {
+        let mut registry = EffectRegistry::new();
+        registry.register_mechanic(Box::new(Shielded("Dummy")));
+        assert_eq!(registry.mechanic_ids(), vec!["shielded"]);
+
+        let player = PlayerState::default();
+        let build = BuildResolved::default();
+        let eval = |target: &TargetResolved, registry: &EffectRegistry| {
+            evaluate_with(
+                &EvalContext {
+                    player: &player,
+                    build: &build,
+                    target,
+                    ordering: None,
+                    adjacent_targets: 0,
+                    distance: None,
+                },
+                registry,
+            )
+        };
+        let dummy = TargetResolved::default();
+        let plain = eval(&dummy, &EffectRegistry::new());
+        let shielded = eval(&dummy, &registry);
+        assert_eq!(shielded.max_hit, plain.max_hit / 2);
+        assert_eq!(shielded.effects_applied, vec!["shielded"]);
+
+        // Other targets, and registries derived from this one
+        let other = TargetResolved {
+            name: "Other".to_string(),
+            ..TargetResolved::default()
+        };
+        assert_eq!(eval(&other, &registry).max_hit, plain.max_hit);
+        let profile = crate::ordering::OrderingProfile::live();
+        assert_eq!(
+            registry.reordered(&profile).mechanic_ids(),
+            vec!["shielded"]
+        );
+    }
+
+    /// Doubles the attack roll and splits each attack into two hits of up
+    /// to half the max hit, for one weapon.
+    struct Twinned(u32);
+
+    impl WeaponMechanic for Twinned {
+        fn id(&self) -> &str {
+            "twinned"
+        }
+
+        fn applies(&self, build: &BuildResolved) -> bool {
+            build.weapon_id == self.0
+        }
+
+        fn modify_rolls(&self, _attack_type: AttackType, state: &mut EngineState) -> bool {
+            state.attack_roll *= 2;
+            true
+        }
+
+        fn hit_ranges(&self, max_hit: i64, _target: &TargetResolved) -> Option<Vec<(i64, i64)>> {
+            Some(vec![(0, max_hit / 2), (0, max_hit / 2)])
+        }
+    }
+
+    #[test]
+    fn test_weapon_mechanics_change_rolls_and_hits() {
+        let mut registry = EffectRegistry::new();
+        registry.register_weapon_mechanic(Box::new(Twinned(4151)));
+        let player = PlayerState::default();
+        let target = TargetResolved {
+            defence_level: 200,
+            ..TargetResolved::default()
+        };
+        let eval = |build: &BuildResolved| {
+            evaluate_with(
+                &EvalContext {
+                    player: &player,
+                    build,
+                    target: &target,
+                    ordering: None,
+                    adjacent_targets: 0,
+                    distance: None,
+                },
+                &registry,
+            )
+        };
+        let plain = eval(&BuildResolved::default());
+        let whip = BuildResolved {
+            weapon_id: 4151,
+            ..BuildResolved::default()
+        };
+        assert_eq!(registry.weapon_mechanics_for(&whip).len(), 1);
+        let twinned = eval(&whip);
+        assert_eq!(twinned.attack_roll, plain.attack_roll * 2);
+        assert!(twinned.accuracy > plain.accuracy);
+        assert_eq!(twinned.hit_max_hits, vec![plain.max_hit / 2; 2]);
+        assert_eq!(twinned.effects_applied, vec!["twinned"]);
+        // Every style the build could switch to rolls the same way
+        assert!(twinned
+            .style_rolls
+            .iter()
+            .all(|roll| roll.attack_roll == plain.attack_roll * 2));
+    }
+}
@@ -0,0 +1,139 @@
+// src/moons.rs
+//
+// Perilous Moons equipment. The armour set effects change how often the
+// matching weapon attacks or add damage over time rather than touching its
+// rolls, so they sit outside the effect pipeline: the evaluation turns them
+// into an average attack interval that scales the DPS, or a burn DPS
+// reported beside it.
+
+use crate::model::BuildResolved;
+
+/// Dual macuahuitl item ID.
+pub const DUAL_MACUAHUITL: u32 = 28997;
+
+/// Eclipse atlatl item IDs, with and without its burn damage.
+pub const ECLIPSE_ATLATL: [u32; 2] = [29000, 29001];
+
+/// Blood moon helm, chestplate and tassets.
+pub const BLOOD_MOON_SET: [u32; 3] = [29028, 29022, 29025];
+
+/// Eclipse moon helm, chestplate and tassets.
+pub const ECLIPSE_MOON_SET: [u32; 3] = [29010, 29004, 29007];
+
+/// Chance (num, den) that an attack which lands makes the next one a tick
+/// faster, with the blood moon set and the dual macuahuitl.
+pub const BLOOD_MOON_SPEEDUP_CHANCE: (u32, u32) = (1, 3);
+
+/// Chance (num, den) that an atlatl hit which lands burns the target, with
+/// the eclipse moon set.
+pub const ECLIPSE_MOON_BURN_CHANCE: (u32, u32) = (1, 5);
+
+/// A Perilous Moons set worn with its weapon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoonSet {
+    /// Blood moon set with the dual macuahuitl: landed hits can speed up
+    /// the next attack.
+    BloodMoon,
+    /// Eclipse moon set with the Eclipse atlatl: landed hits can burn.
+    EclipseMoon,
+}
+
+impl MoonSet {
+    /// The set effect a build has, if it wears a whole set with its weapon.
+    pub fn of(build: &BuildResolved) -> Option<Self> {
+        let wearing = |set: &[u32]| set.iter().all(|id| build.equipped.contains(id));
+        if build.weapon_id == DUAL_MACUAHUITL && wearing(&BLOOD_MOON_SET) {
+            Some(MoonSet::BloodMoon)
+        } else if ECLIPSE_ATLATL.contains(&build.weapon_id) && wearing(&ECLIPSE_MOON_SET) {
+            Some(MoonSet::EclipseMoon)
+        } else {
+            None
+        }
+    }
+
+    /// Average ticks between attacks with the set, from the weapon's
+    /// interval and the chance an attack lands; None if the set doesn't
+    /// change the interval.
+    pub fn expected_interval(self, interval_ticks: u8, accuracy: f64) -> Option<f64> {
+        match self {
+            MoonSet::BloodMoon => {
+                let (num, den) = BLOOD_MOON_SPEEDUP_CHANCE;
+                // A follow-up can't come sooner than the next tick
+                let faster = if interval_ticks > 1 {
+                    accuracy * f64::from(num) / f64::from(den)
+                } else {
+                    0.0
+                };
+                Some(f64::from(interval_ticks) - faster)
+            }
+            MoonSet::EclipseMoon => None,
+        }
+    }
+
+    /// Chance that an attack burns the target, from the chance it lands.
+    pub fn burn_chance(self, accuracy: f64) -> f64 {
+        match self {
+            MoonSet::BloodMoon => 0.0,
+            MoonSet::EclipseMoon => {
+                let (num, den) = ECLIPSE_MOON_BURN_CHANCE;
+                accuracy * f64::from(num) / f64::from(den)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wearing(weapon_id: u32, equipped: &[u32]) -> BuildResolved {
+        BuildResolved {
+            weapon_id,
+            equipped: std::iter::once(weapon_id)
+                .chain(equipped.iter().copied())
+                .collect(),
+            ..BuildResolved::default()
+        }
+    }
+
+    #[test]
+    fn test_blood_moon_needs_every_piece_and_the_macuahuitl() {
+        assert_eq!(
+            MoonSet::of(&wearing(DUAL_MACUAHUITL, &BLOOD_MOON_SET)),
+            Some(MoonSet::BloodMoon)
+        );
+        // Two pieces aren't a set
+        assert_eq!(
+            MoonSet::of(&wearing(DUAL_MACUAHUITL, &BLOOD_MOON_SET[..2])),
+            None
+        );
+        // Nor is the set with another weapon (abyssal whip)
+        assert_eq!(MoonSet::of(&wearing(4151, &BLOOD_MOON_SET)), None);
+        assert_eq!(
+            MoonSet::of(&wearing(ECLIPSE_ATLATL[1], &ECLIPSE_MOON_SET)),
+            Some(MoonSet::EclipseMoon)
+        );
+        assert_eq!(
+            MoonSet::of(&wearing(DUAL_MACUAHUITL, &ECLIPSE_MOON_SET)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_blood_moon_shortens_the_interval_by_landed_procs() {
+        let set = MoonSet::BloodMoon;
+        assert_eq!(set.expected_interval(4, 0.0), Some(4.0));
+        let sure = set.expected_interval(4, 1.0).unwrap();
+        assert!((sure - (4.0 - 1.0 / 3.0)).abs() < 1e-9);
+        assert!((set.expected_interval(4, 0.6).unwrap() - 3.8).abs() < 1e-9);
+        assert_eq!(set.expected_interval(1, 1.0), Some(1.0));
+        assert_eq!(set.burn_chance(1.0), 0.0);
+    }
+
+    #[test]
+    fn test_eclipse_moon_burns_a_fifth_of_landed_hits() {
+        let set = MoonSet::EclipseMoon;
+        assert_eq!(set.expected_interval(4, 1.0), None);
+        assert!((set.burn_chance(0.5) - 0.1).abs() < 1e-9);
+    }
+}
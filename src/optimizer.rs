@@ -0,0 +1,1535 @@
+// src/optimizer.rs
+//
+// Searches over player-side choices (prayers, potions) and gear for a fixed
+// target. Every candidate is scored with the regular `evaluate` pipeline.
+
+use crate::cache::EvalCache;
+use crate::data::{
+    apply_pre_fight_player, get_prayer_multipliers, is_members_potion, is_members_prayer,
+    resolve_player, resolve_player_input, DataError, DataStore, ItemData, Resolver, MELEE_POTIONS,
+    MELEE_PRAYERS,
+};
+use crate::effects::{default_registry, EffectContext, EffectRegistry};
+use crate::formulas::combat_level;
+use crate::model::{
+    AttackType, BoostSpec, BuildInput, BuildResolved, Combat, EquipmentBonuses, EvalContext,
+    EvalResult, PlayerInput, PlayerState, SkillsInput, TargetResolved, ARMOUR_SLOTS,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Upper bound on coordinate-ascent passes over the slots.
+const MAX_GEAR_PASSES: usize = 5;
+
+/// Item ID of the "unarmed" pseudo-weapon in the data set.
+const UNARMED_ID: u32 = 0;
+
+/// Which prayers/potions the boost search may use.
+/// `None` means "anything the engine knows about".
+#[derive(Debug, Clone, Default)]
+pub struct BoostFilter {
+    pub prayers: Option<Vec<String>>,
+    pub potions: Option<Vec<String>>,
+    /// Only free-to-play prayers and potions.
+    pub f2p: bool,
+}
+
+impl BoostFilter {
+    fn allows_prayer(&self, key: &str) -> bool {
+        if self.f2p && is_members_prayer(key) {
+            return false;
+        }
+        self.prayers
+            .as_ref()
+            .map(|allowed| allowed.iter().any(|p| p.eq_ignore_ascii_case(key)))
+            .unwrap_or(true)
+    }
+
+    fn allows_potion(&self, key: &str) -> bool {
+        if self.f2p && is_members_potion(key) {
+            return false;
+        }
+        self.potions
+            .as_ref()
+            .map(|allowed| allowed.iter().any(|p| p.eq_ignore_ascii_case(key)))
+            .unwrap_or(true)
+    }
+}
+
+/// One prayer/potion combination and how it performed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankedBoosts {
+    pub prayers: Vec<String>,
+    pub potion: Option<String>,
+    pub dps: f64,
+    /// DPS change relative to the player's configured prayers/potion.
+    pub dps_delta: f64,
+}
+
+/// Prayers the player has the levels for and the filter allows.
+fn usable_prayers(input: &PlayerInput, filter: &BoostFilter) -> Vec<&'static str> {
+    MELEE_PRAYERS
+        .iter()
+        .filter(|p| {
+            input.skills.prayer >= p.prayer_level
+                && input.skills.defence >= p.defence_level
+                && filter.allows_prayer(p.key)
+        })
+        .map(|p| p.key)
+        .collect()
+}
+
+/// Prayer sets worth trying: nothing, each prayer alone, and every pairing of
+/// an attack-only prayer with a strength-only prayer (these can be run together).
+fn prayer_sets(prayers: &[&'static str]) -> Vec<Vec<&'static str>> {
+    let boosts = |key: &str| {
+        let (atk, str, _) = get_prayer_multipliers(key);
+        (atk != (1, 1), str != (1, 1))
+    };
+
+    let mut sets = vec![vec![]];
+    sets.extend(prayers.iter().map(|p| vec![*p]));
+    for atk in prayers.iter().filter(|p| boosts(p) == (true, false)) {
+        for str in prayers.iter().filter(|p| boosts(p) == (false, true)) {
+            sets.push(vec![*atk, *str]);
+        }
+    }
+    sets
+}
+
+/// Results `rank_boosts` keeps for repeated player states.
+const BOOST_CACHE_SIZE: usize = 256;
+
+/// Results a gear search keeps for builds it tries again (the same swap on
+/// a later pass, or a weapon style already scored).
+const GEAR_CACHE_SIZE: usize = 4096;
+
+/// Evaluate every usable prayer/potion combination for a fixed build and
+/// target, best first.
+pub fn rank_boosts(
+    input: &PlayerInput,
+    build: &BuildResolved,
+    target: &TargetResolved,
+    filter: &BoostFilter,
+) -> Vec<RankedBoosts> {
+    // Prayers that don't help this combat style leave the player unchanged,
+    // so many combinations repeat an evaluation
+    let mut cache = EvalCache::new(BOOST_CACHE_SIZE);
+    let mut dps_for = |prayers: &HashMap<String, bool>, potion: Option<&str>| {
+        let mut boosts = input.boosts.clone();
+        boosts.melee = potion.map(BoostSpec::new);
+        let mut player = resolve_player(&input.skills, prayers, &boosts, &input.flags);
+        apply_pre_fight_player(&mut player, &input.pre_fight);
+        let ctx = EvalContext {
+            player: &player,
+            build,
+            target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        };
+        cache.evaluate(&ctx, default_registry()).dps
+    };
+
+    let baseline = dps_for(
+        &input.prayers,
+        input.boosts.melee.as_ref().map(|b| b.kind.as_str()),
+    );
+
+    let mut potions: Vec<Option<&str>> = vec![None];
+    potions.extend(
+        MELEE_POTIONS
+            .iter()
+            .filter(|p| filter.allows_potion(p))
+            .map(|p| Some(*p)),
+    );
+
+    let mut ranked = Vec::new();
+    for prayer_set in prayer_sets(&usable_prayers(input, filter)) {
+        let prayers: HashMap<String, bool> =
+            prayer_set.iter().map(|p| (p.to_string(), true)).collect();
+        for potion in &potions {
+            let dps = dps_for(&prayers, *potion);
+            ranked.push(RankedBoosts {
+                prayers: prayer_set.iter().map(|p| p.to_string()).collect(),
+                potion: potion.map(str::to_string),
+                dps,
+                dps_delta: dps - baseline,
+            });
+        }
+    }
+
+    ranked.sort_by(|a, b| b.dps.total_cmp(&a.dps));
+    ranked
+}
+
+// =============================================================================
+// Gear Search
+// =============================================================================
+
+/// An item left out of the search because the player lacks the levels.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExcludedItem {
+    pub item_id: u32,
+    pub name: String,
+    pub slot: String,
+    /// (skill, required level) pairs the player doesn't meet.
+    pub missing: Vec<(String, u32)>,
+}
+
+/// Best gear found for a player/target, plus what was filtered out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GearSearchResult {
+    pub build: BuildInput,
+    pub dps: f64,
+    pub excluded: Vec<ExcludedItem>,
+}
+
+/// One item in a best-in-slot ranking.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlotPick {
+    pub item_id: u32,
+    pub name: String,
+    pub dps: f64,
+    /// DPS over leaving the slot empty.
+    pub dps_gain: f64,
+}
+
+/// The best items for one slot, best first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlotRanking {
+    pub slot: String,
+    /// DPS with the slot empty (unarmed, for the weapon).
+    pub empty_dps: f64,
+    pub picks: Vec<SlotPick>,
+}
+
+/// Why an item lost its slot to the one the gear search picked.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum LossReason {
+    /// Hits harder, but lands too much less often.
+    AccuracyTradeOff,
+    /// Lands more often, but its lower max hit costs more.
+    MaxHitTradeOff,
+    /// Neither hits harder nor lands more often.
+    Outclassed,
+    /// Exactly as good; the search keeps the first it found.
+    Tie,
+    /// Would do more DPS, but the player lacks these (skill, level) pairs.
+    Requirements { missing: Vec<(String, u32)> },
+    /// Would do more DPS, but the account constraints (ironman, F2P,
+    /// excluded tags) rule it out.
+    Constraint,
+}
+
+/// A near-optimal item the gear search didn't pick, and why.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectedItem {
+    pub slot: String,
+    pub item_id: u32,
+    pub name: String,
+    /// DPS with this item in the picked build (and its best style, for a
+    /// weapon).
+    pub dps: f64,
+    pub max_hit: u32,
+    pub accuracy: f64,
+    pub reason: LossReason,
+}
+
+/// Training payoff: items that join the best setup after reaching `level`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevelUnlock {
+    pub skill: String,
+    pub level: u32,
+    pub levels_needed: u32,
+    /// (item id, name) pairs in the new best setup that were locked before.
+    pub new_items: Vec<(u32, String)>,
+    pub dps_before: f64,
+    pub dps_after: f64,
+    /// Part of the gain from the new gear alone (old gear at the new level
+    /// vs new gear at the new level).
+    pub dps_from_gear: f64,
+}
+
+/// Limits on which levels and items a search may assume.
+#[derive(Debug, Clone, Default)]
+pub struct AccountConstraints {
+    /// Highest combat level the account may reach (e.g. 88 for a PK bracket).
+    pub max_combat_level: Option<u8>,
+    /// Skip items an ironman can't obtain.
+    pub ironman: bool,
+    /// Skip members items.
+    pub f2p: bool,
+    /// Skip items carrying any of these tags (data/tags).
+    pub exclude_tags: Vec<String>,
+}
+
+impl AccountConstraints {
+    /// Whether these levels are within the constraints.
+    pub fn allows(&self, skills: &SkillsInput) -> bool {
+        self.max_combat_level
+            .map(|cap| combat_level(skills) <= cap)
+            .unwrap_or(true)
+    }
+
+    /// Whether the account may use this item at all (levels aside).
+    pub fn allows_item(&self, item: &ItemData) -> bool {
+        if item.tags.iter().any(|tag| self.exclude_tags.contains(tag)) {
+            return false;
+        }
+        (!self.ironman || item.ironman_obtainable()) && !(self.f2p && item.members)
+    }
+}
+
+/// Does this item help the given attack type at all? Items that add nothing
+/// can't raise DPS, so they're skipped to keep the search small.
+fn is_offensive_for(item: &ItemData, attack_type: AttackType) -> bool {
+    let bonuses = item.get_bonuses();
+    let strength = match attack_type.combat() {
+        Combat::Melee => bonuses.melee_strength,
+        Combat::Ranged => bonuses.ranged_strength,
+        Combat::Magic => bonuses.magic_damage,
+    };
+    bonuses.attack_bonus_for(attack_type) > 0 || strength > 0
+}
+
+/// The bonuses one item adds for a fixed attack type: everything `evaluate`
+/// reads from the equipment. Strength is melee or ranged strength to match
+/// the attack type, and is 0 for magic.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StyleBonuses {
+    pub attack: i32,
+    pub strength: i32,
+    pub magic_damage: i32,
+}
+
+impl StyleBonuses {
+    pub fn of(bonuses: &EquipmentBonuses, attack_type: AttackType) -> Self {
+        let (strength, magic_damage) = match attack_type.combat() {
+            Combat::Melee => (bonuses.melee_strength, 0),
+            Combat::Ranged => (bonuses.ranged_strength, 0),
+            Combat::Magic => (0, bonuses.magic_damage),
+        };
+        Self {
+            attack: bonuses.attack_bonus_for(attack_type),
+            strength,
+            magic_damage,
+        }
+    }
+
+    /// These bonuses on ammo the weapon doesn't fire, whose ranged strength
+    /// doesn't count (as in `Resolver::resolve_build`).
+    fn unfired(self, attack_type: AttackType) -> Self {
+        match attack_type.combat() {
+            Combat::Ranged => Self {
+                strength: 0,
+                ..self
+            },
+            _ => self,
+        }
+    }
+
+    /// Whether `other` is at least as good in every bonus.
+    fn dominated_by(&self, other: &StyleBonuses) -> bool {
+        self.attack <= other.attack
+            && self.strength <= other.strength
+            && self.magic_damage <= other.magic_damage
+    }
+
+    /// Add (`sign` 1) or remove (`sign` -1) these bonuses from a build total.
+    fn apply(&self, bonuses: &mut EquipmentBonuses, attack_type: AttackType, sign: i32) {
+        match attack_type {
+            AttackType::Stab => bonuses.attack_stab += sign * self.attack,
+            AttackType::Slash => bonuses.attack_slash += sign * self.attack,
+            AttackType::Crush => bonuses.attack_crush += sign * self.attack,
+            AttackType::Magic => bonuses.attack_magic += sign * self.attack,
+            AttackType::Ranged => bonuses.attack_ranged += sign * self.attack,
+        }
+        match attack_type.combat() {
+            Combat::Melee => bonuses.melee_strength += sign * self.strength,
+            Combat::Ranged => bonuses.ranged_strength += sign * self.strength,
+            Combat::Magic => bonuses.magic_damage += sign * self.magic_damage,
+        }
+    }
+}
+
+/// Per-slot candidate bonuses precomputed for one attack type, so a slot
+/// swap is scored by patching the resolved build instead of resolving the
+/// whole loadout again.
+///
+/// Candidates that an earlier option in the same slot (or leaving it empty)
+/// matches or beats in every bonus are dropped, unless some effect is keyed
+/// on them (e.g. Salve amulet).
+/// Ammo is never pruned: which ammo fits depends on the weapon.
+#[derive(Debug, Clone)]
+pub struct BonusTable {
+    pub attack_type: AttackType,
+    slots: HashMap<&'static str, Vec<(u32, StyleBonuses)>>,
+}
+
+impl BonusTable {
+    pub fn new(
+        candidates: &HashMap<&str, Vec<&ItemData>>,
+        attack_type: AttackType,
+        keep: &HashSet<u32>,
+    ) -> Self {
+        let mut slots = HashMap::new();
+        for slot in ARMOUR_SLOTS {
+            let rows: Vec<(u32, StyleBonuses)> = candidates
+                .get(slot)
+                .into_iter()
+                .flatten()
+                .map(|item| (item.id, StyleBonuses::of(&item.get_bonuses(), attack_type)))
+                .collect();
+            // Options are tried in order and only a strict improvement is
+            // kept, so a row no better than an earlier one can never win
+            let pruned = rows
+                .iter()
+                .enumerate()
+                .filter(|(i, (id, row))| {
+                    slot == "ammo"
+                        || keep.contains(id)
+                        || !(row.dominated_by(&StyleBonuses::default())
+                            || rows[..*i].iter().any(|(_, other)| row.dominated_by(other)))
+                })
+                .map(|(_, r)| *r)
+                .collect();
+            slots.insert(slot, pruned);
+        }
+        Self { attack_type, slots }
+    }
+
+    /// Candidate (item ID, bonuses) rows for a slot.
+    pub fn slot(&self, slot: &str) -> &[(u32, StyleBonuses)] {
+        self.slots.get(slot).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+/// Greedy per-slot gear search.
+///
+/// Starting from `start`, each pass tries every usable item in every slot
+/// (weapons with their best style for the build's combat style) and keeps
+/// the single best swap per slot, until a pass changes nothing. Items
+/// `constraints` rules out are never considered.
+pub fn optimize_gear(
+    store: &DataStore,
+    player_input: &PlayerInput,
+    start: &BuildInput,
+    target: &TargetResolved,
+    constraints: &AccountConstraints,
+) -> Result<GearSearchResult, DataError> {
+    let resolver = Resolver::new(store);
+    let player = resolve_player_input(player_input);
+    let (candidates, excluded) =
+        gear_candidates(store, player_input, start.style.combat, constraints)?;
+
+    // Locked or disallowed items in the starting build are dropped (weapon
+    // falls back to unarmed)
+    let usable = |id: u32| {
+        store.get_item(id).is_ok_and(|i| {
+            constraints.allows_item(i) && i.unmet_requirements(&player_input.skills).is_empty()
+        })
+    };
+    let mut best = start.clone();
+    if !usable(best.equipment.weapon) {
+        best.equipment.weapon = UNARMED_ID;
+    }
+    for slot in ARMOUR_SLOTS {
+        if let Some(s) = best.equipment.armour_slot_mut(slot) {
+            if s.is_some_and(|id| !usable(id)) {
+                *s = None;
+            }
+        }
+    }
+    // Player situation and target are fixed for the whole search
+    let registry = default_registry().specialize(&EffectContext::for_situation(&player, target));
+    let mut cache = EvalCache::new(GEAR_CACHE_SIZE);
+    // Items an effect asks for, by ID or by tag, aren't pruned for their bonuses
+    let effect_tags = registry.referenced_tags();
+    let mut effect_items = registry.referenced_items();
+    effect_items.extend(
+        candidates
+            .values()
+            .flatten()
+            .filter(|item| {
+                item.tags
+                    .iter()
+                    .any(|tag| effect_tags.contains(tag.as_str()))
+            })
+            .map(|item| item.id),
+    );
+    let mut best_dps = score_build(&resolver, &player, &best, target, &registry, &mut cache)?;
+    let mut table: Option<BonusTable> = None;
+
+    for _ in 0..MAX_GEAR_PASSES {
+        let mut improved = false;
+
+        // Weapon first: it decides the style and whether a shield fits
+        for weapon in candidates.get("weapon").into_iter().flatten() {
+            let mut candidate = best.clone();
+            candidate.equipment.weapon = weapon.id;
+            if weapon.two_handed {
+                candidate.equipment.shield = None;
+            }
+            candidate.equipment.charged_ammo =
+                charged_ammo_for(store, weapon, candidate.equipment.charged_ammo);
+            for ammo in ammo_options(store, weapon, candidate.equipment.ammo, &candidates) {
+                candidate.equipment.ammo = ammo;
+                if let Some((dps, build)) = best_style(
+                    &resolver, &player, &candidate, target, &registry, &mut cache,
+                )? {
+                    if dps > best_dps {
+                        best_dps = dps;
+                        best = build;
+                        improved = true;
+                    }
+                }
+            }
+        }
+
+        let weapon = store.get_item(best.equipment.weapon)?;
+        let ammunition = weapon.ammo_slot_ammunition();
+        if table
+            .as_ref()
+            .is_none_or(|t| t.attack_type != best.style.attack_type)
+        {
+            table = Some(BonusTable::new(
+                &candidates,
+                best.style.attack_type,
+                &effect_items,
+            ));
+        }
+        let table = table.as_ref().expect("table was just built");
+
+        for slot in ARMOUR_SLOTS {
+            if slot == "shield" && weapon.two_handed {
+                continue;
+            }
+            // Ammo must suit the weapon, and can't be left out if it needs some;
+            // ammo it doesn't fire adds no ranged strength
+            let fits =
+                |id: &u32| slot != "ammo" || ammunition.is_none() || store.fires(weapon, *id);
+            let counted = |id: u32, row: StyleBonuses| {
+                if slot == "ammo" && !store.fires(weapon, id) {
+                    row.unfired(table.attack_type)
+                } else {
+                    row
+                }
+            };
+            let options = (slot != "ammo" || ammunition.is_none())
+                .then_some((None, StyleBonuses::default()))
+                .into_iter()
+                .chain(
+                    table
+                        .slot(slot)
+                        .iter()
+                        .filter(|(id, _)| fits(id))
+                        .map(|&(id, row)| (Some(id), counted(id, row))),
+                );
+
+            // Patch the resolved build in place: take the slot's current item
+            // out, then try each option in its place
+            let current = *best.equipment.armour_slot_mut(slot).expect("armour slot");
+            let Some(mut trial) = resolve_usable(&resolver, &best)? else {
+                continue;
+            };
+            if let Some(id) = current {
+                let id = store.canonical_item_id(id);
+                let item = store.get_item(id)?;
+                let row = counted(id, StyleBonuses::of(&item.get_bonuses(), table.attack_type));
+                row.apply(&mut trial.bonuses, table.attack_type, -1);
+                if let Some(pos) = trial.equipped.iter().rposition(|&e| e == id) {
+                    trial.equipped.swap_remove(pos);
+                }
+                if !item.tags.is_empty() {
+                    trial.equipped_tags = tags_of(store, &trial.equipped);
+                }
+            }
+            let untagged = trial.equipped_tags.clone();
+            let mut best_option = None;
+            for (option, row) in options {
+                row.apply(&mut trial.bonuses, table.attack_type, 1);
+                let tagged = option.is_some_and(|id| {
+                    store
+                        .items
+                        .get(&id)
+                        .is_some_and(|item| !item.tags.is_empty())
+                });
+                if let Some(id) = option {
+                    trial.equipped.push(id);
+                }
+                if tagged {
+                    trial.equipped_tags = tags_of(store, &trial.equipped);
+                }
+                if slot == "ammo" {
+                    trial.bolt_effect = bolt_effect(store, &trial);
+                }
+                let ctx = EvalContext {
+                    player: &player,
+                    build: &trial,
+                    target,
+                    ordering: None,
+                    adjacent_targets: 0,
+                    distance: None,
+                };
+                let dps = cache.evaluate(&ctx, &registry).dps;
+                if dps > best_dps {
+                    best_dps = dps;
+                    best_option = Some(option);
+                }
+                row.apply(&mut trial.bonuses, table.attack_type, -1);
+                if option.is_some() {
+                    trial.equipped.pop();
+                }
+                if tagged {
+                    trial.equipped_tags.clone_from(&untagged);
+                }
+            }
+            if let Some(option) = best_option {
+                *best.equipment.armour_slot_mut(slot).expect("armour slot") = option;
+                improved = true;
+            }
+        }
+
+        if !improved {
+            break;
+        }
+    }
+
+    Ok(GearSearchResult {
+        build: best,
+        dps: best_dps,
+        excluded,
+    })
+}
+
+/// Best-in-slot table: for each slot, the `top` usable items by DPS with the
+/// rest of `build` unchanged, and each one's gain over leaving the slot
+/// empty (unarmed, for the weapon).
+///
+/// Weapons are scored with their best style for the build's combat style,
+/// without a shield if two-handed, and with the best ammo they can fire if
+/// the build's doesn't fit. Other slots keep the build's style.
+pub fn bis_table(
+    store: &DataStore,
+    player_input: &PlayerInput,
+    build: &BuildInput,
+    target: &TargetResolved,
+    top: usize,
+    constraints: &AccountConstraints,
+) -> Result<Vec<SlotRanking>, DataError> {
+    let resolver = Resolver::new(store);
+    let player = resolve_player_input(player_input);
+    let (candidates, _) = gear_candidates(store, player_input, build.style.combat, constraints)?;
+    let registry = default_registry().specialize(&EffectContext::for_situation(&player, target));
+    let mut cache = EvalCache::new(GEAR_CACHE_SIZE);
+    let rank = |slot: &str, empty_dps: f64, mut scored: Vec<(u32, f64)>| {
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        let picks = scored
+            .into_iter()
+            .take(top)
+            .map(|(item_id, dps)| SlotPick {
+                item_id,
+                name: store
+                    .get_item(item_id)
+                    .map(|i| i.name.clone())
+                    .unwrap_or_default(),
+                dps,
+                dps_gain: dps - empty_dps,
+            })
+            .collect();
+        SlotRanking {
+            slot: slot.to_string(),
+            empty_dps,
+            picks,
+        }
+    };
+    let mut rankings = Vec::new();
+
+    // Weapons, each with its best style
+    let mut weapon_dps = |candidate: &BuildInput| {
+        best_style(&resolver, &player, candidate, target, &registry, &mut cache)
+            .map(|best| best.map_or(0.0, |(dps, _)| dps))
+    };
+    let unarmed_dps = if store.items.contains_key(&UNARMED_ID) {
+        let mut unarmed = build.clone();
+        unarmed.equipment.weapon = UNARMED_ID;
+        unarmed.equipment.charged_ammo = None;
+        weapon_dps(&unarmed)?
+    } else {
+        0.0
+    };
+    let mut scored = Vec::new();
+    for weapon in candidates.get("weapon").into_iter().flatten() {
+        let mut candidate = build.clone();
+        candidate.equipment.weapon = weapon.id;
+        if weapon.two_handed {
+            candidate.equipment.shield = None;
+        }
+        candidate.equipment.charged_ammo =
+            charged_ammo_for(store, weapon, build.equipment.charged_ammo);
+        let mut best = None;
+        for ammo in ammo_options(store, weapon, build.equipment.ammo, &candidates) {
+            candidate.equipment.ammo = ammo;
+            let dps = weapon_dps(&candidate)?;
+            best = Some(best.map_or(dps, |b: f64| b.max(dps)));
+        }
+        scored.extend(best.map(|dps| (weapon.id, dps)));
+    }
+    rankings.push(rank("weapon", unarmed_dps, scored));
+
+    // Everything else around the build's own weapon
+    let weapon = store.get_item(build.equipment.weapon)?;
+    let ammunition = weapon.ammo_slot_ammunition();
+    for slot in ARMOUR_SLOTS {
+        if slot == "shield" && weapon.two_handed {
+            continue;
+        }
+        let mut dps_with = |id: Option<u32>| {
+            let mut candidate = build.clone();
+            *candidate
+                .equipment
+                .armour_slot_mut(slot)
+                .expect("armour slot") = id;
+            score_build(
+                &resolver, &player, &candidate, target, &registry, &mut cache,
+            )
+        };
+        let empty_dps = dps_with(None)?;
+        let scored = candidates
+            .get(slot)
+            .into_iter()
+            .flatten()
+            .filter(|item| slot != "ammo" || ammunition.is_none() || store.fires(weapon, item.id))
+            .map(|item| Ok((item.id, dps_with(Some(item.id))?)))
+            .collect::<Result<Vec<_>, DataError>>()?;
+        rankings.push(rank(slot, empty_dps, scored));
+    }
+
+    Ok(rankings)
+}
+
+/// Explain a gear search result: per slot, the `top` best items that lost to
+/// the picked one on DPS, with the trade-off that cost them, then any items
+/// that would have beaten it but are locked behind levels or the account
+/// constraints. Each is scored in the picked build with only its slot
+/// changed.
+pub fn explain_gear(
+    store: &DataStore,
+    player_input: &PlayerInput,
+    best: &BuildInput,
+    target: &TargetResolved,
+    top: usize,
+    constraints: &AccountConstraints,
+) -> Result<Vec<RejectedItem>, DataError> {
+    let resolver = Resolver::new(store);
+    let player = resolve_player_input(player_input);
+    let registry = default_registry().specialize(&EffectContext::for_situation(&player, target));
+    let mut cache = EvalCache::new(GEAR_CACHE_SIZE);
+    // Everything relevant to the style, constraints aside, so the ones they
+    // rule out can be reported too
+    let (candidates, locked) = gear_candidates(
+        store,
+        player_input,
+        best.style.combat,
+        &AccountConstraints::default(),
+    )?;
+    let evaluate_build =
+        |build: &BuildInput, cache: &mut EvalCache| -> Result<Option<EvalResult>, DataError> {
+            let Some(resolved) = resolve_usable(&resolver, build)? else {
+                return Ok(None);
+            };
+            let ctx = EvalContext {
+                player: &player,
+                build: &resolved,
+                target,
+                ordering: None,
+                adjacent_targets: 0,
+                distance: None,
+            };
+            Ok(Some(cache.evaluate(&ctx, &registry)))
+        };
+    let Some(picked) = evaluate_build(best, &mut cache)? else {
+        return Ok(vec![]);
+    };
+
+    // (item, build with it) per slot, weapons with their best style and ammo
+    let weapon = store.get_item(best.equipment.weapon)?;
+    let mut trials: Vec<(&str, &ItemData, BuildInput)> = Vec::new();
+    let weapons = candidates.get("weapon").into_iter().flatten().copied();
+    let locked_items = locked.iter().filter_map(|e| store.items.get(&e.item_id));
+    for item in weapons.chain(locked_items.clone()) {
+        let slot = item.equipment.as_ref().map_or("", |e| e.slot.as_str());
+        if slot != "weapon" || item.id == best.equipment.weapon {
+            continue;
+        }
+        let mut candidate = best.clone();
+        candidate.equipment.weapon = item.id;
+        if item.two_handed {
+            candidate.equipment.shield = None;
+        }
+        candidate.equipment.charged_ammo =
+            charged_ammo_for(store, item, best.equipment.charged_ammo);
+        let mut best_trial: Option<(f64, BuildInput)> = None;
+        for ammo in ammo_options(store, item, best.equipment.ammo, &candidates) {
+            candidate.equipment.ammo = ammo;
+            if let Some((dps, build)) = best_style(
+                &resolver, &player, &candidate, target, &registry, &mut cache,
+            )? {
+                if best_trial
+                    .as_ref()
+                    .is_none_or(|(best_dps, _)| dps > *best_dps)
+                {
+                    best_trial = Some((dps, build));
+                }
+            }
+        }
+        trials.extend(best_trial.map(|(_, build)| ("weapon", item, build)));
+    }
+    let ammunition = weapon.ammo_slot_ammunition();
+    for (slot, current) in ARMOUR_SLOTS.into_iter().zip(best.equipment.armour_ids()) {
+        if slot == "shield" && weapon.two_handed {
+            continue;
+        }
+        let items = candidates.get(slot).into_iter().flatten().copied();
+        for item in items.chain(locked_items.clone()) {
+            let fits = slot != "ammo" || ammunition.is_none() || store.fires(weapon, item.id);
+            let in_slot = item.equipment.as_ref().is_some_and(|e| e.slot == slot);
+            if !fits || !in_slot || Some(item.id) == current {
+                continue;
+            }
+            let mut candidate = best.clone();
+            *candidate
+                .equipment
+                .armour_slot_mut(slot)
+                .expect("armour slot") = Some(item.id);
+            trials.push((slot, item, candidate));
+        }
+    }
+
+    let mut rejected: Vec<RejectedItem> = Vec::new();
+    let mut counts: HashMap<(&str, bool), usize> = HashMap::new();
+    let mut scored = Vec::new();
+    for (slot, item, build) in trials {
+        if let Some(result) = evaluate_build(&build, &mut cache)? {
+            scored.push((slot, item, result));
+        }
+    }
+    scored.sort_by(|a, b| b.2.dps.total_cmp(&a.2.dps).then(a.1.id.cmp(&b.1.id)));
+    for (slot, item, result) in scored {
+        let missing = locked
+            .iter()
+            .find(|e| e.item_id == item.id)
+            .map(|e| e.missing.clone());
+        let reason = match missing {
+            Some(missing) => LossReason::Requirements { missing },
+            None if !constraints.allows_item(item) => LossReason::Constraint,
+            // The search only stops once no single swap helps, so usable
+            // items here never beat the pick
+            None if result.dps == picked.dps => LossReason::Tie,
+            None if result.max_hit > picked.max_hit && result.accuracy < picked.accuracy => {
+                LossReason::AccuracyTradeOff
+            }
+            None if result.accuracy > picked.accuracy && result.max_hit < picked.max_hit => {
+                LossReason::MaxHitTradeOff
+            }
+            None => LossReason::Outclassed,
+        };
+        let blocked = matches!(
+            reason,
+            LossReason::Requirements { .. } | LossReason::Constraint
+        );
+        // Blocked items only matter when they'd have won
+        if blocked && result.dps <= picked.dps {
+            continue;
+        }
+        let count = counts.entry((slot, blocked)).or_default();
+        if *count >= top {
+            continue;
+        }
+        *count += 1;
+        rejected.push(RejectedItem {
+            slot: slot.to_string(),
+            item_id: item.id,
+            name: item.name.clone(),
+            dps: result.dps,
+            max_hit: result.max_hit,
+            accuracy: result.accuracy,
+            reason,
+        });
+    }
+
+    // Slot order, best first within a slot
+    let slot_order = |slot: &str| {
+        std::iter::once("weapon")
+            .chain(ARMOUR_SLOTS)
+            .position(|s| s == slot)
+    };
+    rejected.sort_by_key(|r| slot_order(&r.slot));
+    Ok(rejected)
+}
+
+/// Ammo to try with a weapon: the current ammo if it fits (or the weapon
+/// doesn't use the slot), else each candidate ammo the weapon can fire.
+fn ammo_options(
+    store: &DataStore,
+    weapon: &ItemData,
+    current: Option<u32>,
+    candidates: &Candidates,
+) -> Vec<Option<u32>> {
+    if weapon.ammo_slot_ammunition().is_none() || current.is_some_and(|id| store.fires(weapon, id))
+    {
+        return vec![current];
+    }
+    candidates
+        .get("ammo")
+        .into_iter()
+        .flatten()
+        .filter(|a| store.fires(weapon, a.id))
+        .map(|a| Some(a.id))
+        .collect()
+}
+
+/// Charged ammo for a weapon: the current ammo if it can be loaded, else
+/// the strongest the weapon takes, and none for weapons that take none.
+fn charged_ammo_for(store: &DataStore, weapon: &ItemData, current: Option<u32>) -> Option<u32> {
+    let ammunition = weapon.charged_ammunition()?;
+    if current.is_some_and(|id| ammunition.contains(&store.canonical_item_id(id))) {
+        return current;
+    }
+    ammunition
+        .iter()
+        .filter_map(|id| store.items.get(id))
+        .max_by_key(|item| item.get_bonuses().ranged_strength)
+        .map(|item| item.id)
+}
+
+/// Enchanted bolt effect of the build's crossbow and ammo, as the resolver
+/// gives it.
+fn bolt_effect(store: &DataStore, build: &BuildResolved) -> Option<String> {
+    store
+        .ammo
+        .bolt_effect(build.weapon_id, &build.equipped)
+        .map(str::to_string)
+}
+
+/// Tags of these items, sorted and deduplicated as the resolver gives them.
+fn tags_of(store: &DataStore, ids: &[u32]) -> Vec<String> {
+    let mut tags: Vec<String> = ids
+        .iter()
+        .filter_map(|id| store.items.get(id))
+        .flat_map(|item| item.tags.iter().cloned())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Usable items by slot name.
+type Candidates<'a> = HashMap<&'a str, Vec<&'a ItemData>>;
+
+/// Usable items per slot for a combat style, plus the ones left out for
+/// missing levels. Weapons must offer a style of `combat`; other gear must
+/// add some attack or strength for it. Sorted by item ID.
+fn gear_candidates<'a>(
+    store: &'a DataStore,
+    player_input: &PlayerInput,
+    combat: Combat,
+    constraints: &AccountConstraints,
+) -> Result<(Candidates<'a>, Vec<ExcludedItem>), DataError> {
+    let resolver = Resolver::new(store);
+    let mut candidates: HashMap<&str, Vec<&ItemData>> = HashMap::new();
+    let mut excluded = Vec::new();
+    for item in store.items.values() {
+        let Some(ref equipment) = item.equipment else {
+            continue;
+        };
+        // Variants duplicate their base item
+        if !constraints.allows_item(item) || store.canonical_item_id(item.id) != item.id {
+            continue;
+        }
+        let relevant = if equipment.slot == "weapon" {
+            resolver
+                .weapon_styles(item.id)?
+                .iter()
+                .any(|(attack_type, _)| attack_type.combat() == combat)
+        } else {
+            combat
+                .attack_types()
+                .iter()
+                .any(|t| is_offensive_for(item, *t))
+        };
+        if !relevant {
+            continue;
+        }
+        let missing = item.unmet_requirements(&player_input.skills);
+        if missing.is_empty() {
+            candidates
+                .entry(equipment.slot.as_str())
+                .or_default()
+                .push(item);
+        } else {
+            excluded.push(ExcludedItem {
+                item_id: item.id,
+                name: item.name.clone(),
+                slot: equipment.slot.clone(),
+                missing,
+            });
+        }
+    }
+    // HashMap iteration order is random; keep results reproducible
+    for items in candidates.values_mut() {
+        items.sort_by_key(|i| i.id);
+    }
+    excluded.sort_by_key(|e| e.item_id);
+
+    Ok((candidates, excluded))
+}
+
+/// Resolve a build, or `None` if it can't be used as given (gear that can't
+/// be worn together, a spell the weapon can't autocast).
+fn resolve_usable(
+    resolver: &Resolver,
+    build: &BuildInput,
+) -> Result<Option<BuildResolved>, DataError> {
+    match resolver.resolve_build(build) {
+        Ok(resolved) => Ok(Some(resolved)),
+        Err(DataError::InvalidLoadout(_) | DataError::CannotAutocast { .. }) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Score a build input as-is. Loadouts that can't be worn score 0.
+fn score_build(
+    resolver: &Resolver,
+    player: &PlayerState,
+    build: &BuildInput,
+    target: &TargetResolved,
+    registry: &EffectRegistry,
+    cache: &mut EvalCache,
+) -> Result<f64, DataError> {
+    let Some(resolved) = resolve_usable(resolver, build)? else {
+        return Ok(0.0);
+    };
+    let ctx = EvalContext {
+        player,
+        build: &resolved,
+        target,
+        ordering: None,
+        adjacent_targets: 0,
+        distance: None,
+    };
+    Ok(cache.evaluate(&ctx, registry).dps)
+}
+
+/// Score a build with its weapon's best style, returning the updated build.
+/// `None` if the loadout can't be worn.
+fn best_style(
+    resolver: &Resolver,
+    player: &PlayerState,
+    build: &BuildInput,
+    target: &TargetResolved,
+    registry: &EffectRegistry,
+    cache: &mut EvalCache,
+) -> Result<Option<(f64, BuildInput)>, DataError> {
+    let Some(resolved) = resolve_usable(resolver, build)? else {
+        return Ok(None);
+    };
+    let ranked = resolver.rank_styles_with(player, &resolved, target, None, |ctx| {
+        cache.evaluate(ctx, registry)
+    })?;
+    Ok(ranked.first().map(|style| {
+        let mut updated = build.clone();
+        updated.style.attack_type = style.attack_type;
+        updated.style.stance = style.stance;
+        (style.dps, updated)
+    }))
+}
+
+/// Report items that would join the best setup with a few more levels.
+///
+/// For every skill where some excluded item is at most `max_gap` levels away,
+/// re-run the search at each of those requirement levels and keep the ones
+/// where the best setup actually changes. Levels that would break
+/// `constraints` (e.g. push combat over a bracket) are never suggested.
+pub fn nearly_available(
+    store: &DataStore,
+    player_input: &PlayerInput,
+    start: &BuildInput,
+    target: &TargetResolved,
+    max_gap: u32,
+    constraints: &AccountConstraints,
+) -> Result<Vec<LevelUnlock>, DataError> {
+    let baseline = optimize_gear(store, player_input, start, target, constraints)?;
+
+    // Distinct (skill, level) thresholds within reach
+    let mut thresholds: Vec<(String, u32)> = baseline
+        .excluded
+        .iter()
+        .flat_map(|e| e.missing.iter().cloned())
+        .filter(|(skill, required)| {
+            let current = player_input.skills.level(skill).unwrap_or(0) as u32;
+            required - current <= max_gap
+        })
+        .collect();
+    thresholds.sort();
+    thresholds.dedup();
+
+    let resolver = Resolver::new(store);
+    let mut unlocks = Vec::new();
+    for (skill, level) in thresholds {
+        let mut trained = player_input.clone();
+        let Some(slot) = trained.skills.level_mut(&skill) else {
+            continue;
+        };
+        let current = *slot as u32;
+        *slot = level.min(u8::MAX as u32) as u8;
+        if !constraints.allows(&trained.skills) {
+            continue;
+        }
+
+        let after = optimize_gear(store, &trained, &baseline.build, target, constraints)?;
+        let before_items = baseline.build.equipment.item_ids();
+        let new_items: Vec<(u32, String)> = after
+            .build
+            .equipment
+            .item_ids()
+            .into_iter()
+            .filter(|id| !before_items.contains(id))
+            .filter(|id| baseline.excluded.iter().any(|e| e.item_id == *id))
+            .map(|id| {
+                (
+                    id,
+                    store
+                        .get_item(id)
+                        .map(|i| i.name.clone())
+                        .unwrap_or_default(),
+                )
+            })
+            .collect();
+        if new_items.is_empty() {
+            continue;
+        }
+
+        let trained_player = resolve_player_input(&trained);
+        let old_gear_dps = score_build(
+            &resolver,
+            &trained_player,
+            &baseline.build,
+            target,
+            default_registry(),
+            &mut EvalCache::new(1),
+        )?;
+
+        unlocks.push(LevelUnlock {
+            skill,
+            level,
+            levels_needed: level - current,
+            new_items,
+            dps_before: baseline.dps,
+            dps_after: after.dps,
+            dps_from_gear: after.dps - old_gear_dps,
+        });
+    }
+
+    unlocks.sort_by(|a, b| {
+        a.levels_needed
+            .cmp(&b.levels_needed)
+            .then(b.dps_after.total_cmp(&a.dps_after))
+    });
+    Ok(unlocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::test_support::weapon;
+    use crate::data::Obtainability;
+    use crate::model::{
+        BoostsInput, EquipmentInput, FlagsInput, SkillsInput, Stance, StyleInput, WeaponConfig,
+    };
+
+    fn player_with(prayer: u8, defence: u8) -> PlayerInput {
+        PlayerInput {
+            schema_version: 1,
+            skills: SkillsInput {
+                attack: 99,
+                strength: 99,
+                defence,
+                ranged: 99,
+                magic: 99,
+                prayer,
+                hitpoints: 99,
+            },
+            prayers: HashMap::new(),
+            boosts: BoostsInput::default(),
+            flags: FlagsInput::default(),
+            pre_fight: vec![],
+        }
+    }
+
+    #[test]
+    fn test_rank_boosts_maxed_prefers_piety_super_combat() {
+        let ranked = rank_boosts(
+            &player_with(99, 99),
+            &BuildResolved::default(),
+            &TargetResolved::default(),
+            &BoostFilter::default(),
+        );
+        let best = &ranked[0];
+        assert_eq!(best.prayers, vec!["piety"]);
+        assert!(best.dps_delta > 0.0);
+    }
+
+    #[test]
+    fn test_rank_boosts_respects_prayer_level() {
+        let mut build = BuildResolved::default();
+        build.bonuses.attack_slash = 100;
+        build.bonuses.melee_strength = 100;
+        let target = TargetResolved {
+            defence_level: 200,
+            ..Default::default()
+        };
+
+        let ranked = rank_boosts(
+            &player_with(43, 99),
+            &build,
+            &target,
+            &BoostFilter::default(),
+        );
+        assert!(ranked
+            .iter()
+            .all(|r| !r.prayers.iter().any(|p| p == "piety" || p == "chivalry")));
+        assert_eq!(
+            ranked[0].prayers,
+            vec!["incredible_reflexes", "ultimate_strength"]
+        );
+    }
+
+    #[test]
+    fn test_rank_boosts_allowed_list() {
+        let filter = BoostFilter {
+            prayers: Some(vec!["chivalry".to_string()]),
+            potions: Some(vec![]),
+            ..BoostFilter::default()
+        };
+        let ranked = rank_boosts(
+            &player_with(99, 99),
+            &BuildResolved::default(),
+            &TargetResolved::default(),
+            &filter,
+        );
+        // No prayer, or chivalry; never a potion
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].prayers, vec!["chivalry"]);
+        assert!(ranked.iter().all(|r| r.potion.is_none()));
+    }
+
+    #[test]
+    fn test_rank_boosts_f2p() {
+        let filter = BoostFilter {
+            f2p: true,
+            ..BoostFilter::default()
+        };
+        let ranked = rank_boosts(
+            &player_with(99, 99),
+            &BuildResolved::default(),
+            &TargetResolved::default(),
+            &filter,
+        );
+        assert!(ranked
+            .iter()
+            .all(|r| !r.prayers.iter().any(|p| p == "piety" || p == "chivalry")));
+        assert_eq!(
+            ranked[0].prayers,
+            vec!["incredible_reflexes", "ultimate_strength"]
+        );
+        assert_eq!(ranked[0].potion.as_deref(), Some("strength"));
+    }
+
+    #[test]
+    fn test_bonus_table_prunes_dominated_candidates() {
+        let head = |id: u32, slash: i32, strength: i32| {
+            let mut item = weapon(id, "helm", slash, strength, 1);
+            item.equipment.as_mut().unwrap().slot = "head".to_string();
+            item
+        };
+        let items = [
+            head(10, 5, 2),
+            head(11, 3, 1), // worse than 10 in both
+            head(12, 2, 4), // trades accuracy for strength
+            head(13, 5, 2), // same as 10
+            head(14, 0, 0), // no better than an empty slot
+            head(15, 1, 1), // worse than 10, but an effect needs it
+        ];
+        let candidates = HashMap::from([("head", items.iter().collect::<Vec<_>>())]);
+        let table = BonusTable::new(&candidates, AttackType::Slash, &HashSet::from([15]));
+
+        let ids: Vec<u32> = table.slot("head").iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![10, 12, 15]);
+        assert_eq!(
+            table.slot("head")[0].1,
+            StyleBonuses {
+                attack: 5,
+                strength: 2,
+                magic_damage: 0
+            }
+        );
+        // Stab rows read the stab bonus, which these items don't have
+        let stab = BonusTable::new(&candidates, AttackType::Stab, &HashSet::new());
+        assert_eq!(stab.slot("head").len(), 2);
+    }
+
+    fn sword_store() -> DataStore {
+        let mut store = DataStore::new();
+        for item in [
+            weapon(UNARMED_ID, "unarmed", 0, 0, 1),
+            weapon(1, "rune scimitar", 45, 44, 40),
+            weapon(2, "dragon scimitar", 67, 66, 60),
+        ] {
+            store.items.insert(item.id, item);
+        }
+        store
+    }
+
+    fn sword_build(weapon: u32) -> BuildInput {
+        BuildInput {
+            schema_version: 1,
+            equipment: EquipmentInput {
+                weapon,
+                head: None,
+                cape: None,
+                neck: None,
+                ammo: None,
+                body: None,
+                shield: None,
+                legs: None,
+                hands: None,
+                feet: None,
+                ring: None,
+                charged_ammo: None,
+            },
+            style: StyleInput {
+                combat: Combat::Melee,
+                attack_type: AttackType::Slash,
+                stance: Stance::Aggressive,
+                special_attack: false,
+                manual_cast: false,
+                runes: vec![],
+                spell: None,
+            },
+            weapon_config: WeaponConfig::default(),
+        }
+    }
+
+    #[test]
+    fn test_optimize_gear_skips_locked_items() {
+        let store = sword_store();
+        let mut player = player_with(99, 99);
+        player.skills.attack = 57;
+
+        // Starting in a locked weapon doesn't keep it
+        let best = optimize_gear(
+            &store,
+            &player,
+            &sword_build(2),
+            &TargetResolved::default(),
+            &AccountConstraints::default(),
+        )
+        .expect("search failed");
+        assert_eq!(best.build.equipment.weapon, 1);
+        assert_eq!(best.excluded.len(), 1);
+        assert_eq!(best.excluded[0].missing, vec![("attack".to_string(), 60)]);
+    }
+
+    #[test]
+    fn test_bis_table_ranks_weapons_over_unarmed() {
+        let store = sword_store();
+        let rankings = bis_table(
+            &store,
+            &player_with(99, 99),
+            &sword_build(1),
+            &TargetResolved::default(),
+            5,
+            &AccountConstraints::default(),
+        )
+        .expect("bis table failed");
+
+        let weapons = &rankings[0];
+        assert_eq!(weapons.slot, "weapon");
+        let ids: Vec<u32> = weapons.picks.iter().map(|p| p.item_id).collect();
+        assert_eq!(ids, vec![2, 1, UNARMED_ID]);
+        assert!(weapons.empty_dps > 0.0);
+        for pick in &weapons.picks {
+            assert!((pick.dps_gain - (pick.dps - weapons.empty_dps)).abs() < 1e-12);
+        }
+        assert_eq!(weapons.picks[2].dps_gain, 0.0);
+        // No armour in the store: every other slot is listed, empty
+        assert!(rankings[1..].iter().all(|r| r.picks.is_empty()));
+
+        // --top cuts each list
+        let top = bis_table(
+            &store,
+            &player_with(99, 99),
+            &sword_build(1),
+            &TargetResolved::default(),
+            1,
+            &AccountConstraints::default(),
+        )
+        .expect("bis table failed");
+        assert_eq!(top[0].picks.len(), 1);
+    }
+
+    #[test]
+    fn test_explain_gear_gives_reasons() {
+        let mut store = sword_store();
+        for item in [
+            weapon(3, "light sword", 120, 10, 1),
+            weapon(4, "whip of legend", 100, 100, 90),
+            weapon(5, "banned blade", 100, 100, 1),
+        ] {
+            store.items.insert(item.id, item);
+        }
+        store.items.get_mut(&5).unwrap().tags = vec!["banned".to_string()];
+        let mut player = player_with(99, 99);
+        player.skills.attack = 80;
+        let constraints = AccountConstraints {
+            exclude_tags: vec!["banned".to_string()],
+            ..AccountConstraints::default()
+        };
+        let target = TargetResolved {
+            defence_level: 200,
+            ..TargetResolved::default()
+        };
+        let best = optimize_gear(&store, &player, &sword_build(1), &target, &constraints)
+            .expect("search failed");
+        assert_eq!(best.build.equipment.weapon, 2);
+
+        let rejected = explain_gear(&store, &player, &best.build, &target, 5, &constraints)
+            .expect("explain failed");
+        let reason = |id: u32| {
+            rejected
+                .iter()
+                .find(|r| r.item_id == id)
+                .map(|r| r.reason.clone())
+        };
+        assert_eq!(reason(1), Some(LossReason::Outclassed));
+        assert_eq!(reason(3), Some(LossReason::MaxHitTradeOff));
+        assert_eq!(
+            reason(4),
+            Some(LossReason::Requirements {
+                missing: vec![("attack".to_string(), 90)]
+            })
+        );
+        assert_eq!(reason(5), Some(LossReason::Constraint));
+        // Best first
+        assert!(rejected.windows(2).all(|w| w[0].dps >= w[1].dps));
+    }
+
+    #[test]
+    fn test_optimize_gear_skips_excluded_tags() {
+        let mut store = sword_store();
+        store.items.get_mut(&2).unwrap().tags = vec!["demonbane".to_string()];
+        let constraints = AccountConstraints {
+            exclude_tags: vec!["demonbane".to_string()],
+            ..AccountConstraints::default()
+        };
+        let best = optimize_gear(
+            &store,
+            &player_with(99, 99),
+            &sword_build(1),
+            &TargetResolved::default(),
+            &constraints,
+        )
+        .expect("search failed");
+        assert_eq!(best.build.equipment.weapon, 1);
+    }
+
+    #[test]
+    fn test_optimize_gear_ironman_skips_tradeable_only() {
+        let mut store = sword_store();
+        store
+            .items
+            .get_mut(&2)
+            .unwrap()
+            .obtainability
+            .push(Obtainability::TradeableOnly);
+        let player = player_with(99, 99);
+        let target = TargetResolved::default();
+
+        let main = optimize_gear(
+            &store,
+            &player,
+            &sword_build(1),
+            &target,
+            &AccountConstraints::default(),
+        )
+        .expect("search failed");
+        assert_eq!(main.build.equipment.weapon, 2);
+
+        let iron = AccountConstraints {
+            ironman: true,
+            ..AccountConstraints::default()
+        };
+        let best =
+            optimize_gear(&store, &player, &sword_build(2), &target, &iron).expect("search failed");
+        assert_eq!(best.build.equipment.weapon, 1);
+    }
+
+    #[test]
+    fn test_optimize_gear_f2p_skips_members_items() {
+        let mut store = sword_store();
+        store.items.get_mut(&2).unwrap().members = true;
+        let f2p = AccountConstraints {
+            f2p: true,
+            ..AccountConstraints::default()
+        };
+        let best = optimize_gear(
+            &store,
+            &player_with(99, 99),
+            &sword_build(2),
+            &TargetResolved::default(),
+            &f2p,
+        )
+        .expect("search failed");
+        assert_eq!(best.build.equipment.weapon, 1);
+    }
+
+    #[test]
+    fn test_nearly_available_reports_unlock() {
+        let store = sword_store();
+        let mut player = player_with(99, 99);
+        player.skills.attack = 57;
+        let target = TargetResolved::default();
+
+        let unconstrained = AccountConstraints::default();
+
+        let unlocks =
+            nearly_available(&store, &player, &sword_build(1), &target, 5, &unconstrained)
+                .expect("search failed");
+        assert_eq!(unlocks.len(), 1);
+        assert_eq!(unlocks[0].skill, "attack");
+        assert_eq!(unlocks[0].levels_needed, 3);
+        assert_eq!(
+            unlocks[0].new_items,
+            vec![(2, "dragon scimitar".to_string())]
+        );
+        assert!(unlocks[0].dps_from_gear > 0.0);
+
+        // Out of reach with a smaller window
+        let unlocks =
+            nearly_available(&store, &player, &sword_build(1), &target, 2, &unconstrained)
+                .expect("search failed");
+        assert!(unlocks.is_empty());
+
+        // Out of reach under a combat bracket the current levels already sit at
+        let capped = AccountConstraints {
+            max_combat_level: Some(combat_level(&player.skills)),
+            ..AccountConstraints::default()
+        };
+        let unlocks = nearly_available(&store, &player, &sword_build(1), &target, 5, &capped)
+            .expect("search failed");
+        assert!(unlocks.is_empty());
+    }
+}
@@ -0,0 +1,171 @@
+// src/ordering.rs
+//
+// Where each kind of effect sits in the evaluation pipeline. Effects name a
+// slot here (`"order": "salve"`) instead of picking their own stage and
+// priority, so the documented stacking order lives in one table: for
+// ranged, void -> salve or slayer helm -> dragonbane -> twisted bow.
+//
+// The table tracks the live game. When Jagex changes how effects stack,
+// the old order stays available as a profile pinned to the version it
+// held for (data/ordering), so earlier baselines can be reproduced.
+
+use crate::effects::Stage;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// A named position in the pipeline: effects in it apply in `stage`, at
+/// `priority` (lower first).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderSlot {
+    pub name: &'static str,
+    pub stage: Stage,
+    pub priority: i16,
+}
+
+const fn slot(name: &'static str, stage: Stage, priority: i16) -> OrderSlot {
+    OrderSlot {
+        name,
+        stage,
+        priority,
+    }
+}
+
+/// Every slot, in the order effects apply.
+pub const PIPELINE_ORDER: [OrderSlot; 20] = [
+    // Effective levels: elite void's strength bonus replaces the regular one
+    slot("elite_void", Stage::PostEffectiveLevel, 99),
+    slot("void", Stage::PostEffectiveLevel, 100),
+    // Spell base max hit and magic damage added to the gear's before the
+    // magic max hit is set
+    slot("spell_max_hit", Stage::PreRolls, 30),
+    slot("smoke_staff_magic_damage", Stage::PreRolls, 35),
+    slot("salve_magic_damage", Stage::PreRolls, 40),
+    // Flat max hit, then the slayer-task and undead multipliers (avarice
+    // replaces both, and the salve replaces the slayer helm)
+    slot("colossal_blade", Stage::PostMaxHit, 10),
+    slot("avarice_forinthry_surge", Stage::PostMaxHit, 29),
+    slot("avarice", Stage::PostMaxHit, 30),
+    slot("salve", Stage::PostMaxHit, 40),
+    slot("slayer_helm", Stage::PostMaxHit, 50),
+    // Spellbook bonuses of the weapon, on the slayer-task or undead roll
+    slot("smoke_staff", Stage::PostMaxHit, 55),
+    // Weapon bonuses against a kind of target, after the slayer helm;
+    // resisted demonbane comes first so it replaces the full bonus
+    slot("demonbane_resisted", Stage::PostMaxHit, 59),
+    slot("target_bane", Stage::PostMaxHit, 60),
+    slot("revenant_weapon", Stage::PostMaxHit, 60),
+    slot("dharok", Stage::PostMaxHit, 60),
+    slot("obsidian", Stage::PostMaxHit, 65),
+    slot("berserker_necklace", Stage::PostMaxHit, 70),
+    slot("twisted_bow", Stage::PostMaxHit, 80),
+    // Chance-based damage and minimum hits on the final accuracy and max
+    // hit; Verac's set blends its guaranteed hits into the accuracy after
+    // everything else touches it
+    slot("damage_chance", Stage::PostAccuracy, 0),
+    slot("verac", Stage::PostAccuracy, 100),
+];
+
+/// The slot called `name`.
+pub fn slot_named(name: &str) -> Option<OrderSlot> {
+    PIPELINE_ORDER
+        .iter()
+        .find(|slot| slot.name == name)
+        .copied()
+}
+
+/// Version name of the live order, `PIPELINE_ORDER`.
+pub const LIVE_VERSION: &str = "live";
+
+/// Profiles pinned to a game version, embedded when the crate is built:
+/// (version, contents).
+const PINNED_PROFILES: [(&str, &str); 1] =
+    [("2026-10", include_str!("../data/ordering/2026-10.json"))];
+
+/// Where a slot's effects apply in a profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Placement {
+    pub stage: Stage,
+    pub priority: i16,
+}
+
+/// A stacking order for one game version: slot name -> placement. Effects
+/// in slots the profile doesn't list keep their live placement.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrderingProfile {
+    pub version: String,
+    pub slots: BTreeMap<String, Placement>,
+}
+
+impl OrderingProfile {
+    /// The live order.
+    pub fn live() -> Self {
+        Self {
+            version: LIVE_VERSION.to_string(),
+            slots: PIPELINE_ORDER
+                .iter()
+                .map(|slot| {
+                    let placement = Placement {
+                        stage: slot.stage,
+                        priority: slot.priority,
+                    };
+                    (slot.name.to_string(), placement)
+                })
+                .collect(),
+        }
+    }
+
+    /// Placement of a slot's effects.
+    pub fn placement(&self, slot: &str) -> Option<Placement> {
+        self.slots.get(slot).copied()
+    }
+}
+
+/// The profile for `version`: `live` or a pinned version.
+pub fn profile(version: &str) -> Option<OrderingProfile> {
+    if version == LIVE_VERSION {
+        return Some(OrderingProfile::live());
+    }
+    let (_, json) = PINNED_PROFILES.iter().find(|(v, _)| *v == version)?;
+    let profile: OrderingProfile = serde_json::from_str(json)
+        .unwrap_or_else(|e| panic!("pinned ordering profile {version} is invalid: {e}"));
+    Some(profile)
+}
+
+/// Every version `profile` knows, live first.
+pub fn versions() -> Vec<&'static str> {
+    std::iter::once(LIVE_VERSION)
+        .chain(PINNED_PROFILES.iter().map(|(version, _)| *version))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline_order_is_sorted_and_unique() {
+        let places: Vec<(Stage, i16)> = PIPELINE_ORDER
+            .iter()
+            .map(|slot| (slot.stage, slot.priority))
+            .collect();
+        assert!(places.windows(2).all(|pair| pair[0] <= pair[1]));
+        for (i, slot) in PIPELINE_ORDER.iter().enumerate() {
+            assert_eq!(slot_named(slot.name), Some(PIPELINE_ORDER[i]));
+        }
+        assert_eq!(slot_named("nowhere"), None);
+    }
+
+    #[test]
+    fn test_pinned_profiles_parse_and_name_known_slots() {
+        for version in versions() {
+            let profile = profile(version).expect(version);
+            assert_eq!(profile.version, version);
+            for slot in profile.slots.keys() {
+                assert!(slot_named(slot).is_some(), "{version}: unknown slot {slot}");
+            }
+        }
+        assert_eq!(profile("1999-01"), None);
+    }
+}
@@ -0,0 +1,341 @@
+// src/planner.rs
+//
+// Account progression planning: step skills toward goal levels and re-run the
+// gear search at each bracket to show how DPS and recommended gear evolve,
+// and find the levels where a fixed setup gains a max hit.
+//
+// XP figures assume the player is at the very start of their current level.
+
+use crate::data::{apply_pre_fight_player, resolve_player, DataError, DataStore};
+use crate::formulas::{combat_level, evaluate, xp_between};
+use crate::model::{BuildInput, BuildResolved, EvalContext, PlayerInput, TargetResolved};
+use crate::optimizer::{optimize_gear, AccountConstraints};
+use serde::{Deserialize, Serialize};
+
+/// Train `skill` up to `level` over the course of the plan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkillGoal {
+    pub skill: String,
+    pub level: u8,
+}
+
+impl std::str::FromStr for SkillGoal {
+    type Err = String;
+
+    /// Parse "strength=99".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (skill, level) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected skill=level, got {s:?}"))?;
+        let level: u8 = level
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid level in {s:?}"))?;
+        if !(1..=99).contains(&level) {
+            return Err(format!("level must be 1-99, got {level}"));
+        }
+        Ok(SkillGoal {
+            skill: skill.trim().to_lowercase(),
+            level,
+        })
+    }
+}
+
+/// One bracket of the plan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Milestone {
+    /// Levels of the planned skills at this bracket.
+    pub levels: Vec<(String, u8)>,
+    pub combat_level: u8,
+    /// XP per planned skill from the player's current levels to this bracket.
+    pub xp_needed: Vec<(String, u32)>,
+    pub dps: f64,
+    pub build: BuildInput,
+    /// (item id, name) pairs recommended here that weren't at the previous bracket.
+    pub new_items: Vec<(u32, String)>,
+}
+
+/// Step every goal skill up by `step` levels per bracket (capped at its goal)
+/// and find the best gear at each bracket, starting from the player's
+/// current levels. A skill stops short of its goal if training it further
+/// would break `constraints`, and gear is limited to what they allow.
+pub fn plan_progression(
+    store: &DataStore,
+    player_input: &PlayerInput,
+    start: &BuildInput,
+    target: &TargetResolved,
+    goals: &[SkillGoal],
+    step: u8,
+    constraints: &AccountConstraints,
+) -> Result<Vec<Milestone>, DataError> {
+    let step = step.max(1);
+    let mut player = player_input.clone();
+    let mut build = start.clone();
+    let mut previous_items: Vec<u32> = vec![];
+    let mut milestones = Vec::new();
+
+    loop {
+        let best = optimize_gear(store, &player, &build, target, constraints)?;
+        let items = best.build.equipment.item_ids();
+        let new_items = items
+            .iter()
+            .filter(|id| !previous_items.contains(id))
+            .map(|&id| {
+                let name = store.get_item(id).map(|i| i.name.clone());
+                (id, name.unwrap_or_default())
+            })
+            .collect();
+
+        milestones.push(Milestone {
+            levels: goals
+                .iter()
+                .map(|g| {
+                    let level = player.skills.level(&g.skill).unwrap_or(0);
+                    (g.skill.clone(), level)
+                })
+                .collect(),
+            combat_level: combat_level(&player.skills),
+            xp_needed: goals
+                .iter()
+                .map(|g| {
+                    let from = player_input.skills.level(&g.skill).unwrap_or(1);
+                    let to = player.skills.level(&g.skill).unwrap_or(1);
+                    (g.skill.clone(), xp_between(from, to))
+                })
+                .collect(),
+            dps: best.dps,
+            build: best.build.clone(),
+            new_items,
+        });
+        previous_items = items;
+        build = best.build;
+
+        // Advance every unfinished goal by up to one step, one level at a
+        // time so a constrained skill stops exactly at the limit
+        let mut advanced = false;
+        for goal in goals {
+            for _ in 0..step {
+                let mut next = player.skills.clone();
+                let Some(level) = next.level_mut(&goal.skill) else {
+                    break;
+                };
+                if *level >= goal.level {
+                    break;
+                }
+                *level += 1;
+                if !constraints.allows(&next) {
+                    break;
+                }
+                player.skills = next;
+                advanced = true;
+            }
+        }
+        if !advanced {
+            break;
+        }
+    }
+
+    Ok(milestones)
+}
+
+/// A level at which the max hit goes up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Breakpoint {
+    pub level: u8,
+    pub max_hit: u32,
+    pub dps: f64,
+    /// XP from the player's current level to this one.
+    pub xp_needed: u32,
+}
+
+/// Walk `skill` from the player's level up to 99 with a fixed build and
+/// report every level where the max hit increases.
+/// Returns an empty list for skills the player input doesn't track.
+pub fn max_hit_breakpoints(
+    player_input: &PlayerInput,
+    build: &BuildResolved,
+    target: &TargetResolved,
+    skill: &str,
+) -> Vec<Breakpoint> {
+    let Some(current) = player_input.skills.level(skill) else {
+        return vec![];
+    };
+
+    let eval_at = |level: u8| {
+        let mut skills = player_input.skills.clone();
+        if let Some(l) = skills.level_mut(skill) {
+            *l = level;
+        }
+        let mut player = resolve_player(
+            &skills,
+            &player_input.prayers,
+            &player_input.boosts,
+            &player_input.flags,
+        );
+        apply_pre_fight_player(&mut player, &player_input.pre_fight);
+        evaluate(&EvalContext {
+            player: &player,
+            build,
+            target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+    };
+
+    let mut last_max_hit = eval_at(current).max_hit;
+    let mut breakpoints = Vec::new();
+    for level in current.saturating_add(1)..=99 {
+        let result = eval_at(level);
+        if result.max_hit > last_max_hit {
+            breakpoints.push(Breakpoint {
+                level,
+                max_hit: result.max_hit,
+                dps: result.dps,
+                xp_needed: xp_between(current, level),
+            });
+            last_max_hit = result.max_hit;
+        }
+    }
+    breakpoints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::test_support::weapon;
+    use crate::model::{
+        AttackType, BoostsInput, Combat, EquipmentInput, FlagsInput, SkillsInput, Stance,
+        StyleInput, WeaponConfig,
+    };
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_plan_respects_combat_cap() {
+        let player = PlayerInput {
+            schema_version: 1,
+            skills: SkillsInput {
+                attack: 60,
+                strength: 80,
+                defence: 1,
+                ranged: 1,
+                magic: 1,
+                prayer: 52,
+                hitpoints: 80,
+            },
+            prayers: HashMap::new(),
+            boosts: BoostsInput::default(),
+            flags: FlagsInput::default(),
+            pre_fight: vec![],
+        };
+        let build = BuildInput {
+            schema_version: 1,
+            equipment: EquipmentInput {
+                weapon: 0,
+                head: None,
+                cape: None,
+                neck: None,
+                ammo: None,
+                body: None,
+                shield: None,
+                legs: None,
+                hands: None,
+                feet: None,
+                ring: None,
+                charged_ammo: None,
+            },
+            style: StyleInput {
+                combat: Combat::Melee,
+                attack_type: AttackType::Slash,
+                stance: Stance::Aggressive,
+                special_attack: false,
+                manual_cast: false,
+                runes: vec![],
+                spell: None,
+            },
+            weapon_config: WeaponConfig::default(),
+        };
+        let goals = vec!["strength=99".parse().unwrap()];
+        let constraints = AccountConstraints {
+            max_combat_level: Some(75),
+            ..AccountConstraints::default()
+        };
+
+        let mut store = DataStore::new();
+        store.items.insert(0, weapon(0, "unarmed", 0, 0, 1));
+
+        let plan = plan_progression(
+            &store,
+            &player,
+            &build,
+            &TargetResolved::default(),
+            &goals,
+            5,
+            &constraints,
+        )
+        .unwrap();
+
+        let last = plan.last().unwrap();
+        assert_eq!(plan[0].xp_needed, vec![("strength".to_string(), 0)]);
+        assert_eq!(last.xp_needed[0].1, xp_between(80, last.levels[0].1));
+        assert!(plan.iter().all(|m| m.combat_level <= 75));
+        assert!(last.levels[0].1 < 99);
+        // One more strength level would cross the cap
+        let mut over = player.skills.clone();
+        over.strength = last.levels[0].1 + 1;
+        assert!(!constraints.allows(&over));
+    }
+
+    #[test]
+    fn test_max_hit_breakpoints() {
+        let mut player = PlayerInput {
+            schema_version: 1,
+            skills: SkillsInput {
+                attack: 99,
+                strength: 90,
+                defence: 99,
+                ranged: 99,
+                magic: 99,
+                prayer: 99,
+                hitpoints: 99,
+            },
+            prayers: HashMap::new(),
+            boosts: BoostsInput::default(),
+            flags: FlagsInput::default(),
+            pre_fight: vec![],
+        };
+        let mut build = BuildResolved::default();
+        build.bonuses.melee_strength = 100;
+
+        let breakpoints =
+            max_hit_breakpoints(&player, &build, &TargetResolved::default(), "strength");
+        assert!(!breakpoints.is_empty());
+        assert!(breakpoints.windows(2).all(|w| w[0].max_hit < w[1].max_hit));
+        assert_eq!(
+            breakpoints[0].xp_needed,
+            xp_between(90, breakpoints[0].level)
+        );
+
+        player.skills.strength = 99;
+        assert!(
+            max_hit_breakpoints(&player, &build, &TargetResolved::default(), "strength").is_empty()
+        );
+        assert!(
+            max_hit_breakpoints(&player, &build, &TargetResolved::default(), "slayer").is_empty()
+        );
+    }
+
+    #[test]
+    fn test_skill_goal_parse() {
+        let goal: SkillGoal = "Strength=99".parse().unwrap();
+        assert_eq!(
+            goal,
+            SkillGoal {
+                skill: "strength".to_string(),
+                level: 99
+            }
+        );
+        assert!("strength".parse::<SkillGoal>().is_err());
+        assert!("strength=120".parse::<SkillGoal>().is_err());
+    }
+}
@@ -0,0 +1,1098 @@
+// src/render.rs
+//
+// Text rendering of evaluation results. The engine only produces numbers
+// (`EvalResult` and friends); everything a reader sees comes from here, with
+// the words looked up in a message catalog so a front-end can swap in
+// another language, and the amount of detail and coloring picked by a theme.
+// Tabular output goes through `Table`, which aligns columns on the plain text
+// and only then adds ANSI colors, so colored and plain output line up alike.
+
+use crate::effects::{EngineState, Stage};
+use crate::formulas::combat_level;
+use crate::model::{
+    BuildResolved, Combat, EvalResult, PlayerState, RankedStyle, SkillsInput, TargetResolved,
+};
+use crate::optimizer::RankedBoosts;
+use crate::ticks::SECONDS_PER_TICK;
+use std::fmt::Display;
+
+/// Every piece of text the renderer prints. Placeholders `{0}`, `{1}`, ...
+/// in a message are filled with its arguments in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Msg {
+    EvalTitle,
+    /// {0}: target name
+    Target,
+    Results,
+    Dps,
+    MaxHit,
+    Hits,
+    Accuracy,
+    AttackRoll,
+    DefenceRoll,
+    AttackSpeed,
+    /// {0}: ticks, {1}: seconds
+    AttackSpeedValue,
+    AverageSpeed,
+    AverageSpeedValue,
+    PoisonDps,
+    /// {0}: poison DPS
+    PoisonDpsValue,
+    BurnDps,
+    BurnDpsValue,
+    AoeDps,
+    /// {0}: DPS over every target hit
+    AoeDpsValue,
+    /// {0}: why the build can't damage the target
+    Immune,
+    /// {0}: confidence, {1}: what's wrong with the target's data
+    DataWarning,
+    /// {0}: attack type, {1}: stance
+    AutoStyle,
+    StylesByDps,
+    /// {0}: prayers and potion
+    AutoBoosts,
+    BoostsByDps,
+    NoPrayer,
+    NoPotion,
+    AccuracyByType,
+    CurrentMarker,
+    ColRank,
+    ColStyle,
+    ColBoosts,
+    ColDps,
+    ColDelta,
+    ColMaxHit,
+    ColAccuracy,
+    ColAttackType,
+    ColAttackRoll,
+    ColDefenceRoll,
+    BreakdownTitle,
+    PlayerStats,
+    /// {0}: combat level
+    CombatLevel,
+    /// {0}: stance, {1}: accuracy levels, {2}: strength levels
+    StanceLine,
+    /// {0}: base, {1}: potion, {2}/{3}: prayer
+    MagicLine,
+    /// {0}: powered staff
+    SpellBuiltIn,
+    /// {0}: spell, {1}: base max hit
+    SpellLine,
+    /// {0}: base, {1}: potion, {2}/{3}: prayer accuracy, {4}/{5}: prayer damage
+    RangedLine,
+    /// {0}: base, {1}: potion, {2}/{3}: prayer
+    AttackLine,
+    /// {0}: base, {1}: potion, {2}/{3}: prayer
+    StrengthLine,
+    EquipmentBonuses,
+    /// {0}: attack type, {1}: bonus
+    AttackBonus,
+    /// {0}: bonus
+    MeleeStrength,
+    /// {0}: bonus
+    RangedStrength,
+    /// {0}: bonus
+    MagicDamage,
+    TargetHeading,
+    /// {0}: level
+    DefenceLevel,
+    /// {0}: attack type, {1}: bonus
+    DefenceBonus,
+    /// {0}: attributes
+    Attributes,
+    EffectsNone,
+    /// {0}: effect IDs
+    Effects,
+    /// {0}: what's compared (a player)
+    StageComparison,
+    ColStage,
+    ColValue,
+    StageLevels,
+    StagePreRolls,
+    StageRolls,
+    StageAccuracy,
+    StageResult,
+    EffectiveAttack,
+    EffectiveStrength,
+    MagicDamageBonus,
+    DamageMultiplier,
+    StageEffects,
+    Differs,
+}
+
+/// Looks up the text for each message.
+pub trait Catalog {
+    fn text(&self, msg: Msg) -> &str;
+}
+
+/// The built-in English catalog, matching the CLI's output.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct English;
+
+impl Catalog for English {
+    fn text(&self, msg: Msg) -> &str {
+        match msg {
+            Msg::EvalTitle => "=== DPS Evaluation ===",
+            Msg::Target => "Target: {0}",
+            Msg::Results => "Results:",
+            Msg::Dps => "DPS",
+            Msg::MaxHit => "Max Hit",
+            Msg::Hits => "Hits",
+            Msg::Accuracy => "Accuracy",
+            Msg::AttackRoll => "Attack Roll",
+            Msg::DefenceRoll => "Defence Roll",
+            Msg::AttackSpeed => "Attack Speed",
+            Msg::AttackSpeedValue => "{0} ticks ({1}s)",
+            Msg::AverageSpeed => "Avg Speed",
+            Msg::AverageSpeedValue => "{0} ticks ({1}s, set effect included)",
+            Msg::PoisonDps => "Poison DPS",
+            Msg::PoisonDpsValue => "{0} (on top of DPS)",
+            Msg::BurnDps => "Burn DPS",
+            Msg::BurnDpsValue => "{0} (on top of DPS)",
+            Msg::AoeDps => "AoE DPS",
+            Msg::AoeDpsValue => "{0} (all targets, DPS included)",
+            Msg::Immune => "No damage: {0}",
+            Msg::DataWarning => "Data may be off ({0} confidence): {1}",
+            Msg::AutoStyle => "Auto-selected style: {0} / {1}",
+            Msg::StylesByDps => "Styles by DPS:",
+            Msg::AutoBoosts => "Auto-selected boosts: {0}",
+            Msg::BoostsByDps => "Boosts by DPS (delta vs configured):",
+            Msg::NoPrayer => "no prayer",
+            Msg::NoPotion => "no potion",
+            Msg::AccuracyByType => "Accuracy by attack type:",
+            Msg::CurrentMarker => "(current)",
+            Msg::ColRank => "#",
+            Msg::ColStyle => "Style",
+            Msg::ColBoosts => "Prayers, potion",
+            Msg::ColDps => "DPS",
+            Msg::ColDelta => "Delta",
+            Msg::ColMaxHit => "Max",
+            Msg::ColAccuracy => "Acc %",
+            Msg::ColAttackType => "Type",
+            Msg::ColAttackRoll => "Atk roll",
+            Msg::ColDefenceRoll => "Def roll",
+            Msg::BreakdownTitle => "=== Breakdown ===",
+            Msg::PlayerStats => "Player Stats:",
+            Msg::CombatLevel => "Combat Level: {0}",
+            Msg::StanceLine => "Stance:   {0} (+{1} accuracy, +{2} strength)",
+            Msg::MagicLine => "Magic:    {0} (base) + {1} (potion) * {2}/{3} (prayer)",
+            Msg::SpellBuiltIn => "Spell:    built-in ({0})",
+            Msg::SpellLine => "Spell:    {0} (base max hit {1})",
+            Msg::RangedLine => {
+                "Ranged:   {0} (base) + {1} (potion) * {2}/{3} (prayer accuracy), {4}/{5} (prayer damage)"
+            }
+            Msg::AttackLine => "Attack:   {0} (base) + {1} (potion) * {2}/{3} (prayer)",
+            Msg::StrengthLine => "Strength: {0} (base) + {1} (potion) * {2}/{3} (prayer)",
+            Msg::EquipmentBonuses => "Equipment Bonuses:",
+            Msg::AttackBonus => "Attack ({0}): {1}",
+            Msg::MeleeStrength => "Melee Strength: {0}",
+            Msg::RangedStrength => "Ranged Strength: {0}",
+            Msg::MagicDamage => "Magic Damage: {0}%",
+            Msg::TargetHeading => "Target:",
+            Msg::DefenceLevel => "Defence Level: {0}",
+            Msg::DefenceBonus => "Defence ({0}): {1}",
+            Msg::Attributes => "Attributes: {0}",
+            Msg::EffectsNone => "Effects: none",
+            Msg::Effects => "Effects: {0}",
+            Msg::StageComparison => "{0}, stage by stage:",
+            Msg::ColStage => "Stage",
+            Msg::ColValue => "Value",
+            Msg::StageLevels => "Effective levels",
+            Msg::StagePreRolls => "Before rolls",
+            Msg::StageRolls => "Rolls, max hit",
+            Msg::StageAccuracy => "After accuracy",
+            Msg::StageResult => "Result",
+            Msg::EffectiveAttack => "Effective attack",
+            Msg::EffectiveStrength => "Effective strength",
+            Msg::MagicDamageBonus => "Magic damage %",
+            Msg::DamageMultiplier => "Damage multiplier",
+            Msg::StageEffects => "Effects",
+            Msg::Differs => "<- differs",
+        }
+    }
+}
+
+/// Fill a message's `{N}` placeholders with `args`. Placeholders without a
+/// matching argument are left as they are.
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = template.to_string();
+    for (i, arg) in args.iter().enumerate() {
+        out = out.replace(&format!("{{{i}}}"), &arg.to_string());
+    }
+    out
+}
+
+/// How much of a report to show.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Detail {
+    /// Headline numbers only.
+    Compact,
+    /// Results plus style, boost and accuracy tables.
+    #[default]
+    Standard,
+    /// Standard plus the breakdown of levels, bonuses and effects.
+    Verbose,
+}
+
+/// Presentation choices that don't change what was computed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Theme {
+    pub detail: Detail,
+    /// Emit ANSI colors. Off by default; the CLI turns it on for terminals
+    /// unless `--no-color` or `NO_COLOR` is set.
+    pub color: bool,
+}
+
+impl Theme {
+    /// `text` with `paint`'s color, or unchanged when color is off.
+    pub fn paint(&self, text: &str, paint: Paint) -> String {
+        match paint.code() {
+            Some(code) if self.color => format!("\x1b[{code}m{text}\x1b[0m"),
+            _ => text.to_string(),
+        }
+    }
+}
+
+/// What a piece of text means, which picks its color.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Paint {
+    #[default]
+    Plain,
+    /// The best value among those compared (bold green).
+    Best,
+    /// An improvement (green).
+    Gain,
+    /// A loss (red).
+    Loss,
+    /// Something the user should look at (yellow).
+    Warning,
+    /// Table headings (bold).
+    Heading,
+}
+
+impl Paint {
+    /// Gain for positive deltas, Loss for negative ones.
+    pub fn for_delta(delta: f64) -> Self {
+        if delta > 0.0 {
+            Paint::Gain
+        } else if delta < 0.0 {
+            Paint::Loss
+        } else {
+            Paint::Plain
+        }
+    }
+
+    fn code(self) -> Option<&'static str> {
+        match self {
+            Paint::Plain => None,
+            Paint::Best => Some("1;32"),
+            Paint::Gain => Some("32"),
+            Paint::Loss => Some("31"),
+            Paint::Warning => Some("33"),
+            Paint::Heading => Some("1"),
+        }
+    }
+}
+
+/// Column alignment in a `Table`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+/// One table cell: its text and how to color it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Cell {
+    pub text: String,
+    pub paint: Paint,
+}
+
+impl Cell {
+    pub fn painted(text: impl Into<String>, paint: Paint) -> Self {
+        Self {
+            text: text.into(),
+            paint,
+        }
+    }
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Self {
+        Cell::painted(text, Paint::Plain)
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(text: &str) -> Self {
+        Cell::painted(text, Paint::Plain)
+    }
+}
+
+/// Rows of cells printed in aligned columns, two spaces apart. Widths come
+/// from the plain text, so color doesn't affect alignment; a left-aligned
+/// last column isn't padded.
+#[derive(Debug, Clone)]
+pub struct Table {
+    align: Vec<Align>,
+    header: Option<Vec<String>>,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    /// A table with one column per entry in `align`.
+    pub fn new(align: &[Align]) -> Self {
+        Self {
+            align: align.to_vec(),
+            header: None,
+            rows: vec![],
+        }
+    }
+
+    pub fn header<S: Into<String>>(mut self, header: impl IntoIterator<Item = S>) -> Self {
+        self.header = Some(header.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Add a row. Missing trailing cells are left blank.
+    pub fn row<C: Into<Cell>>(&mut self, cells: impl IntoIterator<Item = C>) {
+        self.rows.push(cells.into_iter().map(Into::into).collect());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// The table's lines, each starting with `indent`.
+    pub fn render(&self, theme: &Theme, indent: &str) -> Vec<String> {
+        let header = self.header.as_ref().map(|header| {
+            header
+                .iter()
+                .map(|text| Cell::painted(text.as_str(), Paint::Heading))
+                .collect::<Vec<_>>()
+        });
+        let rows: Vec<&Vec<Cell>> = header.iter().chain(&self.rows).collect();
+        let columns = self.align.len();
+        let mut widths = vec![0; columns];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(cell.text.chars().count());
+            }
+        }
+
+        rows.iter()
+            .map(|row| {
+                let mut line = indent.to_string();
+                for (i, (&align, &width)) in self.align.iter().zip(&widths).enumerate() {
+                    let (text, paint) = row
+                        .get(i)
+                        .map_or(("", Paint::Plain), |c| (c.text.as_str(), c.paint));
+                    let pad = " ".repeat(width - text.chars().count());
+                    if i > 0 {
+                        line.push_str("  ");
+                    }
+                    match align {
+                        Align::Right => {
+                            line.push_str(&pad);
+                            line.push_str(&theme.paint(text, paint));
+                        }
+                        Align::Left => {
+                            line.push_str(&theme.paint(text, paint));
+                            if i + 1 < columns {
+                                line.push_str(&pad);
+                            }
+                        }
+                    }
+                }
+                line.trim_end().to_string()
+            })
+            .collect()
+    }
+}
+
+/// Everything an evaluation report shows.
+pub struct EvalReport<'a> {
+    pub skills: &'a SkillsInput,
+    pub player: &'a PlayerState,
+    pub build: &'a BuildResolved,
+    pub target: &'a TargetResolved,
+    pub result: &'a EvalResult,
+    /// Styles tried by auto-style, best first (empty if not used).
+    pub ranked_styles: &'a [RankedStyle],
+    /// Boosts tried by auto-boosts, best first (empty if not used).
+    pub ranked_boosts: &'a [RankedBoosts],
+}
+
+/// Renders reports with a catalog's text and a theme.
+pub struct Renderer<'a> {
+    pub catalog: &'a dyn Catalog,
+    pub theme: Theme,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(catalog: &'a dyn Catalog, theme: Theme) -> Self {
+        Self { catalog, theme }
+    }
+
+    fn text(&self, msg: Msg, args: &[&dyn Display]) -> String {
+        fill(self.catalog.text(msg), args)
+    }
+
+    /// The full text report for an evaluation, one line per `\n`.
+    pub fn eval(&self, report: &EvalReport) -> String {
+        let mut out = Lines::default();
+        let compact = self.theme.detail == Detail::Compact;
+        if !compact {
+            out.push(self.text(Msg::EvalTitle, &[]));
+            out.blank();
+        }
+        out.push(self.text(Msg::Target, &[&report.target.name]));
+        if !compact {
+            out.blank();
+            out.push(self.text(Msg::Results, &[]));
+        }
+        self.results(&mut out, report.result, compact);
+        if compact {
+            return out.finish();
+        }
+
+        self.ranked_styles(&mut out, report);
+        self.ranked_boosts(&mut out, report.ranked_boosts);
+        self.style_rolls(&mut out, report);
+        if self.theme.detail == Detail::Verbose {
+            self.breakdown(&mut out, report);
+        }
+        out.finish()
+    }
+
+    /// Headline numbers as an aligned label/value list.
+    fn results(&self, out: &mut Lines, result: &EvalResult, compact: bool) {
+        let mut rows = vec![
+            (Msg::Dps, format!("{:.4}", result.dps)),
+            (Msg::MaxHit, result.max_hit.to_string()),
+        ];
+        if result.hit_max_hits.len() > 1 {
+            let hits: Vec<String> = result.hit_max_hits.iter().map(u32::to_string).collect();
+            rows.push((Msg::Hits, hits.join(" + ")));
+        }
+        rows.push((Msg::Accuracy, format!("{:.2}%", result.accuracy * 100.0)));
+        if !compact {
+            rows.push((Msg::AttackRoll, result.attack_roll.to_string()));
+            rows.push((Msg::DefenceRoll, result.defence_roll.to_string()));
+        }
+        let speed = self.text(
+            Msg::AttackSpeedValue,
+            &[
+                &result.interval_ticks.0,
+                &format!("{:.1}", result.interval_ticks.as_secs_f64()),
+            ],
+        );
+        rows.push((Msg::AttackSpeed, speed));
+        if let Some(ticks) = result.expected_interval_ticks {
+            let average = self.text(
+                Msg::AverageSpeedValue,
+                &[
+                    &format!("{ticks:.2}"),
+                    &format!("{:.2}", ticks * SECONDS_PER_TICK),
+                ],
+            );
+            rows.push((Msg::AverageSpeed, average));
+        }
+        if result.poison_dps > 0.0 {
+            let poison = self.text(Msg::PoisonDpsValue, &[&format!("{:.4}", result.poison_dps)]);
+            rows.push((Msg::PoisonDps, poison));
+        }
+        if result.burn_dps > 0.0 {
+            let burn = self.text(Msg::BurnDpsValue, &[&format!("{:.4}", result.burn_dps)]);
+            rows.push((Msg::BurnDps, burn));
+        }
+        if let Some(aoe_dps) = result.aoe_dps {
+            let aoe = self.text(Msg::AoeDpsValue, &[&format!("{aoe_dps:.4}")]);
+            rows.push((Msg::AoeDps, aoe));
+        }
+
+        // Pad labels to the longest one in any row the report can show, so
+        // the value column doesn't move when optional rows appear
+        let width = [
+            Msg::Dps,
+            Msg::MaxHit,
+            Msg::Hits,
+            Msg::Accuracy,
+            Msg::AttackRoll,
+            Msg::DefenceRoll,
+            Msg::AttackSpeed,
+            Msg::AverageSpeed,
+            Msg::PoisonDps,
+            Msg::BurnDps,
+            Msg::AoeDps,
+        ]
+        .iter()
+        .map(|&msg| self.catalog.text(msg).chars().count())
+        .max()
+        .unwrap_or(0)
+            + 4;
+        for (label, value) in rows {
+            let label = format!("{}:", self.catalog.text(label));
+            out.push(format!("  {label:<width$}{value}"));
+        }
+        if let Some(reason) = &result.immunity {
+            let text = self.text(Msg::Immune, &[reason]);
+            out.push(format!("  {}", self.theme.paint(&text, Paint::Warning)));
+        }
+        for flag in &result.data_warnings {
+            let text = self.text(Msg::DataWarning, &[&flag.confidence.as_str(), &flag.note]);
+            out.push(format!("  {}", self.theme.paint(&text, Paint::Warning)));
+        }
+    }
+
+    /// Results side by side, one column each (named by `labels`), stage by
+    /// stage through the pipeline: each stage's values and the effects that
+    /// applied in it, with the rows where the results differ marked.
+    pub fn stage_comparison(
+        &self,
+        subject: &str,
+        labels: &[String],
+        results: &[&EvalResult],
+    ) -> String {
+        let mut aligns = vec![Align::Left, Align::Left];
+        aligns.extend(results.iter().map(|_| Align::Right));
+        aligns.push(Align::Left);
+        let mut header = self.headings(&[Msg::ColStage, Msg::ColValue]);
+        header.extend(labels.iter().cloned());
+        let mut table = Table::new(&aligns).header(header);
+        let mut row = |stage: String, value: Msg, cells: Vec<String>| {
+            let differs = cells.windows(2).any(|pair| pair[0] != pair[1]);
+            let paint = if differs {
+                Paint::Warning
+            } else {
+                Paint::Plain
+            };
+            let marker = if differs {
+                Cell::painted(self.text(Msg::Differs, &[]), Paint::Warning)
+            } else {
+                Cell::default()
+            };
+            table.row(
+                [Cell::from(stage), Cell::from(self.text(value, &[]))]
+                    .into_iter()
+                    .chain(cells.into_iter().map(|text| Cell::painted(text, paint)))
+                    .chain(std::iter::once(marker)),
+            );
+        };
+
+        for (stage, heading, values) in STAGE_ROWS {
+            let traces: Vec<_> = results
+                .iter()
+                .map(|result| result.stages.iter().find(|trace| trace.stage == stage))
+                .collect();
+            let mut heading = Some(self.text(heading, &[]));
+            for (msg, value) in values {
+                let cells = traces
+                    .iter()
+                    .map(|trace| trace.map_or("-".to_string(), |t| value(&t.state)))
+                    .collect();
+                row(heading.take().unwrap_or_default(), *msg, cells);
+            }
+            let effects = traces
+                .iter()
+                .map(|trace| match trace {
+                    Some(t) if !t.effects.is_empty() => t.effects.join(", "),
+                    _ => "-".to_string(),
+                })
+                .collect();
+            row(String::new(), Msg::StageEffects, effects);
+        }
+        let dps = results
+            .iter()
+            .map(|result| format!("{:.4}", result.dps))
+            .collect();
+        row(self.text(Msg::StageResult, &[]), Msg::Dps, dps);
+
+        let mut out = Lines::default();
+        out.push(self.text(Msg::StageComparison, &[&subject]));
+        out.table(&table, &self.theme);
+        out.finish()
+    }
+
+    fn ranked_styles(&self, out: &mut Lines, report: &EvalReport) {
+        if report.ranked_styles.is_empty() {
+            return;
+        }
+        let build = report.build;
+        out.blank();
+        out.push(self.text(
+            Msg::AutoStyle,
+            &[
+                &format!("{:?}", build.attack_type),
+                &format!("{:?}", build.stance),
+            ],
+        ));
+        out.push(self.text(Msg::StylesByDps, &[]));
+        let mut table = Table::new(&[
+            Align::Right,
+            Align::Left,
+            Align::Right,
+            Align::Right,
+            Align::Right,
+        ])
+        .header(self.headings(&[
+            Msg::ColRank,
+            Msg::ColStyle,
+            Msg::ColDps,
+            Msg::ColMaxHit,
+            Msg::ColAccuracy,
+        ]));
+        // Ranked best first, so the first row holds the best DPS
+        for (rank, style) in report.ranked_styles.iter().enumerate() {
+            let dps_paint = if rank == 0 { Paint::Best } else { Paint::Plain };
+            table.row([
+                Cell::from((rank + 1).to_string()),
+                format!("{:?}/{:?}", style.attack_type, style.stance).into(),
+                Cell::painted(format!("{:.4}", style.dps), dps_paint),
+                style.max_hit.to_string().into(),
+                format!("{:.2}", style.accuracy * 100.0).into(),
+            ]);
+        }
+        out.table(&table, &self.theme);
+    }
+
+    fn headings(&self, msgs: &[Msg]) -> Vec<String> {
+        msgs.iter()
+            .map(|&msg| self.catalog.text(msg).to_string())
+            .collect()
+    }
+
+    fn ranked_boosts(&self, out: &mut Lines, ranked: &[RankedBoosts]) {
+        let Some(best) = ranked.first() else {
+            return;
+        };
+        let describe = |r: &RankedBoosts| {
+            let prayers = if r.prayers.is_empty() {
+                self.catalog.text(Msg::NoPrayer).to_string()
+            } else {
+                r.prayers.join(" + ")
+            };
+            let potion = r
+                .potion
+                .as_deref()
+                .unwrap_or(self.catalog.text(Msg::NoPotion));
+            format!("{prayers}, {potion}")
+        };
+        out.blank();
+        out.push(self.text(Msg::AutoBoosts, &[&describe(best)]));
+        out.push(self.text(Msg::BoostsByDps, &[]));
+        let mut table = Table::new(&[Align::Right, Align::Left, Align::Right, Align::Right])
+            .header(self.headings(&[Msg::ColRank, Msg::ColBoosts, Msg::ColDps, Msg::ColDelta]));
+        for (rank, boosts) in ranked.iter().take(10).enumerate() {
+            let dps_paint = if rank == 0 { Paint::Best } else { Paint::Plain };
+            table.row([
+                Cell::from((rank + 1).to_string()),
+                describe(boosts).into(),
+                Cell::painted(format!("{:.4}", boosts.dps), dps_paint),
+                Cell::painted(
+                    format!("{:+.4}", boosts.dps_delta),
+                    Paint::for_delta(boosts.dps_delta),
+                ),
+            ]);
+        }
+        out.table(&table, &self.theme);
+    }
+
+    fn style_rolls(&self, out: &mut Lines, report: &EvalReport) {
+        let rolls = &report.result.style_rolls;
+        if rolls.len() <= 1 {
+            return;
+        }
+        out.blank();
+        out.push(self.text(Msg::AccuracyByType, &[]));
+        let best = rolls.iter().map(|r| r.accuracy).fold(f64::MIN, f64::max);
+        let mut table = Table::new(&[
+            Align::Left,
+            Align::Right,
+            Align::Right,
+            Align::Right,
+            Align::Left,
+        ])
+        .header(self.headings(&[
+            Msg::ColAttackType,
+            Msg::ColAccuracy,
+            Msg::ColAttackRoll,
+            Msg::ColDefenceRoll,
+        ]));
+        for roll in rolls {
+            let marker = if roll.attack_type == report.build.attack_type {
+                self.catalog.text(Msg::CurrentMarker)
+            } else {
+                ""
+            };
+            let paint = if roll.accuracy == best {
+                Paint::Best
+            } else {
+                Paint::Plain
+            };
+            table.row([
+                Cell::from(format!("{:?}", roll.attack_type)),
+                Cell::painted(format!("{:.2}", roll.accuracy * 100.0), paint),
+                roll.attack_roll.to_string().into(),
+                roll.defence_roll.to_string().into(),
+                marker.into(),
+            ]);
+        }
+        out.table(&table, &self.theme);
+    }
+
+    /// Levels, bonuses and effects that went into the result.
+    fn breakdown(&self, out: &mut Lines, report: &EvalReport) {
+        let (player, build, target) = (report.player, report.build, report.target);
+        let line = |out: &mut Lines, msg, args: &[&dyn Display]| {
+            out.push(format!("  {}", self.text(msg, args)));
+        };
+
+        out.blank();
+        out.push(self.text(Msg::BreakdownTitle, &[]));
+        out.blank();
+        out.push(self.text(Msg::PlayerStats, &[]));
+        line(out, Msg::CombatLevel, &[&combat_level(report.skills)]);
+        let stance = build
+            .stance
+            .bonuses(build.combat, build.powered_staff.is_some());
+        line(
+            out,
+            Msg::StanceLine,
+            &[
+                &format!("{:?}", build.stance),
+                &stance.accuracy,
+                &stance.strength,
+            ],
+        );
+        match build.combat {
+            Combat::Magic => {
+                line(
+                    out,
+                    Msg::MagicLine,
+                    &[
+                        &player.magic,
+                        &player.potion_magic_boost,
+                        &player.prayer_magic_mult.0,
+                        &player.prayer_magic_mult.1,
+                    ],
+                );
+                match (build.powered_staff, &build.spell) {
+                    (Some(staff), _) => line(out, Msg::SpellBuiltIn, &[&format!("{staff:?}")]),
+                    (None, Some(spell)) => {
+                        line(out, Msg::SpellLine, &[&spell.name, &spell.base_max_hit])
+                    }
+                    (None, None) => {}
+                }
+            }
+            Combat::Ranged => line(
+                out,
+                Msg::RangedLine,
+                &[
+                    &player.ranged,
+                    &player.potion_ranged_boost,
+                    &player.prayer_ranged_attack_mult.0,
+                    &player.prayer_ranged_attack_mult.1,
+                    &player.prayer_ranged_strength_mult.0,
+                    &player.prayer_ranged_strength_mult.1,
+                ],
+            ),
+            Combat::Melee => {
+                line(
+                    out,
+                    Msg::AttackLine,
+                    &[
+                        &player.attack,
+                        &player.potion_attack_boost,
+                        &player.prayer_attack_mult.0,
+                        &player.prayer_attack_mult.1,
+                    ],
+                );
+                line(
+                    out,
+                    Msg::StrengthLine,
+                    &[
+                        &player.strength,
+                        &player.potion_strength_boost,
+                        &player.prayer_strength_mult.0,
+                        &player.prayer_strength_mult.1,
+                    ],
+                );
+            }
+        }
+
+        out.blank();
+        out.push(self.text(Msg::EquipmentBonuses, &[]));
+        line(
+            out,
+            Msg::AttackBonus,
+            &[
+                &format!("{:?}", build.attack_type),
+                &build.bonuses.attack_bonus_for(build.attack_type),
+            ],
+        );
+        match build.combat {
+            Combat::Melee => line(out, Msg::MeleeStrength, &[&build.melee_strength()]),
+            Combat::Ranged => line(out, Msg::RangedStrength, &[&build.bonuses.ranged_strength]),
+            Combat::Magic => line(out, Msg::MagicDamage, &[&build.bonuses.magic_damage]),
+        }
+
+        out.blank();
+        out.push(self.text(Msg::TargetHeading, &[]));
+        line(out, Msg::DefenceLevel, &[&target.defence_level]);
+        line(
+            out,
+            Msg::DefenceBonus,
+            &[
+                &format!("{:?}", build.attack_type),
+                &target.defence_bonuses.defence_bonus_for(build.attack_type),
+            ],
+        );
+        line(out, Msg::Attributes, &[&format!("{:?}", target.attributes)]);
+        out.blank();
+        let effects = &report.result.effects_applied;
+        if effects.is_empty() {
+            out.push(self.text(Msg::EffectsNone, &[]));
+        } else {
+            out.push(self.text(Msg::Effects, &[&effects.join(", ")]));
+        }
+    }
+}
+
+/// Values shown for an engine state in a stage comparison.
+type StateValue = fn(&EngineState) -> String;
+
+/// A stage of a stage comparison, with its heading and the values it can
+/// change.
+type StageRows = (Stage, Msg, &'static [(Msg, StateValue)]);
+
+const STAGE_ROWS: [StageRows; 4] = [
+    (
+        Stage::PostEffectiveLevel,
+        Msg::StageLevels,
+        &[
+            (Msg::EffectiveAttack, |s| s.effective_attack.to_string()),
+            (Msg::EffectiveStrength, |s| s.effective_strength.to_string()),
+        ],
+    ),
+    (
+        Stage::PreRolls,
+        Msg::StagePreRolls,
+        &[(Msg::MagicDamageBonus, |s| s.magic_damage.to_string())],
+    ),
+    (
+        Stage::PostMaxHit,
+        Msg::StageRolls,
+        &[
+            (Msg::AttackRoll, |s| s.attack_roll.to_string()),
+            (Msg::MaxHit, |s| s.max_hit.to_string()),
+            (Msg::Accuracy, |s| format!("{:.2}%", s.accuracy * 100.0)),
+        ],
+    ),
+    (
+        Stage::PostAccuracy,
+        Msg::StageAccuracy,
+        &[
+            (Msg::Accuracy, |s| format!("{:.2}%", s.accuracy * 100.0)),
+            (Msg::DamageMultiplier, |s| {
+                format!("{:.4}", s.expected_damage)
+            }),
+        ],
+    ),
+];
+
+/// Output being built up a line at a time.
+#[derive(Default)]
+struct Lines(Vec<String>);
+
+impl Lines {
+    fn push(&mut self, line: String) {
+        self.0.push(line);
+    }
+
+    fn blank(&mut self) {
+        self.0.push(String::new());
+    }
+
+    fn table(&mut self, table: &Table, theme: &Theme) {
+        self.0.extend(table.render(theme, "  "));
+    }
+
+    fn finish(self) -> String {
+        self.0.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::StageTrace;
+
+    fn report_for<'a>(
+        skills: &'a SkillsInput,
+        player: &'a PlayerState,
+        build: &'a BuildResolved,
+        target: &'a TargetResolved,
+        result: &'a EvalResult,
+    ) -> EvalReport<'a> {
+        EvalReport {
+            skills,
+            player,
+            build,
+            target,
+            result,
+            ranked_styles: &[],
+            ranked_boosts: &[],
+        }
+    }
+
+    /// A catalog that only changes two labels, to show the rest of the
+    /// report follows the catalog.
+    struct Shouting;
+
+    impl Catalog for Shouting {
+        fn text(&self, msg: Msg) -> &str {
+            match msg {
+                Msg::Dps => "SCHADEN PRO SEKUNDE",
+                Msg::Target => "ZIEL: {0}",
+                other => English.text(other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_fill_placeholders() {
+        assert_eq!(fill("{0} ticks ({1}s)", &[&4, &"2.4"]), "4 ticks (2.4s)");
+        assert_eq!(fill("{0} and {0}, {1}", &[&"a"]), "a and a, {1}");
+    }
+
+    #[test]
+    fn test_eval_report_detail_and_catalog() {
+        let skills = SkillsInput {
+            attack: 99,
+            strength: 99,
+            defence: 99,
+            ranged: 99,
+            magic: 99,
+            prayer: 99,
+            hitpoints: 99,
+        };
+        let player = PlayerState::default();
+        let build = BuildResolved::default();
+        let target = TargetResolved::default();
+        let result = EvalResult {
+            dps: 1.5,
+            max_hit: 30,
+            ..EvalResult::default()
+        };
+        let report = report_for(&skills, &player, &build, &target, &result);
+        let render = |catalog: &dyn Catalog, detail| {
+            let theme = Theme {
+                detail,
+                color: false,
+            };
+            Renderer::new(catalog, theme).eval(&report)
+        };
+
+        let standard = render(&English, Detail::Standard);
+        assert!(standard.starts_with("=== DPS Evaluation ==="));
+        assert!(standard.contains("  DPS:            1.5000\n"));
+        assert!(standard.contains("  Attack Speed:   4 ticks (2.4s)"));
+        assert!(!standard.contains("Breakdown"));
+
+        let compact = render(&English, Detail::Compact);
+        assert!(compact.starts_with("Target: Dummy"));
+        assert!(!compact.contains("Attack Roll"));
+
+        assert!(render(&English, Detail::Verbose).contains("=== Breakdown ==="));
+
+        // Labels are padded to the catalog's longest one
+        let shouting = render(&Shouting, Detail::Standard);
+        assert!(shouting.contains("ZIEL: Dummy"));
+        assert!(shouting.contains("  SCHADEN PRO SEKUNDE:   1.5000"));
+        assert!(shouting.contains(&format!("  {:<23}30", "Max Hit:")));
+    }
+
+    #[test]
+    fn test_stage_comparison_marks_divergent_rows() {
+        let result = |max_hit, effects: &[&str], dps| EvalResult {
+            dps,
+            stages: Stage::ALL
+                .iter()
+                .map(|&stage| StageTrace {
+                    stage,
+                    state: EngineState {
+                        max_hit,
+                        ..EngineState::default()
+                    },
+                    effects: match stage {
+                        Stage::PostMaxHit => effects.iter().map(|e| e.to_string()).collect(),
+                        _ => vec![],
+                    },
+                })
+                .collect(),
+            ..EvalResult::default()
+        };
+        let plain = result(25, &[], 1.0);
+        let salve = result(30, &["salve_ei_melee"], 1.2);
+        let renderer = Renderer::new(&English, Theme::default());
+        let labels = ["plain".to_string(), "salve".to_string()];
+        let text = renderer.stage_comparison("maxed", &labels, &[&plain, &salve]);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "maxed, stage by stage:");
+        let words = |value: &str| -> Vec<&str> {
+            let line = lines.iter().find(|line| line.contains(value)).unwrap();
+            line.split_whitespace().collect()
+        };
+        assert!(words("Max Hit").ends_with(&["25", "30", "<-", "differs"]));
+        assert!(words("Effective attack").ends_with(&["0", "0"]));
+        assert!(words("salve_ei_melee").ends_with(&["<-", "differs"]));
+        assert!(words("DPS").ends_with(&["1.0000", "1.2000", "<-", "differs"]));
+    }
+
+    #[test]
+    fn test_table_alignment_and_color() {
+        let mut table =
+            Table::new(&[Align::Left, Align::Right, Align::Left]).header(["Style", "DPS", "Note"]);
+        table.row([
+            Cell::from("Slash"),
+            Cell::painted("10.5000", Paint::Best),
+            Cell::painted("+0.5", Paint::Gain),
+        ]);
+        table.row([Cell::from("Stab"), Cell::from("9.0000")]);
+
+        let plain = table.render(&Theme::default(), "  ");
+        assert_eq!(
+            plain,
+            [
+                "  Style      DPS  Note",
+                "  Slash  10.5000  +0.5",
+                "  Stab    9.0000",
+            ]
+        );
+
+        // Colors wrap the text only, so the columns still line up
+        let theme = Theme {
+            color: true,
+            ..Theme::default()
+        };
+        let colored = table.render(&theme, "");
+        assert_eq!(
+            colored[0],
+            "\x1b[1mStyle\x1b[0m      \x1b[1mDPS\x1b[0m  \x1b[1mNote\x1b[0m"
+        );
+        assert_eq!(
+            colored[1],
+            "Slash  \x1b[1;32m10.5000\x1b[0m  \x1b[32m+0.5\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_delta_paint() {
+        assert_eq!(Paint::for_delta(0.1), Paint::Gain);
+        assert_eq!(Paint::for_delta(-0.1), Paint::Loss);
+        assert_eq!(Paint::for_delta(0.0), Paint::Plain);
+        assert_eq!(Theme::default().paint("x", Paint::Warning), "x");
+    }
+}
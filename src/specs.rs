@@ -0,0 +1,104 @@
+// src/specs.rs
+//
+// Special attacks in the evaluation. Most are described by data/specs:
+// multipliers on the attack roll and max hit, a number of hits that each
+// roll accuracy on their own, and whether they skip the accuracy roll or
+// ignore part of the target's magic defence. Specs the data can't describe
+// register a mechanic here by spec name: a damage range other than 0 to
+// the max hit, or a distribution of their own for hits that depend on each
+// other, like the dragon claws' (which the data's `hits` then doesn't
+// apply to).
+
+/// What a special attack does beyond its data.
+pub struct SpecMechanic {
+    /// Spec name, as in data/specs.
+    pub spec: &'static str,
+    /// Damage range (min, max) of a landed hit from the max hit, when it
+    /// isn't 0 to the max hit.
+    pub damage_range: Option<fn(max_hit: i64) -> (i64, i64)>,
+    /// Average damage of one special attack from the chance a single
+    /// accuracy roll lands and the max hit (multipliers applied), for
+    /// specs whose hits depend on each other.
+    pub expected_damage: Option<fn(accuracy: f64, max_hit: i64) -> f64>,
+}
+
+/// Every registered mechanic.
+pub const MECHANICS: [SpecMechanic; 2] = [
+    SpecMechanic {
+        spec: "Slice and Dice",
+        damage_range: None,
+        expected_damage: Some(dragon_claws),
+    },
+    // Voidwaker: 50% to 150% of the max hit (the data has it always land)
+    SpecMechanic {
+        spec: "Disrupt",
+        damage_range: Some(|max_hit| (max_hit / 2, max_hit * 3 / 2)),
+        expected_damage: None,
+    },
+];
+
+/// The mechanic registered for `spec`, if any.
+pub fn mechanic(spec: &str) -> Option<&'static SpecMechanic> {
+    MECHANICS
+        .iter()
+        .find(|mechanic| mechanic.spec.eq_ignore_ascii_case(spec))
+}
+
+/// Mean of `damage(roll)` over a roll uniform from `low` to `high`
+/// inclusive.
+fn uniform_mean(low: i64, high: i64, damage: impl Fn(i64) -> i64) -> f64 {
+    let high = high.max(low);
+    let total: i64 = (low..=high).map(damage).sum();
+    total as f64 / (high - low + 1) as f64
+}
+
+/// Dragon claws (Slice and Dice): up to four accuracy rolls, stopping at
+/// the first that lands, which sets the damage of it and the hits after:
+/// - 1st lands: 50% to 100% (less 1) of the max hit, then half, a quarter
+///   and a quarter plus 1
+/// - 2nd: 37.5% to 87.5%, then half and half plus 1
+/// - 3rd: 25% to 75%, then that plus 1
+/// - 4th: 25% to 125%
+/// - none: two thirds of the time the last two hits deal 1 each
+pub fn dragon_claws(accuracy: f64, max_hit: i64) -> f64 {
+    let miss = 1.0 - accuracy;
+    let first = uniform_mean(max_hit / 2, max_hit - 1, |hit| {
+        let quarter = hit / 2 / 2;
+        hit + hit / 2 + quarter + quarter + 1
+    });
+    let second = uniform_mean(max_hit * 3 / 8, max_hit * 7 / 8, |hit| {
+        hit + hit / 2 + hit / 2 + 1
+    });
+    let third = uniform_mean(max_hit / 4, max_hit * 3 / 4, |hit| hit + hit + 1);
+    let fourth = uniform_mean(max_hit / 4, max_hit * 5 / 4, |hit| hit);
+    accuracy * first
+        + miss * accuracy * second
+        + miss.powi(2) * accuracy * third
+        + miss.powi(3) * accuracy * fourth
+        + miss.powi(4) * 2.0 * 2.0 / 3.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dragon_claws_cascade() {
+        // Always the first hit: 20-39, then 10-19, 5-9 and 6-10
+        let first: f64 = (20..40).map(|h| h + h / 2 + 2 * (h / 4) + 1).sum::<i64>() as f64 / 20.0;
+        assert!((dragon_claws(1.0, 40) - first).abs() < 1e-9);
+        // Never: 1 + 1 two thirds of the time
+        assert!((dragon_claws(0.0, 40) - 4.0 / 3.0).abs() < 1e-9);
+        // Missing the first rolls shift the damage later, not away
+        assert!(dragon_claws(0.5, 40) > 0.5 * first);
+    }
+
+    #[test]
+    fn test_mechanic_lookup() {
+        assert!(mechanic("Slice and Dice").is_some());
+        assert!(mechanic("slice and dice").is_some());
+        assert!(mechanic("Puncture").is_none());
+        let disrupt = mechanic("Disrupt").unwrap();
+        assert_eq!(disrupt.damage_range.map(|range| range(50)), Some((25, 75)));
+    }
+}
@@ -0,0 +1,96 @@
+// src/ticks.rs
+//
+// Game time. The engine counts whole game ticks; seconds, `Duration`s and
+// clock-style text ("1:23.4") are derived from them for rates and display.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+/// Length of one game tick in seconds.
+pub const SECONDS_PER_TICK: f64 = 0.6;
+
+const MILLIS_PER_TICK: u64 = 600;
+
+/// A number of game ticks. Serializes as a plain number.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Ticks(pub u32);
+
+impl Ticks {
+    pub const MINUTE: Ticks = Ticks(100);
+    pub const HOUR: Ticks = Ticks(6000);
+
+    /// Whole minutes of game time, saturating.
+    pub fn from_minutes(minutes: u32) -> Self {
+        Ticks(minutes.saturating_mul(Self::MINUTE.0))
+    }
+
+    /// Ticks that fit in a duration, rounded down.
+    pub fn from_duration(duration: Duration) -> Self {
+        let ticks = duration.as_millis() / u128::from(MILLIS_PER_TICK);
+        Ticks(u32::try_from(ticks).unwrap_or(u32::MAX))
+    }
+
+    pub fn as_secs_f64(self) -> f64 {
+        f64::from(self.0) * SECONDS_PER_TICK
+    }
+
+    pub fn as_hours_f64(self) -> f64 {
+        f64::from(self.0) / f64::from(Self::HOUR.0)
+    }
+
+    pub fn as_duration(self) -> Duration {
+        Duration::from_millis(u64::from(self.0) * MILLIS_PER_TICK)
+    }
+}
+
+impl From<u8> for Ticks {
+    fn from(ticks: u8) -> Self {
+        Ticks(u32::from(ticks))
+    }
+}
+
+impl From<Ticks> for Duration {
+    fn from(ticks: Ticks) -> Self {
+        ticks.as_duration()
+    }
+}
+
+/// Clock time: "m:ss.s", or "h:mm:ss.s" from an hour up.
+impl fmt::Display for Ticks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // A tick is exactly 6 tenths of a second
+        let tenths = u64::from(self.0) * 6;
+        let (hours, minutes) = (tenths / 36_000, tenths / 600 % 60);
+        let (seconds, tenth) = (tenths / 10 % 60, tenths % 10);
+        if hours > 0 {
+            write!(f, "{hours}:{minutes:02}:{seconds:02}.{tenth}")
+        } else {
+            write!(f, "{minutes}:{seconds:02}.{tenth}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(Ticks(4).as_duration(), Duration::from_millis(2400));
+        assert!((Ticks(5).as_secs_f64() - 3.0).abs() < 1e-12);
+        assert_eq!(Ticks::from_minutes(60), Ticks::HOUR);
+        assert_eq!(Ticks::from_duration(Duration::from_millis(1799)), Ticks(2));
+        assert!((Ticks(3000).as_hours_f64() - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_clock_format() {
+        assert_eq!(Ticks(4).to_string(), "0:02.4");
+        assert_eq!(Ticks(139).to_string(), "1:23.4");
+        assert_eq!(Ticks::HOUR.to_string(), "1:00:00.0");
+    }
+}
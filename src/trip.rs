@@ -0,0 +1,693 @@
+// src/trip.rs
+//
+// Tick-based trip simulation: runs a fixed setup for a whole trip while
+// potion boosts decay and drained target stats regenerate, re-drinking
+// according to a policy, and reports the average DPS and how many doses the
+// policy used. Given the distance to the target, ranged and magic hits land
+// only once their projectile arrives, so damage dealt by a deadline (a
+// healer to kill before it heals, a shield about to come back) leaves out
+// the attacks still in flight. How often the target hits back (tanked,
+// flinched or from a safespot) gives the damage the player takes.
+
+use crate::data::{apply_pre_fight_target, resolve_player, resolve_player_input};
+use crate::effects::{default_registry, EffectContext, EffectRegistry};
+use crate::formulas::{attack_interval, evaluate_with};
+use crate::model::{
+    BoostSpec, BuildResolved, Combat, EvalContext, PlayerInput, PlayerState, PotionVariant,
+    TargetResolved,
+};
+use crate::ticks::{Ticks, SECONDS_PER_TICK};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// Boosted (and drained) stats move one level back toward base this often,
+/// for players and NPCs alike.
+pub const STAT_RESTORE_TICKS: u32 = 100;
+
+/// How long a divine potion holds its boost (5 minutes).
+pub const DIVINE_DURATION_TICKS: u32 = 500;
+
+/// When to drink another dose of the player's potion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RepotPolicy {
+    /// Drink once at the start of the trip and let it run out.
+    Once,
+    /// Re-drink as soon as the largest remaining potion boost falls below
+    /// this many levels.
+    BelowBoost(i32),
+}
+
+/// How often the target gets to attack the player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Exposure {
+    /// Fighting it in the open: it attacks at its own speed.
+    Tanked,
+    /// Flinching: it gets an attack in only once every this many ticks (at
+    /// most as often as its own speed allows).
+    Flinched(u32),
+    /// From a safespot it can't reach: it never attacks.
+    Safespot,
+}
+
+impl Exposure {
+    /// Ticks between the target's attacks, or None if it never attacks.
+    pub fn attack_period(self, target: &TargetResolved) -> Option<u32> {
+        if target.attack_speed == 0 || target.max_hit == 0 {
+            return None;
+        }
+        let speed = u32::from(target.attack_speed);
+        match self {
+            Exposure::Tanked => Some(speed),
+            Exposure::Flinched(every) => Some(every.max(speed)),
+            Exposure::Safespot => None,
+        }
+    }
+}
+
+/// Outcome of a simulated trip.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TripSummary {
+    pub ticks: Ticks,
+    /// Attacks made, the first on the trip's first tick (casts, for a
+    /// spell's rune cost).
+    pub attacks: u32,
+    /// Doses drunk, including the one at the start.
+    pub doses: u32,
+    pub doses_per_hour: f64,
+    /// Damage landed over the trip, per second.
+    pub avg_dps: f64,
+    /// Damage landed by the trip's end.
+    pub total_damage: f64,
+    /// Damage from attacks made during the trip that hadn't landed by its
+    /// end.
+    #[serde(default)]
+    pub damage_in_flight: f64,
+    /// Ticks from each attack until its hit lands.
+    #[serde(default)]
+    pub hit_delay_ticks: u32,
+    /// Tick the landed damage first reaches the target's hitpoints, on
+    /// average; none if it doesn't within the trip.
+    #[serde(default)]
+    pub first_kill_tick: Option<Ticks>,
+    /// Attacks the target got in, the first one attack period into the
+    /// trip.
+    #[serde(default)]
+    pub target_attacks: u32,
+    /// Damage the player takes over the trip: half the target's max hit
+    /// per attack, as if every attack landed.
+    #[serde(default)]
+    pub damage_taken: f64,
+    /// Target defence after pre-fight drains, at the end of the trip, and
+    /// averaged over it.
+    pub start_target_defence: u16,
+    pub end_target_defence: u16,
+    pub avg_target_defence: f64,
+}
+
+/// Ticks from an attack until its hit lands on a target `distance` tiles
+/// away. Melee hits land at once; projectiles take a tick to leave and
+/// then travel, spells slowest and thrown weapons fastest.
+pub fn hit_delay(build: &BuildResolved, distance: u8) -> u32 {
+    let distance = u32::from(distance);
+    match build.combat {
+        Combat::Melee => 0,
+        Combat::Ranged if build.thrown => 1 + distance / 6,
+        Combat::Ranged => 1 + (3 + distance) / 6,
+        Combat::Magic => 1 + (1 + distance) / 3,
+    }
+}
+
+/// Simulate `duration` of continuous attacking with a fixed build.
+///
+/// The trip starts right after drinking (and after any pre-fight actions),
+/// so the first stat restore happens `STAT_RESTORE_TICKS` in. Re-drinking
+/// tops each stat up to the potion's boost without removing larger boosts
+/// from pre-fight actions. No dose is drunk on the trip's final tick.
+///
+/// `target` is taken before pre-fight actions; their defence drains are
+/// applied here and regenerate toward the target's base defence.
+///
+/// With a `distance` in tiles, each attack's damage lands `hit_delay`
+/// ticks after it's made, and what hasn't landed by the trip's end is
+/// reported apart from the total.
+///
+/// `exposure` sets how often the target attacks back. The player's defence
+/// isn't rolled, so `damage_taken` is what an undefended player would take.
+pub fn simulate_trip(
+    input: &PlayerInput,
+    build: &BuildResolved,
+    base_target: &TargetResolved,
+    duration: Ticks,
+    policy: RepotPolicy,
+    distance: Option<u8>,
+    exposure: Exposure,
+) -> TripSummary {
+    let ticks = duration.0;
+    let delay = distance.map_or(0, |distance| hit_delay(build, distance));
+    let potion = resolve_player(&input.skills, &input.prayers, &input.boosts, &input.flags);
+    let has_potion = potion_boosts(&potion).iter().any(|&b| b > 0);
+    let divine_held = divine_held(input, &potion);
+    let divine = divine_held.contains(&true);
+
+    let mut player = resolve_player_input(input);
+    let mut target = base_target.clone();
+    apply_pre_fight_target(&mut target, &input.pre_fight);
+    let start_target_defence = target.defence_level;
+    let mut defence_ticks = 0.0;
+
+    // Only stats change during the trip, so effect conditions on the
+    // player's situation and the target are settled once
+    let registry = default_registry().specialize(&EffectContext::for_situation(&player, &target));
+
+    let mut doses = u32::from(has_potion);
+    // Tick at which the current divine dose wears off
+    let mut divine_expires = (has_potion && divine).then_some(DIVINE_DURATION_TICKS);
+    let mut dps = dps_for(&player, build, &target, distance, &registry);
+    let mut total_damage = 0.0;
+    let mut damage_in_flight = 0.0;
+    let mut first_kill_tick = None;
+
+    for tick in 1..=ticks {
+        // Damage of this tick's attacks, landing `delay` ticks later
+        if tick + delay <= ticks {
+            total_damage += dps * SECONDS_PER_TICK;
+            if first_kill_tick.is_none() && total_damage >= f64::from(base_target.hitpoints) {
+                first_kill_tick = Some(Ticks(tick + delay));
+            }
+        } else {
+            damage_in_flight += dps * SECONDS_PER_TICK;
+        }
+        defence_ticks += target.defence_level as f64;
+
+        let mut changed = false;
+        if divine_expires == Some(tick) {
+            for (boost, held) in potion_boosts_mut(&mut player).into_iter().zip(divine_held) {
+                if held {
+                    *boost = (*boost).min(0);
+                }
+            }
+            divine_expires = None;
+            changed = true;
+        }
+        if tick % STAT_RESTORE_TICKS == 0 {
+            let held = if divine_expires.is_some() {
+                divine_held
+            } else {
+                [false; 5]
+            };
+            restore_one_level(&mut player, held);
+            let base = base_target.defence_level;
+            target.defence_level = match target.defence_level.cmp(&base) {
+                Ordering::Less => target.defence_level + 1,
+                Ordering::Greater => target.defence_level - 1,
+                Ordering::Equal => base,
+            };
+            changed = true;
+        }
+        if !changed {
+            continue;
+        }
+
+        if let RepotPolicy::BelowBoost(threshold) = policy {
+            let remaining = potion_boosts(&player).into_iter().max().unwrap_or(0);
+            if has_potion && remaining < threshold && tick < ticks {
+                for (boost, full) in potion_boosts_mut(&mut player)
+                    .into_iter()
+                    .zip(potion_boosts(&potion))
+                {
+                    *boost = (*boost).max(full);
+                }
+                if divine {
+                    divine_expires = Some(tick + DIVINE_DURATION_TICKS);
+                }
+                doses += 1;
+            }
+        }
+        dps = dps_for(&player, build, &target, distance, &registry);
+    }
+
+    let interval = attack_interval(build.attack_speed, build.combat, build.stance).max(1);
+    let target_attacks = exposure
+        .attack_period(base_target)
+        .map_or(0, |period| ticks / period);
+    TripSummary {
+        ticks: duration,
+        attacks: ticks.div_ceil(u32::from(interval)),
+        doses,
+        doses_per_hour: if ticks > 0 {
+            doses as f64 / duration.as_hours_f64()
+        } else {
+            0.0
+        },
+        avg_dps: if ticks > 0 {
+            total_damage / duration.as_secs_f64()
+        } else {
+            dps
+        },
+        total_damage,
+        damage_in_flight,
+        hit_delay_ticks: delay,
+        first_kill_tick,
+        target_attacks,
+        damage_taken: f64::from(target_attacks) * f64::from(base_target.max_hit) / 2.0,
+        start_target_defence,
+        end_target_defence: target.defence_level,
+        avg_target_defence: if ticks > 0 {
+            defence_ticks / ticks as f64
+        } else {
+            start_target_defence as f64
+        },
+    }
+}
+
+/// Potion boosts: attack, strength, defence, ranged and magic.
+fn potion_boosts(player: &PlayerState) -> [i32; 5] {
+    [
+        player.potion_attack_boost,
+        player.potion_strength_boost,
+        player.potion_defence_boost,
+        player.potion_ranged_boost,
+        player.potion_magic_boost,
+    ]
+}
+
+fn potion_boosts_mut(player: &mut PlayerState) -> [&mut i32; 5] {
+    [
+        &mut player.potion_attack_boost,
+        &mut player.potion_strength_boost,
+        &mut player.potion_defence_boost,
+        &mut player.potion_ranged_boost,
+        &mut player.potion_magic_boost,
+    ]
+}
+
+/// Which of the `potion_boosts` stats a divine potion boosts: those it
+/// holds until its timer runs out, rather than letting them decay.
+fn divine_held(input: &PlayerInput, potion: &PlayerState) -> [bool; 5] {
+    let divine = |spec: &Option<BoostSpec>| {
+        spec.as_ref()
+            .is_some_and(|spec| spec.variant == PotionVariant::Divine)
+    };
+    let (melee, ranged, magic) = (
+        divine(&input.boosts.melee),
+        divine(&input.boosts.ranged),
+        divine(&input.boosts.magic),
+    );
+    let mut held = [melee, melee, melee, ranged, magic];
+    for (held, boost) in held.iter_mut().zip(potion_boosts(potion)) {
+        *held &= boost > 0;
+    }
+    held
+}
+
+/// Move every boosted or drained stat one level back toward base, except
+/// the `held` ones a divine potion is holding.
+fn restore_one_level(player: &mut PlayerState, held: [bool; 5]) {
+    for (boost, held) in potion_boosts_mut(player).into_iter().zip(held) {
+        if !held {
+            *boost -= boost.signum();
+        }
+    }
+}
+
+fn dps_for(
+    player: &PlayerState,
+    build: &BuildResolved,
+    target: &TargetResolved,
+    distance: Option<u8>,
+    registry: &EffectRegistry,
+) -> f64 {
+    let ctx = EvalContext {
+        player,
+        build,
+        target,
+        ordering: None,
+        adjacent_targets: 0,
+        distance,
+    };
+    evaluate_with(&ctx, registry).dps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{BoostsInput, FlagsInput, PreFightAction, SkillsInput};
+    use std::collections::HashMap;
+
+    fn player(potion: Option<BoostSpec>) -> PlayerInput {
+        PlayerInput {
+            schema_version: 1,
+            skills: SkillsInput {
+                attack: 99,
+                strength: 99,
+                defence: 99,
+                ranged: 99,
+                magic: 99,
+                prayer: 99,
+                hitpoints: 99,
+            },
+            prayers: HashMap::new(),
+            boosts: BoostsInput {
+                melee: potion,
+                ..BoostsInput::default()
+            },
+            flags: FlagsInput::default(),
+            pre_fight: vec![],
+        }
+    }
+
+    #[test]
+    fn test_repot_policy_doses_and_dps() {
+        let mut build = BuildResolved::default();
+        build.bonuses.melee_strength = 100;
+        let target = TargetResolved::default();
+        let input = player(Some(BoostSpec::new("super_combat")));
+
+        // Super combat at 99 is +19; it runs out after 19 minutes
+        let once = simulate_trip(
+            &input,
+            &build,
+            &target,
+            Ticks::HOUR,
+            RepotPolicy::Once,
+            None,
+            Exposure::Tanked,
+        );
+        assert_eq!(once.doses, 1);
+
+        // Dropping below +15 happens every 5 minutes
+        let repot = simulate_trip(
+            &input,
+            &build,
+            &target,
+            Ticks::HOUR,
+            RepotPolicy::BelowBoost(15),
+            None,
+            Exposure::Tanked,
+        );
+        assert_eq!(repot.doses, 12);
+        assert!((repot.doses_per_hour - 12.0).abs() < 1e-9);
+        assert!(repot.avg_dps > once.avg_dps);
+
+        // Without a potion the policy never drinks
+        let none = simulate_trip(
+            &player(None),
+            &build,
+            &target,
+            Ticks::HOUR,
+            RepotPolicy::BelowBoost(15),
+            None,
+            Exposure::Tanked,
+        );
+        assert_eq!(none.doses, 0);
+        assert!(none.avg_dps < once.avg_dps);
+    }
+
+    #[test]
+    fn test_repot_policy_covers_ranging_potions() {
+        let mut build = BuildResolved {
+            combat: Combat::Ranged,
+            attack_type: crate::model::AttackType::Ranged,
+            ..BuildResolved::default()
+        };
+        build.bonuses.ranged_strength = 100;
+        let target = TargetResolved::default();
+        let mut input = player(None);
+        input.boosts.ranged = Some(BoostSpec::new("ranging"));
+        let trip = |policy| {
+            simulate_trip(
+                &input,
+                &build,
+                &target,
+                Ticks::HOUR,
+                policy,
+                None,
+                Exposure::Tanked,
+            )
+        };
+
+        // Ranging at 99 is +13; dropping below +10 happens every 4 minutes
+        let once = trip(RepotPolicy::Once);
+        assert_eq!(once.doses, 1);
+        let repot = trip(RepotPolicy::BelowBoost(10));
+        assert!(repot.doses > 1);
+        assert_eq!(repot.doses, 15);
+        assert!(repot.avg_dps > once.avg_dps);
+    }
+
+    #[test]
+    fn test_divine_holds_boost_then_drops() {
+        let mut build = BuildResolved::default();
+        build.bonuses.melee_strength = 100;
+        let target = TargetResolved::default();
+        let spec = BoostSpec {
+            variant: PotionVariant::Divine,
+            ..BoostSpec::new("super_combat")
+        };
+        let input = player(Some(spec));
+        let full = simulate_trip(
+            &input,
+            &build,
+            &target,
+            Ticks(0),
+            RepotPolicy::Once,
+            None,
+            Exposure::Tanked,
+        )
+        .avg_dps;
+
+        // Full boost for the whole 5 minutes
+        let five = simulate_trip(
+            &input,
+            &build,
+            &target,
+            Ticks(500),
+            RepotPolicy::Once,
+            None,
+            Exposure::Tanked,
+        );
+        assert!((five.avg_dps - full).abs() < 1e-9);
+
+        // One sip every 5 minutes keeps it at full all hour
+        let hour = simulate_trip(
+            &input,
+            &build,
+            &target,
+            Ticks::HOUR,
+            RepotPolicy::BelowBoost(19),
+            None,
+            Exposure::Tanked,
+        );
+        assert_eq!(hour.doses, 12);
+        assert!((hour.avg_dps - full).abs() < 1e-9);
+
+        // Left alone it is gone after 5 minutes, unlike a regular potion
+        let once = simulate_trip(
+            &input,
+            &build,
+            &target,
+            Ticks(1000),
+            RepotPolicy::Once,
+            None,
+            Exposure::Tanked,
+        );
+        let unboosted = simulate_trip(
+            &player(None),
+            &build,
+            &target,
+            Ticks(0),
+            RepotPolicy::Once,
+            None,
+            Exposure::Tanked,
+        );
+        assert!((once.avg_dps - (full + unboosted.avg_dps) / 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_divine_holds_only_the_stats_it_boosts() {
+        let divine_ranging = BoostSpec {
+            variant: PotionVariant::Divine,
+            ..BoostSpec::new("ranging")
+        };
+        let mut build = BuildResolved {
+            combat: Combat::Ranged,
+            attack_type: crate::model::AttackType::Ranged,
+            ..BuildResolved::default()
+        };
+        build.bonuses.ranged_strength = 100;
+        let target = TargetResolved::default();
+        let mut input = player(None);
+        input.boosts.ranged = Some(divine_ranging);
+        let trip = |input: &PlayerInput, build: &BuildResolved, ticks| {
+            simulate_trip(
+                input,
+                build,
+                &target,
+                Ticks(ticks),
+                RepotPolicy::Once,
+                None,
+                Exposure::Tanked,
+            )
+            .avg_dps
+        };
+
+        // A divine ranging potion holds for 5 minutes, then is gone
+        let full = trip(&input, &build, 0);
+        assert!((trip(&input, &build, 500) - full).abs() < 1e-9);
+        let unboosted = trip(&player(None), &build, 0);
+        assert!((trip(&input, &build, 1000) - (full + unboosted) / 2.0).abs() < 1e-9);
+
+        // It doesn't hold a regular potion's melee boosts, which decay
+        let mut melee = BuildResolved::default();
+        melee.bonuses.melee_strength = 100;
+        let regular = player(Some(BoostSpec::new("super_combat")));
+        let mut both = regular.clone();
+        both.boosts.ranged = input.boosts.ranged.clone();
+        assert!((trip(&both, &melee, 1000) - trip(&regular, &melee, 1000)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_target_defence_regenerates() {
+        let build = BuildResolved::default();
+        let target = TargetResolved {
+            defence_level: 100,
+            ..TargetResolved::default()
+        };
+        let mut input = player(None);
+        input.pre_fight = vec![PreFightAction::DragonWarhammer];
+
+        // 100 -> 70, back one level a minute
+        let short = simulate_trip(
+            &input,
+            &build,
+            &target,
+            Ticks(1000),
+            RepotPolicy::Once,
+            None,
+            Exposure::Tanked,
+        );
+        assert_eq!(short.start_target_defence, 70);
+        assert_eq!(short.end_target_defence, 80);
+        assert!((short.avg_target_defence - 74.5).abs() < 1e-9);
+
+        let long = simulate_trip(
+            &input,
+            &build,
+            &target,
+            Ticks::HOUR,
+            RepotPolicy::Once,
+            None,
+            Exposure::Tanked,
+        );
+        assert_eq!(long.end_target_defence, 100);
+
+        let undrained = simulate_trip(
+            &player(None),
+            &build,
+            &target,
+            Ticks::HOUR,
+            RepotPolicy::Once,
+            None,
+            Exposure::Tanked,
+        );
+        assert!(long.avg_dps > undrained.avg_dps);
+    }
+
+    #[test]
+    fn test_hit_delay_by_projectile_and_distance() {
+        let ranged = |thrown| BuildResolved {
+            combat: Combat::Ranged,
+            thrown,
+            ..BuildResolved::default()
+        };
+        let magic = BuildResolved {
+            combat: Combat::Magic,
+            ..BuildResolved::default()
+        };
+        assert_eq!(hit_delay(&BuildResolved::default(), 10), 0);
+        assert_eq!(hit_delay(&ranged(false), 1), 1);
+        assert_eq!(hit_delay(&ranged(false), 3), 2);
+        assert_eq!(hit_delay(&ranged(true), 5), 1);
+        assert_eq!(hit_delay(&ranged(true), 6), 2);
+        assert_eq!(hit_delay(&magic, 1), 1);
+        assert_eq!(hit_delay(&magic, 2), 2);
+        assert_eq!(hit_delay(&magic, 8), 4);
+    }
+
+    #[test]
+    fn test_damage_in_flight_misses_the_deadline() {
+        let mut build = BuildResolved {
+            combat: Combat::Ranged,
+            attack_type: crate::model::AttackType::Ranged,
+            ..BuildResolved::default()
+        };
+        build.bonuses.ranged_strength = 100;
+        let target = TargetResolved {
+            hitpoints: 20,
+            ..TargetResolved::default()
+        };
+        let trip = |distance| {
+            simulate_trip(
+                &player(None),
+                &build,
+                &target,
+                Ticks(50),
+                RepotPolicy::Once,
+                distance,
+                Exposure::Tanked,
+            )
+        };
+        let adjacent = trip(None);
+        assert_eq!(adjacent.hit_delay_ticks, 0);
+        assert_eq!(adjacent.damage_in_flight, 0.0);
+
+        // 9 tiles: 3 ticks of attacks are still travelling at the end
+        let far = trip(Some(9));
+        assert_eq!(far.hit_delay_ticks, 3);
+        let per_tick = adjacent.total_damage / 50.0;
+        assert!((far.damage_in_flight - 3.0 * per_tick).abs() < 1e-9);
+        assert!((far.total_damage + far.damage_in_flight - adjacent.total_damage).abs() < 1e-9);
+        assert!(far.avg_dps < adjacent.avg_dps);
+        // The kill lands just as late
+        let near = adjacent.first_kill_tick.unwrap();
+        assert_eq!(far.first_kill_tick, Some(Ticks(near.0 + 3)));
+    }
+
+    #[test]
+    fn test_exposure_sets_how_often_the_target_hits_back() {
+        let build = BuildResolved::default();
+        let target = TargetResolved {
+            max_hit: 20,
+            attack_speed: 4,
+            ..TargetResolved::default()
+        };
+        let trip = |exposure| {
+            simulate_trip(
+                &player(None),
+                &build,
+                &target,
+                Ticks::MINUTE,
+                RepotPolicy::Once,
+                None,
+                exposure,
+            )
+        };
+        // 100 ticks: 25 attacks at its own speed averaging 10 each
+        let tanked = trip(Exposure::Tanked);
+        assert_eq!(tanked.target_attacks, 25);
+        assert!((tanked.damage_taken - 250.0).abs() < 1e-9);
+        let flinched = trip(Exposure::Flinched(10));
+        assert_eq!(flinched.target_attacks, 10);
+        assert!((flinched.damage_taken - 100.0).abs() < 1e-9);
+        // No faster than its own speed, and never from a safespot
+        assert_eq!(trip(Exposure::Flinched(1)).target_attacks, 25);
+        assert_eq!(trip(Exposure::Safespot).damage_taken, 0.0);
+        // Neither changes the damage dealt
+        assert_eq!(flinched.total_damage, tanked.total_damage);
+        // A target that doesn't attack never hits back
+        assert_eq!(
+            Exposure::Tanked.attack_period(&TargetResolved::default()),
+            None
+        );
+    }
+}
@@ -4,7 +4,22 @@
 
 mod common;
 
-use common::{assert_result_matches, eval_from_fixtures, Expected};
+use common::{
+    assert_result_matches, eval_from_fixtures, load_store, read_fixture, resolve_fixtures, Expected,
+};
+use osrs_sim::data::{resolve_player_input, DataStore, SpecData, ITEMS_DUMP, MONSTERS_DUMP};
+use osrs_sim::determinism::{batch_hash, DETERMINISM_SEED};
+use osrs_sim::effects::{default_registry, parse_effects, EffectRegistry, Stage};
+use osrs_sim::formulas::evaluate_with;
+use osrs_sim::fuzz::{FuzzOutcome, Fuzzer};
+use osrs_sim::optimizer::{optimize_gear, AccountConstraints};
+use osrs_sim::ordering::{self, OrderingProfile, Placement};
+use osrs_sim::{
+    evaluate, validate_context, AttackType, BuildInput, BuildResolved, Combat, Confidence,
+    ContextError, DataError, EquipmentInput, EvalContext, EvalResult, PlayerInput, Resolver,
+    Stance, StyleInput, TargetInput, TargetResolved, Ticks,
+};
+use std::path::Path;
 
 /// Voidwaker + Bandos vs TzTok-Jad
 /// - 99 all stats, super strength potion, no prayer
@@ -66,3 +81,2032 @@ fn voidwaker_bandos_vs_jad_aggressive() {
 
     assert_result_matches(&result, &expected, "voidwaker_bandos_vs_jad_aggressive");
 }
+
+/// Voidwaker + Bandos vs TzTok-Jad after a DBA spec and one landed DWH spec
+/// - 99 all stats, no potions, no prayer
+/// - Rampage: 99 -> 90 Attack, 99 -> 118 Strength (10 + 36/4)
+/// - DWH: Jad Defence 480 -> 336
+#[test]
+fn voidwaker_bandos_vs_jad_dba_dwh() {
+    let result = eval_from_fixtures(
+        "players/maxed_dba_dwh.json",
+        "builds/voidwaker_bandos_slash_accurate.json",
+        "targets/tztok_jad.json",
+    );
+
+    let expected = Expected {
+        dps: 2.058,
+        max_hit: 30,
+        accuracy: 0.3293,
+    };
+
+    assert_result_matches(&result, &expected, "voidwaker_bandos_vs_jad_dba_dwh");
+}
+
+/// Voidwaker + Bandos + Salve amulet (ei) vs Vorkath (undead)
+/// - 99 all stats, no potions, no prayer
+/// - Salve (ei): attack roll 15840 -> 19008, max hit 25 -> 30
+#[test]
+fn voidwaker_salve_ei_vs_vorkath() {
+    let result = eval_from_fixtures(
+        "players/maxed_no_boosts.json",
+        "builds/voidwaker_bandos_salve_ei_slash_accurate.json",
+        "targets/vorkath.json",
+    );
+
+    let expected = Expected {
+        dps: 1.549,
+        max_hit: 30,
+        accuracy: 0.2478,
+    };
+
+    assert_result_matches(&result, &expected, "voidwaker_salve_ei_vs_vorkath");
+    assert_eq!(
+        result.effects_applied,
+        vec!["salve_ei_melee", "salve_ei_melee_accuracy"]
+    );
+}
+
+/// Corrupted Voidwaker (league cosmetic) resolves to the Voidwaker, so the
+/// numbers and the Salve (ei) effects match the base build exactly.
+#[test]
+fn corrupted_voidwaker_matches_base_item() {
+    let base = eval_from_fixtures(
+        "players/maxed_no_boosts.json",
+        "builds/voidwaker_bandos_salve_ei_slash_accurate.json",
+        "targets/vorkath.json",
+    );
+    let variant = eval_from_fixtures(
+        "players/maxed_no_boosts.json",
+        "builds/corrupted_voidwaker_bandos_salve_ei_slash_accurate.json",
+        "targets/vorkath.json",
+    );
+
+    assert_eq!(variant.max_hit, base.max_hit);
+    assert_eq!(variant.attack_roll, base.attack_roll);
+    assert_eq!(variant.effects_applied, base.effects_applied);
+    assert!((variant.dps - base.dps).abs() < 1e-12);
+}
+
+/// A variant of a weapon that's checked by ID (the Zaryte crossbow's bolt
+/// boost) resolves to the base weapon's ID, so it keeps the effect.
+#[test]
+fn weapon_variants_resolve_to_the_base_weapon_id() {
+    let (zaryte_crossbow, ruby_bolts_e, ornament) = (26374, 9242, 999_999);
+    let mut store = load_store();
+    let mut variant = store.get_item(zaryte_crossbow).unwrap().clone();
+    variant.id = ornament;
+    variant.name = "Zaryte crossbow (or)".to_string();
+    store.items.insert(ornament, variant);
+    store.variants.insert(ornament, zaryte_crossbow);
+
+    let player = resolve_player_input(&read_fixture("players/maxed_no_boosts.json"));
+    let resolver = Resolver::new(&store);
+    let target = resolver
+        .resolve_target(&read_fixture("targets/dummy.json"))
+        .unwrap();
+    let eval = |weapon| {
+        let build = resolver
+            .resolve_build(&BuildInput {
+                schema_version: 1,
+                equipment: EquipmentInput {
+                    weapon,
+                    ammo: Some(ruby_bolts_e),
+                    ..Default::default()
+                },
+                style: StyleInput {
+                    combat: Combat::Ranged,
+                    attack_type: AttackType::Ranged,
+                    stance: Stance::Rapid,
+                    special_attack: false,
+                    manual_cast: false,
+                    runes: vec![],
+                    spell: None,
+                },
+                weapon_config: Default::default(),
+            })
+            .unwrap();
+        assert_eq!(build.weapon_id, zaryte_crossbow);
+        evaluate(&EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+        .dps
+    };
+    assert_eq!(eval(ornament), eval(zaryte_crossbow));
+}
+
+/// Ammo checks follow the weapon: ballistas fire javelins, not bolts, and
+/// blowpipes and crystal bows carry their own ammo.
+#[test]
+fn slot_validation_ammo_edge_cases() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let violations = |weapon: u32, ammo: Option<u32>, shield: Option<u32>| {
+        resolver
+            .slot_violations(&EquipmentInput {
+                weapon,
+                ammo,
+                shield,
+                ..EquipmentInput::default()
+            })
+            .expect("Failed to validate slots")
+    };
+    let (heavy_ballista, dragon_javelin, dragon_bolts) = (19481, 19484, 21905);
+    let (toxic_blowpipe, bow_of_faerdhinen, twisted_bow) = (12926, 25865, 20997);
+    let (crystal_halberd, dragon_defender, abyssal_whip) = (23987, 12954, 4151);
+
+    assert!(violations(heavy_ballista, Some(dragon_javelin), None).is_empty());
+    assert_eq!(
+        violations(heavy_ballista, Some(dragon_bolts), None).len(),
+        1
+    );
+    assert_eq!(violations(twisted_bow, None, None).len(), 1);
+    assert!(violations(toxic_blowpipe, None, None).is_empty());
+    assert!(violations(bow_of_faerdhinen, Some(dragon_bolts), None).is_empty());
+
+    // Halberds are two-handed and reach two tiles
+    assert_eq!(
+        violations(crystal_halberd, None, Some(dragon_defender)).len(),
+        1
+    );
+    assert!(violations(abyssal_whip, None, Some(dragon_defender)).is_empty());
+    let range = |id| store.ranges.range_of(store.get_item(id).unwrap());
+    assert_eq!(range(crystal_halberd), 2);
+    assert_eq!(range(abyssal_whip), 1);
+}
+
+/// Crossbows fire bolts up to their own tier (data/ammo.json), even if an
+/// item's ammunition list says otherwise, and enchanted bolts carry their
+/// effect into the resolved build.
+#[test]
+fn crossbow_tiers_gate_bolts_and_enchanted_bolts_resolve_their_effect() {
+    let (rune_crossbow, zaryte_crossbow) = (9185, 26374);
+    let (runite_bolts, dragon_bolts, ruby_bolts, ruby_bolts_e) = (9144, 21905, 9339, 9242);
+
+    let mut store = load_store();
+    store
+        .items
+        .get_mut(&rune_crossbow)
+        .and_then(|item| item.ammunition.as_mut())
+        .expect("rune crossbow fires ammo")
+        .push(dragon_bolts);
+    let resolver = Resolver::new(&store);
+    let equipment = |weapon: u32, ammo: u32| EquipmentInput {
+        weapon,
+        ammo: Some(ammo),
+        ..EquipmentInput::default()
+    };
+    let violations = |weapon: u32, ammo: u32| {
+        resolver
+            .slot_violations(&equipment(weapon, ammo))
+            .expect("Failed to validate slots")
+    };
+    assert!(violations(rune_crossbow, runite_bolts).is_empty());
+    assert_eq!(violations(rune_crossbow, dragon_bolts).len(), 1);
+    assert!(violations(zaryte_crossbow, dragon_bolts).is_empty());
+
+    let bolt_effect = |ammo: u32| {
+        resolver
+            .resolve_build(&BuildInput {
+                schema_version: 1,
+                equipment: equipment(zaryte_crossbow, ammo),
+                style: StyleInput {
+                    combat: Combat::Ranged,
+                    attack_type: AttackType::Ranged,
+                    stance: Stance::Rapid,
+                    special_attack: false,
+                    manual_cast: false,
+                    runes: vec![],
+                    spell: None,
+                },
+                weapon_config: Default::default(),
+            })
+            .expect("Failed to resolve build")
+            .bolt_effect
+    };
+    assert_eq!(bolt_effect(ruby_bolts_e).as_deref(), Some("ruby"));
+    assert_eq!(bolt_effect(ruby_bolts), None);
+
+    let ruby = store.ammo.zaryte_spec_damage("ruby");
+    assert_eq!((ruby.num, ruby.den), (11, 10));
+    let opal = store.ammo.zaryte_spec_damage("opal");
+    assert_eq!((opal.num, opal.den), (1, 1));
+}
+
+/// Ammo adds its ranged strength only when the weapon fires it: dragon
+/// arrows (60) count with a twisted bow but not with a bow of faerdhinen
+/// or a whip, and a bow handed bolts is rejected by `resolve_equipment`.
+#[test]
+fn ammo_ranged_strength_counts_only_when_fired() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let (twisted_bow, bow_of_faerdhinen, abyssal_whip) = (20997, 25865, 4151);
+    let (dragon_arrows, runite_bolts) = (11212, 9144);
+    let ranged_strength = |weapon: u32, ammo: Option<u32>| {
+        resolver
+            .resolve_equipment(&[ammo], weapon, Stance::Rapid, AttackType::Ranged)
+            .map(|build| build.bonuses.ranged_strength)
+    };
+    let strength = |weapon: u32, ammo: Option<u32>| {
+        ranged_strength(weapon, ammo).expect("Failed to resolve equipment")
+    };
+
+    assert_eq!(
+        strength(twisted_bow, Some(dragon_arrows)),
+        strength(twisted_bow, None) + 60
+    );
+    assert_eq!(
+        strength(bow_of_faerdhinen, Some(dragon_arrows)),
+        strength(bow_of_faerdhinen, None)
+    );
+    assert_eq!(strength(abyssal_whip, Some(dragon_arrows)), 0);
+    assert!(matches!(
+        ranged_strength(twisted_bow, Some(runite_bolts)),
+        Err(DataError::InvalidLoadout(_))
+    ));
+}
+
+/// Ruby bolts (e) from a rune crossbow: 6% of attacks take a fifth of the
+/// target's hitpoints, capped at 100, so they gain far more against a
+/// high-hitpoint boss than against a 100-hitpoint dummy. The Kandarin hard
+/// diary raises the proc chance to 6.6%.
+#[test]
+fn ruby_bolt_procs_scale_with_target_hitpoints() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player: PlayerInput = read_fixture("players/maxed_no_boosts.json");
+    let dummy = resolver
+        .resolve_target_for(&read_fixture("targets/dummy.json"), &player)
+        .unwrap();
+    let mut boss = dummy.clone();
+    boss.hitpoints = 2000;
+    let (rune_crossbow, ruby_bolts, ruby_bolts_e) = (9185, 9339, 9242);
+    let dps_for = |player: &PlayerInput, ammo: u32, target: &TargetResolved| {
+        let build = resolver
+            .resolve_build(&BuildInput {
+                schema_version: 1,
+                equipment: EquipmentInput {
+                    weapon: rune_crossbow,
+                    ammo: Some(ammo),
+                    ..Default::default()
+                },
+                style: StyleInput {
+                    combat: Combat::Ranged,
+                    attack_type: AttackType::Ranged,
+                    stance: Stance::Rapid,
+                    special_attack: false,
+                    manual_cast: false,
+                    runes: vec![],
+                    spell: None,
+                },
+                weapon_config: Default::default(),
+            })
+            .expect("Failed to resolve build");
+        evaluate(&EvalContext {
+            player: &resolve_player_input(player),
+            build: &build,
+            target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+    };
+    let dps = |ammo: u32, target: &TargetResolved| dps_for(&player, ammo, target);
+
+    let plain = dps(ruby_bolts, &boss);
+    let enchanted = dps(ruby_bolts_e, &boss);
+    assert_eq!(plain.max_hit, enchanted.max_hit);
+    // Per attack: 94% of the plain average plus 6% of 100
+    let per_attack = |r: &EvalResult| r.accuracy * f64::from(r.max_hit) / 2.0;
+    let expected = plain.dps * (0.94 * per_attack(&plain) + 6.0) / per_attack(&plain);
+    assert!((enchanted.dps - expected).abs() < 1e-9);
+
+    let dummy_gain = dps(ruby_bolts_e, &dummy).dps - dps(ruby_bolts, &dummy).dps;
+    assert!(enchanted.dps - plain.dps > dummy_gain);
+
+    let mut diarist = player.clone();
+    diarist.flags.kandarin_hard_diary = true;
+    let with_diary = dps_for(&diarist, ruby_bolts_e, &boss);
+    let expected = plain.dps * (0.934 * per_attack(&plain) + 6.6) / per_attack(&plain);
+    assert!((with_diary.dps - expected).abs() < 1e-9);
+}
+
+/// Spells come from data/spells and can be named either way.
+#[test]
+fn spell_data_loaded_from_files() {
+    use osrs_sim::data::{SpellElement, Spellbook};
+
+    let store = load_store();
+    let surge = store.get_spell("Fire Surge").expect("fire surge");
+    assert_eq!(surge.base_max_hit, 24);
+    assert_eq!(surge.level, 95);
+    assert_eq!(surge.element, Some(SpellElement::Fire));
+    assert_eq!(surge.runes.get("wrath"), Some(&1));
+
+    let barrage = store.get_spell("ice_barrage").expect("ice barrage");
+    assert_eq!(barrage.spellbook, Spellbook::Ancient);
+    assert_eq!(barrage.element, None);
+    assert!(barrage.members);
+    assert!(!store.get_spell("fire_blast").unwrap().members);
+    assert!(store.get_spell("fire_storm").is_err());
+}
+
+/// Spells need a weapon that can autocast them.
+#[test]
+fn autocast_requires_capable_weapon() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let mut build: BuildInput = read_fixture("builds/kodai_ancestral_ice_barrage.json");
+    let (staff_of_fire, toxic_sotd, abyssal_whip) = (1387, 12904, 4151);
+
+    let resolve_with = |build: &BuildInput, weapon: u32, spell: &str| {
+        let mut build = build.clone();
+        build.equipment.weapon = weapon;
+        build.style.spell = Some(spell.to_string());
+        resolver.resolve_build(&build)
+    };
+    assert!(resolve_with(&build, staff_of_fire, "fire_surge").is_ok());
+    assert!(resolve_with(&build, toxic_sotd, "flames_of_zamorak").is_ok());
+
+    let err = resolve_with(&build, staff_of_fire, "ice_barrage").unwrap_err();
+    assert_eq!(err.to_string(), "staff of fire can't autocast ice barrage");
+    assert!(matches!(
+        resolve_with(&build, abyssal_whip, "fire_surge"),
+        Err(DataError::CannotAutocast { .. })
+    ));
+    // God spells need their god's staff, even on an ancient-capable one
+    build.style.spell = Some("flames_of_zamorak".to_string());
+    assert!(resolver.resolve_build(&build).is_err());
+}
+
+/// Sunfire runes stand in for fire runes and give fire spells a minimum hit
+/// of 10% of the max hit; they do nothing for spells without fire runes.
+#[test]
+fn sunfire_runes_boost_fire_spells_and_replace_fire_runes() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player = resolve_player_input(&read_fixture("players/maxed_no_boosts.json"));
+    let target = resolver
+        .resolve_target(&read_fixture("targets/dummy.json"))
+        .unwrap();
+    let build: BuildInput = read_fixture("builds/kodai_ancestral_ice_barrage.json");
+    let eval_with = |build: &BuildInput, spell: &str, runes: &[&str]| {
+        let mut build = build.clone();
+        build.style.spell = Some(spell.to_string());
+        build.style.runes = runes.iter().map(|r| r.to_string()).collect();
+        let build = resolver.resolve_build(&build)?;
+        let result = evaluate(&EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        });
+        Ok::<_, DataError>((build, result))
+    };
+
+    let (_, plain) = eval_with(&build, "fire_surge", &[]).unwrap();
+    let (sunfire_build, sunfire) = eval_with(&build, "fire_surge", &["sunfire"]).unwrap();
+    let runes = &sunfire_build.spell.as_ref().unwrap().runes;
+    assert_eq!(runes.get("sunfire"), Some(&10));
+    assert_eq!(runes.get("fire"), None);
+    assert_eq!(sunfire.effects_applied, vec!["sunfire_runes"]);
+    assert_eq!(sunfire.max_hit, plain.max_hit);
+    let max_hit = f64::from(plain.max_hit);
+    let min_hit = f64::from(plain.max_hit / 10);
+    assert!(min_hit > 0.0);
+    assert!((sunfire.dps - plain.dps * (min_hit + max_hit) / max_hit).abs() < 1e-9);
+
+    let (_, barrage) = eval_with(&build, "ice_barrage", &["sunfire"]).unwrap();
+    assert!(barrage.effects_applied.is_empty());
+
+    assert!(matches!(
+        eval_with(&build, "fire_surge", &["moonfire"]),
+        Err(DataError::UnknownRune(_))
+    ));
+}
+
+/// Magic Dart scales with magic level: 10 + 99/10 = 19 from a slayer's
+/// staff, and 13 + 99/6 = 29 from a slayer's staff (e), but only on task.
+/// Both are then scaled by the gear's magic damage like any spell.
+#[test]
+fn magic_dart_scales_with_magic_and_the_enhanced_staff_on_task() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let target = resolver
+        .resolve_target(&read_fixture("targets/dummy.json"))
+        .unwrap();
+    let (slayer_staff, slayer_staff_e) = (4170, 21255);
+    let max_hit = |weapon: u32, on_task: bool| {
+        let mut player: PlayerInput = read_fixture("players/maxed_no_boosts.json");
+        player.flags.on_slayer_task = on_task;
+        let mut build: BuildInput = read_fixture("builds/kodai_ancestral_ice_barrage.json");
+        build.equipment.weapon = weapon;
+        build.style.spell = Some("magic_dart".to_string());
+        let build = resolver.resolve_build(&build).unwrap();
+        let result = evaluate(&EvalContext {
+            player: &resolve_player_input(&player),
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        });
+        let magic_damage = i64::from(build.bonuses.magic_damage);
+        (result.max_hit, magic_damage)
+    };
+    let scaled = |base: i64, magic_damage: i64| base * (100 + magic_damage) / 100;
+
+    let (plain, magic_damage) = max_hit(slayer_staff, true);
+    assert_eq!(i64::from(plain), scaled(19, magic_damage));
+    let (enhanced, magic_damage) = max_hit(slayer_staff_e, true);
+    assert_eq!(i64::from(enhanced), scaled(29, magic_damage));
+    let (off_task, magic_damage) = max_hit(slayer_staff_e, false);
+    assert_eq!(i64::from(off_task), scaled(19, magic_damage));
+
+    // Only the staves that can cast it autocast it
+    let mut build: BuildInput = read_fixture("builds/kodai_ancestral_ice_barrage.json");
+    build.style.spell = Some("magic_dart".to_string());
+    assert!(matches!(
+        resolver.resolve_build(&build),
+        Err(DataError::CannotAutocast { .. })
+    ));
+}
+
+/// Chaos gauntlets add 3 to the bolt spells' base max hit and Charge adds
+/// 10 to a god spell's with its cape, both before magic damage scales it.
+#[test]
+fn chaos_gauntlets_and_charge_raise_the_spell_base_max_hit() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let target = resolver
+        .resolve_target(&read_fixture("targets/dummy.json"))
+        .unwrap();
+    let (chaos_gauntlets, zamorak_cape) = (777, 2414);
+    let eval = |charge: bool, edit: &dyn Fn(&mut BuildInput)| {
+        let mut player: PlayerInput = read_fixture("players/maxed_no_boosts.json");
+        player.flags.charge = charge;
+        let mut build: BuildInput = read_fixture("builds/kodai_ancestral_ice_barrage.json");
+        edit(&mut build);
+        let build = resolver.resolve_build(&build).unwrap();
+        let result = evaluate(&EvalContext {
+            player: &resolve_player_input(&player),
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        });
+        let scaled = move |base: i64| base * (100 + i64::from(build.bonuses.magic_damage)) / 100;
+        (result, scaled)
+    };
+    let casting = |spell: &'static str| {
+        move |build: &mut BuildInput| {
+            build.style.spell = Some(spell.to_string());
+            build.style.manual_cast = true;
+        }
+    };
+
+    // Fire bolt: 12, 15 with the gauntlets
+    let (plain, scaled) = eval(false, &casting("fire_bolt"));
+    assert_eq!(i64::from(plain.max_hit), scaled(12));
+    let (gauntlets, scaled) = eval(false, &|build: &mut BuildInput| {
+        casting("fire_bolt")(build);
+        build.equipment.hands = Some(chaos_gauntlets);
+    });
+    assert_eq!(i64::from(gauntlets.max_hit), scaled(15));
+    assert_eq!(gauntlets.effects_applied, vec!["chaos_gauntlets"]);
+    let (blast, scaled) = eval(false, &|build: &mut BuildInput| {
+        casting("fire_blast")(build);
+        build.equipment.hands = Some(chaos_gauntlets);
+    });
+    assert_eq!(i64::from(blast.max_hit), scaled(16));
+
+    // Saradomin strike: 20, 30 charged with the (imbued) saradomin cape
+    let (uncharged, scaled) = eval(false, &casting("saradomin_strike"));
+    assert_eq!(i64::from(uncharged.max_hit), scaled(20));
+    let (charged, scaled) = eval(true, &casting("saradomin_strike"));
+    assert_eq!(i64::from(charged.max_hit), scaled(30));
+    assert_eq!(charged.effects_applied, vec!["charge_saradomin_strike"]);
+    // The wrong god's cape doesn't count
+    let (wrong_cape, scaled) = eval(true, &|build: &mut BuildInput| {
+        casting("saradomin_strike")(build);
+        build.equipment.cape = Some(zamorak_cape);
+    });
+    assert_eq!(i64::from(wrong_cape.max_hit), scaled(20));
+}
+
+/// The smoke battlestaff and mystic smoke staff give standard spells 10%
+/// more accuracy and 10% magic damage, added to the gear's; spells from
+/// other spellbooks get neither.
+#[test]
+fn smoke_staves_boost_standard_spells_only() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player = resolve_player_input(&read_fixture("players/maxed_no_boosts.json"));
+    let target = resolver
+        .resolve_target(&read_fixture("targets/dummy.json"))
+        .unwrap();
+    let (smoke_battlestaff, mystic_smoke_staff) = (11998, 12000);
+    let eval = |weapon: u32, spell: &str, registry: &EffectRegistry| {
+        let mut build: BuildInput = read_fixture("builds/kodai_ancestral_ice_barrage.json");
+        build.equipment.weapon = weapon;
+        build.style.spell = Some(spell.to_string());
+        build.style.manual_cast = true;
+        let build = resolver.resolve_build(&build).unwrap();
+        let result = evaluate_with(
+            &EvalContext {
+                player: &player,
+                build: &build,
+                target: &target,
+                ordering: None,
+                adjacent_targets: 0,
+                distance: None,
+            },
+            registry,
+        );
+        (result, i64::from(build.bonuses.magic_damage))
+    };
+    let none = EffectRegistry::new();
+
+    for staff in [smoke_battlestaff, mystic_smoke_staff] {
+        // Fire surge: 24 base, scaled by the gear's magic damage plus 10
+        let (boosted, magic_damage) = eval(staff, "fire_surge", default_registry());
+        let (plain, _) = eval(staff, "fire_surge", &none);
+        assert_eq!(i64::from(boosted.max_hit), 24 * (110 + magic_damage) / 100);
+        assert_eq!(boosted.attack_roll, plain.attack_roll * 11 / 10);
+        assert_eq!(
+            boosted.effects_applied,
+            vec!["smoke_staff", "smoke_staff_accuracy"]
+        );
+    }
+
+    // Ice barrage is an ancient spell
+    let (ancient, _) = eval(smoke_battlestaff, "ice_barrage", default_registry());
+    let (plain, _) = eval(smoke_battlestaff, "ice_barrage", &none);
+    assert_eq!(ancient.max_hit, plain.max_hit);
+    assert_eq!(ancient.attack_roll, plain.attack_roll);
+    assert!(ancient.effects_applied.is_empty());
+}
+
+/// Manual casts work from any weapon, replace a powered staff's own spell,
+/// and go off every 5 ticks whatever the weapon's listed speed.
+#[test]
+fn manual_casts_ignore_autocast_limits_and_weapon_speed() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let mut build: BuildInput = read_fixture("builds/kodai_ancestral_ice_barrage.json");
+    build.style.manual_cast = true;
+    let (abyssal_whip, trident) = (4151, 11905);
+
+    let resolve_with = |weapon: u32, spell: &str| {
+        let mut build = build.clone();
+        build.equipment.weapon = weapon;
+        build.style.spell = Some(spell.to_string());
+        resolver
+            .resolve_build(&build)
+            .expect("Failed to resolve build")
+    };
+    let whip = resolve_with(abyssal_whip, "fire_surge");
+    assert_eq!(whip.attack_speed, 5);
+    assert_eq!(whip.spell.map(|s| s.base_max_hit), Some(24));
+
+    let trident = resolve_with(trident, "ice_barrage");
+    assert_eq!(trident.attack_speed, 5);
+    assert!(trident.powered_staff.is_none());
+}
+
+/// A harmonised nightmare staff casts standard spells every 4 ticks, other
+/// staves and spellbooks every 5; the kodai wand's and Elidinis' ward's
+/// magic damage add up: fire surge 24 * 120% -> 28.
+#[test]
+fn magic_weapon_passives_set_cast_speed_and_damage() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player: PlayerInput = read_fixture("players/maxed_no_boosts.json");
+    let target = resolver
+        .resolve_target_for(&read_fixture("targets/dummy.json"), &player)
+        .unwrap();
+    let (nightmare_staff, harmonised_staff, kodai_wand, elidinis_ward_f) =
+        (24422, 24423, 21006, 27251);
+    let eval_with = |weapon: u32, shield: Option<u32>, spell: &str| {
+        let build = resolver
+            .resolve_build(&BuildInput {
+                schema_version: 1,
+                equipment: EquipmentInput {
+                    weapon,
+                    shield,
+                    ..Default::default()
+                },
+                style: StyleInput {
+                    combat: Combat::Magic,
+                    attack_type: AttackType::Magic,
+                    stance: Stance::Accurate,
+                    special_attack: false,
+                    manual_cast: false,
+                    runes: vec![],
+                    spell: Some(spell.to_string()),
+                },
+                weapon_config: Default::default(),
+            })
+            .expect("Failed to resolve build");
+        evaluate(&EvalContext {
+            player: &resolve_player_input(&player),
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+    };
+
+    assert_eq!(
+        eval_with(harmonised_staff, None, "fire_surge").interval_ticks,
+        Ticks(4)
+    );
+    assert_eq!(
+        eval_with(harmonised_staff, None, "ice_barrage").interval_ticks,
+        Ticks(5)
+    );
+    assert_eq!(
+        eval_with(nightmare_staff, None, "fire_surge").interval_ticks,
+        Ticks(5)
+    );
+
+    let kodai = eval_with(kodai_wand, Some(elidinis_ward_f), "fire_surge");
+    assert_eq!(kodai.max_hit, 28);
+    assert_eq!(kodai.interval_ticks, Ticks(5));
+}
+
+/// Ranged effects stack in the order `ordering::PIPELINE_ORDER` documents:
+/// void on the effective levels, then the salve, then the dragon hunter
+/// crossbow (against Vorkath, undead and a dragon). The profile pinned to
+/// 2026-10 matches it, and a profile moving weapon bonuses ahead of the
+/// salve swaps them.
+#[test]
+fn ranged_effects_stack_void_then_salve_then_dragonbane() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player: PlayerInput = read_fixture("players/maxed_no_boosts.json");
+    let target = resolver
+        .resolve_target_for(&read_fixture("targets/vorkath.json"), &player)
+        .unwrap();
+    let (dragon_hunter_crossbow, dragon_bolts, salve_ei) = (21012, 21905, 12018);
+    let elite_void = [11664, 13072, 13073, 8842];
+    let build = resolver
+        .resolve_build(&BuildInput {
+            schema_version: 1,
+            equipment: EquipmentInput {
+                weapon: dragon_hunter_crossbow,
+                ammo: Some(dragon_bolts),
+                neck: Some(salve_ei),
+                head: Some(elite_void[0]),
+                body: Some(elite_void[1]),
+                legs: Some(elite_void[2]),
+                hands: Some(elite_void[3]),
+                ..Default::default()
+            },
+            style: StyleInput {
+                combat: Combat::Ranged,
+                attack_type: AttackType::Ranged,
+                stance: Stance::Rapid,
+                special_attack: false,
+                manual_cast: false,
+                runes: vec![],
+                spell: None,
+            },
+            weapon_config: Default::default(),
+        })
+        .expect("Failed to resolve build");
+    let player = resolve_player_input(&player);
+    let eval = |ordering: Option<&OrderingProfile>| {
+        evaluate(&EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering,
+            adjacent_targets: 0,
+            distance: None,
+        })
+    };
+    let live = eval(None);
+    // Each stage's trace holds the effects it applied
+    let stages: Vec<Stage> = live.stages.iter().map(|trace| trace.stage).collect();
+    assert_eq!(stages, Stage::ALL);
+    let traced: Vec<String> = live
+        .stages
+        .iter()
+        .flat_map(|trace| trace.effects.clone())
+        .collect();
+    assert_eq!(traced, live.effects_applied);
+    assert_eq!(
+        live.effects_applied,
+        vec![
+            "elite_void_ranged_str",
+            "void_ranged_atk",
+            "salve_ei_ranged",
+            "salve_ei_ranged_accuracy",
+            "dragon_hunter_crossbow",
+            "dragon_hunter_crossbow_accuracy",
+        ]
+    );
+
+    let pinned = eval(Some(&ordering::profile("2026-10").unwrap()));
+    assert_eq!(pinned.effects_applied, live.effects_applied);
+    assert_eq!(pinned.max_hit, live.max_hit);
+    assert_eq!(pinned.dps, live.dps);
+
+    let mut bane_first = OrderingProfile::live();
+    bane_first.slots.insert(
+        "target_bane".to_string(),
+        Placement {
+            stage: Stage::PostMaxHit,
+            priority: 35,
+        },
+    );
+    let reordered = eval(Some(&bane_first));
+    assert_eq!(
+        reordered.effects_applied[2..],
+        [
+            "dragon_hunter_crossbow",
+            "dragon_hunter_crossbow_accuracy",
+            "salve_ei_ranged",
+            "salve_ei_ranged_accuracy",
+        ]
+    );
+}
+
+/// Venator bow vs TzTok-Jad with two more Jads next to it: the main
+/// target's DPS doesn't change, and arrows that land bounce on for up to
+/// two thirds of the max hit, each bounce rolling its own accuracy.
+#[test]
+fn venator_bow_bounces_add_aoe_dps() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player: PlayerInput = read_fixture("players/maxed_no_boosts.json");
+    let target = resolver
+        .resolve_target_for(&read_fixture("targets/tztok_jad.json"), &player)
+        .unwrap();
+    let build = resolver
+        .resolve_build(&BuildInput {
+            schema_version: 1,
+            equipment: EquipmentInput {
+                weapon: osrs_sim::aoe::VENATOR_BOW,
+                ammo: Some(11212),
+                ..Default::default()
+            },
+            style: StyleInput {
+                combat: Combat::Ranged,
+                attack_type: AttackType::Ranged,
+                stance: Stance::Rapid,
+                special_attack: false,
+                manual_cast: false,
+                runes: vec![],
+                spell: None,
+            },
+            weapon_config: Default::default(),
+        })
+        .expect("Failed to resolve build");
+    let player = resolve_player_input(&player);
+    let eval = |adjacent_targets: u8| {
+        evaluate(&EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets,
+            distance: None,
+        })
+    };
+
+    let alone = eval(0);
+    assert_eq!(alone.aoe_dps, None);
+    let stacked = eval(2);
+    assert_eq!(stacked.dps, alone.dps);
+    let (accuracy, bounce_max) = (stacked.accuracy, stacked.max_hit * 2 / 3);
+    let bounces = (accuracy.powi(2) + accuracy.powi(3)) * f64::from(bounce_max) / 2.0;
+    let seconds = stacked.interval_ticks.as_secs_f64();
+    let total = stacked.aoe_dps.expect("bounces");
+    assert!((total - (stacked.dps + bounces / seconds)).abs() < 1e-9);
+    assert_eq!(eval(5).aoe_dps, stacked.aoe_dps);
+}
+
+/// Ice barrage with a kodai and Ancestral into a stack of TzTok-Jads: each
+/// of up to 8 others in the 3x3 rolls the same accuracy against its own
+/// defence as the main target, so the total is the main DPS per target.
+/// Ice blitz hits only the main target.
+#[test]
+fn barrage_aoe_dps_covers_the_3x3() {
+    let store = load_store();
+    let (player, build, target) = resolve_fixtures(
+        &store,
+        "players/maxed_augury_imbued_heart.json",
+        "builds/kodai_ancestral_ice_barrage.json",
+        "targets/tztok_jad.json",
+    );
+    let eval = |build: &BuildResolved, adjacent_targets: u8| {
+        evaluate(&EvalContext {
+            player: &player,
+            build,
+            target: &target,
+            ordering: None,
+            adjacent_targets,
+            distance: None,
+        })
+    };
+
+    let alone = eval(&build, 0);
+    assert_eq!(alone.aoe_dps, None);
+    let stacked = eval(&build, 4);
+    assert_eq!(stacked.dps, alone.dps);
+    assert!((stacked.aoe_dps.expect("area spell") - 5.0 * alone.dps).abs() < 1e-9);
+    let packed = eval(&build, 20).aoe_dps.expect("area spell");
+    assert!((packed - 9.0 * alone.dps).abs() < 1e-9);
+
+    let blitz = BuildResolved {
+        spell: build.spell.clone().map(|spell| osrs_sim::model::Spell {
+            name: "ice_blitz".to_string(),
+            ..spell
+        }),
+        ..build.clone()
+    };
+    assert_eq!(eval(&blitz, 4).aoe_dps, None);
+}
+
+/// Dual macuahuitl with the whole blood moon set: an attack that lands
+/// makes the next a tick faster a third of the time, so the average
+/// interval is 4 - accuracy / 3 ticks and DPS rises to match.
+#[test]
+fn blood_moon_set_shortens_the_macuahuitl_interval() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player: PlayerInput = read_fixture("players/maxed_super_str.json");
+    let target = resolver
+        .resolve_target_for(&read_fixture("targets/tztok_jad.json"), &player)
+        .unwrap();
+    let player = resolve_player_input(&player);
+    let eval = |head: Option<u32>| {
+        let build = resolver
+            .resolve_build(&BuildInput {
+                schema_version: 1,
+                equipment: EquipmentInput {
+                    weapon: 28997,
+                    head,
+                    body: Some(29022),
+                    legs: Some(29025),
+                    ..Default::default()
+                },
+                style: StyleInput {
+                    combat: Combat::Melee,
+                    attack_type: AttackType::Crush,
+                    stance: Stance::Aggressive,
+                    special_attack: false,
+                    manual_cast: false,
+                    runes: vec![],
+                    spell: None,
+                },
+                weapon_config: Default::default(),
+            })
+            .expect("Failed to resolve build");
+        evaluate(&EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+    };
+
+    // Without the helm, every attack takes the weapon's 4 ticks
+    let partial = eval(None);
+    assert_eq!(partial.expected_interval_ticks, None);
+    let set = eval(Some(29028));
+    assert_eq!(set.interval_ticks, Ticks(4));
+    let average = set.expected_interval_ticks.expect("blood moon set");
+    assert!((average - (4.0 - set.accuracy / 3.0)).abs() < 1e-9);
+    let plain = set.accuracy * f64::from(set.max_hit) / 2.0 / 2.4;
+    assert!((set.dps - plain * 4.0 / average).abs() < 1e-9);
+}
+
+/// Eclipse atlatl (accurate), 99 Strength with a super strength potion:
+/// the ranged max hit comes from (99 + 19 + 3 + 8) effective strength and
+/// the melee strength bonus, 40 alone -> 21 and 47 with the eclipse moon
+/// set -> 22. The set's burns add DPS of their own; Ranged only sets the
+/// accuracy.
+#[test]
+fn eclipse_atlatl_scales_ranged_damage_with_strength() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player: PlayerInput = read_fixture("players/maxed_super_str.json");
+    let target = resolver
+        .resolve_target_for(&read_fixture("targets/tztok_jad.json"), &player)
+        .unwrap();
+    let player = resolve_player_input(&player);
+    let build_for = |set: bool| {
+        resolver
+            .resolve_build(&BuildInput {
+                schema_version: 1,
+                equipment: EquipmentInput {
+                    weapon: 29000,
+                    head: set.then_some(29010),
+                    body: set.then_some(29004),
+                    legs: set.then_some(29007),
+                    ..Default::default()
+                },
+                style: StyleInput {
+                    combat: Combat::Ranged,
+                    attack_type: AttackType::Ranged,
+                    stance: Stance::Accurate,
+                    special_attack: false,
+                    manual_cast: false,
+                    runes: vec![],
+                    spell: None,
+                },
+                weapon_config: Default::default(),
+            })
+            .expect("Failed to resolve build")
+    };
+    let eval = |player: &osrs_sim::PlayerState, build: &BuildResolved| {
+        evaluate(&EvalContext {
+            player,
+            build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+    };
+
+    let (alone, set) = (build_for(false), build_for(true));
+    let plain = eval(&player, &alone);
+    assert_eq!(plain.max_hit, 21);
+    assert_eq!(plain.burn_dps, 0.0);
+    let moon = eval(&player, &set);
+    assert_eq!(moon.max_hit, 22);
+    let burning = osrs_sim::dot::burn_dps(moon.accuracy / 5.0, 4);
+    assert!(burning > 0.0);
+    assert!((moon.burn_dps - burning).abs() < 1e-9);
+
+    let low_ranged = osrs_sim::PlayerState {
+        ranged: 1,
+        ..player.clone()
+    };
+    let weak = eval(&low_ranged, &set);
+    assert_eq!(weak.max_hit, 22);
+    assert!(weak.accuracy < moon.accuracy);
+}
+
+/// Twisted bow with dragon arrows (accurate), 99 Ranged, no boosts:
+/// (99 + 3 + 8) * (20 + 60 + 64) -> 25, scaled by the target's magic level:
+/// 215% at 250 magic -> 53, and 54% at 1 -> 13. A magic attack bonus above
+/// the magic level counts instead: Zebak (100 magic, +215 magic attack)
+/// gives 199% -> 49, not 131% -> 32.
+#[test]
+fn twisted_bow_scales_with_target_magic_level() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player: PlayerInput = read_fixture("players/maxed_no_boosts.json");
+    let dummy = resolver
+        .resolve_target_for(&read_fixture("targets/dummy.json"), &player)
+        .unwrap();
+    let (twisted_bow, dragon_arrows) = (20997, 11212);
+    let build = resolver
+        .resolve_build(&BuildInput {
+            schema_version: 1,
+            equipment: EquipmentInput {
+                weapon: twisted_bow,
+                ammo: Some(dragon_arrows),
+                ..Default::default()
+            },
+            style: StyleInput {
+                combat: Combat::Ranged,
+                attack_type: AttackType::Ranged,
+                stance: Stance::Accurate,
+                special_attack: false,
+                manual_cast: false,
+                runes: vec![],
+                spell: None,
+            },
+            weapon_config: Default::default(),
+        })
+        .expect("Failed to resolve build");
+    let max_hit = |magic_level: u16, magic_attack_bonus: i32| {
+        let target = TargetResolved {
+            magic_level,
+            magic_attack_bonus,
+            ..dummy.clone()
+        };
+        evaluate(&EvalContext {
+            player: &resolve_player_input(&player),
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+        .max_hit
+    };
+    assert_eq!(max_hit(250, 0), 53);
+    assert_eq!(max_hit(1, 0), 13);
+    assert_eq!(max_hit(100, 0), 32);
+
+    let zebak = resolver.resolve_monster(11732).unwrap();
+    assert_eq!((zebak.magic_level, zebak.magic_attack_bonus), (100, 215));
+    assert_eq!(zebak.twisted_bow_magic(), 215);
+    assert_eq!(max_hit(100, 215), 49);
+    // A bonus below the magic level changes nothing
+    assert_eq!(max_hit(250, 100), 53);
+}
+
+/// Powered staves vs an undead training dummy, 99 Magic, no boosts. Magic
+/// damage stacks additively: gear (tripled by the Shadow), then the imbued
+/// salve's bonus, with the imbued slayer helm multiplying the max hit after.
+/// - Shadow (34) + tormented + salve (ei): 5 * 3 + 20 = +35% -> 45
+/// - Sanguinesti (32) + tormented + salve (ei): 5 + 20 = +25% -> 40
+/// - Shadow + tormented + occult + slayer helm (i): +30% -> 44, * 23/20 -> 50
+#[test]
+fn magic_damage_stacks_gear_then_salve_then_slayer_helm() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player: PlayerInput = read_fixture("players/maxed_no_boosts.json");
+    let mut target = resolver
+        .resolve_target_for(&read_fixture("targets/dummy.json"), &player)
+        .unwrap();
+    target.attributes = vec!["undead".to_string()];
+    let (tumekens_shadow, sanguinesti_staff) = (27275, 22323);
+    let (tormented_bracelet, occult_necklace, salve_ei, slayer_helm_i) =
+        (19544, 12002, 12018, 11865);
+    let max_hit = |weapon: u32, neck: u32, head: Option<u32>| {
+        let build = resolver
+            .resolve_build(&BuildInput {
+                schema_version: 1,
+                equipment: EquipmentInput {
+                    weapon,
+                    neck: Some(neck),
+                    head,
+                    hands: Some(tormented_bracelet),
+                    ..Default::default()
+                },
+                style: StyleInput {
+                    combat: Combat::Magic,
+                    attack_type: AttackType::Magic,
+                    stance: Stance::Accurate,
+                    special_attack: false,
+                    manual_cast: false,
+                    runes: vec![],
+                    spell: None,
+                },
+                weapon_config: Default::default(),
+            })
+            .expect("Failed to resolve build");
+        let mut player = resolve_player_input(&player);
+        player.on_slayer_task = true;
+        evaluate(&EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+        .max_hit
+    };
+
+    assert_eq!(max_hit(tumekens_shadow, salve_ei, None), 45);
+    assert_eq!(max_hit(sanguinesti_staff, salve_ei, None), 40);
+    assert_eq!(
+        max_hit(tumekens_shadow, occult_necklace, Some(slayer_helm_i)),
+        50
+    );
+    // The salve still rules out the helm, across stages
+    assert_eq!(max_hit(tumekens_shadow, salve_ei, Some(slayer_helm_i)), 45);
+}
+
+/// Toxic blowpipe on rapid vs the training dummy, 99 Ranged, no boosts:
+/// darts add only their ranged strength, so 107 * (20 + 35 + 64) -> 20
+/// with dragon darts against 14 empty, and rapid takes 3 ticks to 2.
+#[test]
+fn blowpipe_darts_add_ranged_strength_at_rapid_speed() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player: PlayerInput = read_fixture("players/maxed_no_boosts.json");
+    let target = resolver
+        .resolve_target_for(&read_fixture("targets/dummy.json"), &player)
+        .unwrap();
+    let (toxic_blowpipe, bronze_dart, dragon_dart, runite_bolts) = (12926, 806, 11230, 9144);
+    let build_with = |charged_ammo: Option<u32>| {
+        resolver.resolve_build(&BuildInput {
+            schema_version: 1,
+            equipment: EquipmentInput {
+                weapon: toxic_blowpipe,
+                charged_ammo,
+                ..Default::default()
+            },
+            style: StyleInput {
+                combat: Combat::Ranged,
+                attack_type: AttackType::Ranged,
+                stance: Stance::Rapid,
+                special_attack: false,
+                manual_cast: false,
+                runes: vec![],
+                spell: None,
+            },
+            weapon_config: Default::default(),
+        })
+    };
+    let eval_with = |charged_ammo: Option<u32>| {
+        let build = build_with(charged_ammo).expect("Failed to resolve build");
+        evaluate(&EvalContext {
+            player: &resolve_player_input(&player),
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+    };
+
+    assert_eq!(eval_with(None).max_hit, 14);
+    assert_eq!(eval_with(Some(bronze_dart)).max_hit, 14);
+    let dragon = eval_with(Some(dragon_dart));
+    assert_eq!(dragon.max_hit, 20);
+    assert_eq!(dragon.interval_ticks, Ticks(2));
+
+    // Bolts can't be loaded into a blowpipe
+    assert!(matches!(
+        build_with(Some(runite_bolts)),
+        Err(DataError::InvalidLoadout(violations)) if violations.len() == 1
+    ));
+}
+
+/// Black salamander + harralander tar vs the training dummy, 99 all stats
+/// - Scorch (aggressive): (99 + 3 + 8) * (71 + 64) -> 23
+/// - Flare (accurate): (99 + 3 + 8) * (49 + 64) from the tar -> 19
+/// - Blaze: 99 * (64 + 92) / 640 -> 24, with no spell
+#[test]
+fn salamander_styles_vs_dummy() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player: PlayerInput = read_fixture("players/maxed_no_boosts.json");
+    let target = resolver
+        .resolve_target_for(&read_fixture("targets/dummy.json"), &player)
+        .unwrap();
+    let player = resolve_player_input(&player);
+
+    let base: BuildInput = read_fixture("builds/voidwaker_bandos_slash_aggressive.json");
+    let (black_salamander, harralander_tar) = (10148, 10145);
+    let build_for = |combat, attack_type, stance| BuildInput {
+        equipment: EquipmentInput {
+            weapon: black_salamander,
+            ammo: Some(harralander_tar),
+            ..EquipmentInput::default()
+        },
+        style: StyleInput {
+            combat,
+            attack_type,
+            stance,
+            special_attack: false,
+            manual_cast: false,
+            runes: vec![],
+            spell: None,
+        },
+        ..base.clone()
+    };
+    let evaluate_style = |combat, attack_type, stance| {
+        let build = resolver
+            .resolve_build(&build_for(combat, attack_type, stance))
+            .unwrap();
+        evaluate(&EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+    };
+
+    let scorch = evaluate_style(Combat::Melee, AttackType::Slash, Stance::Aggressive);
+    assert_eq!(scorch.max_hit, 23);
+    let flare = evaluate_style(Combat::Ranged, AttackType::Ranged, Stance::Accurate);
+    assert_eq!(flare.max_hit, 19);
+    let blaze = evaluate_style(Combat::Magic, AttackType::Magic, Stance::Accurate);
+    assert_eq!(blaze.max_hit, 24);
+    assert_eq!(blaze.interval_ticks, Ticks(5));
+
+    // Blaze isn't an autocast
+    let mut build = build_for(Combat::Magic, AttackType::Magic, Stance::Accurate);
+    build.style.spell = Some("fire_bolt".to_string());
+    assert!(matches!(
+        resolver.resolve_build(&build),
+        Err(DataError::CannotAutocast { .. })
+    ));
+}
+
+/// Kodai wand + Ancestral, Ice Barrage vs TzTok-Jad
+/// - 99 all stats, Augury, Imbued heart (+10)
+/// - Effective magic: (99 + 10) * 5/4 + 9 = 145, attack roll 145 * (134 + 64)
+/// - 36% magic damage: 30 -> 40 max hit, 5-tick casts
+#[test]
+fn kodai_ancestral_ice_barrage_vs_jad() {
+    let result = eval_from_fixtures(
+        "players/maxed_augury_imbued_heart.json",
+        "builds/kodai_ancestral_ice_barrage.json",
+        "targets/tztok_jad.json",
+    );
+
+    let expected = Expected {
+        dps: 3.058,
+        max_hit: 40,
+        accuracy: 0.4587,
+    };
+
+    assert_result_matches(&result, &expected, "kodai_ancestral_ice_barrage_vs_jad");
+    assert_eq!(result.attack_roll, 28_710);
+    assert_eq!(result.interval_ticks, Ticks(5));
+}
+
+/// Sanguinesti staff + Ancestral vs TzTok-Jad (no spell in the build)
+/// - 99 all stats, Augury, Imbued heart: visible magic 109
+/// - Built-in spell: 109 / 3 - 1 = 35, 21% magic damage -> 42, 4-tick casts
+/// - Accurate on a powered staff: +2, effective magic 147
+#[test]
+fn sanguinesti_ancestral_vs_jad() {
+    let result = eval_from_fixtures(
+        "players/maxed_augury_imbued_heart.json",
+        "builds/sanguinesti_ancestral_accurate.json",
+        "targets/tztok_jad.json",
+    );
+
+    let expected = Expected {
+        dps: 4.007,
+        max_hit: 42,
+        accuracy: 0.4580,
+    };
+
+    assert_result_matches(&result, &expected, "sanguinesti_ancestral_vs_jad");
+    assert_eq!(result.attack_roll, 28_665);
+    assert_eq!(result.interval_ticks, Ticks(4));
+}
+
+/// Auto-style picks aggressive over accurate for Voidwaker vs Jad without boosts
+/// (the +3 strength buys a max hit, the accuracy loss is small).
+#[test]
+fn voidwaker_auto_style_vs_jad_no_boosts() {
+    let store = load_store();
+    let (player, build, target) = resolve_fixtures(
+        &store,
+        "players/maxed_no_boosts.json",
+        "builds/voidwaker_bandos_slash_accurate.json",
+        "targets/tztok_jad.json",
+    );
+
+    let ranked = Resolver::new(&store)
+        .rank_styles(&player, &build, &target, None)
+        .expect("Failed to rank styles");
+
+    assert_eq!(ranked.len(), 4);
+    assert_eq!(ranked[0].attack_type, AttackType::Slash);
+    assert_eq!(ranked[0].stance, Stance::Aggressive);
+    assert!((ranked[0].dps - 1.337).abs() < 0.01);
+    assert!(ranked.windows(2).all(|w| w[0].dps >= w[1].dps));
+}
+
+/// Weapons reach as far as data/ranges.json says, 2 tiles more on
+/// longrange. A target beyond that fails validation and drops out of the
+/// style ranking, and chinchompas lose accuracy a quarter at a time as the
+/// distance moves away from the fuse's bracket.
+#[test]
+fn distance_limits_reach_and_sets_the_chinchompa_fuse() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player = resolve_player_input(&read_fixture("players/maxed_no_boosts.json"));
+    let target = resolver
+        .resolve_target(&read_fixture("targets/dummy.json"))
+        .unwrap();
+    let black_chinchompa = 11959;
+    let chinchompa = |stance: Stance| {
+        resolver
+            .resolve_build(&BuildInput {
+                schema_version: 1,
+                equipment: EquipmentInput {
+                    weapon: black_chinchompa,
+                    ..Default::default()
+                },
+                style: StyleInput {
+                    combat: Combat::Ranged,
+                    attack_type: AttackType::Ranged,
+                    stance,
+                    special_attack: false,
+                    manual_cast: false,
+                    runes: vec![],
+                    spell: None,
+                },
+                weapon_config: Default::default(),
+            })
+            .expect("Failed to resolve build")
+    };
+    let validate = |build: &BuildResolved, distance: Option<u8>| {
+        validate_context(&EvalContext {
+            player: &player,
+            build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance,
+        })
+    };
+    let attack_roll = |stance, distance| {
+        evaluate(&EvalContext {
+            player: &player,
+            build: &chinchompa(stance),
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance,
+        })
+        .attack_roll
+    };
+
+    // Full accuracy in the fuse's own bracket, or with no distance given
+    for (stance, distance) in [
+        (Stance::Accurate, 2),
+        (Stance::Rapid, 5),
+        (Stance::Longrange, 8),
+    ] {
+        assert_eq!(
+            attack_roll(stance, Some(distance)),
+            attack_roll(stance, None)
+        );
+    }
+    let rapid = attack_roll(Stance::Rapid, None);
+    assert_eq!(attack_roll(Stance::Rapid, Some(2)), rapid * 3 / 4);
+    let accurate = attack_roll(Stance::Accurate, None);
+    assert_eq!(attack_roll(Stance::Accurate, Some(9)), accurate * 2 / 4);
+
+    // 9 tiles, 10 on longrange; only the long fuse reaches 10
+    let rapid = chinchompa(Stance::Rapid);
+    assert_eq!(rapid.attack_range(), 9);
+    assert_eq!(chinchompa(Stance::Longrange).attack_range(), 10);
+    assert!(validate(&rapid, Some(9)).is_ok());
+    assert!(matches!(
+        validate(&rapid, Some(10)),
+        Err(ContextError::OutOfRange {
+            distance: 10,
+            range: 9
+        })
+    ));
+    let ranked = resolver
+        .rank_styles(&player, &rapid, &target, Some(10))
+        .unwrap();
+    let stances: Vec<Stance> = ranked.iter().map(|style| style.stance).collect();
+    assert_eq!(stances, vec![Stance::Longrange]);
+    assert_eq!(
+        resolver
+            .rank_styles(&player, &rapid, &target, None)
+            .unwrap()
+            .len(),
+        3
+    );
+
+    // Melee reaches the next tile, a halberd one more
+    let melee = |weapon: u32| {
+        let mut build: BuildInput = read_fixture("builds/voidwaker_bandos_slash_accurate.json");
+        build.equipment.weapon = weapon;
+        resolver.resolve_build(&build).unwrap()
+    };
+    let (abyssal_whip, crystal_halberd) = (4151, 23987);
+    assert_eq!(melee(abyssal_whip).attack_range(), 1);
+    assert_eq!(melee(crystal_halberd).attack_range(), 2);
+    assert!(validate(&melee(crystal_halberd), Some(2)).is_ok());
+    assert!(resolver
+        .rank_styles(&player, &melee(abyssal_whip), &target, Some(2))
+        .unwrap()
+        .is_empty());
+}
+
+/// The optimizer's reported DPS is the DPS of the build it returns. Ammo a
+/// weapon doesn't fire adds no ranged strength there, so the search must
+/// not count it either: with only a bow of faerdhinen, which carries its
+/// own arrows, dragon javelins in the ammo slot gain nothing.
+#[test]
+fn optimized_dps_matches_the_returned_build() {
+    let (bow_of_faerdhinen, dragon_javelin) = (25865, 19484);
+    let (rune_crossbow, runite_bolts, ruby_bolts_e) = (9185, 9144, 9242);
+    let player: PlayerInput = read_fixture("players/maxed_no_boosts.json");
+    let verify = |items: &[u32], start: &BuildInput| {
+        let mut store = load_store();
+        store.items.retain(|id, _| *id == 0 || items.contains(id));
+        let target = Resolver::new(&store)
+            .resolve_target(&read_fixture("targets/dummy.json"))
+            .unwrap();
+        let result = optimize_gear(
+            &store,
+            &player,
+            start,
+            &target,
+            &AccountConstraints::default(),
+        )
+        .expect("search failed");
+        let build = Resolver::new(&store).resolve_build(&result.build).unwrap();
+        let dps = evaluate(&EvalContext {
+            player: &resolve_player_input(&player),
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+        .dps;
+        assert!((result.dps - dps).abs() < 1e-9, "{} vs {dps}", result.dps);
+        result.build
+    };
+    let mut start = BuildInput {
+        schema_version: 1,
+        equipment: EquipmentInput {
+            weapon: bow_of_faerdhinen,
+            ..Default::default()
+        },
+        style: StyleInput {
+            combat: Combat::Ranged,
+            attack_type: AttackType::Ranged,
+            stance: Stance::Rapid,
+            special_attack: false,
+            manual_cast: false,
+            runes: vec![],
+            spell: None,
+        },
+        weapon_config: Default::default(),
+    };
+    let bow = [bow_of_faerdhinen, dragon_javelin];
+    verify(&bow, &start);
+    // Starting with the javelins worn, their strength isn't taken off twice
+    start.equipment.ammo = Some(dragon_javelin);
+    verify(&bow, &start);
+
+    // Swapping bolts carries the new bolts' enchantment, whichever are worn
+    // at the start
+    let crossbow = [rune_crossbow, runite_bolts, ruby_bolts_e];
+    start.equipment.weapon = rune_crossbow;
+    for ammo in [runite_bolts, ruby_bolts_e] {
+        start.equipment.ammo = Some(ammo);
+        let best = verify(&crossbow, &start);
+        assert!(best.equipment.ammo.is_some());
+    }
+}
+
+/// A short seeded soak over the shipped data: every random scenario must
+/// evaluate without panicking and with sound results.
+#[test]
+fn fuzz_scenarios_hold_invariants() {
+    let store = load_store();
+    let mut fuzzer = Fuzzer::new(&store, 2024);
+    for _ in 0..200 {
+        if let FuzzOutcome::Failed(failure) = fuzzer.step() {
+            panic!(
+                "scenario {} failed: {:?}\n{}",
+                failure.index,
+                failure.problems,
+                serde_json::to_string_pretty(&failure.scenario).unwrap()
+            );
+        }
+    }
+}
+
+/// Canonical hash of 2000 seeded scenarios over the shipped data, as
+/// `fuzz-scenarios --determinism --count 2000` prints it. Every platform
+/// the engine builds for must reproduce it bit for bit; when results change
+/// on purpose (a formula or data fix), update it to the new printed hash.
+const DETERMINISM_HASH: &str = "51c76db15d38f841";
+
+#[test]
+fn determinism_hash_is_pinned() {
+    let store = load_store();
+    let batch = batch_hash(&store, DETERMINISM_SEED, 2000);
+    assert_eq!(batch.evaluated, 2000);
+    assert_eq!(batch, batch_hash(&store, DETERMINISM_SEED, 2000));
+    assert_eq!(batch.hex(), DETERMINISM_HASH);
+}
+
+/// The built-in dummies: no defence, and the undead one triggers the salve.
+#[test]
+fn salve_applies_to_undead_dummy_only() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player_input: PlayerInput = read_fixture("players/maxed_piety_super_combat.json");
+    let player = resolve_player_input(&player_input);
+    let build = resolver
+        .resolve_build(&read_fixture(
+            "builds/voidwaker_bandos_salve_ei_slash_accurate.json",
+        ))
+        .expect("Failed to resolve build");
+
+    let eval_against = |name: &str| {
+        let input = TargetInput::builtin(name).expect("Unknown built-in target");
+        let target = resolver
+            .resolve_target(&input)
+            .expect("Failed to resolve target");
+        assert_eq!(target.defence_level, 0);
+        evaluate(&EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+    };
+
+    let plain = eval_against("dummy");
+    let undead = eval_against("dummy:undead");
+    assert!(!plain.effects_applied.iter().any(|e| e.starts_with("salve")));
+    assert!(undead
+        .effects_applied
+        .contains(&"salve_ei_melee".to_string()));
+    // 36 * 6/5 = 43
+    assert_eq!((plain.max_hit, undead.max_hit), (36, 43));
+    assert!(TargetInput::builtin("dummy:demon").is_none());
+}
+
+/// The berserker necklace finds obsidian weapons through data/tags.
+#[test]
+fn berserker_necklace_boosts_tagged_obsidian_weapon() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player = resolve_player_input(&read_fixture("players/maxed_piety_super_combat.json"));
+    let target = resolver
+        .resolve_target(&TargetInput::builtin("dummy").expect("Unknown built-in target"))
+        .expect("Failed to resolve target");
+
+    let eval_with = |weapon: u32, neck: Option<u32>| {
+        let build = resolver
+            .resolve_build(&BuildInput {
+                schema_version: 1,
+                equipment: EquipmentInput {
+                    weapon,
+                    neck,
+                    ..Default::default()
+                },
+                style: StyleInput {
+                    combat: Combat::Melee,
+                    attack_type: AttackType::Crush,
+                    stance: Stance::Aggressive,
+                    special_attack: false,
+                    manual_cast: false,
+                    runes: vec![],
+                    spell: None,
+                },
+                weapon_config: Default::default(),
+            })
+            .expect("Failed to resolve build");
+        evaluate(&EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+    };
+    let boosted = |result: &osrs_sim::EvalResult| {
+        result
+            .effects_applied
+            .contains(&"berserker_necklace_obsidian".to_string())
+    };
+
+    // Tzhaar-ket-om: 36 alone; the necklace's +7 strength gives 38, x6/5 = 45
+    let plain = eval_with(6528, None);
+    let necklace = eval_with(6528, Some(11128));
+    assert!(!boosted(&plain) && boosted(&necklace));
+    assert_eq!((plain.max_hit, necklace.max_hit), (36, 45));
+    // No bonus with a weapon that isn't tagged obsidian
+    assert!(!boosted(&eval_with(21003, Some(11128))));
+}
+
+/// Every data/effects file is built in, and names only items in the data.
+#[test]
+fn shipped_effect_files_are_built_in_and_reference_known_items() {
+    let store = load_store();
+    let built_in: Vec<&str> = default_registry()
+        .effects
+        .iter()
+        .map(|e| e.id.as_str())
+        .collect();
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("data/effects");
+    for entry in std::fs::read_dir(dir).expect("Failed to read data/effects") {
+        let path = entry.expect("Failed to read entry").path();
+        let json = std::fs::read_to_string(&path).expect("Failed to read effects file");
+        let effects = parse_effects(&json).unwrap_or_else(|e| panic!("{path:?}: {e}"));
+        for effect in effects {
+            assert!(
+                built_in.contains(&effect.id.as_str()),
+                "{path:?}: {} isn't built in",
+                effect.id
+            );
+            for id in effect.condition.equipped.iter().flatten() {
+                assert!(
+                    store.get_item(*id).is_ok(),
+                    "{}: unknown item {id}",
+                    effect.id
+                );
+            }
+        }
+    }
+}
+
+/// The shipped special attack entries are consistent, and spec-db's
+/// templates for the missing weapons are flagged until filled in.
+#[test]
+fn arclight_is_halved_against_demonbane_resistant_demons() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player = resolve_player_input(&read_fixture("players/maxed_piety_super_combat.json"));
+    let build = resolver
+        .resolve_build(&BuildInput {
+            schema_version: 1,
+            equipment: EquipmentInput {
+                weapon: 19675,
+                ..Default::default()
+            },
+            style: StyleInput {
+                combat: Combat::Melee,
+                attack_type: AttackType::Slash,
+                stance: Stance::Aggressive,
+                special_attack: false,
+                manual_cast: false,
+                runes: vec![],
+                spell: None,
+            },
+            weapon_config: Default::default(),
+        })
+        .expect("Failed to resolve build");
+    let effects_against = |monster_id: u32| {
+        let input: TargetInput = serde_json::from_str(&format!(
+            r#"{{"schema_version": 1, "monster_id": {monster_id}}}"#
+        ))
+        .unwrap();
+        let target = resolver
+            .resolve_target(&input)
+            .expect("Failed to resolve target");
+        evaluate(&EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+        .effects_applied
+    };
+
+    // K'ril Tsutsaroth takes the full bonus; Yama is tagged resistant
+    assert_eq!(
+        effects_against(3129),
+        vec!["arclight_demonbane", "arclight_demonbane_accuracy"]
+    );
+    assert_eq!(
+        effects_against(14176),
+        vec![
+            "arclight_demonbane_resisted",
+            "arclight_demonbane_resisted_accuracy"
+        ]
+    );
+}
+
+#[test]
+fn kurask_takes_damage_only_from_leaf_bladed_weapons() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player = resolve_player_input(&read_fixture("players/maxed_piety_super_combat.json"));
+    let target = resolver
+        .resolve_target(&read_fixture("targets/kurask.json"))
+        .expect("Failed to resolve target");
+    let eval_with = |weapon: u32| {
+        let build = resolver
+            .resolve_build(&BuildInput {
+                schema_version: 1,
+                equipment: EquipmentInput {
+                    weapon,
+                    ..Default::default()
+                },
+                style: StyleInput {
+                    combat: Combat::Melee,
+                    attack_type: AttackType::Slash,
+                    stance: Stance::Aggressive,
+                    special_attack: false,
+                    manual_cast: false,
+                    runes: vec![],
+                    spell: None,
+                },
+                weapon_config: Default::default(),
+            })
+            .expect("Failed to resolve build");
+        evaluate(&EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+    };
+
+    // Abyssal whip: no damage, and the result says why
+    let whip = eval_with(4151);
+    assert_eq!(whip.dps, 0.0);
+    assert!(whip.immunity.is_some());
+
+    // Leaf-bladed sword and battleaxe can; the battleaxe hits 17.5% harder
+    let sword = eval_with(11902);
+    assert!(sword.dps > 0.0 && sword.immunity.is_none());
+    let battleaxe = eval_with(20727);
+    assert_eq!(battleaxe.effects_applied, vec!["leaf_bladed_battleaxe"]);
+    assert!(battleaxe.dps > 0.0);
+}
+
+#[test]
+fn flagged_monster_data_carries_warnings_into_results() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player = resolve_player_input(&read_fixture("players/maxed_piety_super_combat.json"));
+    let build = resolver
+        .resolve_build(&read_fixture("builds/voidwaker_bandos_slash_accurate.json"))
+        .expect("Failed to resolve build");
+    let eval_against = |monster_id: u32| {
+        let input: TargetInput = serde_json::from_str(&format!(
+            r#"{{"schema_version": 1, "monster_id": {monster_id}}}"#
+        ))
+        .expect("Invalid target");
+        let target = resolver.resolve_target(&input).expect("Failed to resolve");
+        evaluate(&EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+    };
+
+    // Ogre shaman: placeholder stats in the wiki data
+    let shaman = eval_against(4387);
+    assert_eq!(shaman.data_warnings.len(), 1);
+    assert_eq!(shaman.data_warnings[0].confidence, Confidence::High);
+
+    // Unflagged monsters have none, and JSON output leaves the field out
+    let kurask = eval_against(410);
+    assert!(kurask.data_warnings.is_empty());
+    let json = serde_json::to_value(&kurask).expect("Failed to serialize");
+    assert!(json.get("data_warnings").is_none());
+}
+
+#[test]
+fn loads_single_file_osrsbox_dumps() {
+    let data_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("data");
+    let dump_dir = std::env::temp_dir().join(format!("osrs-sim-dump-{}", std::process::id()));
+    std::fs::create_dir_all(&dump_dir).unwrap();
+
+    // Real entries keyed by ID, with extra osrsbox fields and a null slayer level
+    let mut whip: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(data_dir.join("items/4151.json")).unwrap())
+            .unwrap();
+    whip["wiki_url"] = serde_json::Value::Null;
+    let mut jad: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(data_dir.join("monsters/3127.json")).unwrap(),
+    )
+    .unwrap();
+    jad["slayer_level"] = serde_json::Value::Null;
+    std::fs::write(
+        dump_dir.join(ITEMS_DUMP),
+        serde_json::json!({ "4151": whip }).to_string(),
+    )
+    .unwrap();
+    std::fs::write(
+        dump_dir.join(MONSTERS_DUMP),
+        serde_json::json!({ "3127": jad }).to_string(),
+    )
+    .unwrap();
+
+    let store = DataStore::load_from_dir(&dump_dir);
+    std::fs::remove_dir_all(&dump_dir).unwrap();
+    let store = store.expect("Failed to load dumps");
+    assert_eq!(store.items.len(), 1);
+    assert_eq!(
+        store.get_item(4151).unwrap().name,
+        load_store().items[&4151].name
+    );
+    assert_eq!(store.get_monster(3127).unwrap().slayer_level, 0);
+
+    // Entries are read one at a time: a dump that isn't an object of
+    // entries, or has a bad one, fails to load
+    for dump in [
+        serde_json::json!([whip]),
+        serde_json::json!({ "4151": whip, "4152": { "id": "whip" } }),
+    ] {
+        std::fs::create_dir_all(&dump_dir).unwrap();
+        std::fs::write(dump_dir.join(ITEMS_DUMP), dump.to_string()).unwrap();
+        let store = DataStore::load_from_dir(&dump_dir);
+        std::fs::remove_dir_all(&dump_dir).unwrap();
+        assert!(store.is_err());
+    }
+}
+
+#[test]
+fn shipped_monsters_have_no_problems() {
+    let store = load_store();
+    let problems = store.monster_problems(default_registry());
+    assert!(problems.is_empty(), "{problems:?}");
+
+    // An effect on an attribute no monster has can never apply
+    let mut registry = EffectRegistry::new();
+    let effects = parse_effects(
+        r#"[{"id": "wyrm_only", "name": "Wyrm only", "stage": "post_max_hit",
+             "condition": {"target_attributes": ["wyrm"]},
+             "op": {"op": "add", "stat": "max_hit", "value": 1}}]"#,
+    )
+    .unwrap();
+    for effect in effects {
+        registry.register(effect);
+    }
+    assert_eq!(
+        store.monster_problems(&registry),
+        vec!["attribute \"wyrm\" (checked by effect wyrm_only) is on no monster"]
+    );
+}
+
+#[test]
+fn shipped_specs_are_consistent() {
+    let mut store = load_store();
+    assert!(
+        store.spec_problems().is_empty(),
+        "{:?}",
+        store.spec_problems()
+    );
+
+    let dragon_dagger = store.get_spec(1215).expect("dragon dagger spec");
+    assert_eq!((dragon_dagger.energy, dragon_dagger.hits), (25, 2));
+    // Variants find their base item's spec
+    assert_eq!(
+        store.get_spec(28537).map(|s| s.name.as_str()),
+        Some("The Judgement")
+    );
+
+    let missing = store.missing_specs();
+    assert!(!missing.iter().any(|item| item.id == 1215));
+    let whip = missing
+        .iter()
+        .find(|item| item.id == 4151)
+        .expect("whip has no entry");
+    let template = SpecData::template(whip);
+    store.specs.push(template);
+    assert!(store.missing_specs().iter().all(|item| item.id != 4151));
+    assert_eq!(store.spec_problems().len(), 1);
+}
+
+/// Special attacks vs TzTok-Jad, 99s with a super strength potion. The
+/// dragon dagger's Puncture scales the attack roll and max hit by 115%
+/// and hits twice; the dragon claws' hits cascade through their own
+/// distribution, and the Voidwaker's Disrupt skips the accuracy roll for
+/// 50% to 150% of the max hit.
+#[test]
+fn special_attacks_apply_spec_multipliers_and_hits() {
+    let store = load_store();
+    let resolver = Resolver::new(&store);
+    let player: PlayerInput = read_fixture("players/maxed_super_str.json");
+    let target = resolver
+        .resolve_target_for(&read_fixture("targets/tztok_jad.json"), &player)
+        .unwrap();
+    let player = resolve_player_input(&player);
+    let build_for = |weapon: u32, special_attack: bool| {
+        resolver.resolve_build(&BuildInput {
+            schema_version: 1,
+            equipment: EquipmentInput {
+                weapon,
+                ..Default::default()
+            },
+            style: StyleInput {
+                combat: Combat::Melee,
+                attack_type: AttackType::Slash,
+                stance: Stance::Accurate,
+                special_attack,
+                manual_cast: false,
+                runes: vec![],
+                spell: None,
+            },
+            weapon_config: Default::default(),
+        })
+    };
+    let eval = |weapon: u32, special_attack: bool| {
+        let build = build_for(weapon, special_attack).expect("Failed to resolve build");
+        evaluate(&EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+    };
+    let (dragon_dagger, dragon_claws, voidwaker, abyssal_whip) = (1215, 13652, 27690, 4151);
+
+    let normal = eval(dragon_dagger, false);
+    let puncture = eval(dragon_dagger, true);
+    let spec_max = normal.max_hit * 115 / 100;
+    assert_eq!(puncture.hit_max_hits, vec![spec_max, spec_max]);
+    assert_eq!(puncture.attack_roll, normal.attack_roll * 115 / 100);
+    assert!(puncture.accuracy > normal.accuracy);
+    let expected = 2.0 * puncture.accuracy * f64::from(spec_max) / 2.0 / 2.4;
+    assert!((puncture.dps - expected).abs() < 1e-9);
+
+    let normal = eval(dragon_claws, false);
+    let slice_and_dice = eval(dragon_claws, true);
+    assert_eq!(slice_and_dice.max_hit, normal.max_hit);
+    let expected =
+        osrs_sim::specs::dragon_claws(slice_and_dice.accuracy, i64::from(normal.max_hit)) / 2.4;
+    assert!((slice_and_dice.dps - expected).abs() < 1e-9);
+    assert!(slice_and_dice.dps > 2.0 * normal.dps);
+
+    let normal = eval(voidwaker, false);
+    let disrupt = eval(voidwaker, true);
+    assert!(normal.accuracy < 1.0);
+    assert_eq!(disrupt.accuracy, 1.0);
+    assert_eq!(disrupt.max_hit, normal.max_hit * 3 / 2);
+    let average = f64::from(normal.max_hit / 2 + normal.max_hit * 3 / 2) / 2.0;
+    assert!((disrupt.dps - average / 2.4).abs() < 1e-9);
+
+    assert!(matches!(
+        build_for(abyssal_whip, true),
+        Err(DataError::NoSpecialAttack(_))
+    ));
+}
+
+/// Spec properties declared in data/specs: the Seercull's Soulshot always
+/// hits, and a spec ignoring half the magic defence halves the defence
+/// roll of a magic attack (here given to the kodai wand casting Ice
+/// Barrage against TzTok-Jad).
+#[test]
+fn declarative_spec_fields_skip_accuracy_and_ignore_magic_defence() {
+    let mut store = load_store();
+    let soulshot = store.get_spec(6724).expect("seercull spec");
+    assert!(soulshot.always_hits);
+
+    let seercull_build = |special_attack| BuildInput {
+        schema_version: 1,
+        equipment: EquipmentInput {
+            weapon: 6724,
+            ammo: Some(892),
+            ..Default::default()
+        },
+        style: StyleInput {
+            combat: Combat::Ranged,
+            attack_type: AttackType::Ranged,
+            stance: Stance::Accurate,
+            special_attack,
+            manual_cast: false,
+            runes: vec![],
+            spell: None,
+        },
+        weapon_config: Default::default(),
+    };
+    let player_input: PlayerInput = read_fixture("players/maxed_no_boosts.json");
+    let player = resolve_player_input(&player_input);
+    let eval = |store: &DataStore, build: &BuildInput| {
+        let resolver = Resolver::new(store);
+        let target = resolver
+            .resolve_target_for(&read_fixture("targets/tztok_jad.json"), &player_input)
+            .unwrap();
+        let build = resolver
+            .resolve_build(build)
+            .expect("Failed to resolve build");
+        evaluate(&EvalContext {
+            player: &player,
+            build: &build,
+            target: &target,
+            ordering: None,
+            adjacent_targets: 0,
+            distance: None,
+        })
+    };
+    assert!(eval(&store, &seercull_build(false)).accuracy < 1.0);
+    assert_eq!(eval(&store, &seercull_build(true)).accuracy, 1.0);
+
+    let kodai_wand = 21006;
+    store.specs.push(SpecData {
+        ignores_magic_defence_pct: Some(50),
+        ..SpecData::template(store.get_item(kodai_wand).unwrap())
+    });
+    let mut barrage: BuildInput = read_fixture("builds/kodai_ancestral_ice_barrage.json");
+    let normal = eval(&store, &barrage);
+    barrage.style.special_attack = true;
+    let spec = eval(&store, &barrage);
+    assert_eq!(spec.defence_roll, normal.defence_roll / 2);
+    assert_eq!(spec.attack_roll, normal.attack_roll);
+    assert!(spec.accuracy > normal.accuracy);
+}